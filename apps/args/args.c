@@ -0,0 +1,18 @@
+#include <stdio.h>
+#include <stdlib.h>
+
+int main(int argc, char *argv[])
+{
+    printf("argc: %d\n", argc);
+    for (int i = 0; i < argc; i++)
+    {
+        printf("argv[%d]: \"%s\"\n", i, argv[i]);
+    }
+
+    for (int i = 0; environ[i] != NULL; i++)
+    {
+        printf("envp[%d]: \"%s\"\n", i, environ[i]);
+    }
+
+    return 0;
+}
@@ -0,0 +1,44 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <syscalls.h>
+
+#define BUF_LEN 512
+
+int main(int argc, const char *argv[])
+{
+    if (argc < 2)
+    {
+        printf("Usage: cat [FILE]...\n");
+        exit(1);
+    }
+
+    char buf[BUF_LEN];
+    int status = 0;
+
+    for (int i = 1; i < argc; i++)
+    {
+        int64_t fd = sys_open(argv[i]);
+        if (fd == -1)
+        {
+            printf("cat: %s: failed to open the file\n", argv[i]);
+            status = 1;
+            continue;
+        }
+
+        int64_t len;
+        while ((len = sys_read(fd, buf, BUF_LEN)) > 0)
+        {
+            sys_write(FDN_STDOUT, buf, len);
+        }
+
+        if (len == -1)
+        {
+            printf("cat: %s: failed to read the file\n", argv[i]);
+            status = 1;
+        }
+
+        sys_close(fd);
+    }
+
+    return status;
+}
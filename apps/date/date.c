@@ -0,0 +1,58 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <syscalls.h>
+#include <time.h>
+
+// convert days since 1970-01-01 to a civil date
+static void days_to_date(int64_t days, int *year, int *month, int *day)
+{
+    days += 719468;
+    int64_t era = (days >= 0 ? days : days - 146096) / 146097;
+    int64_t doe = days - era * 146097;
+    int64_t yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    int64_t doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    int64_t mp = (5 * doy + 2) / 153;
+
+    *day = (int)(doy - (153 * mp + 2) / 5 + 1);
+    *month = (int)(mp < 10 ? mp + 3 : mp - 9);
+    *year = (int)(yoe + era * 400 + (*month <= 2 ? 1 : 0));
+}
+
+int main(int argc, const char *argv[])
+{
+    timespec ts;
+
+    if (argc > 1 && strcmp(argv[1], "--help") == 0)
+    {
+        printf("Usage: date [OPTION]\n");
+        printf("Print the current date and time (UTC).\n\n");
+        printf(" -m\tprint the monotonic clock instead\n");
+        exit(0);
+    }
+
+    if (argc > 1 && strcmp(argv[1], "-m") == 0)
+    {
+        if (sys_clock_gettime(CLOCK_MONOTONIC, &ts) == -1)
+        {
+            printf("date: Failed to get monotonic clock\n");
+            exit(1);
+        }
+
+        printf("%d.%09d\n", (int)ts.sec, (int)ts.nsec);
+        exit(0);
+    }
+
+    if (sys_clock_gettime(CLOCK_REALTIME, &ts) == -1)
+    {
+        printf("date: Failed to get realtime clock\n");
+        exit(1);
+    }
+
+    int64_t secs = ts.sec % 86400;
+    int year, month, day;
+    days_to_date(ts.sec / 86400, &year, &month, &day);
+
+    printf("%04d-%02d-%02d %02d:%02d:%02d UTC\n", year, month, day, (int)(secs / 3600), (int)(secs % 3600 / 60), (int)(secs % 60));
+    return 0;
+}
@@ -0,0 +1,16 @@
+#ifndef _DIRENT_H
+#define _DIRENT_H
+
+#include <stdint.h>
+#include <stdbool.h>
+
+#define DIRENT_NAME_LEN 256
+
+typedef struct
+{
+    uint64_t size;
+    bool is_dir;
+    char name[DIRENT_NAME_LEN];
+} dirent;
+
+#endif
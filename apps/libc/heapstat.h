@@ -0,0 +1,13 @@
+#ifndef _HEAPSTAT_H
+#define _HEAPSTAT_H
+
+#include <stdint.h>
+
+typedef struct
+{
+    uint64_t allocated;
+    uint64_t free;
+    uint64_t largest_free_block;
+} heap_stats;
+
+#endif
@@ -2,9 +2,21 @@
 #pragma GCC diagnostic ignored "-Wimplicit-function-declaration"
 
 #include "stdio.h"
+#include "stdlib.h"
 
-void _start(int argc, char const *argv[])
+char **environ = NULL;
+
+// initial stack: argc, argv[0..argc], NULL, envp[0..], NULL
+__asm__(".global _start\n"
+        "_start:\n"
+        "    mov (%rsp), %rdi\n"
+        "    lea 8(%rsp), %rsi\n"
+        "    lea 8(%rsi,%rdi,8), %rdx\n"
+        "    call __libc_start\n");
+
+void __libc_start(int argc, char const *argv[], char *envp[])
 {
+    environ = envp;
     exit((uint64_t)main(argc, argv));
 }
 
@@ -0,0 +1,27 @@
+#ifndef _SOCKET_H
+#define _SOCKET_H
+
+#include <stdint.h>
+
+#define AF_INET 2
+#define SOCK_DGRAM 2
+
+typedef struct
+{
+    uint16_t family; // AF_INET
+    uint16_t port;   // network byte order
+    uint8_t addr[4];
+    uint8_t zero[8];
+} sockaddr_in;
+
+static inline uint16_t htons(uint16_t value)
+{
+    return (value << 8) | (value >> 8);
+}
+
+static inline uint16_t ntohs(uint16_t value)
+{
+    return htons(value);
+}
+
+#endif
@@ -2,10 +2,12 @@
 #define _STAT_H
 
 #include <stdint.h>
+#include <stdbool.h>
 
 typedef struct
 {
     uint64_t size;
+    bool is_dir;
 } f_stat;
 
 #endif
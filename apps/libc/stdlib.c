@@ -17,8 +17,26 @@ void *malloc(size_t len)
 
 int atoi(const char *str)
 {
-    printf("[DEBUG]atoi called\n");
-    return -1;
+    int sign = 1;
+    int value = 0;
+
+    while (*str == ' ' || *str == '\t' || *str == '\n')
+        str++;
+
+    if (*str == '-' || *str == '+')
+    {
+        if (*str == '-')
+            sign = -1;
+        str++;
+    }
+
+    while (*str >= '0' && *str <= '9')
+    {
+        value = value * 10 + (*str - '0');
+        str++;
+    }
+
+    return sign * value;
 }
 
 double atof(const char *__nptr)
@@ -70,6 +88,19 @@ int system(const char *command)
     return -1;
 }
 
+char *getenv(const char *name)
+{
+    size_t len = strlen(name);
+
+    for (char **env = environ; env != NULL && *env != NULL; env++)
+    {
+        if (strncmp(*env, name, len) == 0 && (*env)[len] == '=')
+            return *env + len + 1;
+    }
+
+    return NULL;
+}
+
 int remove(const char *__filename)
 {
     printf("[DEBUG]remove called\n");
@@ -3,6 +3,8 @@
 
 #include <stddef.h>
 
+extern char **environ;
+
 extern int abs(int i);
 extern void *malloc(size_t len);
 extern int atoi(const char *str);
@@ -11,6 +13,7 @@ extern void free(void *ptr);
 extern void *calloc(size_t count, size_t size);
 extern void *realloc(void *ptr, size_t size);
 extern int system(const char *command);
+extern char *getenv(const char *name);
 extern int remove(const char *__filename);
 extern int rename(const char *__old, const char *__new);
 
@@ -113,3 +113,118 @@ int64_t sys_add_image_to_window(int64_t wd, uint64_t image_width, uint64_t image
 {
     return syscall(SN_ADD_IMAGE_TO_WINDOW, (uint64_t)wd, image_width, image_height, (uint64_t)pixel_format, (uint64_t)framebuf);
 }
+
+int64_t sys_stat_path(const char *path, f_stat *buf)
+{
+    return syscall(SN_STAT_PATH, (uint64_t)path, (uint64_t)buf, 0, 0, 0);
+}
+
+int64_t sys_mkdir(const char *path)
+{
+    return syscall(SN_MKDIR, (uint64_t)path, 0, 0, 0, 0);
+}
+
+int64_t sys_remove(const char *path, bool recursive)
+{
+    return syscall(SN_REMOVE, (uint64_t)path, (uint64_t)recursive, 0, 0, 0);
+}
+
+int64_t sys_sleep_ms(uint64_t ms)
+{
+    return syscall(SN_SLEEP_MS, ms, 0, 0, 0, 0);
+}
+
+int64_t sys_spawn(const char *args, int64_t stdin_fd, int64_t stdout_fd)
+{
+    return syscall(SN_SPAWN, (uint64_t)args, (uint64_t)stdin_fd, (uint64_t)stdout_fd, 0, 0);
+}
+
+int64_t sys_wait(uint64_t pid)
+{
+    return syscall(SN_WAIT, pid, 0, 0, 0, 0);
+}
+
+int64_t sys_pipe(int64_t *fds)
+{
+    return syscall(SN_PIPE, (uint64_t)fds, 0, 0, 0, 0);
+}
+
+int64_t sys_screenshot(const char *path)
+{
+    return syscall(SN_SCREENSHOT, (uint64_t)path, 0, 0, 0, 0);
+}
+
+int64_t sys_heap_stats(heap_stats *buf)
+{
+    return syscall(SN_HEAP_STATS, (uint64_t)buf, 0, 0, 0, 0);
+}
+
+int64_t sys_poweroff()
+{
+    return syscall(SN_POWEROFF, 0, 0, 0, 0, 0);
+}
+
+int64_t sys_reboot()
+{
+    return syscall(SN_REBOOT, 0, 0, 0, 0, 0);
+}
+
+int64_t sys_readdir(const char *path, dirent *buf, int buf_len)
+{
+    return syscall(SN_READDIR, (uint64_t)path, (uint64_t)buf, (uint64_t)buf_len, 0, 0);
+}
+
+int64_t sys_getpid()
+{
+    return syscall(SN_GETPID, 0, 0, 0, 0, 0);
+}
+
+int64_t sys_socket(int domain, int type)
+{
+    return syscall(SN_SOCKET, (uint64_t)domain, (uint64_t)type, 0, 0, 0);
+}
+
+int64_t sys_bind(int64_t sd, const sockaddr_in *addr)
+{
+    return syscall(SN_BIND, (uint64_t)sd, (uint64_t)addr, 0, 0, 0);
+}
+
+int64_t sys_sendto(int64_t sd, const void *buf, int buf_len, const sockaddr_in *addr)
+{
+    return syscall(SN_SENDTO, (uint64_t)sd, (uint64_t)buf, (uint64_t)buf_len, (uint64_t)addr, 0);
+}
+
+int64_t sys_recvfrom(int64_t sd, void *buf, int buf_len, sockaddr_in *addr)
+{
+    return syscall(SN_RECVFROM, (uint64_t)sd, (uint64_t)buf, (uint64_t)buf_len, (uint64_t)addr, 0);
+}
+
+int64_t sys_close_socket(int64_t sd)
+{
+    return syscall(SN_CLOSE_SOCKET, (uint64_t)sd, 0, 0, 0, 0);
+}
+
+int64_t sys_clock_gettime(uint64_t clock_id, timespec *buf)
+{
+    return syscall(SN_CLOCK_GETTIME, clock_id, (uint64_t)buf, 0, 0, 0);
+}
+
+void *sys_mmap(void *addr, size_t len, int prot, int flags)
+{
+    return (void *)syscall(SN_MMAP, (uint64_t)addr, (uint64_t)len, (uint64_t)prot, (uint64_t)flags, 0);
+}
+
+int64_t sys_munmap(void *addr, size_t len)
+{
+    return syscall(SN_MUNMAP, (uint64_t)addr, (uint64_t)len, 0, 0, 0);
+}
+
+void *sys_brk(void *addr)
+{
+    return (void *)syscall(SN_BRK, (uint64_t)addr, 0, 0, 0, 0);
+}
+
+int64_t sys_gethostbyname(const char *hostname, uint8_t *addr)
+{
+    return syscall(SN_GETHOSTBYNAME, (uint64_t)hostname, (uint64_t)addr, 0, 0, 0);
+}
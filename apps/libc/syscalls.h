@@ -2,11 +2,16 @@
 #include <stddef.h>
 #include "utsname.h"
 #include "stat.h"
+#include "heapstat.h"
+#include "dirent.h"
+#include "socket.h"
+#include "time.h"
+#include "mman.h"
 
 #ifndef _SYSCALLS_H
 #define _SYSCALLS_H
 
-// syscall numbers
+// syscall numbers (same as enum Syscall of the kernel)
 #define SN_READ 0
 #define SN_WRITE 1
 #define SN_OPEN 2
@@ -26,6 +31,32 @@
 #define SN_SBRKSZ 16
 #define SN_FLUSH_WINDOW 17
 #define SN_ADD_IMAGE_TO_WINDOW 18
+#define SN_STAT_PATH 19
+#define SN_MKDIR 20
+#define SN_REMOVE 21
+#define SN_SLEEP_MS 22
+#define SN_SPAWN 23
+#define SN_WAIT 24
+#define SN_PIPE 25
+#define SN_SCREENSHOT 26
+#define SN_HEAP_STATS 27
+#define SN_POWEROFF 28
+#define SN_REBOOT 29
+#define SN_READDIR 30
+#define SN_GETPID 31
+#define SN_SOCKET 32
+#define SN_BIND 33
+#define SN_SENDTO 34
+#define SN_RECVFROM 35
+#define SN_CLOSE_SOCKET 36
+#define SN_CLOCK_GETTIME 37
+#define SN_MMAP 38
+#define SN_MUNMAP 39
+#define SN_BRK 40
+#define SN_GETHOSTBYNAME 41
+
+// returned as -ENOSYS for undefined syscall numbers
+#define ENOSYS 38
 
 // defined file descriptor numbers
 #define FDN_STDIN 0
@@ -51,5 +82,28 @@ extern int64_t sys_getcwdenames(char *buf, int buf_len);
 extern size_t sys_sbrksz(const void *target);
 extern int64_t sys_flush_window(int64_t wd);
 extern int64_t sys_add_image_to_window(int64_t wd, uint64_t image_width, uint64_t image_height, uint8_t pixel_format, const char *framebuf);
+extern int64_t sys_stat_path(const char *path, f_stat *buf);
+extern int64_t sys_mkdir(const char *path);
+extern int64_t sys_remove(const char *path, bool recursive);
+extern int64_t sys_sleep_ms(uint64_t ms);
+extern int64_t sys_spawn(const char *args, int64_t stdin_fd, int64_t stdout_fd);
+extern int64_t sys_wait(uint64_t pid);
+extern int64_t sys_pipe(int64_t *fds);
+extern int64_t sys_screenshot(const char *path);
+extern int64_t sys_heap_stats(heap_stats *buf);
+extern int64_t sys_poweroff();
+extern int64_t sys_reboot();
+extern int64_t sys_readdir(const char *path, dirent *buf, int buf_len);
+extern int64_t sys_getpid();
+extern int64_t sys_socket(int domain, int type);
+extern int64_t sys_bind(int64_t sd, const sockaddr_in *addr);
+extern int64_t sys_sendto(int64_t sd, const void *buf, int buf_len, const sockaddr_in *addr);
+extern int64_t sys_recvfrom(int64_t sd, void *buf, int buf_len, sockaddr_in *addr);
+extern int64_t sys_close_socket(int64_t sd);
+extern int64_t sys_clock_gettime(uint64_t clock_id, timespec *buf);
+extern void *sys_mmap(void *addr, size_t len, int prot, int flags);
+extern int64_t sys_munmap(void *addr, size_t len);
+extern void *sys_brk(void *addr);
+extern int64_t sys_gethostbyname(const char *hostname, uint8_t *addr);
 
 #endif
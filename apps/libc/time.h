@@ -0,0 +1,15 @@
+#ifndef _TIME_H
+#define _TIME_H
+
+#include <stdint.h>
+
+#define CLOCK_REALTIME 0
+#define CLOCK_MONOTONIC 1
+
+typedef struct
+{
+    int64_t sec;
+    int64_t nsec;
+} timespec;
+
+#endif
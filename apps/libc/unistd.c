@@ -0,0 +1,26 @@
+#include "unistd.h"
+#include "syscalls.h"
+
+int brk(void *addr)
+{
+    if (sys_brk(addr) != addr)
+        return -1;
+
+    return 0;
+}
+
+// returns the previous break, or (void *)-1 on failure
+void *sbrk(intptr_t increment)
+{
+    char *current = sys_brk(NULL);
+    if (current == (void *)-1)
+        return (void *)-1;
+
+    if (increment == 0)
+        return current;
+
+    if (sys_brk(current + increment) != current + increment)
+        return (void *)-1;
+
+    return current;
+}
@@ -0,0 +1,9 @@
+#ifndef _UNISTD_H
+#define _UNISTD_H
+
+#include <stdint.h>
+
+extern int brk(void *addr);
+extern void *sbrk(intptr_t increment);
+
+#endif
@@ -0,0 +1,21 @@
+#include <stdio.h>
+#include <syscalls.h>
+
+int main(int argc, char *argv[])
+{
+    if (argc != 2)
+    {
+        printf("Usage: nslookup HOSTNAME\n");
+        return 1;
+    }
+
+    uint8_t addr[4];
+    if (sys_gethostbyname(argv[1], addr) == -1)
+    {
+        printf("nslookup: Failed to resolve %s\n", argv[1]);
+        return -1;
+    }
+
+    printf("%s: %d.%d.%d.%d\n", argv[1], addr[0], addr[1], addr[2], addr[3]);
+    return 0;
+}
@@ -0,0 +1,63 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+#define CHUNK_SIZE (64 * 1024)
+#define CHUNKS_LEN 64
+#define PAGE_SIZE 4096
+
+int main(int argc, const char *argv[])
+{
+    char *start = sbrk(0);
+    if (start == (void *)-1)
+    {
+        printf("sbrktest: Failed to get the program break\n");
+        exit(1);
+    }
+
+    // grow the heap by chunks
+    int chunks = 0;
+    for (; chunks < CHUNKS_LEN; chunks++)
+    {
+        char *chunk = sbrk(CHUNK_SIZE);
+        if (chunk == (void *)-1)
+        {
+            printf("sbrktest: sbrk failed after %d KiB\n", chunks * (CHUNK_SIZE / 1024));
+            exit(1);
+        }
+
+        // touch every page of the chunk
+        for (int i = 0; i < CHUNK_SIZE; i += PAGE_SIZE)
+        {
+            if (chunk[i] != 0)
+            {
+                printf("sbrktest: Page was not zero-filled at offset 0x%x\n", (int)(chunk + i - start));
+                exit(1);
+            }
+            chunk[i] = (char)chunks;
+        }
+    }
+
+    // verify the written data
+    for (int c = 0; c < chunks; c++)
+    {
+        for (int i = 0; i < CHUNK_SIZE; i += PAGE_SIZE)
+        {
+            if (start[c * CHUNK_SIZE + i] != (char)c)
+            {
+                printf("sbrktest: Data mismatch in chunk %d\n", c);
+                exit(1);
+            }
+        }
+    }
+
+    // release the heap
+    if (brk(start) != 0 || sbrk(0) != start)
+    {
+        printf("sbrktest: Failed to shrink the heap\n");
+        exit(1);
+    }
+    printf("sbrktest: OK\n");
+
+    return 0;
+}
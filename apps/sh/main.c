@@ -6,6 +6,7 @@
 #include <syscalls.h>
 
 #define BUF_LEN 128
+#define APPS_DIR_PATH "/mnt/initramfs/apps"
 
 #define MS_IN_A_DAY (24 * 60 * 60 * 1000)
 #define MS_IN_A_HOUR (60 * 60 * 1000)
@@ -15,7 +16,90 @@
 static char buf[BUF_LEN] = {0};
 static char *splitted_buf[BUF_LEN];
 static char cwd_path[BUF_LEN] = {0};
-static char cwdenames[BUF_LEN * 10] = {0};
+
+// connect stdout of the left command to stdin of the right command
+void exec_pipeline(char *left[], int left_len, char *right[], int right_len)
+{
+    int64_t fds[2];
+    if (sys_pipe(fds) == -1)
+    {
+        printf("sh: exec: failed to create pipe\n");
+        return;
+    }
+
+    char *left_args = concatenate((const char **)left, left_len, " ");
+    char *right_args = concatenate((const char **)right, right_len, " ");
+    if (left_args == NULL || right_args == NULL)
+    {
+        printf("sh: exec: failed to concatenate arguments\n");
+        sys_close(fds[0]);
+        sys_close(fds[1]);
+        return;
+    }
+
+    // the pipe ends are moved to the spawned processes
+    int64_t left_pid = sys_spawn(left_args, -1, fds[1]);
+    if (left_pid == -1)
+    {
+        printf("sh: exec: failed to spawn\n");
+        sys_close(fds[1]);
+    }
+
+    int64_t right_pid = sys_spawn(right_args, fds[0], -1);
+    if (right_pid == -1)
+    {
+        printf("sh: exec: failed to spawn\n");
+        sys_close(fds[0]);
+    }
+
+    if (left_pid != -1)
+    {
+        sys_wait(left_pid);
+    }
+
+    if (right_pid != -1)
+    {
+        sys_wait(right_pid);
+    }
+}
+
+// run the program in the foreground, the command name is searched in APPS_DIR_PATH
+int exec_program(char *args[], int args_len)
+{
+    char path[BUF_LEN];
+    if (strchr(args[0], '/') != NULL)
+    {
+        snprintf(path, sizeof(path), "%s", args[0]);
+    }
+    else
+    {
+        snprintf(path, sizeof(path), "%s/%s.elf", APPS_DIR_PATH, args[0]);
+    }
+
+    const char *argv[BUF_LEN];
+    argv[0] = path;
+    for (int i = 1; i < args_len; i++)
+    {
+        argv[i] = args[i];
+    }
+
+    char *cmdline = concatenate(argv, args_len, " ");
+    if (cmdline == NULL)
+    {
+        printf("sh: failed to concatenate arguments\n");
+        return -1;
+    }
+
+    int64_t pid = sys_spawn(cmdline, -1, -1);
+    free(cmdline);
+    if (pid == -1)
+    {
+        return -1;
+    }
+
+    sys_wait(pid);
+    return 0;
+}
 
 void exec_cmd(char *cmd)
 {
@@ -52,40 +136,119 @@ void exec_cmd(char *cmd)
             return;
         }
     }
-    else if (strcmp(splitted_buf[0], "ls") == 0)
+    else if (strcmp(splitted_buf[0], "pwd") == 0)
     {
-        if (sys_getcwdenames(cwdenames, sizeof(cwdenames)) == -1)
+        if (sys_getcwd(cwd_path, sizeof(cwd_path)) == -1)
         {
-            printf("sh: ls: failed to get entry names in the current working directory\n");
+            printf("sh: pwd: failed to get the current directory\n");
             return;
         }
 
-        char old_c = '\0';
+        printf("%s\n", cwd_path);
+    }
+    else if (strcmp(splitted_buf[0], "echo") == 0)
+    {
+        for (int i = 1; i < cmdargs_len; i++)
+        {
+            printf(i == cmdargs_len - 1 ? "%s" : "%s ", splitted_buf[i]);
+        }
+        printf("\n");
+    }
+    else if (strcmp(splitted_buf[0], "mkdir") == 0)
+    {
+        if (cmdargs_len < 2)
+        {
+            printf("sh: mkdir: missing argument\n");
+            return;
+        }
 
-        for (int i = 0; i < (int)sizeof(cwdenames); i++)
+        if (sys_mkdir(splitted_buf[1]) == -1)
         {
-            char c = cwdenames[i];
+            printf("sh: mkdir: failed to create directory\n");
+            return;
+        }
+    }
+    else if (strcmp(splitted_buf[0], "rm") == 0)
+    {
+        if (cmdargs_len < 2)
+        {
+            printf("sh: rm: missing argument\n");
+            return;
+        }
 
-            // end of name list
-            if (old_c == '\0' && c == '\0' && i > 0)
-            {
-                break;
-            }
+        bool recursive = strcmp(splitted_buf[1], "-r") == 0;
+        if (recursive && cmdargs_len < 3)
+        {
+            printf("sh: rm: missing argument\n");
+            return;
+        }
+
+        if (sys_remove(splitted_buf[recursive ? 2 : 1], recursive) == -1)
+        {
+            printf("sh: rm: failed to remove\n");
+            return;
+        }
+    }
+    else if (strcmp(splitted_buf[0], "ls") == 0)
+    {
+        bool long_format = false;
+        char *path = ".";
 
-            if (c == '\0')
+        for (int i = 1; i < cmdargs_len; i++)
+        {
+            if (strcmp(splitted_buf[i], "-l") == 0)
             {
-                printf("  ");
+                long_format = true;
             }
             else
             {
-                printf("%c", c);
+                path = splitted_buf[i];
             }
+        }
+
+        // get the number of entries first
+        int64_t entries_len = sys_readdir(path, NULL, 0);
+        if (entries_len == -1)
+        {
+            printf("sh: ls: failed to read the directory\n");
+            return;
+        }
+
+        int64_t entries_cap = entries_len + 1;
+        dirent *entries = (dirent *)malloc(sizeof(dirent) * entries_cap);
+        if (entries == NULL)
+        {
+            printf("sh: ls: failed to allocate memory\n");
+            return;
+        }
 
-            old_c = cwdenames[i];
+        // the directory may have changed in between
+        entries_len = sys_readdir(path, entries, entries_cap);
+        if (entries_len == -1)
+        {
+            printf("sh: ls: failed to read the directory\n");
+            free(entries);
+            return;
+        }
+
+        if (entries_len > entries_cap)
+        {
+            entries_len = entries_cap;
+        }
 
-            // clear
-            cwdenames[i] = '\0';
+        for (int i = 0; i < entries_len; i++)
+        {
+            if (long_format)
+            {
+                printf("%c %d %s\n", entries[i].is_dir ? 'd' : '-', (int)entries[i].size, entries[i].name);
+            }
+            else
+            {
+                printf("%s  ", entries[i].name);
+            }
         }
+
+        free(entries);
     }
     else if (strcmp(splitted_buf[0], "cat") == 0)
     {
@@ -224,12 +387,29 @@ void exec_cmd(char *cmd)
     }
     else if (strcmp(splitted_buf[0], "exec") == 0)
     {
+        // run in the background if the last argument is "&"
+        int background = 0;
+        if (cmdargs_len > 1 && strcmp(splitted_buf[cmdargs_len - 1], "&") == 0)
+        {
+            background = 1;
+            cmdargs_len--;
+        }
+
         if (cmdargs_len < 2)
         {
             printf("sh: exec: missing argument\n");
             return;
         }
 
+        for (int i = 2; i < cmdargs_len - 1; i++)
+        {
+            if (strcmp(splitted_buf[i], "|") == 0)
+            {
+                exec_pipeline(splitted_buf + 1, i - 1, splitted_buf + i + 1, cmdargs_len - i - 1);
+                return;
+            }
+        }
+
         char *args = splitted_buf[1];
         if (cmdargs_len > 2)
         {
@@ -242,12 +422,42 @@ void exec_cmd(char *cmd)
             }
         }
 
+        if (background)
+        {
+            int64_t pid = sys_spawn(args, -1, -1);
+            if (pid == -1)
+            {
+                printf("sh: exec: failed to spawn\n");
+                return;
+            }
+
+            printf("[%d]\n", pid);
+            return;
+        }
+
         if (sys_exec(args) == -1)
         {
             printf("sh: exec: failed to execute\n");
             return;
         }
     }
+    else if (strcmp(splitted_buf[0], "wait") == 0)
+    {
+        if (cmdargs_len < 2)
+        {
+            printf("sh: wait: missing argument\n");
+            return;
+        }
+
+        int64_t exit_code = sys_wait(atoi(splitted_buf[1]));
+        if (exit_code == -1)
+        {
+            printf("sh: wait: failed to wait\n");
+            return;
+        }
+
+        printf("[%s] exited (code: %d)\n", splitted_buf[1], exit_code);
+    }
     else if (strcmp(splitted_buf[0], "window") == 0)
     {
         WindowDescriptor *wdesc = create_window("test window", 200, 50, 300, 200);
@@ -257,7 +467,41 @@ void exec_cmd(char *cmd)
             return;
         }
     }
-    else
+    else if (strcmp(splitted_buf[0], "screenshot") == 0)
+    {
+        const char *path = cmdargs_len < 2 ? "/tmp/screenshot.bmp" : splitted_buf[1];
+        if (sys_screenshot(path) == -1)
+        {
+            printf("sh: screenshot: failed to capture the screen\n");
+            return;
+        }
+
+        printf("saved to %s\n", path);
+    }
+    else if (strcmp(splitted_buf[0], "heap") == 0)
+    {
+        heap_stats stats;
+        sys_heap_stats(&stats);
+
+        printf("allocated: %d bytes\n", stats.allocated);
+        printf("free: %d bytes\n", stats.free);
+        printf("largest free block: %d bytes\n", stats.largest_free_block);
+    }
+    else if (strcmp(splitted_buf[0], "poweroff") == 0)
+    {
+        if (sys_poweroff() == -1)
+        {
+            printf("sh: poweroff: failed to power off\n");
+        }
+    }
+    else if (strcmp(splitted_buf[0], "reboot") == 0)
+    {
+        if (sys_reboot() == -1)
+        {
+            printf("sh: reboot: failed to reboot\n");
+        }
+    }
+    else if (exec_program(splitted_buf, cmdargs_len) == -1)
     {
         printf("sh: %s: command not found\n", cmd);
     }
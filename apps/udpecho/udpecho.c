@@ -0,0 +1,65 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <syscalls.h>
+
+#define DEFAULT_PORT 7
+#define BUF_LEN 1472
+
+int main(int argc, char *argv[])
+{
+    int port = DEFAULT_PORT;
+    if (argc > 1)
+    {
+        port = atoi(argv[1]);
+    }
+
+    if (port <= 0 || port > 0xffff)
+    {
+        printf("Usage: udpecho [PORT]\n");
+        return 1;
+    }
+
+    int64_t sd = sys_socket(AF_INET, SOCK_DGRAM);
+    if (sd == -1)
+    {
+        printf("udpecho: Failed to create a socket\n");
+        return -1;
+    }
+
+    sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.family = AF_INET;
+    addr.port = htons(port);
+
+    if (sys_bind(sd, &addr) == -1)
+    {
+        printf("udpecho: Failed to bind port %d\n", port);
+        sys_close_socket(sd);
+        return -1;
+    }
+
+    printf("udpecho: Listening on port %d\n", port);
+
+    char buf[BUF_LEN];
+    for (;;)
+    {
+        sockaddr_in src_addr;
+        int64_t len = sys_recvfrom(sd, buf, BUF_LEN, &src_addr);
+        if (len == -1)
+        {
+            printf("udpecho: Failed to receive a datagram\n");
+            break;
+        }
+
+        printf("udpecho: %d bytes from %d.%d.%d.%d:%d\n", (int)len, src_addr.addr[0], src_addr.addr[1], src_addr.addr[2], src_addr.addr[3], ntohs(src_addr.port));
+
+        if (sys_sendto(sd, buf, len, &src_addr) == -1)
+        {
+            printf("udpecho: Failed to send a datagram\n");
+        }
+    }
+
+    sys_close_socket(sd);
+    return -1;
+}
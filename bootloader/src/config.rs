@@ -1,10 +1,14 @@
-use common::kernel_config::KernelConfig;
+use common::kernel_config::{
+    KernelConfig, LogLevel, DEFAULT_DNS_SERVER, DEFAULT_IPV4_ADDR, DEFAULT_IPV4_GATEWAY,
+    DEFAULT_IPV4_NETMASK,
+};
 
 #[derive(Debug)]
 pub struct BootConfig<'a> {
     pub kernel_path: &'a str,
     pub initramfs_path: &'a str,
     pub resolution: (usize, usize),
+    pub cmdline: &'a str, // followed by the load options of the bootloader
 }
 
 impl Default for BootConfig<'_> {
@@ -13,6 +17,7 @@ impl Default for BootConfig<'_> {
             kernel_path: "\\EFI\\myos\\kernel.elf",
             initramfs_path: "initramfs.img",
             resolution: (800, 600),
+            cmdline: "",
         }
     }
 }
@@ -20,4 +25,11 @@ impl Default for BootConfig<'_> {
 pub const KERNEL_CONFIG: KernelConfig = KernelConfig {
     init_cwd_path: "/mnt/initramfs",
     init_app_exec_args: Some("/mnt/initramfs/apps/sh.elf"),
+    font_path: None,
+    log_level: LogLevel::Trace,
+    ipv4_addr: DEFAULT_IPV4_ADDR,
+    ipv4_netmask: DEFAULT_IPV4_NETMASK,
+    ipv4_gateway: DEFAULT_IPV4_GATEWAY,
+    dns_server: DEFAULT_DNS_SERVER,
+    cmdline: None,
 };
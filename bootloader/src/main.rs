@@ -6,22 +6,27 @@ mod config;
 #[macro_use]
 extern crate alloc;
 
-use alloc::vec::Vec;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 use common::{
     boot_info::BootInfo,
-    elf::{Elf64, SegmentType},
+    elf::{Elf64, Elf64Error, SegmentType, Type},
     graphic_info::{self, GraphicInfo},
+    kernel_config::KernelConfig,
     mem_desc::{self, UEFI_PAGE_SIZE},
 };
 use config::{BootConfig, KERNEL_CONFIG};
 use core::{mem, slice::from_raw_parts_mut};
-use log::info;
+use log::{error, info};
 use uefi::{
     boot::{self, *},
     entry,
     mem::memory_map::MemoryMap,
     proto::{
         console::gop::{GraphicsOutput, PixelFormat},
+        loaded_image::LoadedImage,
         media::{file::*, fs::SimpleFileSystem},
     },
     system,
@@ -42,7 +47,13 @@ fn efi_main() -> Status {
     info!("{:?}", graphic_info);
 
     // load kernel
-    let kernel_entry_point_addr = load_elf(config.kernel_path);
+    let kernel_entry_point_addr = match load_elf(config.kernel_path) {
+        Ok(addr) => addr,
+        Err(err) => {
+            error!("Failed to load kernel: {:?}", err);
+            return Status::LOAD_ERROR;
+        }
+    };
     info!("Kernel entry point: 0x{:x}", kernel_entry_point_addr);
 
     // load initramfs
@@ -51,6 +62,10 @@ fn efi_main() -> Status {
     // get RSDP address
     let rsdp_virt_addr = rsdp_addr();
 
+    // kernel cmdline
+    let cmdline = cmdline(config.cmdline);
+    info!("Kernel cmdline: {:?}", cmdline);
+
     // exit boot service and get memory map
     info!("Exit boot services");
     let mut mem_map = Vec::with_capacity(128);
@@ -79,7 +94,10 @@ fn efi_main() -> Status {
         initramfs_start_virt_addr,
         initramfs_page_cnt,
         rsdp_virt_addr,
-        kernel_config: KERNEL_CONFIG,
+        kernel_config: KernelConfig {
+            cmdline,
+            ..KERNEL_CONFIG
+        },
     };
 
     jump_to_entry(kernel_entry_point_addr, &bi);
@@ -94,6 +112,31 @@ fn rsdp_addr() -> Option<u64> {
     })
 }
 
+// copied to LOADER_DATA to be kept after exiting boot services
+fn cmdline(config_cmdline: &str) -> Option<&'static str> {
+    let mut cmdline = config_cmdline.to_string();
+    if let Some(load_options) = load_options() {
+        cmdline.push(' ');
+        cmdline.push_str(&load_options);
+    }
+
+    let cmdline = cmdline.trim();
+    if cmdline.is_empty() {
+        return None;
+    }
+
+    let buf = boot::allocate_pool(MemoryType::LOADER_DATA, cmdline.len()).ok()?;
+    let buf = unsafe { from_raw_parts_mut(buf.as_ptr(), cmdline.len()) };
+    buf.copy_from_slice(cmdline.as_bytes());
+    core::str::from_utf8(buf).ok()
+}
+
+fn load_options() -> Option<String> {
+    let loaded_image = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let load_options = loaded_image.load_options_as_cstr16().ok()?;
+    Some(load_options.to_string())
+}
+
 fn read_file(path: &str) -> RegularFile {
     info!("Opening file: \"{}\"", path);
     let sfs_handle = boot::get_handle_for_protocol::<SimpleFileSystem>().unwrap();
@@ -115,7 +158,7 @@ fn read_file(path: &str) -> RegularFile {
     }
 }
 
-fn load_elf(path: &str) -> u64 {
+fn load_elf(path: &str) -> Result<u64, Elf64Error> {
     let mut file = read_file(path);
 
     let file_info = file.get_boxed_info::<FileInfo>().unwrap();
@@ -125,7 +168,12 @@ fn load_elf(path: &str) -> u64 {
     file.read(&mut buf).unwrap();
 
     // load elf
-    let elf = Elf64::new(&buf).unwrap();
+    let elf = Elf64::new(&buf)?;
+
+    // the kernel is loaded at the linked address
+    if elf.header().elf_type() != Type::Executable {
+        return Err(Elf64Error::UnsupportedTypeError(elf.header().elf_type()));
+    }
 
     let mut dest_start = usize::MAX;
     let mut dest_end = 0;
@@ -163,7 +211,7 @@ fn load_elf(path: &str) -> u64 {
     }
 
     info!("Loaded ELF at: 0x{:x}", dest_start);
-    elf.header().entry_point
+    Ok(elf.header().entry_point)
 }
 
 fn load_initramfs(path: &str) -> (u64, usize) {
@@ -6,6 +6,12 @@ use core::mem::size_of;
 
 const MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
 
+// dynamic section tags
+const DT_NULL: i64 = 0;
+const DT_RELA: i64 = 7;
+const DT_RELASZ: i64 = 8;
+const DT_RELAENT: i64 = 9;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Class {
     Bit32,
@@ -220,6 +226,14 @@ impl Elf64ProgramHeader {
     pub fn flags(&self) -> SegmentFlags {
         self.flags.into()
     }
+
+    pub fn is_executable(&self) -> bool {
+        (self.flags & 0x1) != 0
+    }
+
+    pub fn is_writable(&self) -> bool {
+        (self.flags & 0x2) != 0
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -294,9 +308,59 @@ impl Elf64SectionHeader {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationType {
+    None,
+    Relative,
+    Other(u32),
+}
+
+impl From<u32> for RelocationType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::None,
+            8 => Self::Relative,
+            x => Self::Other(x),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[repr(C)]
+pub struct Elf64Rela {
+    pub offset: u64,
+    info: u64,
+    pub addend: i64,
+}
+
+impl Elf64Rela {
+    pub fn relocation_type(&self) -> RelocationType {
+        ((self.info & 0xffff_ffff) as u32).into()
+    }
+
+    pub fn symbol_index(&self) -> u32 {
+        (self.info >> 32) as u32
+    }
+}
+
+#[derive(Debug)]
+#[repr(C)]
+struct Elf64Dyn {
+    tag: i64,
+    value: u64,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Elf64Error {
     InvalidMagicNumberError,
+    TooShortDataError(usize),
+    UnsupportedClassError(Class),
+    UnsupportedDataError(Data),
+    UnsupportedMachineError(Machine),
+    UnsupportedTypeError(Type),
+    InvalidProgramHeadersError,
+    InvalidDynamicSegmentError,
+    UnsupportedRelocationTypeError(RelocationType),
 }
 
 #[derive(Debug)]
@@ -305,13 +369,42 @@ pub struct Elf64<'a> {
 }
 
 impl<'a> Elf64<'a> {
+    // only x86-64 little-endian executables (including PIE) are supported
     pub fn new(data: &'a [u8]) -> Result<Self, Elf64Error> {
+        if data.len() < size_of::<Elf64Header>() {
+            return Err(Elf64Error::TooShortDataError(data.len()));
+        }
+
         let header = unsafe { &*(data.as_ptr() as *const Elf64Header) };
 
         if !header.is_valid() {
             return Err(Elf64Error::InvalidMagicNumberError);
         }
 
+        if header.class() != Class::Bit64 {
+            return Err(Elf64Error::UnsupportedClassError(header.class()));
+        }
+
+        if header.data() != Data::LittleEndian {
+            return Err(Elf64Error::UnsupportedDataError(header.data()));
+        }
+
+        if header.machine() != Machine::X8664 {
+            return Err(Elf64Error::UnsupportedMachineError(header.machine()));
+        }
+
+        if header.elf_type() != Type::Executable && header.elf_type() != Type::Shared {
+            return Err(Elf64Error::UnsupportedTypeError(header.elf_type()));
+        }
+
+        let ph_end = (header.ph_num as u64)
+            .checked_mul(size_of::<Elf64ProgramHeader>() as u64)
+            .and_then(|len| len.checked_add(header.ph_offset));
+        match ph_end {
+            Some(end) if end <= data.len() as u64 => (),
+            _ => return Err(Elf64Error::InvalidProgramHeadersError),
+        }
+
         Ok(Self { data })
     }
 
@@ -425,6 +518,102 @@ impl<'a> Elf64<'a> {
         Some(&self.data[offset..offset + file_size])
     }
 
+    // path of the dynamic linker (PT_INTERP)
+    pub fn interpreter(&self) -> Option<&str> {
+        let ph = self
+            .program_headers()
+            .into_iter()
+            .find(|ph| ph.segment_type() == SegmentType::Interpreter)?;
+        let offset = ph.offset as usize;
+        let data = self
+            .data
+            .get(offset..offset.checked_add(ph.file_size as usize)?)?;
+
+        core::str::from_utf8(data)
+            .ok()
+            .map(|s| s.trim_end_matches('\0'))
+    }
+
+    // relocation entries referred by DT_RELA of the dynamic segment
+    pub fn relocations(&self) -> Result<Vec<&Elf64Rela>, Elf64Error> {
+        let dynamic_ph = match self
+            .program_headers()
+            .into_iter()
+            .find(|ph| ph.segment_type() == SegmentType::Dynamic)
+        {
+            Some(ph) => ph,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut rela_addr = None;
+        let mut rela_size = 0;
+        let mut rela_entry_size = size_of::<Elf64Rela>() as u64;
+
+        let dyn_offset = dynamic_ph.offset as usize;
+        let dyn_len = dynamic_ph.file_size as usize / size_of::<Elf64Dyn>();
+        match dyn_offset.checked_add(dyn_len * size_of::<Elf64Dyn>()) {
+            Some(end) if end <= self.data.len() => (),
+            _ => return Err(Elf64Error::InvalidDynamicSegmentError),
+        }
+
+        for i in 0..dyn_len {
+            let entry = unsafe {
+                &*(self
+                    .data
+                    .as_ptr()
+                    .add(dyn_offset + size_of::<Elf64Dyn>() * i)
+                    as *const Elf64Dyn)
+            };
+
+            match entry.tag {
+                DT_NULL => break,
+                DT_RELA => rela_addr = Some(entry.value),
+                DT_RELASZ => rela_size = entry.value,
+                DT_RELAENT => rela_entry_size = entry.value,
+                _ => (),
+            }
+        }
+
+        let rela_addr = match rela_addr {
+            Some(addr) => addr,
+            None => return Ok(Vec::new()),
+        };
+
+        if rela_entry_size < size_of::<Elf64Rela>() as u64 {
+            return Err(Elf64Error::InvalidDynamicSegmentError);
+        }
+
+        let rela_offset = self
+            .virt_addr_to_offset(rela_addr)
+            .ok_or(Elf64Error::InvalidDynamicSegmentError)?;
+        match rela_offset.checked_add(rela_size) {
+            Some(end) if end <= self.data.len() as u64 => (),
+            _ => return Err(Elf64Error::InvalidDynamicSegmentError),
+        }
+
+        let mut relas = Vec::new();
+        for i in 0..rela_size / rela_entry_size {
+            let rela = unsafe {
+                &*(self
+                    .data
+                    .as_ptr()
+                    .add((rela_offset + rela_entry_size * i) as usize)
+                    as *const Elf64Rela)
+            };
+            relas.push(rela);
+        }
+
+        Ok(relas)
+    }
+
+    fn virt_addr_to_offset(&self, virt_addr: u64) -> Option<u64> {
+        self.program_headers()
+            .into_iter()
+            .filter(|ph| ph.segment_type() == SegmentType::Load)
+            .find(|ph| virt_addr >= ph.virt_addr && virt_addr < ph.virt_addr + ph.file_size)
+            .map(|ph| ph.offset + (virt_addr - ph.virt_addr))
+    }
+
     fn string_table(&self) -> Option<&[u8]> {
         let strtab_section_header = self
             .section_headers()
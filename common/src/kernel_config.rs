@@ -1,7 +1,109 @@
-#[derive(Debug)]
+use core::{net::Ipv4Addr, str::FromStr};
+
+const CMDLINE_INIT_CWD_PATH_KEY: &str = "cwd";
+const CMDLINE_INIT_APP_EXEC_ARGS_KEY: &str = "init";
+const CMDLINE_FONT_PATH_KEY: &str = "font";
+const CMDLINE_LOG_LEVEL_KEY: &str = "loglevel";
+const CMDLINE_IPV4_ADDR_KEY: &str = "ip";
+const CMDLINE_IPV4_NETMASK_KEY: &str = "netmask";
+const CMDLINE_IPV4_GATEWAY_KEY: &str = "gateway";
+const CMDLINE_DNS_SERVER_KEY: &str = "dns";
+const CMDLINE_NONE_VALUE: &str = "none";
+
+// QEMU user network
+pub const DEFAULT_IPV4_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 15);
+pub const DEFAULT_IPV4_NETMASK: Ipv4Addr = Ipv4Addr::new(255, 255, 255, 0);
+pub const DEFAULT_IPV4_GATEWAY: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+pub const DEFAULT_DNS_SERVER: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            "trace" => Ok(Self::Trace),
+            _ => Err(()),
+        }
+    }
+}
+
+// "key=value" tokens separated by whitespaces, values can be quoted by '"'
+pub struct CmdlineTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> CmdlineTokens<'a> {
+    pub fn new(cmdline: &'a str) -> Self {
+        Self { rest: cmdline }
+    }
+}
+
+impl<'a> Iterator for CmdlineTokens<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.rest = self.rest.trim_start();
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let token_end = self
+                .rest
+                .find(char::is_whitespace)
+                .unwrap_or(self.rest.len());
+            let (key, value) = match self.rest[..token_end].split_once('=') {
+                Some(kv) => kv,
+                // not a "key=value" token
+                None => {
+                    self.rest = &self.rest[token_end..];
+                    continue;
+                }
+            };
+
+            // quoted value can contain whitespaces
+            if value.starts_with('"') {
+                let value_start = key.len() + 2;
+                let value_len = self.rest[value_start..]
+                    .find('"')
+                    .unwrap_or(self.rest.len() - value_start);
+                let value = &self.rest[value_start..value_start + value_len];
+                self.rest = self.rest.get(value_start + value_len + 1..).unwrap_or("");
+                return Some((key, value));
+            }
+
+            self.rest = &self.rest[token_end..];
+            return Some((key, value));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct KernelConfig<'a> {
     pub init_cwd_path: &'a str,
     pub init_app_exec_args: Option<&'a str>,
+    pub font_path: Option<&'a str>, // PSF1/PSF2 font
+    pub log_level: LogLevel,
+    pub ipv4_addr: Ipv4Addr,
+    pub ipv4_netmask: Ipv4Addr,
+    pub ipv4_gateway: Ipv4Addr,
+    pub dns_server: Ipv4Addr,
+    pub cmdline: Option<&'a str>,
 }
 
 impl Default for KernelConfig<'_> {
@@ -9,6 +111,74 @@ impl Default for KernelConfig<'_> {
         Self {
             init_cwd_path: "/",
             init_app_exec_args: None,
+            font_path: None,
+            log_level: LogLevel::Trace,
+            ipv4_addr: DEFAULT_IPV4_ADDR,
+            ipv4_netmask: DEFAULT_IPV4_NETMASK,
+            ipv4_gateway: DEFAULT_IPV4_GATEWAY,
+            dns_server: DEFAULT_DNS_SERVER,
+            cmdline: None,
+        }
+    }
+}
+
+impl<'a> KernelConfig<'a> {
+    // the last token wins
+    pub fn cmdline_value(&self, key: &str) -> Option<&'a str> {
+        CmdlineTokens::new(self.cmdline?)
+            .filter(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+            .last()
+    }
+
+    // override the fields by the cmdline, unknown keys and invalid values are ignored
+    // "init=none" and "font=none" clear the optional fields
+    pub fn with_cmdline(&self) -> Self {
+        let mut config = self.clone();
+        let cmdline = match self.cmdline {
+            Some(cmdline) => cmdline,
+            None => return config,
+        };
+
+        let optional = |value: &'a str| match value {
+            CMDLINE_NONE_VALUE => None,
+            v => Some(v),
+        };
+
+        for (key, value) in CmdlineTokens::new(cmdline) {
+            match key {
+                CMDLINE_INIT_CWD_PATH_KEY if !value.is_empty() => config.init_cwd_path = value,
+                CMDLINE_INIT_APP_EXEC_ARGS_KEY => config.init_app_exec_args = optional(value),
+                CMDLINE_FONT_PATH_KEY => config.font_path = optional(value),
+                CMDLINE_LOG_LEVEL_KEY => {
+                    if let Ok(log_level) = value.parse() {
+                        config.log_level = log_level;
+                    }
+                }
+                CMDLINE_IPV4_ADDR_KEY => {
+                    if let Ok(addr) = value.parse() {
+                        config.ipv4_addr = addr;
+                    }
+                }
+                CMDLINE_IPV4_NETMASK_KEY => {
+                    if let Ok(addr) = value.parse() {
+                        config.ipv4_netmask = addr;
+                    }
+                }
+                CMDLINE_IPV4_GATEWAY_KEY => {
+                    if let Ok(addr) = value.parse() {
+                        config.ipv4_gateway = addr;
+                    }
+                }
+                CMDLINE_DNS_SERVER_KEY => {
+                    if let Ok(addr) = value.parse() {
+                        config.dns_server = addr;
+                    }
+                }
+                _ => (),
+            }
         }
+
+        config
     }
 }
@@ -26,4 +26,64 @@ impl Default for Utsname {
 #[derive(Debug, Copy, Clone, Default)]
 pub struct Stat {
     pub size: u64, // file size (bytes)
+    pub is_dir: bool,
 }
+
+pub const DIRENT_NAME_LEN: usize = 256;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Dirent {
+    pub size: u64, // file size (bytes)
+    pub is_dir: bool,
+    pub name: [u8; DIRENT_NAME_LEN], // null terminated
+}
+
+impl Default for Dirent {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            is_dir: false,
+            name: [0; DIRENT_NAME_LEN],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HeapStats {
+    pub allocated: u64, // bytes
+    pub free: u64,
+    pub largest_free_block: u64,
+}
+
+pub const AF_INET: u16 = 2;
+pub const SOCK_DGRAM: u64 = 2;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SockaddrIn {
+    pub family: u16, // AF_INET
+    pub port: u16,   // network byte order
+    pub addr: [u8; 4],
+    pub zero: [u8; 8],
+}
+
+pub const CLOCK_REALTIME: u64 = 0;
+pub const CLOCK_MONOTONIC: u64 = 1;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Timespec {
+    pub sec: i64,
+    pub nsec: i64,
+}
+
+pub const PROT_NONE: u64 = 0x0;
+pub const PROT_READ: u64 = 0x1;
+pub const PROT_WRITE: u64 = 0x2;
+pub const PROT_EXEC: u64 = 0x4;
+
+pub const MAP_PRIVATE: u64 = 0x02;
+pub const MAP_FIXED: u64 = 0x10;
+pub const MAP_ANONYMOUS: u64 = 0x20;
@@ -1,17 +1,58 @@
 use super::addr::{IoPortAddress, VirtualAddress};
-use crate::error::Result;
+use crate::{
+    device::pci_bus::conf_space,
+    error::{Error, Result},
+};
 use alloc::vec::Vec;
-use core::{mem::size_of, ptr::read_unaligned, slice};
-use log::info;
+use core::{
+    mem::{offset_of, size_of},
+    ptr::{read_unaligned, write_volatile},
+    slice,
+};
+use log::{info, warn};
 
 static mut ACPI: Acpi = Acpi::new();
 
 const RSDP_SIGNATURE: [u8; 8] = *b"RSD PTR ";
 const XSDT_SIGNATURE: [u8; 4] = *b"XSDT";
 const FADT_SIGNATURE: [u8; 4] = *b"FACP";
+const DSDT_SIGNATURE: [u8; 4] = *b"DSDT";
+const MADT_SIGNATURE: [u8; 4] = *b"APIC";
+const HPET_SIGNATURE: [u8; 4] = *b"HPET";
+const MCFG_SIGNATURE: [u8; 4] = *b"MCFG";
 
 const PM_TIMER_FREQ: u32 = 3579545;
 
+const PM1_CNT_SCI_EN: u16 = 1 << 0;
+const PM1_CNT_SLP_TYP_SHIFT: u16 = 10;
+const PM1_CNT_SLP_TYP_MASK: u16 = 0x7 << PM1_CNT_SLP_TYP_SHIFT;
+const PM1_CNT_SLP_EN: u16 = 1 << 13;
+// SLP_TYP used if \_S5 was not found in the DSDT (QEMU)
+const FALLBACK_S5_SLP_TYP: u16 = 0;
+const ACPI_ENABLE_TIMEOUT_MS: u32 = 300;
+
+const FADT_FLAGS_RESET_REG_SUP: u32 = 1 << 10;
+const ADDR_SPACE_SYSTEM_MEMORY: u8 = 0;
+const ADDR_SPACE_SYSTEM_IO: u8 = 1;
+const ADDR_SPACE_PCI_CONF: u8 = 2;
+const RESET_CONTROL_REG_ADDR: IoPortAddress = IoPortAddress::new(0xcf9);
+const PS2_CMD_AND_STATE_REG_ADDR: IoPortAddress = IoPortAddress::new(0x64);
+const PS2_CMD_PULSE_RESET_LINE: u8 = 0xfe;
+const PS2_WAIT_READY_LOOP_CNT: usize = 100_000;
+
+// MADT interrupt controller structure types
+const MADT_TYPE_LOCAL_APIC: u8 = 0;
+const MADT_TYPE_IO_APIC: u8 = 1;
+const MADT_TYPE_LOCAL_X2APIC: u8 = 9;
+const MADT_LOCAL_APIC_FLAGS_ENABLED: u32 = 1 << 0;
+const MADT_LOCAL_APIC_FLAGS_ONLINE_CAPABLE: u32 = 1 << 1;
+
+// AML opcodes
+const AML_NAME_OP: u8 = 0x08;
+const AML_PACKAGE_OP: u8 = 0x12;
+const AML_BYTE_PREFIX: u8 = 0x0a;
+const AML_ROOT_CHAR: u8 = b'\\';
+
 #[derive(Debug)]
 #[repr(C, packed)]
 struct RootSystemDescriptorPointer {
@@ -70,28 +111,105 @@ impl DescriptionHeader {
         bytes.iter().fold(0u8, |acc, &b| acc.wrapping_add(b)) == 0
     }
 
-    fn entries_count(&self) -> usize {
-        (self.len as usize - size_of::<Self>()) / size_of::<u64>()
+    // length of the data following the table structure
+    fn data_len(&self, table_size: usize) -> Result<usize> {
+        (self.len as usize)
+            .checked_sub(table_size)
+            .ok_or(AcpiError::InvalidTableLengthError.into())
+    }
+
+    fn entries_count(&self) -> Result<usize> {
+        Ok(self.data_len(size_of::<Self>())? / size_of::<u64>())
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct GenericAddressStructure {
+    addr_space_id: u8,
+    bit_width: u8,
+    bit_offset: u8,
+    access_size: u8,
+    addr: u64,
+}
+
 #[derive(Debug)]
 #[repr(C, packed)]
 struct FixedAcpiDescriptionTable {
     header: DescriptionHeader,
-    reserved0: [u8; 40],
+    firmware_ctrl: u32,
+    dsdt: u32,
+    reserved0: [u8; 4],
+    smi_cmd: u32,
+    acpi_enable: u8,
+    acpi_disable: u8,
+    reserved1: [u8; 10],
+    pm1a_cnt_block: u32,
+    pm1b_cnt_block: u32,
+    reserved2: [u8; 4],
     pm_timer_block: u32,
-    reserved1: [u8; 32],
+    reserved3: [u8; 32],
     flags: u32,
-    reserved2: [u8; 160],
+    reset_reg: GenericAddressStructure,
+    reset_value: u8,
+    reserved4: [u8; 11],
+    x_dsdt: u64,
+    reserved5: [u8; 128],
+}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+struct MultipleApicDescriptionTable {
+    header: DescriptionHeader,
+    local_apic_addr: u32,
+    flags: u32,
+}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+struct HighPrecisionEventTimerTable {
+    header: DescriptionHeader,
+    event_timer_block_id: u32,
+    base_addr: GenericAddressStructure,
+    hpet_num: u8,
+    min_clock_tick: u16,
+    page_protection: u8,
+}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+struct PciExpressMemoryMappedConfigurationTable {
+    header: DescriptionHeader,
+    reserved: [u8; 8],
 }
 
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct McfgConfigurationSpaceBaseAddressEntry {
+    base_addr: u64,
+    pci_segment_group: u16,
+    start_bus_num: u8,
+    end_bus_num: u8,
+    reserved: u32,
+}
+
+// base address, start bus number, end bus number
+pub type PciEcamInfo = (u64, u8, u8);
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum AcpiError {
     InvalidSignatureError,
     InvalidRevisionError(u8),
     InvalidChecksumError,
+    InvalidTableLengthError,
     FixedAcpiDescriptionTableWasNotFound,
+    MultipleApicDescriptionTableWasNotFound,
+    IoApicWasNotFound,
+    HighPrecisionEventTimerTableWasNotFound,
+    PciExpressMemoryMappedConfigurationTableWasNotFound,
+    Pm1ControlBlockWasNotFound,
+    ResetRegisterIsNotSupported,
+    UnsupportedAddressSpaceError(u8),
     NotInitialized,
 }
 
@@ -147,12 +265,13 @@ impl Acpi {
         }
 
         // 4 bytes align
+        let entries_count = xsdt.entries_count()?;
         let u32_entries: &[u32] = unsafe {
             slice::from_raw_parts(
                 xsdt_virt_addr
                     .offset(size_of::<DescriptionHeader>())
                     .as_ptr(),
-                xsdt.entries_count() * 2,
+                entries_count * 2,
             )
         };
 
@@ -180,6 +299,280 @@ impl Acpi {
         Ok(fadt)
     }
 
+    fn madt(&self) -> Result<&MultipleApicDescriptionTable> {
+        let (_, xsdt_entries) = self.xsdt()?;
+
+        for entry_addr in xsdt_entries {
+            let entry_addr: VirtualAddress = entry_addr.into();
+            let entry = unsafe { &*(entry_addr.as_ptr() as *const MultipleApicDescriptionTable) };
+            if !entry.header.is_valid(MADT_SIGNATURE) {
+                continue;
+            }
+
+            if !entry.header.is_valid_checksum() {
+                return Err(AcpiError::InvalidChecksumError.into());
+            }
+
+            return Ok(entry);
+        }
+
+        Err(AcpiError::MultipleApicDescriptionTableWasNotFound.into())
+    }
+
+    fn hpet(&self) -> Result<&HighPrecisionEventTimerTable> {
+        let (_, xsdt_entries) = self.xsdt()?;
+
+        for entry_addr in xsdt_entries {
+            let entry_addr: VirtualAddress = entry_addr.into();
+            let entry = unsafe { &*(entry_addr.as_ptr() as *const HighPrecisionEventTimerTable) };
+            if !entry.header.is_valid(HPET_SIGNATURE) {
+                continue;
+            }
+
+            if !entry.header.is_valid_checksum() {
+                return Err(AcpiError::InvalidChecksumError.into());
+            }
+
+            return Ok(entry);
+        }
+
+        Err(AcpiError::HighPrecisionEventTimerTableWasNotFound.into())
+    }
+
+    fn mcfg(&self) -> Result<&PciExpressMemoryMappedConfigurationTable> {
+        let (_, xsdt_entries) = self.xsdt()?;
+
+        for entry_addr in xsdt_entries {
+            let entry_addr: VirtualAddress = entry_addr.into();
+            let entry = unsafe {
+                &*(entry_addr.as_ptr() as *const PciExpressMemoryMappedConfigurationTable)
+            };
+            if !entry.header.is_valid(MCFG_SIGNATURE) {
+                continue;
+            }
+
+            if !entry.header.is_valid_checksum() {
+                return Err(AcpiError::InvalidChecksumError.into());
+            }
+
+            return Ok(entry);
+        }
+
+        Err(AcpiError::PciExpressMemoryMappedConfigurationTableWasNotFound.into())
+    }
+
+    // ECAM of PCI segment group 0
+    fn pci_ecam(&self) -> Result<PciEcamInfo> {
+        let mcfg = self.mcfg()?;
+        let header_size = size_of::<PciExpressMemoryMappedConfigurationTable>();
+        let entry_size = size_of::<McfgConfigurationSpaceBaseAddressEntry>();
+        let entries_count = (mcfg.header.len as usize).saturating_sub(header_size) / entry_size;
+
+        for i in 0..entries_count {
+            let entry = unsafe {
+                read_unaligned(
+                    (mcfg as *const _ as *const u8).add(header_size + i * entry_size)
+                        as *const McfgConfigurationSpaceBaseAddressEntry,
+                )
+            };
+
+            if entry.pci_segment_group == 0 {
+                return Ok((entry.base_addr, entry.start_bus_num, entry.end_bus_num));
+            }
+        }
+
+        Err(AcpiError::PciExpressMemoryMappedConfigurationTableWasNotFound.into())
+    }
+
+    // local APIC IDs, I/O APIC address
+    fn interrupt_controllers(&self) -> Result<(Vec<u32>, Option<u32>)> {
+        let madt = self.madt()?;
+        let entries_len = madt
+            .header
+            .data_len(size_of::<MultipleApicDescriptionTable>())?;
+        let entries = unsafe {
+            slice::from_raw_parts(
+                (madt as *const MultipleApicDescriptionTable as *const u8)
+                    .add(size_of::<MultipleApicDescriptionTable>()),
+                entries_len,
+            )
+        };
+
+        Ok(parse_madt_entries(entries))
+    }
+
+    fn dsdt(&self) -> Result<&DescriptionHeader> {
+        let fadt = self
+            .fadt()?
+            .ok_or(AcpiError::FixedAcpiDescriptionTableWasNotFound)?;
+        let fadt_len = fadt.header.len as usize;
+        let x_dsdt = fadt.x_dsdt;
+
+        // X_DSDT is not present in ACPI 1.0 FADT
+        let dsdt_virt_addr: VirtualAddress =
+            if fadt_len >= size_of::<FixedAcpiDescriptionTable>() && x_dsdt != 0 {
+                x_dsdt.into()
+            } else {
+                (fadt.dsdt as u64).into()
+            };
+        let dsdt = unsafe { &*(dsdt_virt_addr.as_ptr() as *const DescriptionHeader) };
+
+        if !dsdt.is_valid(DSDT_SIGNATURE) {
+            return Err(AcpiError::InvalidSignatureError.into());
+        }
+
+        if !dsdt.is_valid_checksum() {
+            return Err(AcpiError::InvalidChecksumError.into());
+        }
+
+        Ok(dsdt)
+    }
+
+    // definition block following the DSDT header
+    fn dsdt_aml(&self) -> Result<&[u8]> {
+        let dsdt = self.dsdt()?;
+        let aml_len = dsdt.data_len(size_of::<DescriptionHeader>())?;
+
+        Ok(unsafe {
+            slice::from_raw_parts(
+                (dsdt as *const DescriptionHeader as *const u8).add(size_of::<DescriptionHeader>()),
+                aml_len,
+            )
+        })
+    }
+
+    // SLP_TYPa, SLP_TYPb
+    fn s5_sleep_type(&self) -> (u16, u16) {
+        let aml = match self.dsdt_aml() {
+            Ok(aml) => aml,
+            Err(err) => {
+                warn!("acpi: Failed to read DSDT: {:?}", err);
+                &[]
+            }
+        };
+
+        parse_s5_sleep_type(aml).unwrap_or_else(|| {
+            warn!("acpi: \\_S5 was not found, use fallback SLP_TYP");
+            (FALLBACK_S5_SLP_TYP, FALLBACK_S5_SLP_TYP)
+        })
+    }
+
+    fn enable_acpi_mode(&self, fadt: &FixedAcpiDescriptionTable) -> Result<()> {
+        let pm1a_cnt: IoPortAddress = fadt.pm1a_cnt_block.into();
+        let smi_cmd = fadt.smi_cmd;
+        let acpi_enable = fadt.acpi_enable;
+
+        // already enabled, or hardware-reduced ACPI
+        if pm1a_cnt.in16() & PM1_CNT_SCI_EN != 0 || smi_cmd == 0 || acpi_enable == 0 {
+            return Ok(());
+        }
+
+        IoPortAddress::new(smi_cmd).out8(acpi_enable);
+        for _ in 0..ACPI_ENABLE_TIMEOUT_MS {
+            if pm1a_cnt.in16() & PM1_CNT_SCI_EN != 0 {
+                return Ok(());
+            }
+            self.pm_timer_wait_ms(1)?;
+        }
+
+        Err(Error::Failed("Failed to enable ACPI mode"))
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        let fadt = self
+            .fadt()?
+            .ok_or(AcpiError::FixedAcpiDescriptionTableWasNotFound)?;
+        let pm1a_cnt_block = fadt.pm1a_cnt_block;
+        let pm1b_cnt_block = fadt.pm1b_cnt_block;
+
+        if pm1a_cnt_block == 0 {
+            return Err(AcpiError::Pm1ControlBlockWasNotFound.into());
+        }
+
+        let (slp_typ_a, slp_typ_b) = self.s5_sleep_type();
+        self.enable_acpi_mode(fadt)?;
+
+        for (cnt_block, slp_typ) in [(pm1a_cnt_block, slp_typ_a), (pm1b_cnt_block, slp_typ_b)] {
+            if cnt_block == 0 {
+                continue;
+            }
+
+            let pm1_cnt: IoPortAddress = cnt_block.into();
+            let value = pm1_cnt.in16() & !PM1_CNT_SLP_TYP_MASK;
+            pm1_cnt.out16(value | (slp_typ << PM1_CNT_SLP_TYP_SHIFT) | PM1_CNT_SLP_EN);
+        }
+
+        // give the chipset some time to power off
+        self.pm_timer_wait_ms(100)?;
+        Err(Error::Failed("Failed to shutdown"))
+    }
+
+    fn reset_by_reset_reg(&self) -> Result<()> {
+        let fadt = self
+            .fadt()?
+            .ok_or(AcpiError::FixedAcpiDescriptionTableWasNotFound)?;
+        let flags = fadt.flags;
+
+        // RESET_REG is not present in ACPI 1.0 FADT
+        if (fadt.header.len as usize) <= offset_of!(FixedAcpiDescriptionTable, reset_value)
+            || flags & FADT_FLAGS_RESET_REG_SUP == 0
+        {
+            return Err(AcpiError::ResetRegisterIsNotSupported.into());
+        }
+
+        let reset_reg = fadt.reset_reg;
+        let reset_value = fadt.reset_value;
+        let addr = reset_reg.addr;
+
+        match reset_reg.addr_space_id {
+            ADDR_SPACE_SYSTEM_MEMORY => {
+                let virt_addr: VirtualAddress = addr.into();
+                unsafe { write_volatile(virt_addr.as_ptr_mut::<u8>(), reset_value) };
+            }
+            ADDR_SPACE_SYSTEM_IO => IoPortAddress::new(addr as u32).out8(reset_value),
+            // device, function and offset on bus 0
+            ADDR_SPACE_PCI_CONF => {
+                let device = ((addr >> 32) & 0xffff) as usize;
+                let func = ((addr >> 16) & 0xffff) as usize;
+                let offset = (addr & 0xffff) as usize;
+                let shift = (offset % 4) * 8;
+
+                let data = conf_space::read_conf_space(0, device, func, offset & !0x3)?;
+                let data = (data & !(0xff << shift)) | (reset_value as u32) << shift;
+                conf_space::write_conf_space(0, device, func, offset & !0x3, data)?;
+            }
+            id => return Err(AcpiError::UnsupportedAddressSpaceError(id).into()),
+        }
+
+        Ok(())
+    }
+
+    fn reboot(&self) -> Result<()> {
+        match self.reset_by_reset_reg() {
+            Ok(()) => {
+                let _ = self.pm_timer_wait_ms(100);
+                warn!("acpi: Failed to reboot by the reset register");
+            }
+            Err(err) => warn!("acpi: Reset register is not available: {:?}", err),
+        }
+
+        // reset control register, full reset
+        RESET_CONTROL_REG_ADDR.out8(0x02);
+        RESET_CONTROL_REG_ADDR.out8(0x06);
+        let _ = self.pm_timer_wait_ms(100);
+
+        // pulse the CPU reset line via the keyboard controller
+        for _ in 0..PS2_WAIT_READY_LOOP_CNT {
+            if PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x2 == 0 {
+                break;
+            }
+        }
+        PS2_CMD_AND_STATE_REG_ADDR.out8(PS2_CMD_PULSE_RESET_LINE);
+        let _ = self.pm_timer_wait_ms(100);
+
+        Err(Error::Failed("Failed to reboot"))
+    }
+
     // addr, bit width == 32
     fn pm_timer_io_addr(&self) -> Result<(IoPortAddress, bool)> {
         let fadt = self
@@ -210,9 +603,169 @@ pub fn init(rsdp_virt_addr: VirtualAddress) -> Result<()> {
     unsafe { ACPI.init(rsdp_virt_addr) }?;
     info!("acpi: Initialized");
 
+    match cpu_apic_ids() {
+        Ok(ids) => info!(
+            "acpi: Found {} CPU(s) (local APIC IDs: {:?})",
+            ids.len(),
+            ids
+        ),
+        Err(err) => warn!("acpi: Failed to enumerate CPUs: {:?}", err),
+    }
+
     Ok(())
 }
 
 pub fn pm_timer_wait_ms(ms: u32) -> Result<()> {
     unsafe { ACPI.pm_timer_wait_ms(ms) }
 }
+
+// usable processors only
+pub fn cpu_apic_ids() -> Result<Vec<u32>> {
+    let (ids, _) = unsafe { ACPI.interrupt_controllers() }?;
+    Ok(ids)
+}
+
+// first I/O APIC
+pub fn io_apic_addr() -> Result<u32> {
+    let (_, addr) = unsafe { ACPI.interrupt_controllers() }?;
+    addr.ok_or(AcpiError::IoApicWasNotFound.into())
+}
+
+// physical address of the first HPET event timer block
+pub fn hpet_base_addr() -> Result<u64> {
+    let hpet = unsafe { ACPI.hpet() }?;
+    let base_addr = hpet.base_addr;
+
+    if base_addr.addr_space_id != ADDR_SPACE_SYSTEM_MEMORY {
+        return Err(AcpiError::UnsupportedAddressSpaceError(base_addr.addr_space_id).into());
+    }
+
+    Ok(base_addr.addr)
+}
+
+pub fn pci_ecam() -> Result<PciEcamInfo> {
+    unsafe { ACPI.pci_ecam() }
+}
+
+// return only if failed
+pub fn shutdown() -> Result<()> {
+    info!("acpi: Shutting down...");
+    unsafe { ACPI.shutdown() }
+}
+
+// return only if failed
+pub fn reboot() -> Result<()> {
+    info!("acpi: Rebooting...");
+    unsafe { ACPI.reboot() }
+}
+
+// search "Name (_S5, Package () { SLP_TYPa, SLP_TYPb, ... })" in the AML
+fn parse_s5_sleep_type(aml: &[u8]) -> Option<(u16, u16)> {
+    let i = aml.windows(4).enumerate().position(|(i, w)| {
+        w == b"_S5_"
+            && i >= 1
+            && (aml[i - 1] == AML_NAME_OP
+                || (i >= 2 && aml[i - 2] == AML_NAME_OP && aml[i - 1] == AML_ROOT_CHAR))
+            && aml.get(i + 4) == Some(&AML_PACKAGE_OP)
+    })?;
+
+    // skip PkgLength and NumElements
+    let pkg_len_bytes = ((*aml.get(i + 5)? & 0xc0) >> 6) as usize + 1;
+    let mut data = aml.get(i + 5 + pkg_len_bytes + 1..)?.iter();
+
+    let mut read_integer = || -> Option<u16> {
+        let mut value = *data.next()?;
+        if value == AML_BYTE_PREFIX {
+            value = *data.next()?;
+        }
+        Some(value as u16 & 0x7)
+    };
+
+    let slp_typ_a = read_integer()?;
+    let slp_typ_b = read_integer()?;
+    Some((slp_typ_a, slp_typ_b))
+}
+
+// type, length, data...
+fn parse_madt_entries(entries: &[u8]) -> (Vec<u32>, Option<u32>) {
+    let read_u32 = |data: &[u8], offset: usize| -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let is_usable = |flags: u32| {
+        flags & (MADT_LOCAL_APIC_FLAGS_ENABLED | MADT_LOCAL_APIC_FLAGS_ONLINE_CAPABLE) != 0
+    };
+
+    let mut apic_ids = Vec::new();
+    let mut io_apic_addr = None;
+    let mut offset = 0;
+
+    while offset + 2 <= entries.len() {
+        let ty = entries[offset];
+        let len = entries[offset + 1] as usize;
+        if len < 2 || offset + len > entries.len() {
+            break;
+        }
+        let entry = &entries[offset..offset + len];
+
+        match ty {
+            MADT_TYPE_LOCAL_APIC if len >= 8 => {
+                if read_u32(entry, 4).is_some_and(is_usable) {
+                    apic_ids.push(entry[3] as u32);
+                }
+            }
+            MADT_TYPE_IO_APIC if len >= 12 && io_apic_addr.is_none() => {
+                io_apic_addr = read_u32(entry, 4);
+            }
+            MADT_TYPE_LOCAL_X2APIC if len >= 16 => {
+                if read_u32(entry, 8).is_some_and(is_usable) {
+                    if let Some(id) = read_u32(entry, 4) {
+                        apic_ids.push(id);
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        offset += len;
+    }
+
+    (apic_ids, io_apic_addr)
+}
+
+#[test_case]
+fn test_parse_madt_entries() {
+    let entries = [
+        // local APIC, processor 0, APIC ID 0, enabled
+        0x00, 0x08, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+        // local APIC, processor 1, APIC ID 2, disabled
+        0x00, 0x08, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00,
+        // I/O APIC, ID 0, address 0xfec00000, GSI base 0
+        0x01, 0x0c, 0x00, 0x00, 0x00, 0x00, 0xc0, 0xfe, 0x00, 0x00, 0x00, 0x00,
+        // interrupt source override
+        0x02, 0x0a, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // local x2APIC, ID 0x100, enabled
+        0x09, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00,
+        0x00,
+    ];
+
+    let (apic_ids, io_apic_addr) = parse_madt_entries(&entries);
+    assert_eq!(apic_ids, vec![0, 0x100]);
+    assert_eq!(io_apic_addr, Some(0xfec0_0000));
+}
+
+#[test_case]
+fn test_parse_s5_sleep_type() {
+    // Name (\_S5, Package (0x04) { 0x05, Zero, Zero, Zero })
+    let aml = [
+        0x10, 0x08, 0x08, b'\\', b'_', b'S', b'5', b'_', 0x12, 0x07, 0x04, 0x0a, 0x05, 0x00, 0x00,
+        0x00,
+    ];
+    assert_eq!(parse_s5_sleep_type(&aml), Some((5, 0)));
+
+    // Name (_S5, Package (0x02) { One, One })
+    let aml = [0x08, b'_', b'S', b'5', b'_', 0x12, 0x04, 0x02, 0x01, 0x01];
+    assert_eq!(parse_s5_sleep_type(&aml), Some((1, 1)));
+
+    assert_eq!(parse_s5_sleep_type(b"_S5_"), None);
+}
@@ -0,0 +1,121 @@
+use crate::{
+    arch::addr::VirtualAddress,
+    error::{Error, Result},
+};
+use core::sync::atomic::{AtomicU64, Ordering};
+use log::info;
+
+const IO_REG_SELECT_OFFSET: usize = 0x00;
+const IO_WINDOW_OFFSET: usize = 0x10;
+
+const IO_APIC_REG_VER: u32 = 0x01;
+const IO_APIC_REG_REDIRECTION_TABLE_BASE: u32 = 0x10;
+
+const REDIRECTION_ENTRY_MASKED: u64 = 1 << 16;
+
+// 0 if not initialized
+static IO_APIC_BASE_ADDR: AtomicU64 = AtomicU64::new(0);
+
+// fixed delivery mode, physical destination, edge triggered, active high
+#[derive(Debug, Clone, Copy)]
+struct RedirectionEntry(u64);
+
+impl RedirectionEntry {
+    fn new(vec_num: u8, dest_apic_id: u8, masked: bool) -> Self {
+        let mut value = vec_num as u64 | (dest_apic_id as u64) << 56;
+        if masked {
+            value |= REDIRECTION_ENTRY_MASKED;
+        }
+
+        Self(value)
+    }
+}
+
+fn base_addr() -> Result<VirtualAddress> {
+    match IO_APIC_BASE_ADDR.load(Ordering::Relaxed) {
+        0 => Err(Error::Failed("I/O APIC is not initialized")),
+        addr => Ok(VirtualAddress::new(addr)),
+    }
+}
+
+fn read_reg(base_addr: VirtualAddress, reg: u32) -> u32 {
+    unsafe {
+        (base_addr.offset(IO_REG_SELECT_OFFSET).as_ptr_mut() as *mut u32).write_volatile(reg);
+        (base_addr.offset(IO_WINDOW_OFFSET).as_ptr() as *const u32).read_volatile()
+    }
+}
+
+fn write_reg(base_addr: VirtualAddress, reg: u32, value: u32) {
+    unsafe {
+        (base_addr.offset(IO_REG_SELECT_OFFSET).as_ptr_mut() as *mut u32).write_volatile(reg);
+        (base_addr.offset(IO_WINDOW_OFFSET).as_ptr_mut() as *mut u32).write_volatile(value);
+    }
+}
+
+fn max_redirection_entry(base_addr: VirtualAddress) -> u8 {
+    (read_reg(base_addr, IO_APIC_REG_VER) >> 16) as u8
+}
+
+fn write_redirection_entry(base_addr: VirtualAddress, irq: u8, entry: RedirectionEntry) {
+    let reg = IO_APIC_REG_REDIRECTION_TABLE_BASE + irq as u32 * 2;
+    // keep the entry masked while the destination is updated
+    write_reg(base_addr, reg, REDIRECTION_ENTRY_MASKED as u32);
+    write_reg(base_addr, reg + 1, (entry.0 >> 32) as u32);
+    write_reg(base_addr, reg, entry.0 as u32);
+}
+
+// mask all redirection entries
+pub fn init(base_addr: u32) -> Result<()> {
+    if base_addr == 0 {
+        return Err(Error::Failed("Invalid I/O APIC address"));
+    }
+
+    let base_addr = VirtualAddress::new(base_addr as u64);
+    let max_entry = max_redirection_entry(base_addr);
+    for irq in 0..=max_entry {
+        write_redirection_entry(base_addr, irq, RedirectionEntry::new(0, 0, true));
+    }
+
+    IO_APIC_BASE_ADDR.store(base_addr.get(), Ordering::Relaxed);
+    info!(
+        "io-apic: Initialized (base: 0x{:x}, {} entries)",
+        base_addr.get(),
+        max_entry as usize + 1
+    );
+
+    Ok(())
+}
+
+// ISA IRQs are assumed to be identity mapped to GSIs (no interrupt source overrides)
+pub fn set_redirection(irq: u8, vec_num: u8, dest_apic_id: u8) -> Result<()> {
+    let base_addr = base_addr()?;
+    if irq > max_redirection_entry(base_addr) {
+        return Err(Error::Failed("Invalid IRQ number"));
+    }
+
+    write_redirection_entry(
+        base_addr,
+        irq,
+        RedirectionEntry::new(vec_num, dest_apic_id, false),
+    );
+    Ok(())
+}
+
+pub fn mask(irq: u8) -> Result<()> {
+    let base_addr = base_addr()?;
+    if irq > max_redirection_entry(base_addr) {
+        return Err(Error::Failed("Invalid IRQ number"));
+    }
+
+    write_redirection_entry(base_addr, irq, RedirectionEntry::new(0, 0, true));
+    Ok(())
+}
+
+#[test_case]
+fn test_redirection_entry() {
+    let entry = RedirectionEntry::new(0x21, 1, false);
+    assert_eq!(entry.0, 0x0100_0000_0000_0021);
+
+    let entry = RedirectionEntry::new(0x2c, 0, true);
+    assert_eq!(entry.0, 0x0000_0000_0001_002c);
+}
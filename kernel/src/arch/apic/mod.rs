@@ -1,8 +1,15 @@
 use crate::arch::addr::*;
 
+pub mod io_apic;
+
 const LOCAL_APIC_REG_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00020);
+const END_OF_INT_REG_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee000b0);
 
 pub fn local_apic_id() -> u8 {
     let reg = unsafe { &*(LOCAL_APIC_REG_VIRT_ADDR.as_ptr() as *const u32) };
     (reg >> 24) as u8
 }
+
+pub fn notify_end_of_int() {
+    unsafe { (END_OF_INT_REG_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(0) };
+}
@@ -1,27 +1,42 @@
 use super::{
+    addr::VirtualAddress,
     gdt::*,
     register::{control::Cr3, Register},
 };
+use crate::{
+    error::Result,
+    mem::paging::{self, PAGE_SIZE},
+};
 use common::boot_info::BootInfo;
 use core::arch::asm;
 
 const KERNEL_STACK_SIZE: usize = 1024 * 1024;
 static KERNEL_STACK: KernelStack = KernelStack::new();
 
-#[repr(align(16))]
-struct KernelStack([u8; KERNEL_STACK_SIZE]);
+#[repr(C, align(4096))]
+struct KernelStack {
+    guard_page: [u8; PAGE_SIZE],
+    stack: [u8; KERNEL_STACK_SIZE],
+}
 
 impl KernelStack {
     const fn new() -> Self {
-        Self([0; KERNEL_STACK_SIZE])
+        Self {
+            guard_page: [0; PAGE_SIZE],
+            stack: [0; KERNEL_STACK_SIZE],
+        }
     }
 
     fn len(&self) -> usize {
-        self.0.len()
+        self.stack.len()
     }
 
     fn as_ptr(&self) -> *const u8 {
-        self.0.as_ptr()
+        self.stack.as_ptr()
+    }
+
+    fn guard_page_virt_addr(&self) -> VirtualAddress {
+        (self.guard_page.as_ptr() as u64).into()
     }
 }
 
@@ -42,6 +57,15 @@ pub fn switch_kernel_stack(
     unreachable!();
 }
 
+// must be called after the kernel page table was created
+pub fn set_kernel_stack_guard_page() -> Result<()> {
+    paging::set_guard_page(KERNEL_STACK.guard_page_virt_addr())
+}
+
+pub fn is_kernel_stack_guard_page(virt_addr: VirtualAddress) -> bool {
+    virt_addr == KERNEL_STACK.guard_page_virt_addr()
+}
+
 // software context switch
 #[naked]
 extern "sysv64" fn switch_context(next_ctx: &Context, current_ctx: &Context) {
@@ -111,6 +135,41 @@ extern "sysv64" fn switch_context(next_ctx: &Context, current_ctx: &Context) {
     }
 }
 
+// fxsave64 / fxrstor64 area
+#[derive(Debug, Clone, Copy)]
+#[repr(C, align(16))]
+pub struct FpuContext([u8; 512]);
+
+impl FpuContext {
+    const FCW_DEFAULT: u16 = 0x037f; // mask all x87 exceptions
+    const MXCSR_DEFAULT: u32 = 0x1f80; // mask all SIMD exceptions
+
+    pub const fn new() -> Self {
+        let mut area = [0; 512];
+        let fcw = Self::FCW_DEFAULT.to_le_bytes();
+        area[0] = fcw[0];
+        area[1] = fcw[1];
+        let mxcsr = Self::MXCSR_DEFAULT.to_le_bytes();
+        area[24] = mxcsr[0];
+        area[25] = mxcsr[1];
+        area[26] = mxcsr[2];
+        area[27] = mxcsr[3];
+        Self(area)
+    }
+
+    pub fn save(&mut self) {
+        unsafe { asm!("fxsave64 [{}]", in(reg) self.0.as_mut_ptr()) }
+    }
+
+    pub fn restore(&self) {
+        unsafe { asm!("fxrstor64 [{}]", in(reg) self.0.as_ptr()) }
+    }
+
+    pub fn mxcsr(&self) -> u32 {
+        u32::from_le_bytes([self.0[24], self.0[25], self.0[26], self.0[27]])
+    }
+}
+
 #[derive(PartialEq, Eq)]
 pub enum ContextMode {
     Kernel,
@@ -145,7 +204,7 @@ pub struct Context {
     /* + 0xa8 */ pub r13: u64,
     /* + 0xb0 */ pub r14: u64,
     /* + 0xb8 */ pub r15: u64,
-    /* + 0xc0 */ pub fpu_context: [u8; 512],
+    /* + 0xc0 */ pub fpu_context: FpuContext,
 }
 
 impl Context {
@@ -175,7 +234,7 @@ impl Context {
             r13: 0,
             r14: 0,
             r15: 0,
-            fpu_context: [0; 512],
+            fpu_context: FpuContext::new(),
         }
     }
 
@@ -201,3 +260,52 @@ impl Context {
         switch_context(next_ctx, self);
     }
 }
+
+#[test_case]
+fn test_fpu_context_default() {
+    let fpu_context = FpuContext::new();
+    assert_eq!(fpu_context.mxcsr(), 0x1f80);
+}
+
+#[test_case]
+fn test_fpu_context_switch() {
+    fn set_and_save(fpu_context: &mut FpuContext, value: f64) {
+        unsafe {
+            asm!(
+                "movq xmm0, {}",
+                "fxsave64 [{}]",
+                in(reg) value.to_bits(),
+                in(reg) fpu_context.0.as_mut_ptr(),
+                out("xmm0") _
+            );
+        }
+    }
+
+    fn restore_and_get(fpu_context: &FpuContext) -> f64 {
+        let bits: u64;
+        unsafe {
+            asm!(
+                "fxrstor64 [{}]",
+                "movq {}, xmm0",
+                in(reg) fpu_context.0.as_ptr(),
+                out(reg) bits,
+                out("xmm0") _
+            );
+        }
+        f64::from_bits(bits)
+    }
+
+    let mut current = FpuContext::new();
+    current.save();
+
+    let mut task_a = FpuContext::new();
+    let mut task_b = FpuContext::new();
+    set_and_save(&mut task_a, 1.25);
+    set_and_save(&mut task_b, -3.5);
+
+    assert_eq!(restore_and_get(&task_a), 1.25);
+    assert_eq!(restore_and_get(&task_b), -3.5);
+    assert_eq!(restore_and_get(&task_a), 1.25);
+
+    current.restore();
+}
@@ -5,6 +5,8 @@ use crate::println;
 
 const CPUID_EAX_VENDOR_ID: u32 = 0;
 const CPUID_EAX_VERSION_INFO: u32 = 1;
+const CPUID_EAX_EXT_MAX: u32 = 0x80000000;
+const CPUID_EAX_EXT_FEATURE_INFO: u32 = 0x80000001;
 
 #[derive(Debug)]
 pub struct VersionInfo {
@@ -192,6 +194,17 @@ pub fn version_info() -> VersionInfo {
     VersionInfo::parse(eax, ebx, ecx, edx)
 }
 
+// execute disable bit of page table entries
+pub fn is_nx_supported() -> bool {
+    let (ext_max, _, _, _) = cpuid(CPUID_EAX_EXT_MAX);
+    if ext_max < CPUID_EAX_EXT_FEATURE_INFO {
+        return false;
+    }
+
+    let (_, _, _, edx) = cpuid(CPUID_EAX_EXT_FEATURE_INFO);
+    (edx & (1 << 20)) != 0
+}
+
 pub fn print_cpuid() {
     println!("vendor_id: {}", vendor_id());
     println!("version_info: {:?}", version_info());
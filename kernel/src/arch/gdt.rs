@@ -176,3 +176,16 @@ pub fn init() {
     info!("gdt: Initialized");
     info!("gdt: TSS initialized");
 }
+
+// application processors share the GDT, but not the TSS (no user mode on APs yet)
+pub fn init_ap() {
+    {
+        let gdt = unsafe { GDT.try_lock() }.unwrap();
+        let limit = (size_of::<GlobalDescriptorTable>() - 1) as u16;
+        let base = gdt.entries.as_ptr() as u64;
+        arch::lgdt(&arch::DescriptorTableArgs { limit, base });
+    }
+
+    segment::set_ds_es_fs_gs(0);
+    segment::set_ss_cs(KERNEL_MODE_SS_VALUE, KERNEL_MODE_CS_VALUE);
+}
@@ -1,19 +1,36 @@
-use super::{addr::*, task};
+use super::{
+    acpi,
+    addr::*,
+    apic::{self, io_apic},
+    context, task,
+    tss::FAULT_IST_INDEX,
+};
 use crate::{
     arch::{
         self,
         register::{control::Cr2, segment::Cs, Register},
     },
-    device,
+    debug,
     error::{Error, Result},
     mem::paging,
     util::mutex::Mutex,
 };
 use alloc::string::String;
-use core::{mem::size_of, panic};
+use core::{
+    mem::size_of,
+    panic,
+    sync::atomic::{AtomicBool, Ordering},
+};
 use log::*;
 
 static mut IDT: Mutex<InterruptDescriptorTable> = Mutex::new(InterruptDescriptorTable::new());
+static IS_IO_APIC_MODE: AtomicBool = AtomicBool::new(false);
+
+// set false to keep routing legacy IRQs through the 8259 PIC
+const USE_IO_APIC: bool = true;
+
+// 128 + SIGSEGV
+const USER_PAGE_FAULT_EXIT_STATUS: u64 = 139;
 
 // https://github.com/rust-osdev/x86_64/blob/master/src/structures/idt.rs
 #[repr(transparent)]
@@ -94,7 +111,7 @@ pub struct InterruptStackFrame {
 // idt
 const IDT_LEN: usize = 256;
 const _VEC_DIVIDE_ERR: usize = 0x00;
-const _VEC_DEBUG: usize = 0x01;
+const VEC_DEBUG: usize = 0x01;
 const _VEC_NMI_INT: usize = 0x02;
 const VEC_BREAKPOINT: usize = 0x03;
 const _VEC_OVERFLOW: usize = 0x04;
@@ -114,8 +131,13 @@ const _VEC_SIMD_FLOATING_POINT_EX: usize = 0x13;
 const _VEC_VIRT_EX: usize = 0x14;
 const _VEC_CTRL_PROTECTION_EX: usize = 0x15;
 
-pub const VEC_PS2_KBD: usize = 0x21; // ps/2 keyboard
-pub const VEC_PS2_MOUSE: usize = 0x2c; // ps/2 mouse
+// PIC IRQ0 - 15 are mapped to 0x20 - 0x2f
+const PIC_VEC_BASE: usize = 0x20;
+const PIC_IRQ_LEN: u8 = 16;
+const DYN_VEC_START: usize = 0x30;
+
+pub const IRQ_PS2_KBD: u8 = 1; // ps/2 keyboard
+pub const IRQ_PS2_MOUSE: u8 = 12; // ps/2 mouse
 
 const MASTER_PIC_ADDR: IoPortAddress = IoPortAddress::new(0x20);
 const SLAVE_PIC_ADDR: IoPortAddress = IoPortAddress::new(0xa0);
@@ -165,6 +187,11 @@ impl GateDescriptor {
         self.0 = (self.0 & !0xffff_ffff_0000_0000_0000_0000) | ((offset_high as u128) << 64);
     }
 
+    fn set_ist(&mut self, index: u8) {
+        let index = index & 0x7; // 3 bits
+        self.0 = (self.0 & !0x7_0000_0000) | ((index as u128) << 32);
+    }
+
     fn set_selector(&mut self, selector: u16) {
         self.0 = (self.0 & !0xffff_0000) | ((selector as u128) << 16);
     }
@@ -174,6 +201,11 @@ impl GateDescriptor {
         self.0 = (self.0 & !0x0f00_0000_0000) | ((gate_type as u128) << 40);
     }
 
+    fn set_dpl(&mut self, dpl: u8) {
+        let dpl = dpl & 0x3; // 2 bits
+        self.0 = (self.0 & !0x6000_0000_0000) | ((dpl as u128) << 45);
+    }
+
     fn set_p(&mut self, value: bool) {
         self.0 = (self.0 & !0x8000_0000_0000) | ((value as u128) << 47);
     }
@@ -218,7 +250,7 @@ impl InterruptDescriptorTable {
         handler: InterruptHandler,
         gate_type: GateType,
     ) -> Result<u8> {
-        for i in 32..IDT_LEN {
+        for i in DYN_VEC_START..IDT_LEN {
             let desc = &mut self.entries[i];
             if desc.is_null() {
                 desc.set_handler(handler, gate_type);
@@ -229,6 +261,21 @@ impl InterruptDescriptorTable {
         Err(Error::Failed("No available interrupt vector"))
     }
 
+    fn remove_handler(&mut self, vec_num: usize) -> Result<()> {
+        // exception handlers can't be removed
+        if vec_num < PIC_VEC_BASE || vec_num >= IDT_LEN {
+            return Err(Error::Failed("Invalid interrupt vector number"));
+        }
+
+        let desc = &mut self.entries[vec_num];
+        if desc.is_null() {
+            return Err(Error::Failed("Interrupt handler is not set"));
+        }
+        *desc = GateDescriptor::new();
+
+        Ok(())
+    }
+
     fn load(&self) {
         let limit = (size_of::<GateDescriptor>() * IDT_LEN - 1) as u16;
         let base = self.entries.as_ptr() as u64;
@@ -237,12 +284,64 @@ impl InterruptDescriptorTable {
     }
 }
 
+fn set_pic_irq_mask(irq: u8, masked: bool) {
+    let (pic_addr, line) = if irq < 8 {
+        (MASTER_PIC_ADDR, irq)
+    } else {
+        (SLAVE_PIC_ADDR, irq - 8)
+    };
+
+    let mask = pic_addr.offset(1).in8();
+    let mask = if masked {
+        mask | (1 << line)
+    } else {
+        mask & !(1 << line)
+    };
+    pic_addr.offset(1).out8(mask);
+}
+
+fn set_irq_mask(irq: u8, masked: bool) -> Result<()> {
+    if !IS_IO_APIC_MODE.load(Ordering::Relaxed) {
+        set_pic_irq_mask(irq, masked);
+        return Ok(());
+    }
+
+    if masked {
+        io_apic::mask(irq)
+    } else {
+        let vec_num = (PIC_VEC_BASE + irq as usize) as u8;
+        io_apic::set_redirection(irq, vec_num, apic::local_apic_id())
+    }
+}
+
 pub fn notify_end_of_int() {
+    if IS_IO_APIC_MODE.load(Ordering::Relaxed) {
+        apic::notify_end_of_int();
+        return;
+    }
+
     MASTER_PIC_ADDR.out8(PIC_END_OF_INT_CMD);
     SLAVE_PIC_ADDR.out8(PIC_END_OF_INT_CMD);
 }
 
-extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+// the stack frame argument is the interrupted frame itself, so RIP and RFLAGS written to it
+// are restored by iretq
+extern "x86-interrupt" fn debug_handler(mut stack_frame: InterruptStackFrame) {
+    // single step of the user app
+    if stack_frame.code_seg & 0x3 == 0x3 {
+        debug::user_app_debugger(&mut stack_frame, debug::StopReason::SingleStep);
+        return;
+    }
+
+    panic!("int: DEBUG, {:?}", stack_frame);
+}
+
+extern "x86-interrupt" fn breakpoint_handler(mut stack_frame: InterruptStackFrame) {
+    if stack_frame.code_seg & 0x3 == 0x3 {
+        debug::user_app_debugger(&mut stack_frame, debug::StopReason::Breakpoint);
+        return;
+    }
+
     panic!("int: BREAKPOINT, {:?}", stack_frame);
 }
 
@@ -250,6 +349,12 @@ extern "x86-interrupt" fn general_protection_fault_handler(stack_frame: Interrup
     error!("int: GENERAL PROTECTION FAULT, {:?}", stack_frame);
 
     if task::is_running_user_task() {
+        if let Some(line_info) = task::find_user_line_by_ip(stack_frame.ins_ptr) {
+            error!(
+                "int: at {}:{}:{}",
+                line_info.file, line_info.line, line_info.column
+            );
+        }
         task::debug_user_task();
         task::return_task(122);
         unreachable!();
@@ -266,18 +371,45 @@ extern "x86-interrupt" fn page_fault_handler(
     let page_virt_addr = (accessed_virt_addr & !0xfff).into();
     let page_table_entry = paging::read_page_table_entry(page_virt_addr);
 
+    if context::is_kernel_stack_guard_page(page_virt_addr) {
+        panic!(
+            "int: KERNEL STACK OVERFLOW, Accessed virtual address: 0x{:x}, {:?}, {:?}",
+            accessed_virt_addr, error_code, stack_frame
+        );
+    }
+
+    // first access to the demand-zero pages
+    if task::is_running_user_task() {
+        match task::handle_demand_zero_page(page_virt_addr) {
+            Ok(true) => return,
+            Ok(false) => (),
+            Err(err) => error!("int: Failed to map the demand-zero page: {:?}", err),
+        }
+    }
+
     error!(
         "int: PAGE FAULT, Accessed virtual address: 0x{:x}, {:?}, {:?}, Page table entry (at 0x{:x}): {:?}",
         accessed_virt_addr, error_code, stack_frame, page_virt_addr.get(), page_table_entry
     );
 
-    if task::is_running_user_task() {
+    // faults from the user mode only terminate the task
+    if stack_frame.code_seg & 0x3 == 0x3 && task::is_running_user_task() {
+        match task::find_user_line_by_ip(stack_frame.ins_ptr) {
+            Some(line_info) => error!(
+                "int: Segmentation fault at 0x{:x} ({}:{}:{})",
+                accessed_virt_addr, line_info.file, line_info.line, line_info.column
+            ),
+            None => error!(
+                "int: Segmentation fault at 0x{:x} (ip: 0x{:x})",
+                accessed_virt_addr, stack_frame.ins_ptr
+            ),
+        }
         task::debug_user_task();
-        task::return_task(123);
+        task::return_task(USER_PAGE_FAULT_EXIT_STATUS);
         unreachable!();
     }
 
-    panic!();
+    panic!("int: PAGE FAULT in the kernel mode");
 }
 
 extern "x86-interrupt" fn double_fault_handler() {
@@ -301,19 +433,40 @@ pub fn init_pic() {
     SLAVE_PIC_ADDR.offset(1).out8(2);
     SLAVE_PIC_ADDR.offset(1).out8(0x1); // none buffer mode
 
-    // mask all
+    // mask all except cascade (IRQ lines are unmasked by set_irq_handler())
     MASTER_PIC_ADDR.offset(1).out8(0xfb);
     SLAVE_PIC_ADDR.offset(1).out8(0xff);
 
-    // allow interrupts
-    MASTER_PIC_ADDR.offset(1).out8(0xf9);
-    SLAVE_PIC_ADDR.offset(1).out8(0xef);
-
     info!("idt: PIC initialized");
 }
 
+// route legacy IRQs through the I/O APIC found in the MADT and disable the PIC
+// must be called after acpi::init() and before any IRQ handlers are registered
+pub fn init_io_apic() -> Result<()> {
+    if !USE_IO_APIC {
+        return Ok(());
+    }
+
+    io_apic::init(acpi::io_apic_addr()?)?;
+
+    arch::disabled_int(|| {
+        MASTER_PIC_ADDR.offset(1).out8(0xff);
+        SLAVE_PIC_ADDR.offset(1).out8(0xff);
+        IS_IO_APIC_MODE.store(true, Ordering::Relaxed);
+    });
+
+    info!("idt: Legacy IRQs are routed through the I/O APIC");
+    Ok(())
+}
+
 pub fn init_idt() {
     let mut idt = unsafe { IDT.try_lock() }.unwrap();
+    idt.set_handler(
+        VEC_DEBUG,
+        InterruptHandler::WithStackFrame(debug_handler),
+        GateType::Trap,
+    )
+    .unwrap();
     idt.set_handler(
         VEC_BREAKPOINT,
         InterruptHandler::WithStackFrame(breakpoint_handler),
@@ -338,23 +491,22 @@ pub fn init_idt() {
         GateType::Interrupt,
     )
     .unwrap();
-    idt.set_handler(
-        VEC_PS2_KBD,
-        InterruptHandler::Normal(device::ps2_keyboard::poll_int_ps2_kbd_driver),
-        GateType::Interrupt,
-    )
-    .unwrap();
-    idt.set_handler(
-        VEC_PS2_MOUSE,
-        InterruptHandler::Normal(device::ps2_mouse::poll_int_ps2_mouse_driver),
-        GateType::Interrupt,
-    )
-    .unwrap();
+    // page fault by kernel stack overflow can't be handled on the same stack
+    idt.entries[VEC_PAGE_FAULT].set_ist(FAULT_IST_INDEX);
+    idt.entries[VEC_DOUBLE_FAULT].set_ist(FAULT_IST_INDEX);
+    // allow int3 from user apps
+    idt.entries[VEC_BREAKPOINT].set_dpl(3);
     idt.load();
+    arch::enable_int();
 
     info!("idt: Initialized");
 }
 
+// load the IDT shared with the BSP
+pub fn init_ap() {
+    unsafe { IDT.try_lock() }.unwrap().load();
+}
+
 pub fn set_handler(vec_num: usize, handler: InterruptHandler, gate_type: GateType) -> Result<()> {
     let mut idt = unsafe { IDT.try_lock() }?;
     idt.set_handler(vec_num, handler, gate_type)?;
@@ -368,3 +520,44 @@ pub fn set_handler_dyn_vec(handler: InterruptHandler, gate_type: GateType) -> Re
     idt.load();
     Ok(vec_num)
 }
+
+pub fn remove_handler(vec_num: usize) -> Result<()> {
+    let mut idt = unsafe { IDT.try_lock() }?;
+    idt.remove_handler(vec_num)?;
+    idt.load();
+    Ok(())
+}
+
+// register handler for legacy IRQ line and unmask it
+pub fn set_irq_handler(irq: u8, handler: InterruptHandler, gate_type: GateType) -> Result<u8> {
+    if irq >= PIC_IRQ_LEN {
+        return Err(Error::Failed("Invalid IRQ number"));
+    }
+
+    let vec_num = PIC_VEC_BASE + irq as usize;
+    set_handler(vec_num, handler, gate_type)?;
+    arch::disabled_int(|| set_irq_mask(irq, false))?;
+
+    Ok(vec_num as u8)
+}
+
+pub fn remove_irq_handler(irq: u8) -> Result<()> {
+    if irq >= PIC_IRQ_LEN {
+        return Err(Error::Failed("Invalid IRQ number"));
+    }
+
+    arch::disabled_int(|| set_irq_mask(irq, true))?;
+    remove_handler(PIC_VEC_BASE + irq as usize)
+}
+
+#[test_case]
+fn test_set_remove_handler_dyn_vec() {
+    extern "x86-interrupt" fn dummy_handler() {}
+
+    let vec_num =
+        set_handler_dyn_vec(InterruptHandler::Normal(dummy_handler), GateType::Interrupt).unwrap();
+    assert!(vec_num as usize >= DYN_VEC_START);
+    assert!(remove_handler(vec_num as usize).is_ok());
+    assert!(remove_handler(vec_num as usize).is_err());
+    assert!(remove_handler(VEC_PAGE_FAULT).is_err());
+}
@@ -14,6 +14,7 @@ pub mod gdt;
 pub mod idt;
 pub mod qemu;
 pub mod register;
+pub mod smp;
 pub mod syscall;
 pub mod task;
 pub mod tss;
@@ -75,13 +76,41 @@ pub fn hlt() {
     unsafe { asm!("hlt") }
 }
 
-pub fn disabled_int<F: FnMut() -> R, R>(mut func: F) -> R {
+pub fn enable_int() {
+    unsafe { asm!("sti") }
+}
+
+pub fn is_int_enabled() -> bool {
+    let rflags: u64;
+    unsafe { asm!("pushfq", "pop {}", out(reg) rflags) };
+    (rflags & 0x200) != 0 // IF
+}
+
+pub fn disabled_int<F: FnOnce() -> R, R>(func: F) -> R {
+    // keep interrupts disabled if already disabled (nested or in interrupt handler)
+    let int_enabled = is_int_enabled();
     unsafe { asm!("cli") };
     let func_res = func();
-    unsafe { asm!("sti") };
+    if int_enabled {
+        enable_int();
+    }
     func_res
 }
 
+pub fn rdtsc() -> u64 {
+    let (high, low): (u32, u32);
+    unsafe { asm!("rdtsc", out("edx") high, out("eax") low) };
+    (high as u64) << 32 | low as u64
+}
+
+// none if the hardware random number generator is not ready
+pub fn rdrand() -> Option<u64> {
+    let value: u64;
+    let ok: u8;
+    unsafe { asm!("rdrand {}", "setc {}", out(reg) value, out(reg_byte) ok) };
+    (ok != 0).then_some(value)
+}
+
 pub fn int3() {
     unsafe { asm!("int3") }
 }
@@ -170,6 +199,12 @@ pub fn ltr(sel: u16) {
     }
 }
 
+pub fn invlpg(virt_addr: u64) {
+    unsafe {
+        asm!("invlpg [{}]", in(reg) virt_addr, options(nostack, preserves_flags));
+    }
+}
+
 pub fn read_msr(addr: u32) -> u64 {
     let low: u32;
     let high: u32;
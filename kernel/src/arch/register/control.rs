@@ -35,6 +35,10 @@ impl Cr0 {
         self.0 = (self.0 & !0x8000_0000) | ((value as u64) << 31);
     }
 
+    pub fn set_write_protect(&mut self, value: bool) {
+        self.0 = (self.0 & !0x1_0000) | ((value as u64) << 16);
+    }
+
     pub fn set_emulation(&mut self, value: bool) {
         self.0 = (self.0 & !0x04) | ((value as u64) << 2);
     }
@@ -47,6 +51,10 @@ impl Cr0 {
         (self.0 & 0x8000_0000) != 0
     }
 
+    pub fn write_protect(&self) -> bool {
+        (self.0 & 0x1_0000) != 0
+    }
+
     pub fn emulation(&self) -> bool {
         (self.0 & 0x04) != 0
     }
@@ -5,6 +5,7 @@ const IA32_EFER_MSR_ADDR: u32 = 0xc0000080;
 const IA32_STAR_MSR_ADDR: u32 = 0xc0000081;
 const IA32_LSTAR_MSR_ADDR: u32 = 0xc0000082;
 const IA32_FMASK_MSR_ADDR: u32 = 0xc0000084;
+const IA32_KERNEL_GS_BASE_MSR_ADDR: u32 = 0xc0000102;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ExtendedFeatureEnableRegister(u64);
@@ -35,6 +36,14 @@ impl ExtendedFeatureEnableRegister {
     pub fn syscall_enable(&self) -> bool {
         (self.raw() & 0x1) != 0
     }
+
+    pub fn set_no_execute_enable(&mut self, value: bool) {
+        self.set_raw((self.raw() & !0x800) | ((value as u64) << 11));
+    }
+
+    pub fn no_execute_enable(&self) -> bool {
+        (self.raw() & 0x800) != 0
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -130,6 +139,27 @@ impl SystemCallFlagMaskRegister {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct KernelGsBaseRegister(u64);
+
+impl Register<u64> for KernelGsBaseRegister {
+    fn read() -> Self {
+        Self(arch::read_msr(IA32_KERNEL_GS_BASE_MSR_ADDR))
+    }
+
+    fn write(&self) {
+        arch::write_msr(IA32_KERNEL_GS_BASE_MSR_ADDR, self.0)
+    }
+
+    fn raw(&self) -> u64 {
+        self.0
+    }
+
+    fn set_raw(&mut self, value: u64) {
+        self.0 = value;
+    }
+}
+
 pub struct Xcr0(u64);
 
 impl Register<u64> for Xcr0 {
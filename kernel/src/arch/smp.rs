@@ -0,0 +1,326 @@
+use super::{
+    acpi,
+    addr::{PhysicalAddress, VirtualAddress},
+    apic, gdt, idt,
+    register::{
+        control::{Cr0, Cr3, Cr4},
+        model_specific::{ExtendedFeatureEnableRegister, KernelGsBaseRegister},
+        Register,
+    },
+};
+use crate::{
+    arch,
+    error::{Error, Result},
+    mem::{bitmap, paging::PAGE_SIZE},
+};
+use alloc::boxed::Box;
+use core::{
+    arch::global_asm,
+    ptr::{addr_of, null_mut},
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+use log::{info, warn};
+
+// SIPI vector is the page number of the trampoline (below 1MiB)
+// must be same as the address hard-coded in the trampoline
+const TRAMPOLINE_PHYS_ADDR: u64 = 0x8000;
+const AP_STACK_PAGE_LEN: usize = 16;
+const AP_STARTUP_TIMEOUT_MS: u32 = 100;
+
+const ICR_LOW_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00300);
+const ICR_HIGH_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00310);
+const ICR_DELIVERY_MODE_INIT: u32 = 0x5 << 8;
+const ICR_DELIVERY_MODE_STARTUP: u32 = 0x6 << 8;
+const ICR_DELIVERY_STATUS_PENDING: u32 = 1 << 12;
+const ICR_LEVEL_ASSERT: u32 = 1 << 14;
+
+const EFER_LMA: u64 = 1 << 10;
+
+// includes the BSP
+static CPU_COUNT: AtomicUsize = AtomicUsize::new(1);
+// per-CPU data passed to the AP being started
+static AP_PER_CPU_DATA: AtomicPtr<PerCpuData> = AtomicPtr::new(null_mut());
+
+// real mode -> protected mode -> long mode, then call the entry on the given stack
+// the AP uses the BSP's page table, so the trampoline and kernel must be identity mapped
+// CR3 is loaded in 32-bit mode, so the PML4 table must be below 4GiB
+global_asm!(
+    ".global smp_trampoline_start",
+    ".global smp_trampoline_end",
+    ".global smp_trampoline_cr0",
+    ".global smp_trampoline_cr3",
+    ".global smp_trampoline_cr4",
+    ".global smp_trampoline_efer",
+    ".global smp_trampoline_stack",
+    ".global smp_trampoline_entry",
+    ".code16",
+    "smp_trampoline_start:",
+    "cli",
+    "cld",
+    "mov ax, cs",
+    "mov ds, ax",
+    "lgdt [TRAMPOLINE_GDT_PTR_OFFSET]",
+    "mov eax, cr0",
+    "or eax, 1",
+    "mov cr0, eax",
+    // jmp 0x08:protected_mode
+    ".byte 0x66, 0xea",
+    ".long 0x8000 + (2f - smp_trampoline_start)",
+    ".word 0x08",
+    ".code32",
+    "2:",
+    "mov ax, 0x18",
+    "mov ds, ax",
+    "mov es, ax",
+    "mov ss, ax",
+    "mov eax, cr4",
+    "or eax, 1 << 5", // PAE
+    "mov cr4, eax",
+    "mov eax, [TRAMPOLINE_CR3_ADDR]",
+    "mov cr3, eax",
+    "mov ecx, 0xc0000080", // EFER
+    "mov eax, [TRAMPOLINE_EFER_ADDR]",
+    "mov edx, [TRAMPOLINE_EFER_ADDR + 4]",
+    "wrmsr",
+    "mov eax, cr0",
+    "or eax, 0x80000001", // PG, PE
+    "mov cr0, eax",
+    // jmp 0x10:long_mode
+    ".byte 0xea",
+    ".long 0x8000 + (3f - smp_trampoline_start)",
+    ".word 0x10",
+    ".code64",
+    "3:",
+    "mov ax, 0x18",
+    "mov ds, ax",
+    "mov es, ax",
+    "mov ss, ax",
+    "mov rax, [TRAMPOLINE_CR0_ADDR]",
+    "mov cr0, rax",
+    "mov rax, [TRAMPOLINE_CR4_ADDR]",
+    "mov cr4, rax",
+    "mov rsp, [TRAMPOLINE_STACK_ADDR]",
+    "mov rax, [TRAMPOLINE_ENTRY_ADDR]",
+    "call rax",
+    "4:",
+    "hlt",
+    "jmp 4b",
+    ".align 8",
+    // null, 32-bit code, 64-bit code, data
+    "smp_trampoline_gdt:",
+    ".quad 0",
+    ".quad 0x00cf9a000000ffff",
+    ".quad 0x00af9a000000ffff",
+    ".quad 0x00cf92000000ffff",
+    "smp_trampoline_gdt_ptr:",
+    ".word 4 * 8 - 1",
+    ".long 0x8000 + (smp_trampoline_gdt - smp_trampoline_start)",
+    ".align 8",
+    "smp_trampoline_cr0:",
+    ".quad 0",
+    "smp_trampoline_cr3:",
+    ".quad 0",
+    "smp_trampoline_cr4:",
+    ".quad 0",
+    "smp_trampoline_efer:",
+    ".quad 0",
+    "smp_trampoline_stack:",
+    ".quad 0",
+    "smp_trampoline_entry:",
+    ".quad 0",
+    "smp_trampoline_end:",
+    // memory operands can't contain label differences
+    ".set TRAMPOLINE_GDT_PTR_OFFSET, smp_trampoline_gdt_ptr - smp_trampoline_start",
+    ".set TRAMPOLINE_CR0_ADDR, 0x8000 + (smp_trampoline_cr0 - smp_trampoline_start)",
+    ".set TRAMPOLINE_CR3_ADDR, 0x8000 + (smp_trampoline_cr3 - smp_trampoline_start)",
+    ".set TRAMPOLINE_CR4_ADDR, 0x8000 + (smp_trampoline_cr4 - smp_trampoline_start)",
+    ".set TRAMPOLINE_EFER_ADDR, 0x8000 + (smp_trampoline_efer - smp_trampoline_start)",
+    ".set TRAMPOLINE_STACK_ADDR, 0x8000 + (smp_trampoline_stack - smp_trampoline_start)",
+    ".set TRAMPOLINE_ENTRY_ADDR, 0x8000 + (smp_trampoline_entry - smp_trampoline_start)",
+);
+
+extern "C" {
+    static smp_trampoline_start: u8;
+    static smp_trampoline_end: u8;
+    static smp_trampoline_cr0: u8;
+    static smp_trampoline_cr3: u8;
+    static smp_trampoline_cr4: u8;
+    static smp_trampoline_efer: u8;
+    static smp_trampoline_stack: u8;
+    static smp_trampoline_entry: u8;
+}
+
+#[derive(Debug)]
+pub struct PerCpuData {
+    pub cpu_index: usize,
+    pub apic_id: u32,
+}
+
+struct Trampoline {
+    virt_addr: VirtualAddress,
+}
+
+impl Trampoline {
+    fn install() -> Result<Self> {
+        let mem_frame_info = bitmap::alloc_mem_frame_at(PhysicalAddress::new(TRAMPOLINE_PHYS_ADDR))
+            .map_err(|_| Error::Failed("Trampoline memory frame is not available"))?;
+        let virt_addr = mem_frame_info.frame_start_virt_addr()?;
+
+        let cr3 = Cr3::read().raw();
+        if cr3 > u32::MAX as u64 {
+            return Err(Error::Failed("PML4 table is not below 4GiB"));
+        }
+
+        let start = unsafe { addr_of!(smp_trampoline_start) };
+        let len = unsafe { addr_of!(smp_trampoline_end) } as usize - start as usize;
+        assert!(len <= PAGE_SIZE);
+        unsafe {
+            virt_addr
+                .as_ptr_mut::<u8>()
+                .copy_from_nonoverlapping(start, len)
+        };
+
+        let trampoline = Self { virt_addr };
+        unsafe {
+            trampoline.write(addr_of!(smp_trampoline_cr0), Cr0::read().raw());
+            trampoline.write(addr_of!(smp_trampoline_cr3), cr3);
+            trampoline.write(addr_of!(smp_trampoline_cr4), Cr4::read().raw());
+            // LMA is set by the CPU
+            trampoline.write(
+                addr_of!(smp_trampoline_efer),
+                ExtendedFeatureEnableRegister::read().raw() & !EFER_LMA,
+            );
+            trampoline.write(addr_of!(smp_trampoline_entry), ap_entry as *const () as u64);
+        }
+
+        Ok(trampoline)
+    }
+
+    fn write(&self, label: *const u8, value: u64) {
+        let offset = label as usize - unsafe { addr_of!(smp_trampoline_start) } as usize;
+        unsafe {
+            (self.virt_addr.offset(offset).as_ptr_mut() as *mut u64).write_volatile(value);
+        }
+    }
+
+    fn sipi_vector(&self) -> u8 {
+        (TRAMPOLINE_PHYS_ADDR / PAGE_SIZE as u64) as u8
+    }
+}
+
+fn send_ipi(apic_id: u8, value: u32) {
+    unsafe {
+        (ICR_HIGH_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile((apic_id as u32) << 24);
+        (ICR_LOW_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(value);
+
+        while (ICR_LOW_VIRT_ADDR.as_ptr() as *const u32).read_volatile()
+            & ICR_DELIVERY_STATUS_PENDING
+            != 0
+        {}
+    }
+}
+
+fn set_per_cpu_data(data: &'static PerCpuData) {
+    // the kernel doesn't use swapgs, and reloading the gs selector on context switch clears GS base
+    let mut reg = KernelGsBaseRegister::read();
+    reg.set_raw(data as *const PerCpuData as u64);
+    reg.write();
+}
+
+fn start_ap(trampoline: &Trampoline, cpu_index: usize, apic_id: u8) -> Result<()> {
+    let stack = bitmap::alloc_mem_frame(AP_STACK_PAGE_LEN)?;
+    bitmap::mem_clear(&stack)?;
+    let stack_top = stack.frame_start_virt_addr()?.offset(stack.frame_size);
+    trampoline.write(unsafe { addr_of!(smp_trampoline_stack) }, stack_top.get());
+
+    let data = Box::leak(Box::new(PerCpuData {
+        cpu_index,
+        apic_id: apic_id as u32,
+    }));
+    AP_PER_CPU_DATA.store(data, Ordering::SeqCst);
+    let cpu_count = CPU_COUNT.load(Ordering::SeqCst);
+
+    // INIT-SIPI-SIPI
+    send_ipi(apic_id, ICR_DELIVERY_MODE_INIT | ICR_LEVEL_ASSERT);
+    acpi::pm_timer_wait_ms(10)?;
+    for _ in 0..2 {
+        send_ipi(
+            apic_id,
+            ICR_DELIVERY_MODE_STARTUP | ICR_LEVEL_ASSERT | trampoline.sipi_vector() as u32,
+        );
+        acpi::pm_timer_wait_ms(1)?;
+    }
+
+    for _ in 0..AP_STARTUP_TIMEOUT_MS {
+        if CPU_COUNT.load(Ordering::SeqCst) > cpu_count {
+            return Ok(());
+        }
+        acpi::pm_timer_wait_ms(1)?;
+    }
+
+    Err(Error::Failed("Application processor did not respond"))
+}
+
+extern "sysv64" fn ap_entry() -> ! {
+    gdt::init_ap();
+    idt::init_ap();
+
+    let data = unsafe { &*AP_PER_CPU_DATA.load(Ordering::SeqCst) };
+    set_per_cpu_data(data);
+
+    info!(
+        "smp: CPU {} started (APIC ID: {})",
+        data.cpu_index, data.apic_id
+    );
+    CPU_COUNT.fetch_add(1, Ordering::SeqCst);
+
+    // TODO: join the task scheduler
+    loop {
+        unsafe { core::arch::asm!("cli") };
+        arch::hlt();
+    }
+}
+
+// start all application processors listed in the MADT
+pub fn init() -> Result<()> {
+    let bsp_apic_id = apic::local_apic_id();
+    set_per_cpu_data(Box::leak(Box::new(PerCpuData {
+        cpu_index: 0,
+        apic_id: bsp_apic_id as u32,
+    })));
+
+    let apic_ids = acpi::cpu_apic_ids()?;
+    if apic_ids.len() <= 1 {
+        return Ok(());
+    }
+
+    let trampoline = Trampoline::install()?;
+
+    for apic_id in apic_ids {
+        if apic_id == bsp_apic_id as u32 {
+            continue;
+        }
+
+        // x2APIC IDs can't be targeted by xAPIC IPIs
+        if apic_id > u8::MAX as u32 {
+            warn!("smp: APIC ID {} is not supported", apic_id);
+            continue;
+        }
+
+        if let Err(err) = start_ap(&trampoline, cpu_count(), apic_id as u8) {
+            warn!("smp: Failed to start CPU (APIC ID: {}): {:?}", apic_id, err);
+        }
+    }
+
+    info!("smp: {} CPU(s) started", cpu_count());
+    Ok(())
+}
+
+pub fn cpu_count() -> usize {
+    CPU_COUNT.load(Ordering::SeqCst)
+}
+
+pub fn current_cpu() -> Option<&'static PerCpuData> {
+    let ptr = KernelGsBaseRegister::read().raw() as *const PerCpuData;
+    unsafe { ptr.as_ref() }
+}
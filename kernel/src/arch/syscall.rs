@@ -1,5 +1,6 @@
 use crate::{
     arch::{
+        acpi,
         addr::VirtualAddress,
         apic,
         gdt::*,
@@ -11,20 +12,135 @@ use crate::{
     error::*,
     fs::{
         self,
-        vfs::{self, file_desc::FileDescriptorNumber},
+        vfs::{self, file_desc::FileDescriptorNumber, DirEntry, FileStat, VirtualFileSystemError},
     },
-    graphics::{multi_layer::LayerId, simple_window_manager},
-    mem::{bitmap, paging::PAGE_SIZE},
+    graphics::{frame_buf, multi_layer::LayerId, simple_window_manager},
+    mem::{
+        allocator, bitmap,
+        paging::{self, EntryMode, ReadWrite, PAGE_SIZE},
+    },
+    net::{self, udp::SocketId},
     print, util,
 };
 use alloc::{boxed::Box, ffi::CString, string::*, vec::Vec};
 use common::{
     graphic_info::PixelFormat,
-    libm::{Stat, Utsname},
+    libm::{
+        Dirent, HeapStats, SockaddrIn, Stat, Timespec, Utsname, AF_INET, CLOCK_MONOTONIC,
+        CLOCK_REALTIME, DIRENT_NAME_LEN, MAP_ANONYMOUS, MAP_FIXED, PROT_EXEC, PROT_READ,
+        PROT_WRITE, SOCK_DGRAM,
+    },
+};
+use core::{
+    arch::asm,
+    mem::{size_of, size_of_val},
+    slice,
 };
-use core::{arch::asm, slice};
 use log::*;
 
+// returned for undefined syscall numbers
+const ENOSYS: i64 = 38;
+
+// syscall numbers passed by rdi, keep in sync with apps/libc/syscalls.h
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u64)]
+pub enum Syscall {
+    Read = 0,
+    Write = 1,
+    Open = 2,
+    Close = 3,
+    Exit = 4,
+    Sbrk = 5,
+    Uname = 6,
+    Break = 7,
+    Stat = 8,
+    Uptime = 9,
+    Exec = 10,
+    Getcwd = 11,
+    Chdir = 12,
+    CreateWindow = 13,
+    DestroyWindow = 14,
+    Getcwdenames = 15,
+    Sbrksz = 16,
+    FlushWindow = 17,
+    AddImageToWindow = 18,
+    StatPath = 19,
+    Mkdir = 20,
+    Remove = 21,
+    SleepMs = 22,
+    Spawn = 23,
+    Wait = 24,
+    Pipe = 25,
+    Screenshot = 26,
+    HeapStats = 27,
+    Poweroff = 28,
+    Reboot = 29,
+    Readdir = 30,
+    Getpid = 31,
+    Socket = 32,
+    Bind = 33,
+    SendTo = 34,
+    RecvFrom = 35,
+    CloseSocket = 36,
+    ClockGettime = 37,
+    Mmap = 38,
+    Munmap = 39,
+    Brk = 40,
+    GetHostByName = 41,
+}
+
+impl TryFrom<u64> for Syscall {
+    type Error = Error;
+
+    fn try_from(value: u64) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Read),
+            1 => Ok(Self::Write),
+            2 => Ok(Self::Open),
+            3 => Ok(Self::Close),
+            4 => Ok(Self::Exit),
+            5 => Ok(Self::Sbrk),
+            6 => Ok(Self::Uname),
+            7 => Ok(Self::Break),
+            8 => Ok(Self::Stat),
+            9 => Ok(Self::Uptime),
+            10 => Ok(Self::Exec),
+            11 => Ok(Self::Getcwd),
+            12 => Ok(Self::Chdir),
+            13 => Ok(Self::CreateWindow),
+            14 => Ok(Self::DestroyWindow),
+            15 => Ok(Self::Getcwdenames),
+            16 => Ok(Self::Sbrksz),
+            17 => Ok(Self::FlushWindow),
+            18 => Ok(Self::AddImageToWindow),
+            19 => Ok(Self::StatPath),
+            20 => Ok(Self::Mkdir),
+            21 => Ok(Self::Remove),
+            22 => Ok(Self::SleepMs),
+            23 => Ok(Self::Spawn),
+            24 => Ok(Self::Wait),
+            25 => Ok(Self::Pipe),
+            26 => Ok(Self::Screenshot),
+            27 => Ok(Self::HeapStats),
+            28 => Ok(Self::Poweroff),
+            29 => Ok(Self::Reboot),
+            30 => Ok(Self::Readdir),
+            31 => Ok(Self::Getpid),
+            32 => Ok(Self::Socket),
+            33 => Ok(Self::Bind),
+            34 => Ok(Self::SendTo),
+            35 => Ok(Self::RecvFrom),
+            36 => Ok(Self::CloseSocket),
+            37 => Ok(Self::ClockGettime),
+            38 => Ok(Self::Mmap),
+            39 => Ok(Self::Munmap),
+            40 => Ok(Self::Brk),
+            41 => Ok(Self::GetHostByName),
+            _ => Err(Error::Failed("Undefined syscall number")),
+        }
+    }
+}
+
 #[naked]
 extern "sysv64" fn asm_syscall_handler() {
     unsafe {
@@ -58,9 +174,16 @@ extern "sysv64" fn syscall_handler(
     //let args = [arg0, arg1, arg2, arg3, arg4, arg5];
     //info!("syscall: Called!(args: {:?})", args);
 
-    match arg0 {
-        // read syscall
-        0 => {
+    let syscall = match Syscall::try_from(arg0) {
+        Ok(syscall) => syscall,
+        Err(_) => {
+            error!("syscall: Syscall number 0x{:x} is not defined", arg0);
+            return -ENOSYS;
+        }
+    };
+
+    match syscall {
+        Syscall::Read => {
             let fd = match FileDescriptorNumber::new_val(arg1 as i64) {
                 Ok(fd) => fd,
                 Err(err) => {
@@ -70,13 +193,15 @@ extern "sysv64" fn syscall_handler(
             };
             let buf_addr = arg2.into();
             let buf_len = arg3 as usize;
-            if let Err(err) = sys_read(fd, buf_addr, buf_len) {
-                error!("syscall: read: {:?}", err);
-                return -1;
-            }
+            return match sys_read(fd, buf_addr, buf_len) {
+                Ok(len) => len as i64,
+                Err(err) => {
+                    error!("syscall: read: {:?}", err);
+                    -1
+                }
+            };
         }
-        // write syscall
-        1 => {
+        Syscall::Write => {
             let fd = match FileDescriptorNumber::new_val(arg1 as i64) {
                 Ok(fd) => fd,
                 Err(err) => {
@@ -91,8 +216,7 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // open syscall
-        2 => {
+        Syscall::Open => {
             let filename_ptr = arg1 as *const u8;
             let fd = match sys_open(filename_ptr) {
                 Ok(fd) => fd,
@@ -103,8 +227,7 @@ extern "sysv64" fn syscall_handler(
             };
             return fd.get() as i64;
         }
-        // close syscall
-        3 => {
+        Syscall::Close => {
             let fd = match FileDescriptorNumber::new_val(arg1 as i64) {
                 Ok(fd) => fd,
                 Err(err) => {
@@ -117,14 +240,12 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // exit syscall
-        4 => {
+        Syscall::Exit => {
             let status = arg1;
             sys_exit(status);
             unreachable!();
         }
-        // sbrk syscall
-        5 => {
+        Syscall::Sbrk => {
             let len = arg1 as usize;
             let addr = match sys_sbrk(len) {
                 Ok(addr) => addr.get(),
@@ -135,20 +256,17 @@ extern "sysv64" fn syscall_handler(
             };
             return addr as i64;
         }
-        // uname syscall
-        6 => {
+        Syscall::Uname => {
             if let Err(err) = sys_uname(arg1.into()) {
                 error!("syscall: uname: {:?}", err);
                 return -1;
             }
         }
-        // break syscall
-        7 => {
+        Syscall::Break => {
             sys_break();
             unreachable!();
         }
-        // stat syscall
-        8 => {
+        Syscall::Stat => {
             let fd = match FileDescriptorNumber::new_val(arg1 as i64) {
                 Ok(fd) => fd,
                 Err(err) => {
@@ -162,21 +280,21 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // uptime syscall
-        9 => {
+        Syscall::Uptime => {
             let uptime = sys_uptime();
             return uptime as i64;
         }
-        // exec syscall
-        10 => {
+        Syscall::Exec => {
             let args_ptr = arg1 as *const u8;
-            if let Err(err) = sys_exec(args_ptr) {
-                error!("syscall: exec: {:?}", err);
-                return -1;
-            }
+            return match sys_exec(args_ptr) {
+                Ok(exit_code) => exit_code as i64,
+                Err(err) => {
+                    error!("syscall: exec: {:?}", err);
+                    -1
+                }
+            };
         }
-        // getcwd syscall
-        11 => {
+        Syscall::Getcwd => {
             let buf_addr = arg1.into();
             let buf_len = arg2 as usize;
             if let Err(err) = sys_getcwd(buf_addr, buf_len) {
@@ -184,16 +302,14 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // chdir syscall
-        12 => {
+        Syscall::Chdir => {
             let path_ptr = arg1 as *const u8;
             if let Err(err) = sys_chdir(path_ptr) {
                 error!("syscall: chdir: {:?}", err);
                 return -1;
             }
         }
-        // create_window syscall
-        13 => {
+        Syscall::CreateWindow => {
             let title_ptr = arg1 as *const u8;
             let x_pos = arg2 as usize;
             let y_pos = arg3 as usize;
@@ -208,8 +324,7 @@ extern "sysv64" fn syscall_handler(
                 }
             }
         }
-        // destroy_window syscall
-        14 => {
+        Syscall::DestroyWindow => {
             let wd = match LayerId::new_val(arg1 as i64) {
                 Ok(wd) => wd,
                 Err(err) => {
@@ -223,8 +338,7 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // getcwdenames syscall
-        15 => {
+        Syscall::Getcwdenames => {
             let buf_addr = arg1.into();
             let buf_len = arg2 as usize;
             if let Err(err) = sys_getcwdenames(buf_addr, buf_len) {
@@ -232,8 +346,7 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // sbrksz syscall
-        16 => {
+        Syscall::Sbrksz => {
             let target_addr = arg1.into();
 
             return match sys_sbrksz(target_addr) {
@@ -248,8 +361,7 @@ extern "sysv64" fn syscall_handler(
                 }
             };
         }
-        // flush_window syscall
-        17 => {
+        Syscall::FlushWindow => {
             let wd = match LayerId::new_val(arg1 as i64) {
                 Ok(wd) => wd,
                 Err(err) => {
@@ -263,8 +375,7 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        // add_image_to_window syscall
-        18 => {
+        Syscall::AddImageToWindow => {
             let wd = match LayerId::new_val(arg1 as i64) {
                 Ok(wd) => wd,
                 Err(err) => {
@@ -284,17 +395,238 @@ extern "sysv64" fn syscall_handler(
                 return -1;
             }
         }
-        num => {
-            error!("syscall: Syscall number 0x{:x} is not defined", num);
-            return -1;
+        Syscall::StatPath => {
+            let path_ptr = arg1 as *const u8;
+            if let Err(err) = sys_stat_path(path_ptr, arg2.into()) {
+                error!("syscall: stat_path: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Mkdir => {
+            let path_ptr = arg1 as *const u8;
+            if let Err(err) = sys_mkdir(path_ptr) {
+                error!("syscall: mkdir: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Remove => {
+            let path_ptr = arg1 as *const u8;
+            let recursive = arg2 != 0;
+            if let Err(err) = sys_remove(path_ptr, recursive) {
+                error!("syscall: remove: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::SleepMs => {
+            let ms = arg1 as usize;
+            if let Err(err) = sys_sleep_ms(ms) {
+                error!("syscall: sleep_ms: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Spawn => {
+            let args_ptr = arg1 as *const u8;
+            // negative values are not redirected
+            let stdin = FileDescriptorNumber::new_val(arg2 as i64).ok();
+            let stdout = FileDescriptorNumber::new_val(arg3 as i64).ok();
+            return match sys_spawn(args_ptr, stdin, stdout) {
+                Ok(pid) => pid as i64,
+                Err(err) => {
+                    error!("syscall: spawn: {:?}", err);
+                    -1
+                }
+            };
+        }
+        Syscall::Wait => {
+            let pid = arg1 as usize;
+            return match sys_wait(pid) {
+                Ok(exit_code) => exit_code as i64,
+                Err(err) => {
+                    error!("syscall: wait: {:?}", err);
+                    -1
+                }
+            };
+        }
+        Syscall::Pipe => {
+            let fds_addr = arg1.into();
+            if let Err(err) = sys_pipe(fds_addr) {
+                error!("syscall: pipe: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Screenshot => {
+            let path_ptr = arg1 as *const u8;
+            if let Err(err) = sys_screenshot(path_ptr) {
+                error!("syscall: screenshot: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::HeapStats => {
+            if let Err(err) = sys_heap_stats(arg1.into()) {
+                error!("syscall: heap_stats: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Poweroff => {
+            if let Err(err) = sys_poweroff() {
+                error!("syscall: poweroff: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Reboot => {
+            if let Err(err) = sys_reboot() {
+                error!("syscall: reboot: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Readdir => {
+            let path_ptr = arg1 as *const u8;
+            let buf_addr = arg2.into();
+            let buf_len = arg3 as usize;
+            return match sys_readdir(path_ptr, buf_addr, buf_len) {
+                Ok(len) => len as i64,
+                Err(err) => {
+                    error!("syscall: readdir: {:?}", err);
+                    -1
+                }
+            };
+        }
+        Syscall::Getpid => {
+            return match sys_getpid() {
+                Ok(pid) => pid as i64,
+                Err(err) => {
+                    error!("syscall: getpid: {:?}", err);
+                    -1
+                }
+            };
+        }
+        Syscall::Socket => {
+            let domain = arg1 as u16;
+            let ty = arg2;
+
+            match sys_socket(domain, ty) {
+                Ok(sd) => return sd.get() as i64,
+                Err(err) => {
+                    error!("syscall: socket: {:?}", err);
+                    return -1;
+                }
+            }
+        }
+        Syscall::Bind => {
+            let addr_ptr = arg2 as *const u8;
+
+            if let Err(err) = SocketId::new_val(arg1 as i64).and_then(|sd| sys_bind(sd, addr_ptr)) {
+                error!("syscall: bind: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::SendTo => {
+            let buf_ptr = arg2 as *const u8;
+            let buf_len = arg3 as usize;
+            let addr_ptr = arg4 as *const u8;
+
+            match SocketId::new_val(arg1 as i64)
+                .and_then(|sd| sys_sendto(sd, buf_ptr, buf_len, addr_ptr))
+            {
+                Ok(len) => return len as i64,
+                Err(err) => {
+                    error!("syscall: sendto: {:?}", err);
+                    return -1;
+                }
+            }
+        }
+        Syscall::RecvFrom => {
+            let buf_addr = arg2.into();
+            let buf_len = arg3 as usize;
+            let addr_addr = arg4.into();
+
+            match SocketId::new_val(arg1 as i64)
+                .and_then(|sd| sys_recvfrom(sd, buf_addr, buf_len, addr_addr))
+            {
+                Ok(len) => return len as i64,
+                Err(err) => {
+                    error!("syscall: recvfrom: {:?}", err);
+                    return -1;
+                }
+            }
+        }
+        Syscall::CloseSocket => {
+            if let Err(err) = SocketId::new_val(arg1 as i64).and_then(sys_close_socket) {
+                error!("syscall: close_socket: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::ClockGettime => {
+            if let Err(err) = sys_clock_gettime(arg1, arg2.into()) {
+                error!("syscall: clock_gettime: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Mmap => {
+            let len = arg2 as usize;
+            match sys_mmap(arg1.into(), len, arg3, arg4) {
+                Ok(addr) => return addr.get() as i64,
+                Err(err) => {
+                    error!("syscall: mmap: {:?}", err);
+                    return -1;
+                }
+            }
+        }
+        Syscall::Munmap => {
+            let len = arg2 as usize;
+            if let Err(err) = sys_munmap(arg1.into(), len) {
+                error!("syscall: munmap: {:?}", err);
+                return -1;
+            }
+        }
+        Syscall::Brk => {
+            return match sys_brk(arg1.into()) {
+                Ok(addr) => addr.get() as i64,
+                Err(err) => {
+                    error!("syscall: brk: {:?}", err);
+                    -1
+                }
+            };
+        }
+        Syscall::GetHostByName => {
+            let hostname_ptr = arg1 as *const u8;
+            let addr_addr = arg2.into();
+            if let Err(err) = sys_gethostbyname(hostname_ptr, addr_addr) {
+                error!("syscall: gethostbyname: {:?}", err);
+                return -1;
+            }
         }
     }
 
     0
 }
 
-fn sys_read(fd: FileDescriptorNumber, buf_addr: VirtualAddress, buf_len: usize) -> Result<()> {
-    match fd {
+// return read length
+fn sys_read(fd: FileDescriptorNumber, buf_addr: VirtualAddress, buf_len: usize) -> Result<usize> {
+    check_user_buf(buf_addr, buf_len, ReadWrite::Write)?;
+    if buf_len == 0 {
+        return Ok(0);
+    }
+
+    let fd = task::redirected_fd(fd);
+
+    // wait until data is written or the write end is closed
+    if vfs::is_pipe(&fd)? {
+        loop {
+            match vfs::read_pipe(&fd, buf_len) {
+                Ok(data) => {
+                    copy_to_user(buf_addr, &data)?;
+                    return Ok(data.len());
+                }
+                Err(Error::VirtualFileSystemError(VirtualFileSystemError::WouldBlockError(_))) => {
+                    task::wait_yield()?
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    let read_len = match fd {
         FileDescriptorNumber::STDOUT | FileDescriptorNumber::STDERR => {
             return Err(Error::Failed("fd is not defined"));
         }
@@ -303,7 +635,7 @@ fn sys_read(fd: FileDescriptorNumber, buf_addr: VirtualAddress, buf_len: usize)
                 let mut input_s = None;
                 while input_s.is_none() {
                     if !console::is_ready_get_line() {
-                        super::hlt();
+                        task::wait_yield()?;
                         continue;
                     }
 
@@ -314,47 +646,67 @@ fn sys_read(fd: FileDescriptorNumber, buf_addr: VirtualAddress, buf_len: usize)
                     break;
                 }
 
-                let c_s = CString::new(input_s.unwrap())
-                    .unwrap()
-                    .into_bytes_with_nul();
-                buf_addr.copy_from_nonoverlapping(c_s.as_ptr(), buf_len);
+                // truncate to the buffer, keeping the null terminator
+                let mut c_s = CString::new(input_s.unwrap()).unwrap().into_bytes();
+                let len = c_s.len().min(buf_len - 1);
+                c_s.truncate(len);
+                c_s.push(0);
+                copy_to_user(buf_addr, &c_s)?;
+                len
             }
             // buf_len == 1
             else {
                 let ascii = super::disabled_int(|| console::get_ascii())?;
-                buf_addr.copy_from_nonoverlapping(&(ascii as u8), 1);
+                copy_to_user(buf_addr, &[ascii as u8])?;
+                1
             }
         }
         fd => {
-            let data = vfs::read_file(&fd)?;
-
-            if buf_len < data.len() {
-                return Err(Error::Failed("buffer is too small"));
-            }
-
-            buf_addr.copy_from_nonoverlapping(data.as_ptr(), data.len());
+            let data = vfs::read_file_max(&fd, buf_len)?;
+            copy_to_user(buf_addr, &data)?;
+            data.len()
         }
-    }
+    };
 
-    Ok(())
+    Ok(read_len)
 }
 
 fn sys_write(fd: FileDescriptorNumber, s_ptr: *const u8, s_len: usize) -> Result<()> {
-    let s_slice = unsafe { slice::from_raw_parts(s_ptr, s_len) };
+    let s_buf = copy_from_user(s_ptr, s_len)?;
+    let s_slice = s_buf.as_slice();
+    let fd = task::redirected_fd(fd);
+
+    // wait while the pipe is full
+    if vfs::is_pipe(&fd)? {
+        let mut written_len = 0;
+        while written_len < s_len {
+            let len = vfs::write_pipe(&fd, &s_slice[written_len..])?;
+            if len == 0 {
+                task::wait_yield()?;
+            }
+            written_len += len;
+        }
+
+        return Ok(());
+    }
+
     let s = String::from_utf8_lossy(s_slice).to_string();
 
     match fd {
         FileDescriptorNumber::STDOUT => {
             print!("{}", s);
         }
-        _ => return Err(Error::Failed("fd is not defined")),
+        FileDescriptorNumber::STDIN | FileDescriptorNumber::STDERR => {
+            return Err(Error::Failed("fd is not defined"));
+        }
+        fd => vfs::write_file(&fd, s_slice)?,
     }
 
     Ok(())
 }
 
 fn sys_open(filename_ptr: *const u8) -> Result<FileDescriptorNumber> {
-    let filename = unsafe { util::cstring::from_cstring_ptr(filename_ptr) };
+    let filename = task::resolve_path(&user_cstring(filename_ptr)?)?;
     let fd = vfs::open_file(&filename)?;
     task::push_fd(fd);
 
@@ -386,6 +738,49 @@ fn sys_sbrk(len: usize) -> Result<VirtualAddress> {
     Ok(virt_addr)
 }
 
+// only anonymous mappings are supported
+fn sys_mmap(addr: VirtualAddress, len: usize, prot: u64, flags: u64) -> Result<VirtualAddress> {
+    if flags & MAP_ANONYMOUS == 0 {
+        return Err(Error::Failed("File mapping is not supported"));
+    }
+
+    // W^X same as the program segments
+    if prot & PROT_WRITE != 0 && prot & PROT_EXEC != 0 {
+        return Err(Error::Failed(
+            "Writable and executable mapping is not allowed",
+        ));
+    }
+
+    let rw = match prot & PROT_WRITE != 0 {
+        true => ReadWrite::Write,
+        false => ReadWrite::Read,
+    };
+    // PROT_NONE pages are not accessible from user mode
+    let us = match prot & (PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        true => EntryMode::User,
+        false => EntryMode::Supervisor,
+    };
+    let addr = Some(addr).filter(|a| a.get() != 0);
+
+    task::mmap(
+        addr,
+        len,
+        rw,
+        us,
+        prot & PROT_EXEC == 0,
+        flags & MAP_FIXED != 0,
+    )
+}
+
+fn sys_munmap(addr: VirtualAddress, len: usize) -> Result<()> {
+    task::munmap(addr, len)
+}
+
+// return the current break if the address is null
+fn sys_brk(addr: VirtualAddress) -> Result<VirtualAddress> {
+    task::brk(Some(addr).filter(|a| a.get() != 0))
+}
+
 fn sys_uname(buf_addr: VirtualAddress) -> Result<()> {
     let sysname = env::OS_NAME.as_bytes();
     let nodename = "nodename".as_bytes();
@@ -401,8 +796,7 @@ fn sys_uname(buf_addr: VirtualAddress) -> Result<()> {
     utsname.version[..version.len()].copy_from_slice(version);
     utsname.machine[..machine.len()].copy_from_slice(machine);
     utsname.domainname[..domainname.len()].copy_from_slice(domainname);
-    buf_addr.copy_from_nonoverlapping(&utsname as *const Utsname, 1);
-    Ok(())
+    copy_to_user(buf_addr, slice::from_ref(&utsname))
 }
 
 fn sys_break() {
@@ -411,46 +805,153 @@ fn sys_break() {
 }
 
 fn sys_stat(fd: FileDescriptorNumber, buf_addr: VirtualAddress) -> Result<()> {
-    let size = match fd {
+    let stat = match fd {
         FileDescriptorNumber::STDIN
         | FileDescriptorNumber::STDOUT
-        | FileDescriptorNumber::STDERR => 0,
-        fd => vfs::read_file(&fd)?.len() as u64, // FIXME
+        | FileDescriptorNumber::STDERR => Stat::default(),
+        fd => file_stat_to_stat(vfs::fstat(&fd)?),
     };
 
-    let stat = Stat { size };
-    buf_addr.copy_from_nonoverlapping(&stat as *const Stat, 1);
+    copy_to_user(buf_addr, slice::from_ref(&stat))
+}
+
+fn sys_stat_path(path_ptr: *const u8, buf_addr: VirtualAddress) -> Result<()> {
+    let path = task::resolve_path(&user_cstring(path_ptr)?)?;
+    let stat = file_stat_to_stat(vfs::stat(&path)?);
+
+    copy_to_user(buf_addr, slice::from_ref(&stat))
+}
+
+fn sys_mkdir(path_ptr: *const u8) -> Result<()> {
+    let path = task::resolve_path(&user_cstring(path_ptr)?)?;
+    vfs::mkdir(&path)?;
     Ok(())
 }
 
+fn sys_remove(path_ptr: *const u8, recursive: bool) -> Result<()> {
+    let path = task::resolve_path(&user_cstring(path_ptr)?)?;
+    vfs::remove(&path, recursive)?;
+    Ok(())
+}
+
+fn file_stat_to_stat(file_stat: FileStat) -> Stat {
+    Stat {
+        size: file_stat.size as u64,
+        is_dir: file_stat.is_dir,
+    }
+}
+
+fn sys_heap_stats(buf_addr: VirtualAddress) -> Result<()> {
+    let stats = allocator::stats();
+    let heap_stats = HeapStats {
+        allocated: stats.allocated as u64,
+        free: stats.free as u64,
+        largest_free_block: stats.largest_free_block as u64,
+    };
+
+    copy_to_user(buf_addr, slice::from_ref(&heap_stats))
+}
+
+fn sys_poweroff() -> Result<()> {
+    acpi::shutdown()
+}
+
+fn sys_reboot() -> Result<()> {
+    acpi::reboot()
+}
+
 fn sys_uptime() -> u64 {
     device::local_apic_timer::get_current_ms().unwrap_or(0) as u64
 }
 
-fn sys_exec(args_ptr: *const u8) -> Result<()> {
-    let args = unsafe { util::cstring::from_cstring_ptr(args_ptr) };
-    let args: Vec<&str> = args.split(' ').collect();
-    fs::exec::exec_elf(args[0], &args[1..])?;
+fn sys_clock_gettime(clock_id: u64, buf_addr: VirtualAddress) -> Result<()> {
+    let ts = match clock_id {
+        CLOCK_REALTIME => {
+            // RTC has a resolution of 1 second
+            let sec = device::rtc::now()?.unix_timestamp();
+            Timespec {
+                sec: sec as i64,
+                nsec: 0,
+            }
+        }
+        CLOCK_MONOTONIC => {
+            // fall back to local APIC timer if HPET is not available
+            let ns = match device::hpet::now_ns() {
+                Ok(ns) => ns,
+                Err(_) => {
+                    let ms = device::local_apic_timer::get_current_ms()
+                        .ok_or(Error::Failed("Local APIC timer is not ready"))?;
+                    ms as u64 * 1_000_000
+                }
+            };
+            Timespec {
+                sec: (ns / 1_000_000_000) as i64,
+                nsec: (ns % 1_000_000_000) as i64,
+            }
+        }
+        _ => return Err(Error::Failed("Unsupported clock id")),
+    };
+
+    copy_to_user(buf_addr, slice::from_ref(&ts))
+}
+
+fn sys_sleep_ms(ms: usize) -> Result<()> {
+    let current_ms = device::local_apic_timer::get_current_ms()
+        .ok_or(Error::Failed("Local APIC timer is not ready"))?;
+    let wake_ms = current_ms + ms;
+
+    // kernel tasks are polled by the timer interrupt while waiting
+    while device::local_apic_timer::get_current_ms().unwrap_or(0) < wake_ms {
+        task::wait_yield()?;
+    }
 
     Ok(())
 }
 
+fn sys_exec(args_ptr: *const u8) -> Result<u64> {
+    let args = user_cstring(args_ptr)?;
+    let args: Vec<&str> = args.split(' ').collect();
+    fs::exec::exec_elf(args[0], &args[1..])
+}
+
+fn sys_spawn(
+    args_ptr: *const u8,
+    stdin: Option<FileDescriptorNumber>,
+    stdout: Option<FileDescriptorNumber>,
+) -> Result<usize> {
+    let args = user_cstring(args_ptr)?;
+    let args: Vec<&str> = args.split(' ').collect();
+    fs::exec::spawn_elf(args[0], &args[1..], stdin, stdout)
+}
+
+// fds_addr: [read fd, write fd]
+fn sys_pipe(fds_addr: VirtualAddress) -> Result<()> {
+    let (read_fd, write_fd) = vfs::pipe()?;
+    task::push_fd(read_fd);
+    task::push_fd(write_fd);
+
+    let fds = [read_fd.get(), write_fd.get()];
+    copy_to_user(fds_addr, &fds)
+}
+
+fn sys_wait(pid: usize) -> Result<u64> {
+    fs::exec::wait(pid)
+}
+
 fn sys_getcwd(buf_addr: VirtualAddress, buf_len: usize) -> Result<()> {
-    let cwd = vfs::cwd_path()?;
+    let cwd = task::cwd_path()?;
     let cwd_s = CString::new(cwd.as_str()).unwrap().into_bytes_with_nul();
 
     if buf_len < cwd_s.len() {
         return Err(Error::Failed("Buffer is too small"));
     }
 
-    buf_addr.copy_from_nonoverlapping(cwd_s.as_ptr(), cwd_s.len());
-
-    Ok(())
+    copy_to_user(buf_addr, &cwd_s)
 }
 
 fn sys_chdir(path_ptr: *const u8) -> Result<()> {
-    let path = unsafe { util::cstring::from_cstring_ptr(path_ptr) };
-    vfs::chdir(&path)?;
+    let path = user_cstring(path_ptr)?;
+    task::chdir(&path)?;
     Ok(())
 }
 
@@ -461,7 +962,7 @@ fn sys_create_window(
     width: usize,
     height: usize,
 ) -> Result<LayerId> {
-    let title = unsafe { util::cstring::from_cstring_ptr(title_ptr) };
+    let title = user_cstring(title_ptr)?;
     let wd = simple_window_manager::create_window(title, x_pos, y_pos, width, height)?;
     task::push_wd(wd.clone());
 
@@ -476,7 +977,7 @@ fn sys_destroy_window(wd: LayerId) -> Result<()> {
 }
 
 fn sys_getcwdenames(buf_addr: VirtualAddress, buf_len: usize) -> Result<()> {
-    let entry_names = fs::vfs::cwd_entry_names()?;
+    let entry_names = vfs::entry_names(&task::cwd_path()?)?;
     let entry_names_s: Vec<u8> = entry_names
         .iter()
         .map(|n| CString::new(n.as_str()).unwrap().into_bytes_with_nul())
@@ -487,11 +988,131 @@ fn sys_getcwdenames(buf_addr: VirtualAddress, buf_len: usize) -> Result<()> {
         return Err(Error::Failed("Buffer is too small"));
     }
 
-    buf_addr.copy_from_nonoverlapping(entry_names_s.as_ptr(), entry_names_s.len());
+    copy_to_user(buf_addr, &entry_names_s)
+}
+
+// buf_len: number of entries, return the number of all entries in the directory
+fn sys_readdir(path_ptr: *const u8, buf_addr: VirtualAddress, buf_len: usize) -> Result<usize> {
+    check_user_buf(
+        buf_addr,
+        buf_len.saturating_mul(size_of::<Dirent>()),
+        ReadWrite::Write,
+    )?;
+    let path = task::resolve_path(&user_cstring(path_ptr)?)?;
+    let entries = vfs::readdir(&path)?;
+
+    for (i, entry) in entries.iter().take(buf_len).enumerate() {
+        let dirent = dir_entry_to_dirent(entry);
+        copy_to_user(
+            buf_addr.offset(i * size_of::<Dirent>()),
+            slice::from_ref(&dirent),
+        )?;
+    }
+
+    Ok(entries.len())
+}
+
+fn sys_getpid() -> Result<usize> {
+    task::current_pid().ok_or(Error::Failed("User task is not running"))
+}
+
+fn sys_socket(domain: u16, ty: u64) -> Result<SocketId> {
+    if domain != AF_INET || ty != SOCK_DGRAM {
+        return Err(Error::Failed("Only UDP sockets are supported"));
+    }
+
+    let sd = net::create_udp_socket()?;
+    task::push_sd(sd);
+
+    Ok(sd)
+}
+
+fn sys_bind(sd: SocketId, addr_ptr: *const u8) -> Result<()> {
+    let addr = user_sockaddr_in(addr_ptr)?;
+    task::check_sd(&sd)?;
+    let port = net::bind_udp_socket(sd, u16::from_be(addr.port))?;
+    info!("syscall: bind: socket {} bound to port {}", sd.get(), port);
 
     Ok(())
 }
 
+fn sys_sendto(
+    sd: SocketId,
+    buf_ptr: *const u8,
+    buf_len: usize,
+    addr_ptr: *const u8,
+) -> Result<usize> {
+    let buf = copy_from_user(buf_ptr, buf_len)?;
+    let addr = user_sockaddr_in(addr_ptr)?;
+    task::check_sd(&sd)?;
+    net::send_udp_datagram(sd, addr.addr.into(), u16::from_be(addr.port), &buf)?;
+
+    Ok(buf_len)
+}
+
+// the rest of a datagram longer than the buffer is discarded
+fn sys_recvfrom(
+    sd: SocketId,
+    buf_addr: VirtualAddress,
+    buf_len: usize,
+    addr_addr: VirtualAddress,
+) -> Result<usize> {
+    task::check_sd(&sd)?;
+
+    // wait until a datagram is received
+    let datagram = loop {
+        match net::recv_udp_datagram(sd)? {
+            Some(datagram) => break datagram,
+            None => task::wait_yield()?,
+        }
+    };
+
+    let len = datagram.payload.len().min(buf_len);
+    copy_to_user(buf_addr, &datagram.payload[..len])?;
+
+    if addr_addr.get() != 0 {
+        let addr = SockaddrIn {
+            family: AF_INET,
+            port: datagram.src_port.to_be(),
+            addr: datagram.src_addr.octets(),
+            ..Default::default()
+        };
+        copy_to_user(addr_addr, slice::from_ref(&addr))?;
+    }
+
+    Ok(len)
+}
+
+fn sys_close_socket(sd: SocketId) -> Result<()> {
+    task::check_sd(&sd)?;
+    net::close_udp_socket(sd)?;
+    task::remove_sd(&sd);
+
+    Ok(())
+}
+
+// blocks until the hostname is resolved, the address is written as 4 octets
+fn sys_gethostbyname(hostname_ptr: *const u8, addr_addr: VirtualAddress) -> Result<()> {
+    let hostname = user_cstring(hostname_ptr)?;
+    let addr = net::dns::resolve(&hostname)?;
+    copy_to_user(addr_addr, &addr.octets())
+}
+
+fn dir_entry_to_dirent(entry: &DirEntry) -> Dirent {
+    let mut dirent = Dirent {
+        size: entry.size as u64,
+        is_dir: entry.is_dir,
+        ..Default::default()
+    };
+
+    // truncate long names, keeping the null terminator
+    let name = entry.name.as_bytes();
+    let len = name.len().min(DIRENT_NAME_LEN - 1);
+    dirent.name[..len].copy_from_slice(&name[..len]);
+
+    dirent
+}
+
 fn sys_sbrksz(target_addr: VirtualAddress) -> Result<usize> {
     let size = task::get_memory_frame_size_by_virt_addr(target_addr)?
         .ok_or(Error::Failed("Failed to get memory frame size"))?;
@@ -522,6 +1143,22 @@ fn sys_add_image_to_window(
     Ok(())
 }
 
+fn sys_screenshot(path_ptr: *const u8) -> Result<()> {
+    let path = user_cstring(path_ptr)?;
+    let bitmap = frame_buf::capture()?;
+
+    // overwrite the existing file
+    if vfs::stat(&path).is_ok() {
+        vfs::remove(&path, false)?;
+    }
+    vfs::create_file(&path)?;
+
+    let fd_num = vfs::open_file(&path)?;
+    let res = vfs::write_file(&fd_num, &bitmap);
+    vfs::close_file(&fd_num)?;
+    res
+}
+
 pub fn enable() {
     let mut efer = ExtendedFeatureEnableRegister::read();
     efer.set_syscall_enable(true);
@@ -554,3 +1191,73 @@ pub fn enable() {
 
     info!("syscall: Enabled syscall");
 }
+
+// reject buffers which are not mapped to the user or point to kernel memory
+// rw is the access by the kernel, buffers written by the kernel must be writable
+fn check_user_buf(buf_addr: VirtualAddress, buf_len: usize, rw: ReadWrite) -> Result<()> {
+    task::populate_demand_zero_pages(buf_addr, buf_len)?;
+    if !paging::is_user_accessible(buf_addr, buf_len, rw) {
+        return Err(Error::Failed("bad user pointer"));
+    }
+
+    Ok(())
+}
+
+// copy the user buffer to the kernel after checking the pages
+pub fn copy_from_user(ptr: *const u8, len: usize) -> Result<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    check_user_buf((ptr as u64).into(), len, ReadWrite::Read)?;
+    Ok(unsafe { slice::from_raw_parts(ptr, len) }.to_vec())
+}
+
+// data is written as raw bytes, the user buffer must be writable
+pub fn copy_to_user<T: Copy>(buf_addr: VirtualAddress, data: &[T]) -> Result<()> {
+    check_user_buf(buf_addr, size_of_val(data), ReadWrite::Write)?;
+    buf_addr.copy_from_nonoverlapping(data.as_ptr(), data.len());
+    Ok(())
+}
+
+fn user_sockaddr_in(addr_ptr: *const u8) -> Result<SockaddrIn> {
+    let buf = copy_from_user(addr_ptr, size_of::<SockaddrIn>())?;
+    let addr = unsafe { (buf.as_ptr() as *const SockaddrIn).read_unaligned() };
+
+    if addr.family != AF_INET {
+        return Err(Error::Failed("Unsupported address family"));
+    }
+
+    Ok(addr)
+}
+
+fn user_cstring(s_ptr: *const u8) -> Result<String> {
+    // check each page before reading a byte on it
+    let mut len = 0;
+    loop {
+        let addr = s_ptr as u64 + len as u64;
+        if (len == 0 || addr % PAGE_SIZE as u64 == 0)
+            && !paging::is_user_accessible(addr.into(), 1, ReadWrite::Read)
+        {
+            return Err(Error::Failed("bad user pointer"));
+        }
+
+        if unsafe { *(addr as *const u8) } == 0 {
+            break;
+        }
+        len += 1;
+    }
+
+    Ok(unsafe { util::cstring::from_cstring_ptr(s_ptr) })
+}
+
+#[test_case]
+fn test_syscall_number() {
+    assert_eq!(Syscall::try_from(0).unwrap(), Syscall::Read);
+    assert_eq!(Syscall::try_from(30).unwrap(), Syscall::Readdir);
+    assert!(Syscall::try_from(u64::MAX).is_err());
+
+    for num in 0..42 {
+        assert_eq!(Syscall::try_from(num).unwrap() as u64, num);
+    }
+}
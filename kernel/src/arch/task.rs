@@ -1,24 +1,42 @@
 use crate::{
-    arch::{addr::VirtualAddress, context::*},
+    arch::{addr::*, context::*, tss},
+    debug::{
+        self,
+        dwarf::{Dwarf, LineInfo},
+    },
+    env,
     error::*,
-    fs::{self, vfs::file_desc::FileDescriptorNumber},
+    fs::{
+        self,
+        path::Path,
+        vfs::{self, file_desc::FileDescriptorNumber, VirtualFileSystemError},
+    },
     graphics::{multi_layer::LayerId, simple_window_manager},
     mem::{
         bitmap::{self, MemoryFrameInfo},
         paging::{self, *},
     },
+    net::{self, udp::SocketId},
     util::mutex::Mutex,
 };
-use alloc::{boxed::Box, collections::VecDeque, ffi::CString, vec::Vec};
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    ffi::CString,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use common::elf::{self, *};
 use core::{
     future::Future,
+    mem::size_of,
     pin::Pin,
     ptr::null,
     sync::atomic::*,
     task::{Context as ExecutorContext, Poll, RawWaker, RawWakerVTable, Waker},
 };
-use log::{debug, trace};
+use log::{debug, trace, warn};
 
 static mut TASK_EXECUTOR: Mutex<Executor> = Mutex::new(Executor::new());
 
@@ -26,6 +44,27 @@ static mut KERNEL_TASK: Mutex<Option<Task>> = Mutex::new(None);
 static mut USER_TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
 static mut USER_EXIT_STATUS: Option<u64> = None;
 
+// background tasks run while the foreground task is waiting
+static mut BACKGROUND_TASKS: Mutex<VecDeque<Task>> = Mutex::new(VecDeque::new());
+static mut RUNNING_BACKGROUND_TASK: Mutex<Option<Task>> = Mutex::new(None);
+static mut BACKGROUND_RETURN_CONTEXT: Mutex<Context> = Mutex::new(Context::new());
+
+static mut PROCESS_TABLE: Mutex<Vec<ProcessInfo>> = Mutex::new(Vec::new());
+
+const USER_TASK_STACK_SIZE: usize = 1024 * 1024;
+const PIE_LOAD_BASE: u64 = 0x10000000; // same as the image base of the apps
+const USER_TASK_KERNEL_STACK_LEN: usize = 8; // frames
+const DEMAND_ZERO_MIN_PAGES: usize = 16; // smaller bss is allocated at loading
+
+// the heap and anonymous mappings are placed above the identity mapping of the physical memory
+const BRK_AREA_START: u64 = paging::USER_AREA_START;
+const BRK_AREA_END: u64 = MMAP_AREA_START;
+const MMAP_AREA_START: u64 = 0x180_0000_0000;
+const MMAP_AREA_END: u64 = paging::USER_AREA_END;
+
+// poll kernel tasks and switch user tasks from the local APIC timer interrupt
+static PREEMPTIVE: AtomicBool = AtomicBool::new(false);
+
 #[derive(Default)]
 struct Yield {
     polled: AtomicBool,
@@ -139,30 +178,78 @@ pub fn ready() -> Result<()> {
     Ok(())
 }
 
+pub fn set_preemptive(value: bool) {
+    PREEMPTIVE.store(value, Ordering::SeqCst);
+}
+
+pub fn is_preemptive() -> bool {
+    PREEMPTIVE.load(Ordering::SeqCst)
+}
+
 pub fn spawn(future: impl Future<Output = ()> + 'static) -> Result<()> {
     let task = ExecutorTask::new(future);
     unsafe { TASK_EXECUTOR.try_lock() }?.spawn(task);
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    Exited(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: usize,
+    pub name: String,
+    pub is_background: bool,
+    pub state: ProcessState,
+    pub program_break: VirtualAddress,
+}
+
+// zero-filled pages allocated on the first access
+#[derive(Debug, Clone, Copy)]
+struct DemandZeroRegion {
+    start: VirtualAddress,
+    end: VirtualAddress,
+    rw: ReadWrite,
+    exec_disable: bool,
+}
+
+impl DemandZeroRegion {
+    fn contains(&self, virt_addr: VirtualAddress) -> bool {
+        virt_addr.get() >= self.start.get() && virt_addr.get() < self.end.get()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Task {
     id: TaskId,
     context: Context,
-    args_mem_frame_info: Option<MemoryFrameInfo>,
+    page_table: Option<PhysicalAddress>, // own PML4 table
+    kernel_stack_mem_frame_info: Option<MemoryFrameInfo>,
+    exit_status: Option<u64>,
     stack_mem_frame_info: MemoryFrameInfo,
     program_mem_info: Vec<(MemoryFrameInfo, MappingInfo)>,
+    demand_zero_regions: Vec<DemandZeroRegion>,
+    // the current break is stored in the process table
+    brk_mem_info: Vec<(MemoryFrameInfo, MappingInfo)>, // a page per entry
+    mmap_mem_info: Vec<(MemoryFrameInfo, MappingInfo)>, // a page per entry
     allocated_mem_frame_info: Vec<MemoryFrameInfo>,
     created_wd: Vec<LayerId>,
+    created_sd: Vec<SocketId>,
     opend_fd: Vec<FileDescriptorNumber>,
+    // redirection of stdin / stdout
+    stdin: Option<FileDescriptorNumber>,
+    stdout: Option<FileDescriptorNumber>,
+    // absolute path, the kernel task uses the cwd of the VFS
+    cwd: Option<String>,
+    dwarf: Option<Dwarf>,
 }
 
 impl Drop for Task {
     fn drop(&mut self) {
-        if let Some(args_mem_frame_info) = self.args_mem_frame_info {
-            args_mem_frame_info.set_permissions_to_supervisor().unwrap();
-            bitmap::dealloc_mem_frame(args_mem_frame_info).unwrap();
-        }
+        debug::remove_user_app_breakpoints(self.id.get());
 
         self.stack_mem_frame_info
             .set_permissions_to_supervisor()
@@ -170,24 +257,44 @@ impl Drop for Task {
         bitmap::dealloc_mem_frame(self.stack_mem_frame_info).unwrap();
 
         for (mem_info, mapping_info) in self.program_mem_info.iter() {
-            let start = mapping_info.start;
-            paging::update_mapping(&MappingInfo {
-                start,
-                end: mapping_info.end,
-                phys_addr: start.get().into(),
-                rw: ReadWrite::Write,
-                us: EntryMode::Supervisor,
-                pwt: PageWriteThroughLevel::WriteThrough,
-            })
-            .unwrap();
+            // segments are not mapped in the kernel page table
+            if self.page_table.is_none() {
+                let start = mapping_info.start;
+                paging::update_mapping(&MappingInfo {
+                    start,
+                    end: mapping_info.end,
+                    phys_addr: start.get().into(),
+                    rw: ReadWrite::Write,
+                    us: EntryMode::Supervisor,
+                    pwt: PageWriteThroughLevel::WriteThrough,
+                    exec_disable: false,
+                })
+                .unwrap();
+
+                assert_eq!(
+                    paging::calc_virt_addr(start.get().into()).unwrap().get(),
+                    start.get()
+                );
+            }
+            bitmap::dealloc_mem_frame(*mem_info).unwrap();
+        }
 
-            assert_eq!(
-                paging::calc_virt_addr(start.get().into()).unwrap().get(),
-                start.get()
-            );
+        for (mem_info, mapping_info) in self.brk_mem_info.iter().chain(self.mmap_mem_info.iter()) {
+            // mapped above the physical memory, so just clear the entry
+            if self.page_table.is_none() {
+                paging::unmap(mapping_info.start).unwrap();
+            }
             bitmap::dealloc_mem_frame(*mem_info).unwrap();
         }
 
+        if let Some(pml4_phys_addr) = self.page_table {
+            paging::destroy_user_page_table(pml4_phys_addr).unwrap();
+        }
+
+        if let Some(kernel_stack_mem_frame_info) = self.kernel_stack_mem_frame_info {
+            bitmap::dealloc_mem_frame(kernel_stack_mem_frame_info).unwrap();
+        }
+
         for mem_frame_info in self.allocated_mem_frame_info.iter() {
             mem_frame_info.set_permissions_to_supervisor().unwrap();
             bitmap::dealloc_mem_frame(*mem_frame_info).unwrap();
@@ -198,9 +305,18 @@ impl Drop for Task {
             simple_window_manager::destroy_window(wd).unwrap();
         }
 
-        // close all opend files
+        // close all created sockets
+        for sd in self.created_sd.iter() {
+            if let Err(err) = net::close_udp_socket(*sd) {
+                warn!("task: Failed to close socket {}: {:?}", sd.get(), err);
+            }
+        }
+
+        // close all opend files, removed files were already released
         for fd in self.opend_fd.iter() {
-            fs::vfs::close_file(fd).unwrap();
+            if let Err(err) = fs::vfs::close_file(fd) {
+                warn!("task: Failed to close file {}: {:?}", fd.get(), err);
+            }
         }
 
         trace!("task: Dropped tid: {}", self.id.get());
@@ -208,20 +324,64 @@ impl Drop for Task {
 }
 
 impl Task {
+    // write relocated values through the kernel mapping of the loaded frames
+    fn relocate(
+        elf64: &Elf64,
+        load_bias: u64,
+        program_mem_info: &[(MemoryFrameInfo, MappingInfo)],
+    ) -> Result<()> {
+        for rela in elf64.relocations()? {
+            match rela.relocation_type() {
+                RelocationType::None => continue,
+                RelocationType::Relative => (),
+                ty => return Err(Elf64Error::UnsupportedRelocationTypeError(ty).into()),
+            }
+
+            let target = rela.offset + load_bias;
+            let (frame_info, mapping_info) = program_mem_info
+                .iter()
+                .find(|(_, m)| {
+                    target >= m.start.get() && target + size_of::<u64>() as u64 <= m.end.get()
+                })
+                .ok_or(Error::Failed("Relocation target is out of the segments"))?;
+
+            let value = load_bias.wrapping_add_signed(rela.addend);
+            let ptr = frame_info
+                .frame_start_virt_addr()?
+                .offset((target - mapping_info.start.get()) as usize)
+                .as_ptr_mut::<u64>();
+            unsafe { ptr.write_unaligned(value) };
+        }
+
+        Ok(())
+    }
+
     fn new(
         stack_size: usize, // 4KiB align
         elf64: Option<Elf64>,
         args: Option<&[&str]>, // file name + args
         mode: ContextMode,
+        own_page_table: bool,
     ) -> Result<Self> {
+        let dwarf = elf64.as_ref().and_then(Dwarf::new);
+
         // parse ELF
         let mut entry = None;
         let mut program_mem_info = Vec::new();
+        let mut demand_zero_regions = Vec::new();
         if let Some(elf64) = elf64 {
             let header = elf64.header();
 
-            if header.elf_type() != elf::Type::Executable {
-                return Err(Error::Failed("The file is not an executable file"));
+            // position-independent executables are loaded at PIE_LOAD_BASE
+            let load_bias = match header.elf_type() {
+                elf::Type::Executable => 0,
+                elf::Type::Shared => PIE_LOAD_BASE,
+                _ => return Err(Error::Failed("The file is not an executable file")),
+            };
+
+            // no dynamic linker, only relative relocations are applied
+            if let Some(interpreter) = elf64.interpreter() {
+                debug!("task: Ignored the ELF interpreter: {}", interpreter);
             }
 
             if header.machine() != elf::Machine::X8664 {
@@ -233,13 +393,37 @@ impl Task {
                     continue;
                 }
 
-                let p_virt_addr = program_header.virt_addr;
+                let p_virt_addr = program_header.virt_addr + load_bias;
                 let p_mem_size = program_header.mem_size;
                 let p_file_size = program_header.file_size;
 
-                let pages_needed =
-                    ((p_virt_addr % PAGE_SIZE as u64 + p_mem_size + PAGE_SIZE as u64 - 1)
-                        / PAGE_SIZE as u64) as usize;
+                let page_size = PAGE_SIZE as u64;
+                let seg_start = p_virt_addr / page_size * page_size;
+                let seg_end = (p_virt_addr + p_mem_size).div_ceil(page_size) * page_size;
+                let rw = match program_header.is_writable() && !program_header.is_executable() {
+                    true => ReadWrite::Write,
+                    false => ReadWrite::Read,
+                };
+
+                // large bss beyond the file data is mapped on the first access
+                let demand_zero_start = ((p_virt_addr + p_file_size).div_ceil(page_size)
+                    * page_size)
+                    .max(seg_start + page_size);
+                let mut alloc_end = seg_end;
+                if seg_end > demand_zero_start
+                    && ((seg_end - demand_zero_start) / page_size) as usize >= DEMAND_ZERO_MIN_PAGES
+                {
+                    demand_zero_regions.push(DemandZeroRegion {
+                        start: demand_zero_start.into(),
+                        end: seg_end.into(),
+                        rw,
+                        exec_disable: !program_header.is_executable(),
+                    });
+                    alloc_end = demand_zero_start;
+                }
+
+                let pages_needed = ((alloc_end - seg_start) / page_size) as usize;
+                // zero-filled, including the bss (mem_size > file_size)
                 let user_mem_frame_info = bitmap::alloc_mem_frame(pages_needed)?;
                 bitmap::mem_clear(&user_mem_frame_info)?;
                 let user_mem_frame_start_virt_addr = user_mem_frame_info.frame_start_virt_addr()?;
@@ -252,25 +436,31 @@ impl Task {
                         .copy_from_nonoverlapping(data.as_ptr(), p_file_size as usize);
                 }
 
-                // update page mapping
-                let start_virt_addr = (p_virt_addr / PAGE_SIZE as u64 * PAGE_SIZE as u64).into();
+                // update page mapping (W^X by the segment flags)
+                let start_virt_addr = seg_start.into();
                 let mapping_info = MappingInfo {
                     start: start_virt_addr,
                     end: start_virt_addr.offset(user_mem_frame_info.frame_size),
                     phys_addr: user_mem_frame_info.frame_start_phys_addr,
-                    rw: ReadWrite::Write,
+                    rw,
                     us: EntryMode::User,
                     pwt: PageWriteThroughLevel::WriteThrough,
+                    exec_disable: !program_header.is_executable(),
                 };
-                paging::update_mapping(&mapping_info)?;
+                if !own_page_table {
+                    paging::update_mapping(&mapping_info)?;
+                }
                 program_mem_info.push((user_mem_frame_info, mapping_info));
 
-                if header.entry_point >= p_virt_addr
-                    && header.entry_point < p_virt_addr + p_mem_size
-                {
-                    entry = Some(header.entry_point);
+                let entry_point = header.entry_point + load_bias;
+                if entry_point >= p_virt_addr && entry_point < p_virt_addr + p_mem_size {
+                    entry = Some(entry_point);
                 }
             }
+
+            if load_bias != 0 {
+                Self::relocate(&elf64, load_bias, &program_mem_info)?;
+            }
         }
 
         let rip = match entry {
@@ -285,59 +475,100 @@ impl Task {
             ContextMode::Kernel => stack_mem_frame_info.set_permissions_to_supervisor()?,
             ContextMode::User => stack_mem_frame_info.set_permissions_to_user()?,
         }
-        let rsp =
-            (stack_mem_frame_info.frame_start_virt_addr()?.get() + stack_size as u64 - 63) & !63;
-        assert!(rsp % 64 == 0); // must be 64 bytes align for SSE and AVX instructions, etc.
+        let stack_top = stack_mem_frame_info.frame_start_virt_addr()?.get() + stack_size as u64;
+        let mut rsp = (stack_top - 63) & !63;
 
-        // args
-        let mut args_mem_frame_info = None;
-        let mut arg0 = 0; // args len
-        let mut arg1 = 0; // args virt addr
+        // initial stack (System V AMD64 ABI)
+        // rsp -> argc, argv[0..argc], NULL, envp[0..], NULL, auxv (AT_NULL), strings
+        let mut arg0 = 0; // argc
+        let mut arg1 = 0; // argv virt addr
         if let Some(args) = args {
-            let mut c_args = Vec::new();
-            for arg in args {
-                c_args.extend(CString::new(*arg).unwrap().into_bytes_with_nul());
-            }
-
-            let mut c_args_offset = (args.len() + 2) * 8;
-            let mem_frame_info =
-                bitmap::alloc_mem_frame(((c_args.len() + c_args_offset) / PAGE_SIZE).max(1))?;
-            bitmap::mem_clear(&mem_frame_info)?;
-            match mode {
-                ContextMode::Kernel => mem_frame_info.set_permissions_to_supervisor()?,
-                ContextMode::User => mem_frame_info.set_permissions_to_user()?,
+            let mut strings = Vec::new();
+            let mut offsets = Vec::new();
+            for s in args.iter().chain(env::USER_ENVP.iter()) {
+                offsets.push(strings.len() as u64);
+                strings.extend(CString::new(*s).unwrap().into_bytes_with_nul());
             }
-
-            let args_mem_virt_addr = mem_frame_info.frame_start_virt_addr()?;
-            args_mem_virt_addr
-                .offset(c_args_offset)
-                .copy_from_nonoverlapping(c_args.as_ptr(), c_args.len());
-
-            let mut c_args_ref = Vec::new();
-            for arg in args {
-                c_args_ref.push(args_mem_virt_addr.offset(c_args_offset).get());
-                c_args_offset += arg.len() + 1;
-            }
-            args_mem_virt_addr.copy_from_nonoverlapping(c_args_ref.as_ptr(), c_args_ref.len());
-
-            args_mem_frame_info = Some(mem_frame_info);
+            let strings_addr = (stack_top - strings.len() as u64) & !0xf;
+
+            let (argv_offsets, envp_offsets) = offsets.split_at(args.len());
+            let mut table = vec![args.len() as u64];
+            table.extend(argv_offsets.iter().map(|o| strings_addr + o));
+            table.push(0);
+            table.extend(envp_offsets.iter().map(|o| strings_addr + o));
+            table.push(0);
+            table.extend([0, 0]); // AT_NULL
+
+            rsp = (strings_addr - (table.len() * 8) as u64) & !63;
+            VirtualAddress::new(strings_addr)
+                .copy_from_nonoverlapping(strings.as_ptr(), strings.len());
+            VirtualAddress::new(rsp).copy_from_nonoverlapping(table.as_ptr(), table.len());
+
+            // also passed by registers for apps reading them as _start(argc, argv)
             arg0 = args.len() as u64;
-            arg1 = args_mem_virt_addr.get();
+            arg1 = rsp + 8;
         }
+        assert!(rsp % 64 == 0); // must be 64 bytes align for SSE and AVX instructions, etc.
 
         // context
         let mut context = Context::new();
         context.init(rip, arg0, arg1, rsp, mode);
 
+        // map program segments to the own page table
+        let mut page_table = None;
+        let mut kernel_stack_mem_frame_info = None;
+        if own_page_table {
+            let pml4_phys_addr = paging::create_user_page_table()?;
+            for (_, mapping_info) in program_mem_info.iter() {
+                let MappingInfo {
+                    start,
+                    end,
+                    phys_addr,
+                    rw,
+                    us,
+                    pwt,
+                    exec_disable,
+                } = *mapping_info;
+
+                for offset in (0..(end.get() - start.get()) as usize).step_by(PAGE_SIZE) {
+                    paging::map_to_user_page_table(
+                        pml4_phys_addr,
+                        start.offset(offset),
+                        phys_addr.offset(offset),
+                        rw,
+                        us,
+                        pwt,
+                        exec_disable,
+                    )?;
+                }
+            }
+            context.cr3 = pml4_phys_addr.get();
+            page_table = Some(pml4_phys_addr);
+
+            // used by interrupts from user mode while running this task
+            kernel_stack_mem_frame_info =
+                Some(bitmap::alloc_mem_frame(USER_TASK_KERNEL_STACK_LEN)?);
+        }
+
         Ok(Self {
             id: TaskId::new(),
             context,
-            args_mem_frame_info,
+            page_table,
+            kernel_stack_mem_frame_info,
+            exit_status: None,
             stack_mem_frame_info,
             program_mem_info,
+            demand_zero_regions,
+            brk_mem_info: Vec::new(),
+            mmap_mem_info: Vec::new(),
             allocated_mem_frame_info: Vec::new(),
             created_wd: Vec::new(),
+            created_sd: Vec::new(),
             opend_fd: Vec::new(),
+            stdin: None,
+            stdout: None,
+            cwd: None,
+            dwarf,
         })
     }
 
@@ -351,6 +582,7 @@ impl Task {
                 rw: ReadWrite::Write,
                 us: EntryMode::Supervisor,
                 pwt: PageWriteThroughLevel::WriteThrough,
+                exec_disable: false,
             })?;
 
             assert_eq!(
@@ -359,17 +591,241 @@ impl Task {
             );
         }
 
+        for (_, mapping_info) in self.brk_mem_info.iter().chain(self.mmap_mem_info.iter()) {
+            paging::unmap(mapping_info.start)?;
+        }
+
         Ok(())
     }
 
     fn remap_virt_addr(&self) -> Result<()> {
-        for (_, mapping_info) in self.program_mem_info.iter() {
+        for (_, mapping_info) in self
+            .program_mem_info
+            .iter()
+            .chain(self.brk_mem_info.iter())
+            .chain(self.mmap_mem_info.iter())
+        {
             paging::update_mapping(mapping_info)?;
         }
 
         Ok(())
     }
 
+    fn is_mapped_range(&self, start: u64, end: u64) -> bool {
+        let overlaps = |s: VirtualAddress, e: VirtualAddress| start < e.get() && s.get() < end;
+
+        self.program_mem_info
+            .iter()
+            .chain(self.brk_mem_info.iter())
+            .chain(self.mmap_mem_info.iter())
+            .any(|(_, m)| overlaps(m.start, m.end))
+            || self
+                .demand_zero_regions
+                .iter()
+                .any(|r| overlaps(r.start, r.end))
+    }
+
+    // use the hint address if it is available, otherwise the first free range
+    fn find_mmap_range(&self, hint: Option<u64>, len: u64) -> Option<u64> {
+        let is_available = |start: u64| {
+            start >= MMAP_AREA_START
+                && start
+                    .checked_add(len)
+                    .is_some_and(|end| end <= MMAP_AREA_END && !self.is_mapped_range(start, end))
+        };
+
+        if let Some(hint) = hint.filter(|&h| is_available(h)) {
+            return Some(hint);
+        }
+
+        let mut start = MMAP_AREA_START;
+        while start + len <= MMAP_AREA_END {
+            // skip to the end of the overlapped mapping
+            match self
+                .mmap_mem_info
+                .iter()
+                .filter(|(_, m)| start < m.end.get() && m.start.get() < start + len)
+                .map(|(_, m)| m.end.get())
+                .max()
+            {
+                Some(end) => start = end,
+                None => return Some(start),
+            }
+        }
+
+        None
+    }
+
+    // grow or shrink the heap by whole pages
+    fn set_program_break(&mut self, current: VirtualAddress, new: VirtualAddress) -> Result<()> {
+        if new.get() < BRK_AREA_START || new.get() > BRK_AREA_END {
+            return Err(Error::Failed("Program break is out of the heap"));
+        }
+
+        let page_size = PAGE_SIZE as u64;
+        let old_end = current.get().div_ceil(page_size) * page_size;
+        let new_end = new.get().div_ceil(page_size) * page_size;
+
+        if new_end <= old_end {
+            return self.unmap_heap_pages(new_end, old_end);
+        }
+
+        if self.is_mapped_range(old_end, new_end) {
+            return Err(Error::Failed("Heap collides with other mappings"));
+        }
+
+        for page in (old_end..new_end).step_by(PAGE_SIZE) {
+            match self.map_zero_page(page.into(), ReadWrite::Write, EntryMode::User, true) {
+                Ok(page) => self.brk_mem_info.push(page),
+                Err(err) => {
+                    self.unmap_heap_pages(old_end, page)?;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // must be called while the page table of the task is active
+    fn unmap_heap_pages(&mut self, start: u64, end: u64) -> Result<()> {
+        let (unmapped, mapped): (Vec<_>, Vec<_>) = core::mem::take(&mut self.brk_mem_info)
+            .into_iter()
+            .partition(|(_, m)| m.start.get() >= start && m.start.get() < end);
+        self.brk_mem_info = mapped;
+
+        for (mem_frame_info, mapping_info) in unmapped {
+            paging::unmap(mapping_info.start)?;
+            bitmap::dealloc_mem_frame(mem_frame_info)?;
+        }
+
+        Ok(())
+    }
+
+    // map a zero-filled frame to the page of the task
+    fn map_zero_page(
+        &self,
+        page_virt_addr: VirtualAddress,
+        rw: ReadWrite,
+        us: EntryMode,
+        exec_disable: bool,
+    ) -> Result<(MemoryFrameInfo, MappingInfo)> {
+        let mem_frame_info = bitmap::alloc_mem_frame(1)?;
+        bitmap::mem_clear(&mem_frame_info)?;
+        let mapping_info = MappingInfo {
+            start: page_virt_addr,
+            end: page_virt_addr.offset(PAGE_SIZE),
+            phys_addr: mem_frame_info.frame_start_phys_addr,
+            rw,
+            us,
+            pwt: PageWriteThroughLevel::WriteThrough,
+            exec_disable,
+        };
+
+        let res = match self.page_table {
+            Some(pml4_phys_addr) => paging::map_to_user_page_table(
+                pml4_phys_addr,
+                mapping_info.start,
+                mapping_info.phys_addr,
+                mapping_info.rw,
+                mapping_info.us,
+                mapping_info.pwt,
+                mapping_info.exec_disable,
+            ),
+            None => paging::update_mapping(&mapping_info),
+        };
+
+        if let Err(err) = res {
+            bitmap::dealloc_mem_frame(mem_frame_info)?;
+            return Err(err);
+        }
+        super::invlpg(page_virt_addr.get());
+
+        Ok((mem_frame_info, mapping_info))
+    }
+
+    fn map_anonymous_pages(
+        &mut self,
+        start: VirtualAddress,
+        page_len: usize,
+        rw: ReadWrite,
+        us: EntryMode,
+        exec_disable: bool,
+    ) -> Result<()> {
+        for i in 0..page_len {
+            let page = self.map_zero_page(start.offset(i * PAGE_SIZE), rw, us, exec_disable)?;
+            self.mmap_mem_info.push(page);
+        }
+
+        trace!(
+            "task: Mapped {} anonymous pages: 0x{:x} (tid: {})",
+            page_len,
+            start.get(),
+            self.id.get()
+        );
+        Ok(())
+    }
+
+    // pages in the range which are not mapped by mmap are ignored
+    // must be called while the page table of the task is active
+    fn unmap_anonymous_pages(&mut self, start: u64, end: u64) -> Result<()> {
+        let (unmapped, mapped): (Vec<_>, Vec<_>) = core::mem::take(&mut self.mmap_mem_info)
+            .into_iter()
+            .partition(|(_, m)| m.start.get() >= start && m.start.get() < end);
+        self.mmap_mem_info = mapped;
+
+        for (mem_frame_info, mapping_info) in unmapped {
+            paging::unmap(mapping_info.start)?;
+            bitmap::dealloc_mem_frame(mem_frame_info)?;
+        }
+
+        Ok(())
+    }
+
+    // returns false if the page is not in the demand-zero regions or already mapped
+    fn map_demand_zero_page(&mut self, page_virt_addr: VirtualAddress) -> Result<bool> {
+        let region = match self
+            .demand_zero_regions
+            .iter()
+            .find(|r| r.contains(page_virt_addr))
+        {
+            Some(region) => *region,
+            None => return Ok(false),
+        };
+
+        if self.program_mem_info.iter().any(|(_, m)| {
+            page_virt_addr.get() >= m.start.get() && page_virt_addr.get() < m.end.get()
+        }) {
+            return Ok(false);
+        }
+
+        let page = self.map_zero_page(
+            page_virt_addr,
+            region.rw,
+            EntryMode::User,
+            region.exec_disable,
+        )?;
+        // unmapped and deallocated with the segments
+        self.program_mem_info.push(page);
+
+        trace!(
+            "task: Mapped demand-zero page: 0x{:x} (tid: {})",
+            page_virt_addr.get(),
+            self.id.get()
+        );
+        Ok(true)
+    }
+
+    fn kernel_stack_top(&self) -> Result<u64> {
+        let mem_frame_info = self
+            .kernel_stack_mem_frame_info
+            .ok_or(Error::Failed("Kernel stack was not allocated"))?;
+        Ok(mem_frame_info
+            .frame_start_virt_addr()?
+            .offset(mem_frame_info.frame_size)
+            .get())
+    }
+
     fn switch_to(&self, next_task: &Task) {
         trace!(
             "task: Switch context tid: {} to {}",
@@ -382,27 +838,33 @@ impl Task {
 }
 
 pub fn exec_user_task(elf64: Elf64, file_name: &str, args: &[&str]) -> Result<u64> {
+    if is_running_background_task() {
+        return Err(Error::Failed("Background task cannot execute a task"));
+    }
+
     let kernel_task = unsafe { KERNEL_TASK.get_force_mut() };
     let user_tasks = unsafe { USER_TASKS.get_force_mut() };
 
     if kernel_task.is_none() {
         // stack is unused, because already allocated static area for kernel stack
-        *kernel_task = Some(Task::new(0, None, None, ContextMode::Kernel)?);
+        *kernel_task = Some(Task::new(0, None, None, ContextMode::Kernel, false)?);
     }
 
+    let cwd = cwd_path()?;
     let is_user = !user_tasks.is_empty();
     if is_user {
         user_tasks.last().unwrap().unmap_virt_addr()?;
     }
 
     let user_task = Task::new(
-        1024 * 1024,
+        USER_TASK_STACK_SIZE,
         Some(elf64),
         Some(&[&[file_name], args].concat()),
         ContextMode::User,
+        false,
     );
 
-    let task = match user_task {
+    let mut task = match user_task {
         Ok(task) => task,
         Err(e) => {
             if is_user {
@@ -411,7 +873,10 @@ pub fn exec_user_task(elf64: Elf64, file_name: &str, args: &[&str]) -> Result<u6
             return Err(e);
         }
     };
+    task.cwd = Some(cwd);
 
+    let pid = task.id.get();
+    push_process(pid, file_name, false, BRK_AREA_START.into());
     user_tasks.push(task);
 
     let is_user = user_tasks.len() > 1;
@@ -425,6 +890,7 @@ pub fn exec_user_task(elf64: Elf64, file_name: &str, args: &[&str]) -> Result<u6
 
     // returned
     drop(user_tasks.pop().unwrap());
+    remove_process(pid);
     if let Some(task) = user_tasks.last() {
         task.remap_virt_addr()?;
     }
@@ -442,21 +908,299 @@ pub fn exec_user_task(elf64: Elf64, file_name: &str, args: &[&str]) -> Result<u6
     Ok(exit_status)
 }
 
-pub fn push_allocated_mem_frame_info_for_user_task(mem_frame_info: MemoryFrameInfo) -> Result<()> {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
+// the new task has an own page table and runs in the background
+// stdin / stdout file descriptors are moved from the current task to the new task
+pub fn spawn_user_task(
+    elf64: Elf64,
+    file_name: &str,
+    args: &[&str],
+    stdin: Option<FileDescriptorNumber>,
+    stdout: Option<FileDescriptorNumber>,
+) -> Result<usize> {
+    if is_running_background_task() {
+        return Err(Error::Failed("Background task cannot spawn a task"));
+    }
+
+    let fds: Vec<FileDescriptorNumber> = [stdin, stdout].into_iter().flatten().collect();
+    if let Some(current_task) = current_user_task() {
+        if fds.iter().any(|fd| {
+            !current_task
+                .opend_fd
+                .iter()
+                .any(|cfdn| cfdn.get() == fd.get())
+        }) {
+            return Err(Error::Failed(
+                "File descriptor is not opened by the current task",
+            ));
+        }
+    }
+
+    let cwd = cwd_path()?;
+    let mut task = Task::new(
+        USER_TASK_STACK_SIZE,
+        Some(elf64),
+        Some(&[&[file_name], args].concat()),
+        ContextMode::User,
+        true,
+    )?;
+
+    for fd in fds {
+        if let Some(current_task) = current_user_task() {
+            current_task.opend_fd.retain(|cfdn| cfdn.get() != fd.get());
+        }
+        task.opend_fd.push(fd);
+    }
+    task.stdin = stdin;
+    task.stdout = stdout;
+    task.cwd = Some(cwd);
+
+    let pid = task.id.get();
+    push_process(pid, file_name, true, BRK_AREA_START.into());
+    unsafe { BACKGROUND_TASKS.get_force_mut() }.push_back(task);
+
+    Ok(pid)
+}
+
+pub fn processes() -> Vec<ProcessInfo> {
+    unsafe { PROCESS_TABLE.get_force_mut() }.clone()
+}
+
+// block until the process exits and remove it from the process table
+pub fn wait_process(pid: usize) -> Result<u64> {
+    loop {
+        let process_table = unsafe { PROCESS_TABLE.get_force_mut() };
+        let index = process_table
+            .iter()
+            .position(|p| p.pid == pid)
+            .ok_or(Error::Failed("Process was not found"))?;
+
+        if let ProcessState::Exited(exit_code) = process_table[index].state {
+            remove_process(pid);
+            return Ok(exit_code);
+        }
+
+        wait_yield()?;
+    }
+}
+
+fn push_process(pid: usize, name: &str, is_background: bool, program_break: VirtualAddress) {
+    unsafe { PROCESS_TABLE.get_force_mut() }.push(ProcessInfo {
+        pid,
+        name: name.to_string(),
+        is_background,
+        state: ProcessState::Running,
+        program_break,
+    });
+
+    if let Err(err) = vfs::add_process_entry(pid) {
+        warn!(
+            "task: Failed to add the process entry (pid: {}): {:?}",
+            pid, err
+        );
+    }
+}
+
+fn remove_process(pid: usize) {
+    unsafe { PROCESS_TABLE.get_force_mut() }.retain(|p| p.pid != pid);
+
+    if let Err(err) = vfs::remove_process_entry(pid) {
+        warn!(
+            "task: Failed to remove the process entry (pid: {}): {:?}",
+            pid, err
+        );
+    }
+}
+
+// pid of the running user task
+pub fn current_pid() -> Option<usize> {
+    Some(current_user_task()?.id.get())
+}
+
+// run a background task until it exits, waits or is preempted
+// return false if there is no background task
+fn run_background_task() -> Result<bool> {
+    let task = match unsafe { BACKGROUND_TASKS.get_force_mut() }.pop_front() {
+        Some(task) => task,
+        None => return Ok(false),
+    };
+
+    let running_task = unsafe { RUNNING_BACKGROUND_TASK.get_force_mut() };
+    let return_context = unsafe { BACKGROUND_RETURN_CONTEXT.get_force_mut() };
+
+    let rsp0 = tss::rsp0();
+    tss::set_rsp0(task.kernel_stack_top()?);
+    *running_task = Some(task);
+    return_context.switch_to(&running_task.as_ref().unwrap().context);
+
+    // returned
+    tss::set_rsp0(rsp0);
+    let task = running_task.take().unwrap();
+    match task.exit_status {
+        Some(exit_status) => {
+            if let Some(process) = unsafe { PROCESS_TABLE.get_force_mut() }
+                .iter_mut()
+                .find(|p| p.pid == task.id.get())
+            {
+                process.state = ProcessState::Exited(exit_status);
+            }
+            drop(task);
+        }
+        None => unsafe { BACKGROUND_TASKS.get_force_mut() }.push_back(task),
+    }
+
+    Ok(true)
+}
+
+fn is_running_background_task() -> bool {
+    unsafe { RUNNING_BACKGROUND_TASK.get_force_mut() }.is_some()
+}
+
+// switch back to the foreground task
+fn yield_background_task() {
+    if let Some(task) = unsafe { RUNNING_BACKGROUND_TASK.get_force_mut() }.as_ref() {
+        task.context
+            .switch_to(unsafe { BACKGROUND_RETURN_CONTEXT.get_force_mut() });
+    }
+}
+
+// called from the local APIC timer interrupt that interrupted user mode
+// the running background task is switched back, and the foreground task
+// gives a time slice to the next background task
+pub fn preempt_user_task() {
+    if is_running_background_task() {
+        yield_background_task();
+        return;
+    }
+
+    if !is_preemptive() {
+        return;
+    }
+
+    if let Err(err) = run_background_task() {
+        warn!("task: Failed to run background task: {:?}", err);
+    }
+}
+
+// called by blocking syscalls while waiting
+pub fn wait_yield() -> Result<()> {
+    if is_running_background_task() {
+        yield_background_task();
+        return Ok(());
+    }
+
+    if !run_background_task()? {
+        super::hlt();
+    }
+
+    Ok(())
+}
+
+fn current_user_task() -> Option<&'static mut Task> {
+    if let Some(task) = unsafe { RUNNING_BACKGROUND_TASK.get_force_mut() }.as_mut() {
+        return Some(task);
+    }
+
+    unsafe { USER_TASKS.get_force_mut() }.last_mut()
+}
+
+// called from the page fault handler
+pub fn handle_demand_zero_page(page_virt_addr: VirtualAddress) -> Result<bool> {
+    match current_user_task() {
+        Some(user_task) => user_task.map_demand_zero_page(page_virt_addr),
+        None => Ok(false),
+    }
+}
+
+// map the untouched demand-zero pages of the range before the kernel accesses them
+pub fn populate_demand_zero_pages(virt_addr: VirtualAddress, len: usize) -> Result<()> {
+    let start = virt_addr.get() & !(PAGE_SIZE as u64 - 1);
+    let end = virt_addr.get().saturating_add(len as u64);
+    for page in (start..end).step_by(PAGE_SIZE) {
+        handle_demand_zero_page(page.into())?;
+    }
+
+    Ok(())
+}
+
+// map zero-filled anonymous pages to the current user task and return the start address
+// the fixed address must be page-aligned and in the mmap area, otherwise the address is a hint
+pub fn mmap(
+    addr: Option<VirtualAddress>,
+    len: usize,
+    rw: ReadWrite,
+    us: EntryMode,
+    exec_disable: bool,
+    is_fixed: bool,
+) -> Result<VirtualAddress> {
+    let user_task = current_user_task().ok_or(Error::Failed("User task is not running"))?;
+
+    if len == 0 || len as u64 > MMAP_AREA_END - MMAP_AREA_START {
+        return Err(Error::Failed("Invalid mapping length"));
+    }
+
+    let page_len = len.div_ceil(PAGE_SIZE);
+    let hint = addr.map(|a| a.get()).filter(|h| h % PAGE_SIZE as u64 == 0);
+    if is_fixed && !hint.is_some_and(|h| h >= MMAP_AREA_START) {
+        return Err(Error::Failed("Invalid fixed mapping address"));
+    }
+
+    let len = (page_len * PAGE_SIZE) as u64;
+    let start = match user_task.find_mmap_range(hint, len) {
+        Some(start) if !is_fixed || Some(start) == hint => start,
+        _ => return Err(Error::Failed("No free virtual address range to map")),
+    };
+
+    if let Err(err) = user_task.map_anonymous_pages(start.into(), page_len, rw, us, exec_disable) {
+        user_task.unmap_anonymous_pages(start, start + len)?;
+        return Err(err);
+    }
+
+    Ok(start.into())
+}
+
+// set the program break of the current user task and return the new break
+// the current break is returned if the address is not specified
+pub fn brk(addr: Option<VirtualAddress>) -> Result<VirtualAddress> {
+    let user_task = current_user_task().ok_or(Error::Failed("User task is not running"))?;
+    let process = unsafe { PROCESS_TABLE.get_force_mut() }
         .iter_mut()
-        .last()
-        .unwrap();
+        .find(|p| p.pid == user_task.id.get())
+        .ok_or(Error::Failed("Process was not found"))?;
+
+    let addr = match addr {
+        Some(addr) => addr,
+        None => return Ok(process.program_break),
+    };
+
+    user_task.set_program_break(process.program_break, addr)?;
+    process.program_break = addr;
+
+    Ok(addr)
+}
+
+pub fn munmap(addr: VirtualAddress, len: usize) -> Result<()> {
+    let user_task = current_user_task().ok_or(Error::Failed("User task is not running"))?;
+
+    if addr.get() % PAGE_SIZE as u64 != 0 || len == 0 {
+        return Err(Error::Failed("Invalid unmapping range"));
+    }
+
+    let end = addr
+        .get()
+        .checked_add(len.div_ceil(PAGE_SIZE) as u64 * PAGE_SIZE as u64)
+        .ok_or(Error::Failed("Invalid unmapping range"))?;
+    user_task.unmap_anonymous_pages(addr.get(), end)
+}
+
+pub fn push_allocated_mem_frame_info_for_user_task(mem_frame_info: MemoryFrameInfo) -> Result<()> {
+    let user_task = current_user_task().unwrap();
     user_task.allocated_mem_frame_info.push(mem_frame_info);
 
     Ok(())
 }
 
 pub fn get_memory_frame_size_by_virt_addr(virt_addr: VirtualAddress) -> Result<Option<usize>> {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
-        .iter_mut()
-        .last()
-        .unwrap();
+    let user_task = current_user_task().unwrap();
 
     for mem_frame_info in &user_task.allocated_mem_frame_info {
         if mem_frame_info.frame_start_virt_addr()? == virt_addr {
@@ -468,42 +1212,100 @@ pub fn get_memory_frame_size_by_virt_addr(virt_addr: VirtualAddress) -> Result<O
 }
 
 pub fn push_wd(wd: LayerId) {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
-        .iter_mut()
-        .last()
-        .unwrap();
+    let user_task = current_user_task().unwrap();
 
     user_task.created_wd.push(wd);
 }
 
 pub fn remove_wd(wd: &LayerId) {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
-        .iter_mut()
-        .last()
-        .unwrap();
+    let user_task = current_user_task().unwrap();
 
     user_task.created_wd.retain(|cwd| cwd.get() != wd.get());
 }
 
+pub fn push_sd(sd: SocketId) {
+    let user_task = current_user_task().unwrap();
+
+    user_task.created_sd.push(sd);
+}
+
+pub fn remove_sd(sd: &SocketId) {
+    let user_task = current_user_task().unwrap();
+
+    user_task.created_sd.retain(|csd| csd != sd);
+}
+
+// sockets created by other tasks are not accessible
+pub fn check_sd(sd: &SocketId) -> Result<()> {
+    match current_user_task() {
+        Some(user_task) if user_task.created_sd.contains(sd) => Ok(()),
+        _ => Err(Error::Failed("Socket is not created by the current task")),
+    }
+}
+
 pub fn push_fd(fd: FileDescriptorNumber) {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
-        .iter_mut()
-        .last()
-        .unwrap();
+    let user_task = current_user_task().unwrap();
 
     user_task.opend_fd.push(fd);
 }
 
 pub fn remove_fd(fd: &FileDescriptorNumber) {
-    let user_task = unsafe { USER_TASKS.get_force_mut() }
-        .iter_mut()
-        .last()
-        .unwrap();
+    let user_task = current_user_task().unwrap();
 
     user_task.opend_fd.retain(|cfdn| cfdn.get() != fd.get());
 }
 
+// resolve stdin / stdout redirection of the current task
+pub fn redirected_fd(fd: FileDescriptorNumber) -> FileDescriptorNumber {
+    let redirection = current_user_task().and_then(|task| match fd {
+        FileDescriptorNumber::STDIN => task.stdin,
+        FileDescriptorNumber::STDOUT => task.stdout,
+        _ => None,
+    });
+
+    redirection.unwrap_or(fd)
+}
+
+// the child task inherits the cwd of the current task
+pub fn cwd_path() -> Result<String> {
+    match current_user_task().and_then(|task| task.cwd.clone()) {
+        Some(cwd) => Ok(cwd),
+        None => vfs::cwd_path(),
+    }
+}
+
+pub fn chdir(path: &str) -> Result<()> {
+    let user_task = match current_user_task() {
+        Some(task) => task,
+        None => return vfs::chdir(path),
+    };
+
+    let path = vfs::abs_path(&resolve_path(path)?)?;
+    if !vfs::stat(&path)?.is_dir {
+        return Err(VirtualFileSystemError::NotDirectoryError.into());
+    }
+
+    user_task.cwd = Some(path);
+    Ok(())
+}
+
+// relative paths from user tasks are resolved from their own cwd
+pub fn resolve_path(path: &str) -> Result<String> {
+    if current_user_task().is_none() {
+        return Ok(path.to_string());
+    }
+
+    Ok(Path::new(&cwd_path()?).join(path).to_string())
+}
+
 pub fn return_task(exit_status: u64) {
+    if let Some(task) = unsafe { RUNNING_BACKGROUND_TASK.get_force_mut() }.as_mut() {
+        task.exit_status = Some(exit_status);
+        task.context
+            .switch_to(unsafe { BACKGROUND_RETURN_CONTEXT.get_force_mut() });
+        unreachable!();
+    }
+
     unsafe {
         USER_EXIT_STATUS = Some(exit_status);
     }
@@ -525,16 +1327,33 @@ pub fn return_task(exit_status: u64) {
 
 pub fn debug_user_task() {
     debug!("===USER TASK INFO===");
-    let user_task = unsafe { USER_TASKS.get_force_mut() }.last();
-    if let Some(task) = user_task {
+    if let Some(task) = current_user_task() {
         debug_task(task);
     } else {
         debug!("User task no available");
     }
 }
 
+// resolve the source line of the current user task
+// borrowed from the debug info of the task, must not be kept after it exits
+pub fn find_user_line_by_ip(ip: u64) -> Option<LineInfo<'static>> {
+    current_user_task()?.dwarf.as_ref()?.find_line_by_ip(ip)
+}
+
+// whether the address is in the executable segments of the current user task
+pub fn is_user_code_addr(virt_addr: u64) -> bool {
+    current_user_task().is_some_and(|task| {
+        task.program_mem_info.iter().any(|(_, m)| {
+            m.us == EntryMode::User
+                && !m.exec_disable
+                && virt_addr >= m.start.get()
+                && virt_addr < m.end.get()
+        })
+    })
+}
+
 pub fn is_running_user_task() -> bool {
-    unsafe { USER_TASKS.get_force_mut() }.len() > 1
+    is_running_background_task() || unsafe { USER_TASKS.get_force_mut() }.len() > 1
 }
 
 fn debug_task(task: &Task) {
@@ -5,6 +5,7 @@ use crate::{
 use core::mem::size_of;
 
 static mut TSS: TaskStateSegment = TaskStateSegment::new();
+pub const FAULT_IST_INDEX: u8 = 1;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(packed)]
@@ -74,6 +75,13 @@ impl TaskStateSegment {
             .get();
         self.rsp[0] = rsp0;
 
+        // separate stack for page fault / double fault handlers
+        let ist = mem::bitmap::alloc_mem_frame(frame_len)?
+            .frame_start_virt_addr()?
+            .offset(frame_len * PAGE_SIZE)
+            .get();
+        self.ist[FAULT_IST_INDEX as usize] = ist;
+
         Ok(())
     }
 }
@@ -85,3 +93,14 @@ pub fn init() -> Result<u64> {
         Ok((&TSS as *const _) as u64)
     }
 }
+
+// stack used by interrupts from user mode
+pub fn rsp0() -> u64 {
+    unsafe { TSS.rsp[0] }
+}
+
+pub fn set_rsp0(rsp0: u64) {
+    unsafe {
+        TSS.rsp[0] = rsp0;
+    }
+}
@@ -0,0 +1,535 @@
+use alloc::vec::Vec;
+use common::elf::Elf64;
+use core::fmt;
+
+// line number program
+const DW_LNS_COPY: u8 = 0x01;
+const DW_LNS_ADVANCE_PC: u8 = 0x02;
+const DW_LNS_ADVANCE_LINE: u8 = 0x03;
+const DW_LNS_SET_FILE: u8 = 0x04;
+const DW_LNS_SET_COLUMN: u8 = 0x05;
+const DW_LNS_NEGATE_STMT: u8 = 0x06;
+const DW_LNS_SET_BASIC_BLOCK: u8 = 0x07;
+const DW_LNS_CONST_ADD_PC: u8 = 0x08;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 0x09;
+const DW_LNE_END_SEQUENCE: u8 = 0x01;
+const DW_LNE_SET_ADDRESS: u8 = 0x02;
+
+// line number header entry format (DWARF5)
+const DW_LNCT_PATH: u64 = 0x1;
+const DW_LNCT_DIRECTORY_INDEX: u64 = 0x2;
+
+// attribute forms
+const DW_FORM_BLOCK2: u64 = 0x03;
+const DW_FORM_BLOCK4: u64 = 0x04;
+const DW_FORM_DATA2: u64 = 0x05;
+const DW_FORM_DATA4: u64 = 0x06;
+const DW_FORM_DATA8: u64 = 0x07;
+const DW_FORM_STRING: u64 = 0x08;
+const DW_FORM_BLOCK: u64 = 0x09;
+const DW_FORM_BLOCK1: u64 = 0x0a;
+const DW_FORM_DATA1: u64 = 0x0b;
+const DW_FORM_STRP: u64 = 0x0e;
+const DW_FORM_UDATA: u64 = 0x0f;
+const DW_FORM_DATA16: u64 = 0x1e;
+const DW_FORM_LINE_STRP: u64 = 0x1f;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    fn is_end(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(len)?)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    fn uleb128(&mut self) -> Option<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Option<i64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    fn cstr(&mut self) -> Option<&'a str> {
+        let len = self.data.get(self.offset..)?.iter().position(|b| *b == 0)?;
+        let s = core::str::from_utf8(self.bytes(len)?).ok()?;
+        self.offset += 1; // null
+        Some(s)
+    }
+}
+
+fn str_by_offset(section: &[u8], offset: usize) -> Option<&str> {
+    let mut reader = Reader::new(section);
+    reader.bytes(offset)?;
+    reader.cstr()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LineRow {
+    address: u64,
+    file: u64,
+    line: u64,
+    column: u64,
+    end_sequence: bool,
+}
+
+// borrowed from the debug sections, printing does not allocate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilePath<'a> {
+    dir: &'a str,
+    name: &'a str,
+}
+
+impl fmt::Display for FilePath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.dir.is_empty() || self.name.starts_with('/') {
+            return write!(f, "{}", self.name);
+        }
+
+        write!(f, "{}/{}", self.dir, self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineInfo<'a> {
+    pub file: FilePath<'a>,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Dwarf {
+    debug_line: Vec<u8>,
+    debug_line_str: Vec<u8>,
+    debug_str: Vec<u8>,
+}
+
+impl Dwarf {
+    // return None if the ELF has no line number information
+    pub fn new(elf64: &Elf64) -> Option<Self> {
+        let section_data = |name: &str| {
+            elf64
+                .section_header_by_name(name)
+                .and_then(|sh| elf64.data_by_section_header(sh))
+                .map(|data| data.to_vec())
+        };
+
+        Some(Self {
+            debug_line: section_data(".debug_line")?,
+            debug_line_str: section_data(".debug_line_str").unwrap_or_default(),
+            debug_str: section_data(".debug_str").unwrap_or_default(),
+        })
+    }
+
+    pub fn find_line_by_ip(&self, ip: u64) -> Option<LineInfo<'_>> {
+        let mut reader = Reader::new(&self.debug_line);
+        while !reader.is_end() {
+            let unit_len = reader.u32()?;
+            // 64-bit DWARF is not supported
+            if unit_len >= 0xffff_fff0 {
+                return None;
+            }
+
+            let unit = reader.bytes(unit_len as usize)?;
+            if let Some(info) = self.find_line_in_unit(unit, ip) {
+                return Some(info);
+            }
+        }
+
+        None
+    }
+
+    // unit: line number program unit without the unit_length field
+    fn find_line_in_unit<'a>(&'a self, unit: &'a [u8], ip: u64) -> Option<LineInfo<'a>> {
+        let mut reader = Reader::new(unit);
+        let version = reader.u16()?;
+        if !(2..=5).contains(&version) {
+            return None;
+        }
+
+        if version >= 5 {
+            reader.u8()?; // address_size
+            reader.u8()?; // segment_selector_size
+        }
+
+        let header_len = reader.u32()? as usize;
+        let program_offset = reader.offset + header_len;
+
+        let min_inst_len = reader.u8()? as u64;
+        if version >= 4 {
+            reader.u8()?; // maximum_operations_per_instruction
+        }
+        let default_is_stmt = reader.u8()? != 0;
+        let line_base = reader.u8()? as i8 as i64;
+        let line_range = reader.u8()? as u64;
+        let opcode_base = reader.u8()?;
+        let std_opcode_lens = reader.bytes(opcode_base.checked_sub(1)? as usize)?;
+
+        if line_range == 0 {
+            return None;
+        }
+
+        // the entry tables are only read for the found row
+        let tables_offset = reader.offset;
+
+        // run the line number program
+        reader.offset = program_offset;
+        let new_row = || LineRow {
+            address: 0,
+            file: 1,
+            line: 1,
+            column: 0,
+            end_sequence: false,
+        };
+        let mut row = new_row();
+        let mut prev_row: Option<LineRow> = None;
+        let mut is_stmt = default_is_stmt;
+        let mut found = None;
+
+        while !reader.is_end() && found.is_none() {
+            let opcode = reader.u8()?;
+            let mut emit = false;
+
+            if opcode >= opcode_base {
+                // special opcode
+                let adjusted = (opcode - opcode_base) as u64;
+                row.address += (adjusted / line_range) * min_inst_len;
+                row.line = (row.line as i64 + line_base + (adjusted % line_range) as i64) as u64;
+                emit = true;
+            } else if opcode == 0 {
+                // extended opcode
+                // the length includes the sub opcode, 0 is malformed
+                let len = reader.uleb128()? as usize;
+                let operands_len = len.checked_sub(1)?;
+                let sub_opcode_offset = reader.offset;
+                match reader.u8()? {
+                    DW_LNE_END_SEQUENCE => {
+                        row.end_sequence = true;
+                        emit = true;
+                    }
+                    DW_LNE_SET_ADDRESS => {
+                        row.address = match operands_len {
+                            4 => reader.u32()? as u64,
+                            8 => reader.u64()?,
+                            _ => return None,
+                        }
+                    }
+                    _ => (),
+                }
+                reader.offset = sub_opcode_offset.checked_add(len)?;
+            } else {
+                match opcode {
+                    DW_LNS_COPY => emit = true,
+                    DW_LNS_ADVANCE_PC => row.address += reader.uleb128()? * min_inst_len,
+                    DW_LNS_ADVANCE_LINE => row.line = (row.line as i64 + reader.sleb128()?) as u64,
+                    DW_LNS_SET_FILE => row.file = reader.uleb128()?,
+                    DW_LNS_SET_COLUMN => row.column = reader.uleb128()?,
+                    DW_LNS_NEGATE_STMT => is_stmt = !is_stmt,
+                    DW_LNS_SET_BASIC_BLOCK => (),
+                    DW_LNS_CONST_ADD_PC => {
+                        row.address += ((255 - opcode_base) as u64 / line_range) * min_inst_len
+                    }
+                    DW_LNS_FIXED_ADVANCE_PC => row.address += reader.u16()? as u64,
+                    _ => {
+                        // skip unknown standard opcode
+                        for _ in 0..std_opcode_lens[opcode as usize - 1] {
+                            reader.uleb128()?;
+                        }
+                    }
+                }
+            }
+
+            if !emit {
+                continue;
+            }
+
+            if let Some(prev) = prev_row {
+                if !prev.end_sequence && prev.address <= ip && ip < row.address {
+                    found = Some(prev);
+                }
+            }
+
+            if row.end_sequence {
+                prev_row = None;
+                row = new_row();
+                is_stmt = default_is_stmt;
+            } else {
+                prev_row = Some(row);
+            }
+        }
+
+        let found = found?;
+        let mut reader = Reader::new(unit);
+        reader.offset = tables_offset;
+
+        Some(LineInfo {
+            file: self.file_path(&mut reader, version, found.file)?,
+            line: found.line as usize,
+            column: found.column as usize,
+        })
+    }
+
+    // reader: at the include_directories of the line number program header
+    fn file_path<'a>(
+        &'a self,
+        reader: &mut Reader<'a>,
+        version: u16,
+        file_index: u64,
+    ) -> Option<FilePath<'a>> {
+        let dirs_offset = reader.offset;
+
+        let (name, dir_index) = if version >= 5 {
+            self.read_entry(reader, u64::MAX)?;
+            self.read_entry(reader, file_index)??
+        } else {
+            while !reader.cstr()?.is_empty() {}
+
+            // index 0 is the compilation unit itself
+            let mut entry = None;
+            for i in 1.. {
+                let name = reader.cstr()?;
+                if name.is_empty() {
+                    break;
+                }
+                let dir_index = reader.uleb128()?;
+                reader.uleb128()?; // modification time
+                reader.uleb128()?; // file length
+
+                if i == file_index {
+                    entry = Some((name, dir_index));
+                    break;
+                }
+            }
+            entry?
+        };
+
+        reader.offset = dirs_offset;
+        let dir = if version >= 5 {
+            self.read_entry(reader, dir_index)?.map(|(path, _)| path)
+        } else {
+            // index 0 is the compilation directory
+            let mut dir = None;
+            for i in 1..=dir_index {
+                let path = reader.cstr()?;
+                if path.is_empty() {
+                    break;
+                }
+                if i == dir_index {
+                    dir = Some(path);
+                }
+            }
+            dir
+        };
+
+        Some(FilePath {
+            dir: dir.unwrap_or(""),
+            name,
+        })
+    }
+
+    // read a directory / file name entry table (DWARF5) to the end
+    // return (path, directory index) of the entry at the index if it exists
+    fn read_entry<'a>(
+        &'a self,
+        reader: &mut Reader<'a>,
+        index: u64,
+    ) -> Option<Option<(&'a str, u64)>> {
+        let format_count = reader.u8()?;
+        let formats_offset = reader.offset;
+        for _ in 0..format_count {
+            reader.uleb128()?; // content type
+            reader.uleb128()?; // form
+        }
+        let mut formats = Reader::new(reader.data);
+
+        let count = reader.uleb128()?;
+        let mut entry = None;
+        for i in 0..count {
+            let mut path = "";
+            let mut dir_index = 0;
+
+            formats.offset = formats_offset;
+            for _ in 0..format_count {
+                let content_type = formats.uleb128()?;
+                let (s, value) = self.read_form(reader, formats.uleb128()?)?;
+                match content_type {
+                    DW_LNCT_PATH => path = s.unwrap_or_default(),
+                    DW_LNCT_DIRECTORY_INDEX => dir_index = value,
+                    _ => (),
+                }
+            }
+
+            if i == index {
+                entry = Some((path, dir_index));
+            }
+        }
+
+        Some(entry)
+    }
+
+    // return (string value, integer value)
+    fn read_form<'a>(
+        &'a self,
+        reader: &mut Reader<'a>,
+        form: u64,
+    ) -> Option<(Option<&'a str>, u64)> {
+        let value = match form {
+            DW_FORM_STRING => return Some((Some(reader.cstr()?), 0)),
+            DW_FORM_LINE_STRP => {
+                let offset = reader.u32()? as usize;
+                return Some((Some(str_by_offset(&self.debug_line_str, offset)?), 0));
+            }
+            DW_FORM_STRP => {
+                let offset = reader.u32()? as usize;
+                return Some((Some(str_by_offset(&self.debug_str, offset)?), 0));
+            }
+            DW_FORM_DATA1 => reader.u8()? as u64,
+            DW_FORM_DATA2 => reader.u16()? as u64,
+            DW_FORM_DATA4 => reader.u32()? as u64,
+            DW_FORM_DATA8 => reader.u64()?,
+            DW_FORM_UDATA => reader.uleb128()?,
+            DW_FORM_DATA16 => {
+                reader.bytes(16)?;
+                0
+            }
+            DW_FORM_BLOCK => {
+                let len = reader.uleb128()? as usize;
+                reader.bytes(len)?;
+                0
+            }
+            DW_FORM_BLOCK1 => {
+                let len = reader.u8()? as usize;
+                reader.bytes(len)?;
+                0
+            }
+            DW_FORM_BLOCK2 => {
+                let len = reader.u16()? as usize;
+                reader.bytes(len)?;
+                0
+            }
+            DW_FORM_BLOCK4 => {
+                let len = reader.u32()? as usize;
+                reader.bytes(len)?;
+                0
+            }
+            _ => return None,
+        };
+
+        Some((None, value))
+    }
+}
+
+#[test_case]
+fn test_find_line_by_ip() {
+    use alloc::string::ToString;
+
+    // DWARF5 line number program unit
+    let mut header = Vec::new();
+    header.extend([1, 1, 1, (-5i8) as u8, 14, 13]); // min_inst_len - opcode_base
+    header.extend([0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1]); // standard_opcode_lengths
+    header.extend([1, DW_LNCT_PATH as u8, DW_FORM_STRING as u8]); // directory entry format
+    header.push(1);
+    header.extend(b"/src\0");
+    header.extend([2, DW_LNCT_PATH as u8, DW_FORM_STRING as u8]); // file name entry format
+    header.extend([DW_LNCT_DIRECTORY_INDEX as u8, DW_FORM_DATA1 as u8]);
+    header.push(2);
+    header.extend(b"main.c\0");
+    header.push(0);
+    header.extend(b"main.c\0");
+    header.push(0);
+
+    let mut program = vec![0, 9, DW_LNE_SET_ADDRESS];
+    program.extend(0x1000u64.to_le_bytes());
+    program.extend([DW_LNS_ADVANCE_LINE, 9, DW_LNS_SET_COLUMN, 5, DW_LNS_COPY]); // 0x1000, line 10
+    program.push(13 + (1 + 5) + 14 * 4); // special opcode: address + 4, line + 1
+    program.extend([DW_LNS_ADVANCE_PC, 4, 0, 1, DW_LNE_END_SEQUENCE]); // 0x1008
+
+    let build_dwarf = |program: &[u8]| {
+        let mut unit = Vec::new();
+        unit.extend(5u16.to_le_bytes()); // version
+        unit.extend([8, 0]); // address_size, segment_selector_size
+        unit.extend((header.len() as u32).to_le_bytes());
+        unit.extend(&header);
+        unit.extend(program);
+
+        let mut debug_line = Vec::new();
+        debug_line.extend((unit.len() as u32).to_le_bytes());
+        debug_line.extend(unit);
+
+        Dwarf {
+            debug_line,
+            debug_line_str: Vec::new(),
+            debug_str: Vec::new(),
+        }
+    };
+    let dwarf = build_dwarf(&program);
+
+    let line_info = |ip| {
+        dwarf
+            .find_line_by_ip(ip)
+            .map(|info| (info.file.to_string(), info.line, info.column))
+    };
+    assert_eq!(line_info(0xfff), None);
+    assert_eq!(line_info(0x1002), Some(("/src/main.c".to_string(), 10, 5)));
+    assert_eq!(line_info(0x1006), Some(("/src/main.c".to_string(), 11, 5)));
+    assert_eq!(line_info(0x1008), None);
+
+    // extended opcode without the sub opcode
+    let dwarf = build_dwarf(&[0, 0, DW_LNE_SET_ADDRESS]);
+    assert_eq!(dwarf.find_line_by_ip(0x1000), None);
+}
@@ -0,0 +1,264 @@
+use crate::{
+    arch::{
+        self,
+        idt::InterruptStackFrame,
+        register::{control::Cr0, Register},
+        task,
+    },
+    device::console,
+    error,
+    error::{Error, Result},
+    print, println,
+    util::mutex::Mutex,
+};
+use alloc::{string::String, vec::Vec};
+use core::{arch::asm, ptr};
+
+pub mod dwarf;
+
+const BACKTRACE_MAX_FRAMES: usize = 32;
+const RFLAGS_TF: u64 = 1 << 8; // trap flag
+const INT3: u8 = 0xcc;
+
+static mut USER_APP_DEBUGGER: Mutex<UserAppDebugger> = Mutex::new(UserAppDebugger::new());
+
+// walk the saved rbp chain of the current stack
+// this assumes that all code is compiled with frame pointers
+// ("frame-pointer": "always" in build-target/x86_64-kernel.json),
+// so every frame starts with "push rbp; mov rbp, rsp"
+// called from the panic handler, must not allocate
+pub fn backtrace() {
+    let mut rbp: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) rbp) };
+
+    error!("backtrace:");
+    for i in 0..BACKTRACE_MAX_FRAMES {
+        if rbp == 0 || rbp % 8 != 0 {
+            break;
+        }
+
+        // [rbp] = caller's rbp, [rbp + 8] = return address
+        let next_rbp = unsafe { *(rbp as *const u64) };
+        let ret_addr = unsafe { *((rbp + 8) as *const u64) };
+        if ret_addr == 0 {
+            break;
+        }
+
+        match task::find_user_line_by_ip(ret_addr) {
+            Some(line_info) => error!(
+                "  #{}: 0x{:016x} ({}:{}:{})",
+                i, ret_addr, line_info.file, line_info.line, line_info.column
+            ),
+            None => error!("  #{}: 0x{:016x}", i, ret_addr),
+        }
+
+        // the stack grows downward, so the caller's frame must be above
+        if next_rbp <= rbp {
+            break;
+        }
+        rbp = next_rbp;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint,
+    SingleStep,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Breakpoint {
+    pid: usize,
+    addr: u64,
+    orig_byte: u8,
+}
+
+struct UserAppDebugger {
+    breakpoints: Vec<Breakpoint>,
+    // breakpoint whose original byte is restored to step over it
+    step_over_addr: Option<u64>,
+    // resume without stopping after stepping over the breakpoint
+    is_continuing: bool,
+}
+
+impl UserAppDebugger {
+    const fn new() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            step_over_addr: None,
+            is_continuing: false,
+        }
+    }
+
+    // only the code mapped for the current user task can be patched
+    fn set_breakpoint(&mut self, addr: u64) -> Result<()> {
+        let pid = task::current_pid().ok_or(Error::Failed("User task is not running"))?;
+        if !task::is_user_code_addr(addr) {
+            return Err(Error::Failed("Address is not in the code of the user task"));
+        }
+
+        if self.find_breakpoint(addr).is_some() {
+            return Ok(());
+        }
+
+        let orig_byte = unsafe { ptr::read_volatile(addr as *const u8) };
+        write_code_byte(addr, INT3);
+        self.breakpoints.push(Breakpoint {
+            pid,
+            addr,
+            orig_byte,
+        });
+        Ok(())
+    }
+
+    fn find_breakpoint(&self, addr: u64) -> Option<&Breakpoint> {
+        let pid = task::current_pid()?;
+        self.breakpoints
+            .iter()
+            .find(|bp| bp.pid == pid && bp.addr == addr)
+    }
+
+    // the code is not restored because it is released with the task
+    fn remove_breakpoints(&mut self, pid: usize) {
+        let step_over_addr = self.step_over_addr;
+        if self
+            .breakpoints
+            .iter()
+            .any(|bp| bp.pid == pid && Some(bp.addr) == step_over_addr)
+        {
+            self.step_over_addr = None;
+            self.is_continuing = false;
+        }
+
+        self.breakpoints.retain(|bp| bp.pid != pid);
+    }
+}
+
+// called when the user task exits
+pub fn remove_user_app_breakpoints(pid: usize) {
+    match unsafe { USER_APP_DEBUGGER.try_lock() } {
+        Ok(mut debugger) => debugger.remove_breakpoints(pid),
+        Err(err) => error!("debug: {:?}", err),
+    }
+}
+
+// text segments may be mapped as read-only
+fn write_code_byte(addr: u64, value: u8) {
+    arch::disabled_int(|| {
+        let mut cr0 = Cr0::read();
+        let write_protect = cr0.write_protect();
+        cr0.set_write_protect(false);
+        cr0.write();
+
+        unsafe { ptr::write_volatile(addr as *mut u8, value) };
+
+        cr0.set_write_protect(write_protect);
+        cr0.write();
+    });
+}
+
+fn parse_addr(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn read_line() -> String {
+    loop {
+        if console::is_ready_get_line() {
+            if let Ok(Some(s)) = arch::disabled_int(console::get_line) {
+                return s;
+            }
+        }
+        arch::hlt();
+    }
+}
+
+// stack_frame must point to the interrupted frame on the stack,
+// RIP and RFLAGS are written back to it when returning to the user app
+pub fn user_app_debugger(stack_frame: *mut InterruptStackFrame, reason: StopReason) {
+    let ins_ptr_ptr = unsafe { ptr::addr_of_mut!((*stack_frame).ins_ptr) };
+    let cpu_flags_ptr = unsafe { ptr::addr_of_mut!((*stack_frame).cpu_flags) };
+    let mut ins_ptr = unsafe { ptr::read_volatile(ins_ptr_ptr) };
+
+    let mut debugger = match unsafe { USER_APP_DEBUGGER.try_lock() } {
+        Ok(debugger) => debugger,
+        Err(err) => {
+            error!("debug: {:?}", err);
+            return;
+        }
+    };
+
+    match reason {
+        StopReason::SingleStep => {
+            // stepped over the breakpoint, insert it again
+            if let Some(addr) = debugger.step_over_addr.take() {
+                write_code_byte(addr, INT3);
+
+                if debugger.is_continuing {
+                    debugger.is_continuing = false;
+                    unsafe {
+                        let cpu_flags = ptr::read_volatile(cpu_flags_ptr);
+                        ptr::write_volatile(cpu_flags_ptr, cpu_flags & !RFLAGS_TF);
+                    }
+                    return;
+                }
+            }
+        }
+        StopReason::Breakpoint => {
+            // RIP points to the next of int3
+            let addr = ins_ptr - 1;
+            if let Some(bp) = debugger.find_breakpoint(addr).copied() {
+                write_code_byte(bp.addr, bp.orig_byte);
+                ins_ptr = bp.addr;
+                unsafe { ptr::write_volatile(ins_ptr_ptr, ins_ptr) };
+                debugger.step_over_addr = Some(bp.addr);
+            }
+        }
+    }
+
+    match task::find_user_line_by_ip(ins_ptr) {
+        Some(line_info) => println!(
+            "(dbg) stopped at 0x{:x} ({}:{}:{})",
+            ins_ptr, line_info.file, line_info.line, line_info.column
+        ),
+        None => println!("(dbg) stopped at 0x{:x}", ins_ptr),
+    }
+
+    loop {
+        print!("(dbg) ");
+        let line = read_line();
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let cpu_flags = unsafe { ptr::read_volatile(cpu_flags_ptr) };
+
+        match args.as_slice() {
+            // single step
+            ["s"] => {
+                unsafe { ptr::write_volatile(cpu_flags_ptr, cpu_flags | RFLAGS_TF) };
+                break;
+            }
+            // continue
+            ["c"] => {
+                // step over the current breakpoint before continuing
+                if debugger.step_over_addr.is_some() {
+                    debugger.is_continuing = true;
+                    unsafe { ptr::write_volatile(cpu_flags_ptr, cpu_flags | RFLAGS_TF) };
+                } else {
+                    unsafe { ptr::write_volatile(cpu_flags_ptr, cpu_flags & !RFLAGS_TF) };
+                }
+                break;
+            }
+            // set breakpoint
+            ["b", addr] => match parse_addr(addr) {
+                Some(addr) => match debugger.set_breakpoint(addr) {
+                    Ok(()) => println!("(dbg) breakpoint at 0x{:x}", addr),
+                    Err(err) => println!("(dbg) {:?}", err),
+                },
+                None => println!("(dbg) invalid address: {}", addr),
+            },
+            [] => (),
+            _ => println!("(dbg) unknown command: {}", line.trim()),
+        }
+    }
+}
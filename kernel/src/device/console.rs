@@ -4,12 +4,15 @@ use crate::{
     theme::GLOBAL_THEME,
     util::{ascii::AsciiCode, lifo::Lifo, mutex::Mutex},
 };
-use alloc::{boxed::Box, string::String};
+use alloc::{boxed::Box, collections::VecDeque, string::String};
 use core::fmt::{self, Write};
 
-use super::uart;
+use super::{ps2_keyboard::scan_code::KeyCode, uart};
 
 const IO_BUF_LEN: usize = 512;
+// keep the newline in the input buffer
+const LINE_MAX_LEN: usize = IO_BUF_LEN - 1;
+const LINE_HISTORY_LEN: usize = 32;
 const IO_BUF_DEFAULT_VALUE: ConsoleCharacter = ConsoleCharacter {
     back_color: GLOBAL_THEME.io_buf_default_back_color,
     fore_color: GLOBAL_THEME.io_buf_default_fore_color,
@@ -43,6 +46,212 @@ pub enum BufferType {
     ErrorOutput,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEditKey {
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EscapeSequenceState {
+    Normal,
+    Escape,
+    Csi,
+}
+
+fn cursor_back(echo: &mut String, n: usize) {
+    if n > 0 {
+        let _ = write!(echo, "\x1b[{}D", n);
+    }
+}
+
+fn cursor_forward(echo: &mut String, n: usize) {
+    if n > 0 {
+        let _ = write!(echo, "\x1b[{}C", n);
+    }
+}
+
+// edits the current line, echo is the output to redraw the line
+#[derive(Debug)]
+struct LineEditor {
+    line: String,
+    cursor: usize,
+    history: VecDeque<String>,
+    history_index: Option<usize>,
+    // the line being edited before browsing the history
+    saved_line: String,
+    esc_seq_state: EscapeSequenceState,
+}
+
+impl LineEditor {
+    const fn new() -> Self {
+        Self {
+            line: String::new(),
+            cursor: 0,
+            history: VecDeque::new(),
+            history_index: None,
+            saved_line: String::new(),
+            esc_seq_state: EscapeSequenceState::Normal,
+        }
+    }
+
+    // cursor keys from the serial port (ESC [ A, ...)
+    // keyboards report them as key codes, so their Esc key does not start a sequence
+    fn input_serial(&mut self, ascii_code: AsciiCode, echo: &mut String) -> Option<String> {
+        let c = ascii_code as u8;
+
+        match self.esc_seq_state {
+            EscapeSequenceState::Escape if c == b'[' => {
+                self.esc_seq_state = EscapeSequenceState::Csi;
+                return None;
+            }
+            EscapeSequenceState::Csi => {
+                self.esc_seq_state = EscapeSequenceState::Normal;
+                let key = match c {
+                    b'A' => LineEditKey::Up,
+                    b'B' => LineEditKey::Down,
+                    b'C' => LineEditKey::Right,
+                    b'D' => LineEditKey::Left,
+                    b'H' => LineEditKey::Home,
+                    b'F' => LineEditKey::End,
+                    _ => return None,
+                };
+                self.input_key(key, echo);
+                return None;
+            }
+            _ => self.esc_seq_state = EscapeSequenceState::Normal,
+        }
+
+        if ascii_code == AsciiCode::Escape {
+            self.esc_seq_state = EscapeSequenceState::Escape;
+            return None;
+        }
+
+        self.input(ascii_code, echo)
+    }
+
+    // returns the line if it was entered
+    fn input(&mut self, ascii_code: AsciiCode, echo: &mut String) -> Option<String> {
+        let c = ascii_code as u8;
+
+        match ascii_code {
+            AsciiCode::CarriageReturn | AsciiCode::NewLine => {
+                echo.push('\n');
+                return Some(self.enter());
+            }
+            AsciiCode::Backspace | AsciiCode::Delete => {
+                if self.cursor == 0 {
+                    return None;
+                }
+
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+                cursor_back(echo, 1);
+                self.redraw_tail(echo, 1);
+            }
+            _ if (0x20..0x7f).contains(&c) => {
+                if self.line.len() >= LINE_MAX_LEN {
+                    return None;
+                }
+
+                self.line.insert(self.cursor, c as char);
+                self.cursor += 1;
+                echo.push(c as char);
+                self.redraw_tail(echo, 0);
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    fn input_key(&mut self, key: LineEditKey, echo: &mut String) {
+        match key {
+            LineEditKey::Left if self.cursor > 0 => {
+                self.cursor -= 1;
+                cursor_back(echo, 1);
+            }
+            LineEditKey::Right if self.cursor < self.line.len() => {
+                self.cursor += 1;
+                cursor_forward(echo, 1);
+            }
+            LineEditKey::Home => {
+                cursor_back(echo, self.cursor);
+                self.cursor = 0;
+            }
+            LineEditKey::End => {
+                cursor_forward(echo, self.line.len() - self.cursor);
+                self.cursor = self.line.len();
+            }
+            LineEditKey::Up => {
+                let i = match self.history_index {
+                    None if !self.history.is_empty() => {
+                        self.saved_line = self.line.clone();
+                        self.history.len() - 1
+                    }
+                    Some(i) if i > 0 => i - 1,
+                    _ => return,
+                };
+                self.history_index = Some(i);
+                self.replace_line(self.history[i].clone(), echo);
+            }
+            LineEditKey::Down => match self.history_index {
+                Some(i) if i + 1 < self.history.len() => {
+                    self.history_index = Some(i + 1);
+                    self.replace_line(self.history[i + 1].clone(), echo);
+                }
+                Some(_) => {
+                    self.history_index = None;
+                    let line = core::mem::take(&mut self.saved_line);
+                    self.replace_line(line, echo);
+                }
+                None => (),
+            },
+            _ => (),
+        }
+    }
+
+    fn enter(&mut self) -> String {
+        let line = core::mem::take(&mut self.line);
+        self.cursor = 0;
+        self.history_index = None;
+        self.saved_line.clear();
+
+        if !line.is_empty() && self.history.back() != Some(&line) {
+            if self.history.len() >= LINE_HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(line.clone());
+        }
+
+        line
+    }
+
+    // rewrite the characters after the cursor and erase erase_len cells behind them
+    fn redraw_tail(&self, echo: &mut String, erase_len: usize) {
+        let tail = &self.line[self.cursor..];
+        echo.push_str(tail);
+        for _ in 0..erase_len {
+            echo.push(' ');
+        }
+        cursor_back(echo, tail.len() + erase_len);
+    }
+
+    fn replace_line(&mut self, line: String, echo: &mut String) {
+        cursor_back(echo, self.cursor);
+        let erase_len = self.line.len().saturating_sub(line.len());
+        self.line = line;
+        self.cursor = 0;
+        self.redraw_tail(echo, erase_len);
+        cursor_forward(echo, self.line.len());
+        self.cursor = self.line.len();
+    }
+}
+
 // TTY + PTS
 #[derive(Debug)]
 pub struct Console {
@@ -52,6 +261,9 @@ pub struct Console {
     buf_default_value: ConsoleCharacter,
     use_serial_port: bool,
     is_ready_get_line: bool,
+    // false: raw mode, characters are passed to the input buffer as is
+    is_line_edit: bool,
+    line_editor: LineEditor,
 }
 
 impl Console {
@@ -63,6 +275,8 @@ impl Console {
             buf_default_value: IO_BUF_DEFAULT_VALUE,
             use_serial_port,
             is_ready_get_line: false,
+            is_line_edit: true,
+            line_editor: LineEditor::new(),
         }
     }
 
@@ -162,6 +376,60 @@ impl Console {
         s.chars().rev().collect()
     }
 
+    // returns the echo back
+    pub fn input(&mut self, ascii_code: AsciiCode, is_serial: bool) -> Result<String> {
+        let mut echo = String::new();
+
+        if !self.is_line_edit {
+            self.write_input(ascii_code)?;
+            match ascii_code {
+                AsciiCode::CarriageReturn | AsciiCode::NewLine => echo.push('\n'),
+                code => echo.push(code as u8 as char),
+            }
+            return Ok(echo);
+        }
+
+        let line = if is_serial {
+            self.line_editor.input_serial(ascii_code, &mut echo)
+        } else {
+            self.line_editor.input(ascii_code, &mut echo)
+        };
+
+        if let Some(line) = line {
+            for c in line.bytes() {
+                self.write_input(c.try_into()?)?;
+            }
+            self.write_input(ascii_code)?;
+        }
+
+        Ok(echo)
+    }
+
+    // returns the echo back if the key was consumed by the line editor
+    pub fn input_key(&mut self, key: LineEditKey) -> Option<String> {
+        if !self.is_line_edit {
+            return None;
+        }
+
+        let mut echo = String::new();
+        self.line_editor.input_key(key, &mut echo);
+        Some(echo)
+    }
+
+    fn write_input(&mut self, ascii_code: AsciiCode) -> Result<()> {
+        if self.is_full(BufferType::Input) {
+            self.reset_buf(BufferType::Input);
+        }
+
+        self.write(ascii_code, BufferType::Input)?;
+
+        if ascii_code == AsciiCode::CarriageReturn || ascii_code == AsciiCode::NewLine {
+            self.is_ready_get_line = true;
+        }
+
+        Ok(())
+    }
+
     pub fn get_ascii(&mut self, buf_type: BufferType) -> AsciiCode {
         let buf = match buf_type {
             BufferType::Input => &mut self.input_buf,
@@ -224,19 +492,43 @@ pub fn clear_input_buf() -> Result<()> {
     Ok(())
 }
 
+// input from the keyboards and echo back
 pub fn input(ascii_code: AsciiCode) -> Result<()> {
-    let mut console = unsafe { CONSOLE.try_lock() }?;
-
-    if console.is_full(BufferType::Input) {
-        console.reset_buf(BufferType::Input);
-    }
+    let echo = unsafe { CONSOLE.try_lock() }?.input(ascii_code, false)?;
+    crate::print!("{}", echo);
+    Ok(())
+}
 
-    console.write(ascii_code, BufferType::Input)?;
+// input from the serial port, which sends the cursor keys as escape sequences
+pub fn input_serial(ascii_code: AsciiCode) -> Result<()> {
+    let echo = unsafe { CONSOLE.try_lock() }?.input(ascii_code, true)?;
+    crate::print!("{}", echo);
+    Ok(())
+}
 
-    if ascii_code == AsciiCode::CarriageReturn || ascii_code == AsciiCode::NewLine {
-        console.is_ready_get_line = true;
-    }
+// returns true if the key was consumed by the line editor
+pub fn input_key(key_code: KeyCode) -> Result<bool> {
+    let key = match key_code {
+        KeyCode::CursorLeft => LineEditKey::Left,
+        KeyCode::CursorRight => LineEditKey::Right,
+        KeyCode::CursorUp => LineEditKey::Up,
+        KeyCode::CursorDown => LineEditKey::Down,
+        KeyCode::Home => LineEditKey::Home,
+        KeyCode::End => LineEditKey::End,
+        _ => return Ok(false),
+    };
+
+    let echo = match unsafe { CONSOLE.try_lock() }?.input_key(key) {
+        Some(echo) => echo,
+        None => return Ok(false),
+    };
+    crate::print!("{}", echo);
+    Ok(true)
+}
 
+// raw mode (false) bypasses the line editor
+pub fn set_line_edit(enabled: bool) -> Result<()> {
+    unsafe { CONSOLE.try_lock() }?.is_line_edit = enabled;
     Ok(())
 }
 
@@ -259,3 +551,70 @@ pub fn get_ascii() -> Result<AsciiCode> {
     let mut console = unsafe { CONSOLE.try_lock() }?;
     Ok(console.get_ascii(BufferType::Input))
 }
+
+#[test_case]
+fn test_line_editor() {
+    fn input_str(editor: &mut LineEditor, s: &str) -> Option<String> {
+        let mut echo = String::new();
+        let mut line = None;
+        for c in s.bytes() {
+            line = editor.input_serial(c.try_into().unwrap(), &mut echo);
+        }
+        line
+    }
+
+    let mut editor = LineEditor::new();
+    let mut echo = String::new();
+
+    // insert in the middle of the line
+    assert_eq!(input_str(&mut editor, "ac"), None);
+    editor.input_key(LineEditKey::Left, &mut echo);
+    assert_eq!(echo, "\x1b[1D");
+    echo.clear();
+    editor.input(AsciiCode::SmallB, &mut echo);
+    assert_eq!(echo, "bc\x1b[1D");
+    assert_eq!(editor.line, "abc");
+    assert_eq!(editor.cursor, 2);
+
+    // backspace in the middle of the line
+    echo.clear();
+    editor.input(AsciiCode::Backspace, &mut echo);
+    assert_eq!(echo, "\x1b[1Dc \x1b[2D");
+    assert_eq!(editor.line, "ac");
+
+    // home, end and cursor keys from the serial port
+    editor.input_key(LineEditKey::Home, &mut echo);
+    assert_eq!(editor.cursor, 0);
+    assert_eq!(input_str(&mut editor, "\x1b[F"), None);
+    assert_eq!(editor.cursor, 2);
+    assert_eq!(input_str(&mut editor, "\x1b[D"), None);
+    assert_eq!(editor.cursor, 1);
+
+    // the Esc key of the keyboards is not a sequence start
+    editor.input(AsciiCode::Escape, &mut echo);
+    editor.input(AsciiCode::LSquareBracket, &mut echo);
+    assert_eq!(editor.line, "a[c");
+    editor.input(AsciiCode::Backspace, &mut echo);
+
+    assert_eq!(input_str(&mut editor, "\n"), Some(String::from("ac")));
+    assert_eq!(input_str(&mut editor, "xyz\n"), Some(String::from("xyz")));
+    assert_eq!(input_str(&mut editor, "xyz\n"), Some(String::from("xyz")));
+    assert_eq!(editor.history.len(), 2);
+
+    // browse the history and back to the editing line
+    assert_eq!(input_str(&mut editor, "q"), None);
+    editor.input_key(LineEditKey::Up, &mut echo);
+    assert_eq!(editor.line, "xyz");
+    editor.input_key(LineEditKey::Up, &mut echo);
+    assert_eq!(editor.line, "ac");
+    editor.input_key(LineEditKey::Up, &mut echo);
+    assert_eq!(editor.line, "ac");
+    echo.clear();
+    editor.input_key(LineEditKey::Down, &mut echo);
+    assert_eq!(echo, "\x1b[2Dxyz\x1b[3D\x1b[3C");
+    assert_eq!(editor.line, "xyz");
+    editor.input_key(LineEditKey::Down, &mut echo);
+    assert_eq!(editor.line, "q");
+    assert_eq!(editor.cursor, 1);
+    assert_eq!(input_str(&mut editor, "\n"), Some(String::from("q")));
+}
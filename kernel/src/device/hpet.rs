@@ -0,0 +1,273 @@
+use super::{DeviceDriverFunction, DeviceDriverInfo};
+use crate::{
+    acpi,
+    addr::{PhysicalAddress, VirtualAddress},
+    apic, arch,
+    error::{Error, Result},
+    idt::{self, GateType, InterruptHandler},
+    mem::paging::{self, EntryMode, PageWriteThroughLevel, ReadWrite},
+};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use log::{debug, info};
+
+const GEN_CAP_REG_OFFSET: usize = 0x00;
+const GEN_CONF_REG_OFFSET: usize = 0x10;
+const MAIN_COUNTER_REG_OFFSET: usize = 0xf0;
+const TIMER_CONF_REG_OFFSET: usize = 0x100;
+const TIMER_COMPARATOR_REG_OFFSET: usize = 0x108;
+const TIMER_REG_STRIDE: usize = 0x20;
+
+const GEN_CAP_COUNT_SIZE_CAP: u64 = 1 << 13;
+const GEN_CONF_ENABLE: u64 = 1 << 0;
+const GEN_CONF_LEGACY_REPLACEMENT: u64 = 1 << 1;
+
+const TIMER_CONF_INT_ENABLE: u64 = 1 << 2;
+const TIMER_CONF_PERIODIC: u64 = 1 << 3;
+const TIMER_CONF_PERIODIC_CAP: u64 = 1 << 4;
+const TIMER_CONF_VAL_SET: u64 = 1 << 6;
+const TIMER_CONF_INT_ROUTE_SHIFT: u64 = 9;
+const TIMER_CONF_INT_ROUTE_MASK: u64 = 0x1f << TIMER_CONF_INT_ROUTE_SHIFT;
+
+// the spec limits the counter period to 100ns
+const MAX_COUNTER_PERIOD_FS: u64 = 100_000_000;
+const FS_PER_NS: u64 = 1_000_000;
+// GSIs below 16 are used by ISA IRQs
+const MIN_INT_ROUTE: u32 = 16;
+const PERIODIC_TIMER_NUM: usize = 0;
+
+static mut HPET_DRIVER: HpetDriver = HpetDriver::new();
+// extended main counter value of the last read for 32-bit counters
+static LAST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+struct HpetDriver {
+    device_driver_info: DeviceDriverInfo,
+    base_addr: Option<VirtualAddress>,
+    counter_period_fs: u64,
+    is_counter_64bit: bool,
+}
+
+impl HpetDriver {
+    const fn new() -> Self {
+        Self {
+            device_driver_info: DeviceDriverInfo::new("hpet"),
+            base_addr: None,
+            counter_period_fs: 0,
+            is_counter_64bit: false,
+        }
+    }
+
+    fn base_addr(&self) -> Result<VirtualAddress> {
+        self.base_addr
+            .ok_or(Error::Failed("Device driver is not attached"))
+    }
+
+    fn read_reg(&self, offset: usize) -> Result<u64> {
+        let ptr = self.base_addr()?.offset(offset).as_ptr() as *const u64;
+        Ok(unsafe { ptr.read_volatile() })
+    }
+
+    fn write_reg(&self, offset: usize, value: u64) -> Result<()> {
+        let ptr = self.base_addr()?.offset(offset).as_ptr_mut() as *mut u64;
+        unsafe { ptr.write_volatile(value) };
+        Ok(())
+    }
+
+    fn set_enabled(&self, enabled: bool) -> Result<()> {
+        let mut conf = self.read_reg(GEN_CONF_REG_OFFSET)?;
+        if enabled {
+            conf |= GEN_CONF_ENABLE;
+        } else {
+            conf &= !GEN_CONF_ENABLE;
+        }
+        self.write_reg(GEN_CONF_REG_OFFSET, conf)
+    }
+
+    fn counter(&self) -> Result<u64> {
+        let counter = self.read_reg(MAIN_COUNTER_REG_OFFSET)?;
+        if self.is_counter_64bit {
+            return Ok(counter);
+        }
+
+        let last = LAST_COUNTER.load(Ordering::Relaxed);
+        let counter = extend_counter_32bit(last, counter as u32);
+        LAST_COUNTER.fetch_max(counter, Ordering::Relaxed);
+        Ok(counter)
+    }
+
+    fn now_ns(&self) -> Result<u64> {
+        if !self.device_driver_info.attached {
+            return Err(Error::Failed("Device driver is not attached"));
+        }
+
+        Ok(counter_to_ns(self.counter()?, self.counter_period_fs))
+    }
+
+    fn set_periodic(&self, period_ns: u64, vec_num: u8) -> Result<()> {
+        if !self.device_driver_info.attached {
+            return Err(Error::Failed("Device driver is not attached"));
+        }
+
+        let conf_offset = TIMER_CONF_REG_OFFSET + TIMER_REG_STRIDE * PERIODIC_TIMER_NUM;
+        let comparator_offset = TIMER_COMPARATOR_REG_OFFSET + TIMER_REG_STRIDE * PERIODIC_TIMER_NUM;
+
+        let timer_conf = self.read_reg(conf_offset)?;
+        if timer_conf & TIMER_CONF_PERIODIC_CAP == 0 {
+            return Err(Error::Failed("HPET timer does not support periodic mode"));
+        }
+
+        // pick an I/O APIC input that the timer can be routed to
+        let int_route_cap = (timer_conf >> 32) as u32;
+        let int_route = (MIN_INT_ROUTE..32)
+            .find(|i| int_route_cap & (1 << i) != 0)
+            .ok_or(Error::Failed("HPET timer has no usable interrupt route"))?;
+        apic::io_apic::set_redirection(int_route as u8, vec_num, apic::local_apic_id())?;
+
+        let period = (period_ns * FS_PER_NS / self.counter_period_fs).max(1);
+
+        // the accumulator can only be set while the main counter is halted
+        self.set_enabled(false)?;
+        let timer_conf = (timer_conf & !TIMER_CONF_INT_ROUTE_MASK)
+            | TIMER_CONF_INT_ENABLE
+            | TIMER_CONF_PERIODIC
+            | TIMER_CONF_VAL_SET
+            | (int_route as u64) << TIMER_CONF_INT_ROUTE_SHIFT;
+        self.write_reg(conf_offset, timer_conf)?;
+        self.write_reg(comparator_offset, self.counter()? + period)?;
+        self.write_reg(comparator_offset, period)?;
+        self.set_enabled(true)?;
+
+        Ok(())
+    }
+}
+
+impl DeviceDriverFunction for HpetDriver {
+    type AttachInput = ();
+    type PollNormalOutput = ();
+    type PollInterruptOutput = ();
+
+    fn get_device_driver_info(&self) -> Result<DeviceDriverInfo> {
+        Ok(self.device_driver_info.clone())
+    }
+
+    fn probe(&mut self) -> Result<()> {
+        let phys_addr = PhysicalAddress::new(acpi::hpet_base_addr()?);
+
+        // MMIO may be outside of the identity mapped RAM
+        paging::map(
+            phys_addr.get().into(),
+            phys_addr,
+            ReadWrite::Write,
+            EntryMode::Supervisor,
+            PageWriteThroughLevel::WriteThrough,
+        )?;
+        self.base_addr = Some(phys_addr.get().into());
+
+        Ok(())
+    }
+
+    fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
+        let cap = self.read_reg(GEN_CAP_REG_OFFSET)?;
+        let counter_period_fs = cap >> 32;
+        if counter_period_fs == 0 || counter_period_fs > MAX_COUNTER_PERIOD_FS {
+            return Err(Error::Failed("Invalid HPET counter period"));
+        }
+
+        self.counter_period_fs = counter_period_fs;
+        self.is_counter_64bit = cap & GEN_CAP_COUNT_SIZE_CAP != 0;
+        debug!(
+            "{}: Counter period: {}fs, 64-bit: {}",
+            self.device_driver_info.name, self.counter_period_fs, self.is_counter_64bit
+        );
+
+        // keep the PIT and RTC on their legacy IRQs
+        let conf = self.read_reg(GEN_CONF_REG_OFFSET)?;
+        self.write_reg(GEN_CONF_REG_OFFSET, conf & !GEN_CONF_LEGACY_REPLACEMENT)?;
+        self.set_enabled(true)?;
+
+        self.device_driver_info.attached = true;
+        Ok(())
+    }
+
+    fn poll_normal(&mut self) -> Result<Self::PollNormalOutput> {
+        unimplemented!()
+    }
+
+    fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
+        unimplemented!()
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>> {
+        unimplemented!()
+    }
+
+    fn write(&mut self, _data: &[u8]) -> Result<()> {
+        unimplemented!()
+    }
+}
+
+pub fn get_device_driver_info() -> Result<DeviceDriverInfo> {
+    unsafe { HPET_DRIVER.get_device_driver_info() }
+}
+
+pub fn probe_and_attach() -> Result<()> {
+    unsafe {
+        HPET_DRIVER.probe()?;
+        HPET_DRIVER.attach(())?;
+        info!("{}: Attached!", get_device_driver_info()?.name);
+    }
+
+    Ok(())
+}
+
+// 32-bit counters are also read from the local APIC timer tick not to miss a wrap around
+pub fn now_ns() -> Result<u64> {
+    unsafe { HPET_DRIVER.now_ns() }
+}
+
+pub fn wait_ms(ms: u64) -> Result<()> {
+    let end_ns = now_ns()? + ms * 1_000_000;
+    while now_ns()? < end_ns {
+        core::hint::spin_loop();
+    }
+
+    Ok(())
+}
+
+// routed through the I/O APIC, the handler must call idt::notify_end_of_int()
+pub fn set_periodic(period_ns: u64, handler: InterruptHandler) -> Result<u8> {
+    let vec_num = idt::set_handler_dyn_vec(handler, GateType::Interrupt)?;
+    if let Err(err) = arch::disabled_int(|| unsafe { HPET_DRIVER.set_periodic(period_ns, vec_num) })
+    {
+        idt::remove_handler(vec_num as usize)?;
+        return Err(err);
+    }
+
+    Ok(vec_num)
+}
+
+fn counter_to_ns(counter: u64, counter_period_fs: u64) -> u64 {
+    (counter as u128 * counter_period_fs as u128 / FS_PER_NS as u128) as u64
+}
+
+fn extend_counter_32bit(last: u64, counter: u32) -> u64 {
+    let extended = (last & !0xffff_ffff) | counter as u64;
+    if extended < last {
+        extended + (1 << 32)
+    } else {
+        extended
+    }
+}
+
+#[test_case]
+fn test_counter_conversion() {
+    // 100MHz
+    assert_eq!(counter_to_ns(100_000_000, 10_000_000), 1_000_000_000);
+    // 14.31818MHz
+    assert_eq!(counter_to_ns(14_318_180, 69_841_279), 1_000_000_004);
+    // does not overflow in the intermediate product
+    assert_eq!(counter_to_ns(1 << 48, 10_000_000), 10 << 48);
+
+    assert_eq!(extend_counter_32bit(0x1_0000_0010, 0x20), 0x1_0000_0020);
+    assert_eq!(extend_counter_32bit(0x1_ffff_fff0, 0x10), 0x2_0000_0010);
+}
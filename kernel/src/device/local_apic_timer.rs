@@ -1,28 +1,45 @@
-use super::{DeviceDriverFunction, DeviceDriverInfo};
+use super::{hpet, DeviceDriverFunction, DeviceDriverInfo};
 use crate::{
     acpi,
     addr::VirtualAddress,
-    error::Result,
+    arch,
+    context::FpuContext,
+    error::{Error, Result},
     graphics::{frame_buf, multi_layer},
-    idt::{self, GateType, InterruptHandler},
+    idt::{self, GateType, InterruptHandler, InterruptStackFrame},
     task,
 };
-use alloc::vec::Vec;
+use alloc::{boxed::Box, vec::Vec};
 use core::num::{NonZero, NonZeroUsize};
 use log::{debug, info};
 
 const LVT_TIMER_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00320);
 const INIT_CNT_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00380);
+const CURRENT_CNT_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee00390);
 const DIV_CONF_VIRT_ADDR: VirtualAddress = VirtualAddress::new(0xfee003e0);
 
 const END_OF_INT_REG_ADDR: VirtualAddress = VirtualAddress::new(0xfee000b0);
 
+const LVT_TIMER_MASKED: u32 = 1 << 16;
+const LVT_TIMER_PERIODIC: u32 = 1 << 17;
+const DIV_CONF_DIV_BY_1: u32 = 0b1011;
+const CALIBRATION_MS: u32 = 10;
+// close to the previous fixed initial count (0x100000) on the 1GHz QEMU APIC clock
+const TICK_INTERVAL_MS: usize = 1;
+
 static mut LOCAL_APIC_TIMER_DRIVER: LocalApicTimerDriver = LocalApicTimerDriver::new();
 
+struct OneshotTimer {
+    expire_tick: usize,
+    callback: Box<dyn FnOnce()>,
+}
+
 struct LocalApicTimerDriver {
     device_driver_info: DeviceDriverInfo,
     tick: usize,
     freq: Option<NonZero<usize>>,
+    init_cnt: u32,
+    oneshot_timers: Vec<OneshotTimer>,
 }
 
 impl LocalApicTimerDriver {
@@ -31,11 +48,43 @@ impl LocalApicTimerDriver {
             device_driver_info: DeviceDriverInfo::new("local-apic-timer"),
             tick: 0,
             freq: None,
+            init_cnt: 0,
+            oneshot_timers: Vec::new(),
         }
     }
 
+    fn set_oneshot(&mut self, ms: usize, callback: Box<dyn FnOnce()>) -> Result<()> {
+        let freq = self
+            .freq
+            .ok_or(Error::Failed("Local APIC timer frequency is unknown"))?;
+        // freq is ticks per 10ms
+        let ticks = ((ms * freq.get() + 9) / 10).max(1);
+
+        self.oneshot_timers.push(OneshotTimer {
+            expire_tick: self.tick + ticks,
+            callback,
+        });
+
+        Ok(())
+    }
+
+    fn pop_expired_oneshot_timers(&mut self) -> Vec<OneshotTimer> {
+        let tick = self.tick;
+        let mut expired = Vec::new();
+        let mut i = 0;
+        while i < self.oneshot_timers.len() {
+            if self.oneshot_timers[i].expire_tick <= tick {
+                expired.push(self.oneshot_timers.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+
+        expired
+    }
+
     unsafe fn start(&self) {
-        (INIT_CNT_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(0x0010_0000);
+        (INIT_CNT_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(self.init_cnt);
     }
 
     unsafe fn stop(&self) {
@@ -71,7 +120,7 @@ impl DeviceDriverFunction for LocalApicTimerDriver {
     fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
         // register interrupt handler
         let vec_num = idt::set_handler_dyn_vec(
-            InterruptHandler::Normal(poll_int_local_apic_timer),
+            InterruptHandler::WithStackFrame(poll_int_local_apic_timer),
             GateType::Interrupt,
         )?;
         debug!(
@@ -80,18 +129,30 @@ impl DeviceDriverFunction for LocalApicTimerDriver {
         );
 
         unsafe {
-            // calc freq
+            // count bus clocks with the timer masked
             self.stop();
-            (DIV_CONF_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(0b1011);
+            (DIV_CONF_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(DIV_CONF_DIV_BY_1);
             (LVT_TIMER_VIRT_ADDR.as_ptr_mut() as *mut u32)
-                .write_volatile((2 << 16) | vec_num as u32);
-            // non masked, periodic
-            self.start();
-            acpi::pm_timer_wait_ms(10)?;
-            let tick = self.tick();
+                .write_volatile(LVT_TIMER_MASKED | vec_num as u32);
+            (INIT_CNT_VIRT_ADDR.as_ptr_mut() as *mut u32).write_volatile(u32::MAX);
+            calibration_wait_ms(CALIBRATION_MS)?;
+            let current_cnt = (CURRENT_CNT_VIRT_ADDR.as_ptr() as *const u32).read_volatile();
             self.stop();
 
-            self.freq = NonZeroUsize::new(tick);
+            let cnt_per_ms = (u32::MAX - current_cnt) / CALIBRATION_MS;
+            if cnt_per_ms == 0 {
+                return Err(Error::Failed("Failed to calibrate local APIC timer"));
+            }
+            info!(
+                "{}: Calibrated to {} ticks/ms",
+                self.device_driver_info.name, cnt_per_ms
+            );
+
+            // non masked, periodic
+            (LVT_TIMER_VIRT_ADDR.as_ptr_mut() as *mut u32)
+                .write_volatile(LVT_TIMER_PERIODIC | vec_num as u32);
+            self.init_cnt = cnt_per_ms * TICK_INTERVAL_MS as u32;
+            self.freq = NonZeroUsize::new(10 / TICK_INTERVAL_MS);
             self.tick = 0;
 
             // start timer
@@ -108,11 +169,21 @@ impl DeviceDriverFunction for LocalApicTimerDriver {
     fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
         self.inc_tick();
 
+        // keep the extended 32-bit HPET counter from missing a wrap around
+        let _ = hpet::now_ns();
+
         if get_current_ms().is_some() {
             let _ = multi_layer::draw_to_frame_buf();
             let _ = frame_buf::apply_shadow_buf();
 
-            let _ = task::poll();
+            // preempt the running task (including user tasks) to poll kernel tasks
+            if task::is_preemptive() {
+                // kernel tasks may clobber the FPU / SSE state of the interrupted task
+                let mut fpu_context = FpuContext::new();
+                fpu_context.save();
+                let _ = task::poll();
+                fpu_context.restore();
+            }
         }
 
         Ok(())
@@ -145,16 +216,63 @@ pub fn get_current_tick() -> usize {
     unsafe { LOCAL_APIC_TIMER_DRIVER.tick() }
 }
 
+// call the callback once from the timer interrupt after ms
+pub fn set_oneshot(ms: usize, callback: impl FnOnce() + 'static) -> Result<()> {
+    arch::disabled_int(|| unsafe { LOCAL_APIC_TIMER_DRIVER.set_oneshot(ms, Box::new(callback)) })
+}
+
 pub fn get_current_ms() -> Option<usize> {
     let freq = unsafe { LOCAL_APIC_TIMER_DRIVER.freq }?;
-    Some(get_current_tick() / freq * 10)
+    Some(get_current_tick() * 10 / freq)
+}
+
+// prefer HPET, ACPI PM timer is used if HPET is not available
+fn calibration_wait_ms(ms: u32) -> Result<()> {
+    if hpet::wait_ms(ms as u64).is_ok() {
+        return Ok(());
+    }
+
+    acpi::pm_timer_wait_ms(ms)
 }
 
-extern "x86-interrupt" fn poll_int_local_apic_timer() {
+extern "x86-interrupt" fn poll_int_local_apic_timer(stack_frame: InterruptStackFrame) {
     unsafe {
         let _ = LOCAL_APIC_TIMER_DRIVER.poll_int();
 
+        // callbacks can re-arm timers
+        for timer in LOCAL_APIC_TIMER_DRIVER.pop_expired_oneshot_timers() {
+            (timer.callback)();
+        }
+
         // notify end of interrupt
         (END_OF_INT_REG_ADDR.as_ptr_mut() as *mut u32).write_volatile(0);
     }
+
+    // interrupted the user task in user mode
+    if stack_frame.code_seg & 0x3 == 0x3 {
+        task::preempt_user_task();
+    }
+}
+
+#[test_case]
+fn test_oneshot() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static CNT: AtomicUsize = AtomicUsize::new(0);
+
+    fn callback() {
+        // re-arm from the callback
+        if CNT.fetch_add(1, Ordering::SeqCst) == 0 {
+            set_oneshot(10, callback).unwrap();
+        }
+    }
+
+    set_oneshot(10, callback).unwrap();
+
+    let start_ms = get_current_ms().unwrap();
+    while CNT.load(Ordering::SeqCst) < 2 && get_current_ms().unwrap() - start_ms < 1000 {
+        arch::hlt();
+    }
+
+    assert_eq!(CNT.load(Ordering::SeqCst), 2);
 }
@@ -2,11 +2,14 @@ use crate::error::Result;
 use alloc::vec::Vec;
 
 pub mod console;
+pub mod hpet;
 pub mod local_apic_timer;
 pub mod panic_screen;
 pub mod pci_bus;
+pub mod ps2;
 pub mod ps2_keyboard;
 pub mod ps2_mouse;
+pub mod rtc;
 pub mod rtl8139;
 pub mod speaker;
 pub mod uart;
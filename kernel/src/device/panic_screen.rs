@@ -132,7 +132,7 @@ impl PanicScreenDriver {
 
         for h in 0..font_height {
             for w in 0..font_width {
-                if !(font_glyph[h] << w) & 0x80 == 0x80 {
+                if !FONT.is_glyph_pixel_set(font_glyph, w, h) {
                     self.write_pixel(x + w, y + h, BACK_COLOR);
                 } else {
                     self.write_pixel(x + w, y + h, FORE_COLOR);
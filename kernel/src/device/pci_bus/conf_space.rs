@@ -2,10 +2,15 @@ use crate::addr::IoPortAddress;
 use crate::{
     arch::{addr::*, register::msi::*},
     error::{Error, Result},
-    mem::paging::{self, EntryMode, MappingInfo, PageWriteThroughLevel, ReadWrite, PAGE_SIZE},
+    mem::paging::{
+        self, EntryMode, HugePageSize, MappingInfo, PageWriteThroughLevel, ReadWrite, PAGE_SIZE,
+    },
 };
 use alloc::vec::Vec;
-use core::mem::transmute;
+use core::{
+    mem::transmute,
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+};
 use pci_ids::*;
 
 const PCI_PORT_CONF_REG_ADDR: IoPortAddress = IoPortAddress::new(0xcf8);
@@ -15,12 +20,24 @@ pub const PCI_DEVICE_BUS_LEN: usize = 256;
 pub const PCI_DEVICE_DEVICE_LEN: usize = 32;
 pub const PCI_DEVICE_FUNC_LEN: usize = 8;
 const PCI_CONF_UNIQUE_FIELD_OFFSET: usize = 16;
+const PCI_CONF_SPACE_SIZE: usize = 256;
+pub const PCI_EXT_CONF_SPACE_SIZE: usize = 4096;
+const PCI_ECAM_BUS_SIZE: usize = 1 << 20;
+
+// 0 if ECAM is not available
+static ECAM_BASE_ADDR: AtomicU64 = AtomicU64::new(0);
+static ECAM_START_BUS_NUM: AtomicU8 = AtomicU8::new(0);
+static ECAM_END_BUS_NUM: AtomicU8 = AtomicU8::new(0);
 
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
 pub struct ConfigurationSpaceCommandRegister(u16);
 
 impl ConfigurationSpaceCommandRegister {
+    pub fn write_mem_space_enable(&mut self, value: bool) {
+        self.0 = (self.0 & !0x2) | ((value as u16) << 1);
+    }
+
     pub fn write_bus_master_enable(&mut self, value: bool) {
         self.0 = (self.0 & !0x4) | ((value as u16) << 2);
     }
@@ -293,6 +310,7 @@ impl ConfigurationSpaceNonBridgeField {
                             rw: ReadWrite::Write,
                             us: EntryMode::Supervisor,
                             pwt: PageWriteThroughLevel::WriteThrough,
+                            exec_disable: false,
                         })?;
 
                         let base_addr =
@@ -313,6 +331,7 @@ impl ConfigurationSpaceNonBridgeField {
                             rw: ReadWrite::Write,
                             us: EntryMode::Supervisor,
                             pwt: PageWriteThroughLevel::WriteThrough,
+                            exec_disable: false,
                         })?;
                         result.push((i, base_addr));
                     }
@@ -404,6 +423,42 @@ impl ConfigurationSpacePciToCardBusField {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapId {
+    PowerManagement,
+    Msi,
+    VendorSpecific,
+    PciExpress,
+    Msix,
+    Other(u8),
+}
+
+impl From<u8> for CapId {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => Self::PowerManagement,
+            0x05 => Self::Msi,
+            0x09 => Self::VendorSpecific,
+            0x10 => Self::PciExpress,
+            0x11 => Self::Msix,
+            id => Self::Other(id),
+        }
+    }
+}
+
+impl CapId {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PowerManagement => "Power Management",
+            Self::Msi => "MSI",
+            Self::VendorSpecific => "Vendor Specific",
+            Self::PciExpress => "PCI Express",
+            Self::Msix => "MSI-X",
+            Self::Other(_) => "Unknown",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(transparent)]
 pub struct MsiMessageControlField(u16);
@@ -458,22 +513,160 @@ impl MsiCapabilityField {
     }
 }
 
-pub fn read_conf_space(bus: usize, device: usize, func: usize, byte_offset: usize) -> Result<u32> {
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(transparent)]
+pub struct MsixMessageControlField(u16);
+
+impl MsixMessageControlField {
+    pub fn table_size(&self) -> usize {
+        (self.0 & 0x7ff) as usize + 1
+    }
+
+    pub fn set_function_mask(&mut self, value: bool) {
+        self.0 = (self.0 & !0x4000) | ((value as u16) << 14);
+    }
+
+    pub fn set_is_enable(&mut self, value: bool) {
+        self.0 = (self.0 & !0x8000) | ((value as u16) << 15);
+    }
+
+    pub fn raw(&self) -> u16 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct MsixCapabilityField {
+    pub cap_id: u8,
+    pub next_ptr: u8,
+    pub msg_ctrl: MsixMessageControlField,
+    table_offset_and_bir: u32,
+    pba_offset_and_bir: u32,
+}
+
+impl MsixCapabilityField {
+    pub fn read(bus: usize, device: usize, func: usize, caps_ptr: usize) -> Result<Self> {
+        let mut data: [u32; 3] = [0; 3];
+        for (i, elem) in data.iter_mut().enumerate() {
+            let d = read_conf_space(bus, device, func, caps_ptr + (i * 4))?;
+            *elem = d;
+        }
+
+        Ok(unsafe { transmute::<[u32; 3], Self>(data) })
+    }
+
+    // only message control is writable
+    pub fn write(&self, bus: usize, device: usize, func: usize, caps_ptr: usize) -> Result<()> {
+        let data = unsafe { transmute::<Self, [u32; 3]>(*self) };
+        write_conf_space(bus, device, func, caps_ptr, data[0])
+    }
+
+    pub fn table_bar_index(&self) -> usize {
+        (self.table_offset_and_bir & 0x7) as usize
+    }
+
+    pub fn table_offset(&self) -> usize {
+        (self.table_offset_and_bir & !0x7) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct MsixTableEntry {
+    pub msg_addr_low: MsiMessageAddressField,
+    pub msg_addr_high: u32,
+    pub msg_data: MsiMessageDataField,
+    pub vector_ctrl: u32,
+}
+
+impl MsixTableEntry {
+    pub fn set_is_masked(&mut self, value: bool) {
+        self.vector_ctrl = (self.vector_ctrl & !0x1) | (value as u32);
+    }
+}
+
+// MMIO config access through ECAM, None if not available
+fn ecam_addr(bus: usize, device: usize, func: usize, byte_offset: usize) -> Option<*mut u32> {
+    let base_addr = ECAM_BASE_ADDR.load(Ordering::Relaxed);
+    let start_bus = ECAM_START_BUS_NUM.load(Ordering::Relaxed) as usize;
+    let end_bus = ECAM_END_BUS_NUM.load(Ordering::Relaxed) as usize;
+
+    if base_addr == 0 || bus < start_bus || bus > end_bus {
+        return None;
+    }
+
+    let offset = ecam_offset(bus, device, func, byte_offset);
+    Some((base_addr + offset as u64) as *mut u32)
+}
+
+// the base address of MCFG corresponds to bus 0 even if the start bus is not 0
+fn ecam_offset(bus: usize, device: usize, func: usize, byte_offset: usize) -> usize {
+    bus << 20 | device << 15 | func << 12 | byte_offset
+}
+
+fn check_conf_space_args(bus: usize, device: usize, func: usize, byte_offset: usize) -> Result<()> {
     if bus >= PCI_DEVICE_BUS_LEN
         || device >= PCI_DEVICE_DEVICE_LEN
         || func >= PCI_DEVICE_FUNC_LEN
+        || byte_offset >= PCI_EXT_CONF_SPACE_SIZE
         || byte_offset % 4 != 0
     {
         return Err(Error::Failed("Invalid args"));
     }
 
-    let addr = 0x80000000
+    Ok(())
+}
+
+fn port_conf_addr(bus: usize, device: usize, func: usize, byte_offset: usize) -> Result<u32> {
+    if byte_offset >= PCI_CONF_SPACE_SIZE {
+        return Err(Error::Failed(
+            "Extended configuration space is not available",
+        ));
+    }
+
+    Ok(0x80000000
         | (bus as u32) << 16
         | (device as u32) << 11
         | (func as u32) << 8
-        | byte_offset as u32;
-    PCI_PORT_CONF_REG_ADDR.out32(addr);
+        | byte_offset as u32)
+}
 
+// the whole ECAM region is mapped with 2MB pages
+pub fn init_ecam(base_addr: u64, start_bus: u8, end_bus: u8) -> Result<()> {
+    if base_addr == 0 || end_bus < start_bus {
+        return Err(Error::Failed("Invalid ECAM region"));
+    }
+
+    let start = base_addr + ecam_offset(start_bus as usize, 0, 0, 0) as u64;
+    let size = (end_bus as usize - start_bus as usize + 1) * PCI_ECAM_BUS_SIZE;
+    let page_size = HugePageSize::Size2M;
+    for offset in (0..size).step_by(page_size.size()) {
+        let addr = start + offset as u64;
+        paging::map_huge(
+            addr.into(),
+            addr.into(),
+            page_size,
+            ReadWrite::Write,
+            EntryMode::Supervisor,
+            PageWriteThroughLevel::WriteThrough,
+        )?;
+    }
+
+    ECAM_START_BUS_NUM.store(start_bus, Ordering::Relaxed);
+    ECAM_END_BUS_NUM.store(end_bus, Ordering::Relaxed);
+    ECAM_BASE_ADDR.store(base_addr, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn read_conf_space(bus: usize, device: usize, func: usize, byte_offset: usize) -> Result<u32> {
+    check_conf_space_args(bus, device, func, byte_offset)?;
+
+    if let Some(ptr) = ecam_addr(bus, device, func, byte_offset) {
+        return Ok(unsafe { ptr.read_volatile() });
+    }
+
+    PCI_PORT_CONF_REG_ADDR.out32(port_conf_addr(bus, device, func, byte_offset)?);
     Ok(PCI_PORT_CONF_DATA_REG_ADDR.in32())
 }
 
@@ -484,21 +677,45 @@ pub fn write_conf_space(
     byte_offset: usize,
     data: u32,
 ) -> Result<()> {
-    if bus >= PCI_DEVICE_BUS_LEN
-        || device >= PCI_DEVICE_DEVICE_LEN
-        || func >= PCI_DEVICE_FUNC_LEN
-        || byte_offset % 4 != 0
-    {
-        return Err(Error::Failed("Invalid args"));
+    check_conf_space_args(bus, device, func, byte_offset)?;
+
+    if let Some(ptr) = ecam_addr(bus, device, func, byte_offset) {
+        unsafe { ptr.write_volatile(data) };
+        return Ok(());
     }
 
-    let addr = 0x80000000
-        | (bus as u32) << 16
-        | (device as u32) << 11
-        | (func as u32) << 8
-        | byte_offset as u32;
-    PCI_PORT_CONF_REG_ADDR.out32(addr);
+    PCI_PORT_CONF_REG_ADDR.out32(port_conf_addr(bus, device, func, byte_offset)?);
     PCI_PORT_CONF_DATA_REG_ADDR.out32(data);
 
     Ok(())
 }
+
+#[test_case]
+fn test_msix_capability_field() {
+    // table size 4, table at offset 0x3000 of BAR 0, PBA at offset 0x3800 of BAR 0
+    let data: [u32; 3] = [0x0003_7011, 0x0000_3000, 0x0000_3800];
+    let mut cap = unsafe { transmute::<[u32; 3], MsixCapabilityField>(data) };
+    assert_eq!(CapId::from(cap.cap_id), CapId::Msix);
+    assert_eq!(cap.next_ptr, 0x70);
+    assert_eq!(cap.msg_ctrl.table_size(), 4);
+    assert_eq!(cap.table_bar_index(), 0);
+    assert_eq!(cap.table_offset(), 0x3000);
+
+    cap.msg_ctrl.set_function_mask(true);
+    cap.msg_ctrl.set_is_enable(true);
+    assert_eq!(cap.msg_ctrl.raw(), 0xc003);
+
+    let mut entry = MsixTableEntry::default();
+    entry.set_is_masked(true);
+    assert_eq!(entry.vector_ctrl, 1);
+    assert_eq!(core::mem::size_of::<MsixTableEntry>(), 16);
+}
+
+#[test_case]
+fn test_conf_space_addr() {
+    assert_eq!(ecam_offset(1, 2, 3, 0x104), 0x11_3104);
+    assert_eq!(ecam_offset(0, 31, 7, 0xffc), 0xf_fffc);
+
+    assert!(port_conf_addr(0, 0, 0, 0x100).is_err());
+    assert_eq!(port_conf_addr(1, 2, 3, 0x10).unwrap(), 0x8001_1310);
+}
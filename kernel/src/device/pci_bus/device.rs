@@ -1,14 +1,22 @@
 use super::conf_space::{self, *};
 use crate::{
+    addr::VirtualAddress,
     error::{Error, Result},
+    mem::paging::{self, EntryMode, MappingInfo, PageWriteThroughLevel, ReadWrite, PAGE_SIZE},
     register::msi::*,
 };
 use alloc::vec::Vec;
+use core::mem::size_of;
+
+// 48 capabilities at most in the 192 bytes after the header
+const CAPS_LIST_MAX_LEN: usize = 48;
 
 pub trait PciDeviceFunctions {
     fn bdf(&self) -> (usize, usize, usize);
     fn read_conf_space_header(&self) -> Result<ConfigurationSpaceCommonHeaderField>;
     fn write_conf_space_header(&self, value: ConfigurationSpaceCommonHeaderField) -> Result<()>;
+    fn enable_bus_master(&self) -> Result<()>;
+    fn enable_mmio(&self) -> Result<()>;
     fn read_conf_space_non_bridge_field(&self) -> Result<ConfigurationSpaceNonBridgeField>;
     fn read_conf_space_pci_to_pci_bridge_field(
         &self,
@@ -18,6 +26,7 @@ pub trait PciDeviceFunctions {
     fn read_interrupt_line(&self) -> Result<u8>;
     fn write_interrupt_line(&self, value: u8) -> Result<()>;
     fn device_class(&self) -> (u8, u8, u8);
+    fn capabilities(&self) -> Vec<(CapId, usize)>;
     fn is_available_msi_int(&self) -> bool;
     fn read_msi_caps_list(&self) -> Vec<MsiCapabilityField>;
     fn set_msi_cap(
@@ -25,6 +34,13 @@ pub trait PciDeviceFunctions {
         msg_addr: MsiMessageAddressField,
         msg_data: MsiMessageDataField,
     ) -> Result<()>;
+    fn is_available_msix_int(&self) -> bool;
+    fn set_msix_vector(
+        &self,
+        index: usize,
+        msg_addr: MsiMessageAddressField,
+        msg_data: MsiMessageDataField,
+    ) -> Result<()>;
 }
 
 #[derive(Debug, Clone)]
@@ -49,7 +65,7 @@ impl PciDevice {
     fn read_caps_ptr(&self) -> Option<u8> {
         let conf_space_header = self.read_conf_space_header().ok()?;
 
-        if conf_space_header.status.caps_list_available() {
+        if !conf_space_header.status.caps_list_available() {
             return None;
         }
 
@@ -66,6 +82,13 @@ impl PciDevice {
             _ => None, // unsupported type
         }
     }
+
+    fn find_cap_ptr(&self, cap_id: CapId) -> Option<usize> {
+        self.capabilities()
+            .into_iter()
+            .find(|(id, _)| *id == cap_id)
+            .map(|(_, offset)| offset)
+    }
 }
 
 impl PciDeviceFunctions for PciDevice {
@@ -83,6 +106,18 @@ impl PciDeviceFunctions for PciDevice {
         value.write(bus, device, func)
     }
 
+    fn enable_bus_master(&self) -> Result<()> {
+        let mut conf_space_header = self.read_conf_space_header()?;
+        conf_space_header.command.write_bus_master_enable(true);
+        self.write_conf_space_header(conf_space_header)
+    }
+
+    fn enable_mmio(&self) -> Result<()> {
+        let mut conf_space_header = self.read_conf_space_header()?;
+        conf_space_header.command.write_mem_space_enable(true);
+        self.write_conf_space_header(conf_space_header)
+    }
+
     fn read_conf_space_non_bridge_field(&self) -> Result<ConfigurationSpaceNonBridgeField> {
         let (bus, device, func) = self.bdf;
 
@@ -146,6 +181,28 @@ impl PciDeviceFunctions for PciDevice {
         (class, subclass, prog_if)
     }
 
+    // (capability ID, offset in the configuration space)
+    fn capabilities(&self) -> Vec<(CapId, usize)> {
+        let (bus, device, func) = self.bdf;
+        let mut caps = Vec::new();
+        let mut caps_ptr = match self.read_caps_ptr() {
+            Some(ptr) => ptr as usize & !0x3,
+            None => return caps,
+        };
+
+        // malformed lists may loop
+        while caps_ptr != 0 && caps.len() < CAPS_LIST_MAX_LEN {
+            let data = match conf_space::read_conf_space(bus, device, func, caps_ptr) {
+                Ok(data) => data,
+                Err(_) => break,
+            };
+            caps.push(((data as u8).into(), caps_ptr));
+            caps_ptr = (data >> 8) as u8 as usize & !0x3;
+        }
+
+        caps
+    }
+
     fn is_available_msi_int(&self) -> bool {
         self.read_conf_space_header()
             .unwrap()
@@ -195,7 +252,7 @@ impl PciDeviceFunctions for PciDevice {
         }
 
         for (i, field) in caps_list.iter().enumerate() {
-            if field.cap_id == 5 {
+            if CapId::from(field.cap_id) == CapId::Msi {
                 cap = *field;
                 break;
             }
@@ -220,4 +277,78 @@ impl PciDeviceFunctions for PciDevice {
 
         Ok(())
     }
+
+    fn is_available_msix_int(&self) -> bool {
+        self.find_cap_ptr(CapId::Msix).is_some()
+    }
+
+    fn set_msix_vector(
+        &self,
+        index: usize,
+        msg_addr: MsiMessageAddressField,
+        msg_data: MsiMessageDataField,
+    ) -> Result<()> {
+        let (bus, device, func) = self.bdf;
+        let caps_ptr = self
+            .find_cap_ptr(CapId::Msix)
+            .ok_or(Error::Failed("MSI-X capability fields was not found"))?;
+        let mut cap = MsixCapabilityField::read(bus, device, func, caps_ptr)?;
+
+        if index >= cap.msg_ctrl.table_size() {
+            return Err(Error::Failed("Invalid MSI-X table index"));
+        }
+
+        let table_bar_index = cap.table_bar_index();
+        let bar_phys_addr = self
+            .read_conf_space_non_bridge_field()?
+            .get_bars()?
+            .into_iter()
+            .find_map(|(i, bar)| match bar {
+                BaseAddress::MemoryAddress32BitSpace(addr, _)
+                | BaseAddress::MemoryAddress64BitSpace(addr, _)
+                    if i == table_bar_index =>
+                {
+                    Some(addr)
+                }
+                _ => None,
+            })
+            .ok_or(Error::Failed("MSI-X table BAR was not found"))?;
+
+        // the table may be out of the mapped range of the BAR
+        let entry_phys_addr =
+            bar_phys_addr.offset(cap.table_offset() + index * size_of::<MsixTableEntry>());
+        let page_phys_addr = entry_phys_addr.get() & !(PAGE_SIZE as u64 - 1);
+        let start: VirtualAddress = page_phys_addr.into();
+        paging::update_mapping(&MappingInfo {
+            start,
+            end: start.offset(PAGE_SIZE),
+            phys_addr: page_phys_addr.into(),
+            rw: ReadWrite::Write,
+            us: EntryMode::Supervisor,
+            pwt: PageWriteThroughLevel::WriteThrough,
+            exec_disable: false,
+        })?;
+
+        let entry_ptr = entry_phys_addr.get() as *mut MsixTableEntry;
+        let mut entry = MsixTableEntry {
+            msg_addr_low: msg_addr,
+            msg_addr_high: 0,
+            msg_data,
+            vector_ctrl: unsafe { entry_ptr.read_volatile() }.vector_ctrl,
+        };
+
+        // update the entry while masked
+        entry.set_is_masked(true);
+        unsafe { entry_ptr.write_volatile(entry) };
+        entry.set_is_masked(false);
+        unsafe { entry_ptr.write_volatile(entry) };
+
+        let mut msg_ctrl = cap.msg_ctrl;
+        msg_ctrl.set_is_enable(true);
+        msg_ctrl.set_function_mask(false);
+        cap.msg_ctrl = msg_ctrl;
+        cap.write(bus, device, func, caps_ptr)?;
+
+        Ok(())
+    }
 }
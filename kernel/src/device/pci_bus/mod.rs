@@ -1,5 +1,6 @@
 use super::{DeviceDriverFunction, DeviceDriverInfo};
 use crate::{
+    acpi,
     error::{Error, Result},
     println,
     util::mutex::Mutex,
@@ -7,7 +8,7 @@ use crate::{
 use alloc::vec::Vec;
 use conf_space::*;
 use device::{PciDevice, PciDeviceFunctions};
-use log::{debug, info};
+use log::{debug, info, warn};
 
 pub mod conf_space;
 mod device;
@@ -132,6 +133,12 @@ impl PciBusDriver {
                     println!("BAR{}: {} at 0x{:x}", bar.0, ty, addr);
                 }
             }
+            for (cap_id, offset) in d.capabilities() {
+                match cap_id {
+                    CapId::Other(id) => println!("Capability 0x{:x} at 0x{:x}", id, offset),
+                    cap_id => println!("Capability {} at 0x{:x}", cap_id.name(), offset),
+                }
+            }
             println!("--------------");
         }
     }
@@ -147,6 +154,22 @@ impl DeviceDriverFunction for PciBusDriver {
     }
 
     fn probe(&mut self) -> Result<()> {
+        // fall back to the legacy I/O ports (first 256 bytes only)
+        let ecam_res = acpi::pci_ecam().and_then(|(base_addr, start_bus, end_bus)| {
+            conf_space::init_ecam(base_addr, start_bus, end_bus)?;
+            Ok((base_addr, start_bus, end_bus))
+        });
+        match ecam_res {
+            Ok((base_addr, start_bus, end_bus)) => info!(
+                "{}: Using ECAM at 0x{:x} (bus {}-{})",
+                self.device_driver_info.name, base_addr, start_bus, end_bus
+            ),
+            Err(err) => warn!(
+                "{}: ECAM is not available, using I/O ports: {:?}",
+                self.device_driver_info.name, err
+            ),
+        }
+
         Ok(())
     }
 
@@ -213,6 +236,20 @@ pub fn configure_device<F: FnMut(&mut dyn PciDeviceFunctions) -> Result<()>>(
     f(device_mut)
 }
 
+// used by the drivers' attach, enables MMIO decoding and bus mastering for DMA first
+pub fn attach_device<F: FnMut(&mut dyn PciDeviceFunctions) -> Result<()>>(
+    bus: usize,
+    device: usize,
+    func: usize,
+    mut f: F,
+) -> Result<()> {
+    configure_device(bus, device, func, |d| {
+        d.enable_mmio()?;
+        d.enable_bus_master()?;
+        f(d)
+    })
+}
+
 pub fn find_devices<F: FnMut(&mut dyn PciDeviceFunctions) -> Result<()>>(
     class: u8,
     subclass: u8,
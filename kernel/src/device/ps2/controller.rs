@@ -0,0 +1,206 @@
+use crate::{
+    arch::{self, addr::IoPortAddress},
+    error::{Error, Result},
+    util::mutex::Mutex,
+};
+use log::{info, warn};
+
+const PS2_DATA_REG_ADDR: IoPortAddress = IoPortAddress::new(0x60);
+const PS2_CMD_AND_STATE_REG_ADDR: IoPortAddress = IoPortAddress::new(0x64);
+
+const PS2_CTRL_CMD_READ_CONF: u8 = 0x20;
+const PS2_CTRL_CMD_WRITE_CONF: u8 = 0x60;
+const PS2_CTRL_CMD_DISABLE_PORT2: u8 = 0xa7;
+const PS2_CTRL_CMD_ENABLE_PORT2: u8 = 0xa8;
+const PS2_CTRL_CMD_TEST_PORT2: u8 = 0xa9;
+const PS2_CTRL_CMD_SELF_TEST: u8 = 0xaa;
+const PS2_CTRL_CMD_TEST_PORT1: u8 = 0xab;
+const PS2_CTRL_CMD_DISABLE_PORT1: u8 = 0xad;
+const PS2_CTRL_CMD_ENABLE_PORT1: u8 = 0xae;
+const PS2_CTRL_SELF_TEST_PASSED: u8 = 0x55;
+const PS2_CTRL_PORT_TEST_PASSED: u8 = 0x00;
+
+// configuration byte
+const PS2_CONF_PORT1_INT: u8 = 0x01;
+const PS2_CONF_PORT2_INT: u8 = 0x02;
+const PS2_CONF_PORT1_CLOCK_DISABLED: u8 = 0x10;
+const PS2_CONF_PORT2_CLOCK_DISABLED: u8 = 0x20;
+const PS2_CONF_PORT1_TRANSLATION: u8 = 0x40;
+
+const READ_TIMEOUT_LOOP_CNT: usize = 1_000_000;
+const FLUSH_MAX_LEN: usize = 16;
+
+static mut PS2_CTRL: Mutex<Ps2Controller> = Mutex::new(Ps2Controller::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ps2Port {
+    Port1, // keyboard
+    Port2, // mouse
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Ps2Ports {
+    pub port1: bool,
+    pub port2: bool,
+}
+
+struct Ps2Controller {
+    ports: Option<Ps2Ports>,
+}
+
+impl Ps2Controller {
+    const fn new() -> Self {
+        Self { ports: None }
+    }
+
+    fn init(&mut self) -> Result<Ps2Ports> {
+        // disable devices
+        self.write_cmd(PS2_CTRL_CMD_DISABLE_PORT1);
+        self.write_cmd(PS2_CTRL_CMD_DISABLE_PORT2);
+        self.flush_output_buf();
+
+        // disable interrupts, the keyboard driver expects the scan code set 1
+        let mut conf = self.read_conf()?;
+        conf &= !(PS2_CONF_PORT1_INT | PS2_CONF_PORT2_INT | PS2_CONF_PORT1_CLOCK_DISABLED);
+        conf |= PS2_CONF_PORT1_TRANSLATION;
+        self.write_conf(conf);
+
+        // some controllers are reset by the self-test
+        self.write_cmd(PS2_CTRL_CMD_SELF_TEST);
+        if self.read_data()? != PS2_CTRL_SELF_TEST_PASSED {
+            return Err(Error::Failed("PS/2 controller self-test failed"));
+        }
+        self.write_conf(conf);
+
+        // the port 2 clock is enabled only if the controller has 2 channels
+        let mut is_dual_channel = false;
+        if conf & PS2_CONF_PORT2_CLOCK_DISABLED != 0 {
+            self.write_cmd(PS2_CTRL_CMD_ENABLE_PORT2);
+            is_dual_channel = self.read_conf()? & PS2_CONF_PORT2_CLOCK_DISABLED == 0;
+            self.write_cmd(PS2_CTRL_CMD_DISABLE_PORT2);
+        }
+
+        // interface tests
+        let mut ports = Ps2Ports::default();
+        ports.port1 = self.test_port(PS2_CTRL_CMD_TEST_PORT1)?;
+        if is_dual_channel {
+            ports.port2 = self.test_port(PS2_CTRL_CMD_TEST_PORT2)?;
+        }
+
+        if !ports.port1 && !ports.port2 {
+            return Err(Error::Failed("PS/2 ports were not found"));
+        }
+
+        // enable devices, interrupts are enabled by each driver
+        if ports.port1 {
+            self.write_cmd(PS2_CTRL_CMD_ENABLE_PORT1);
+        }
+        if ports.port2 {
+            self.write_cmd(PS2_CTRL_CMD_ENABLE_PORT2);
+        }
+        self.flush_output_buf();
+
+        self.ports = Some(ports);
+        Ok(ports)
+    }
+
+    fn ports(&self) -> Result<Ps2Ports> {
+        self.ports
+            .ok_or(Error::Failed("PS/2 controller is not initialized"))
+    }
+
+    fn set_int(&mut self, port: Ps2Port, enabled: bool) -> Result<()> {
+        let ports = self.ports()?;
+        let (is_present, int_bit) = match port {
+            Ps2Port::Port1 => (ports.port1, PS2_CONF_PORT1_INT),
+            Ps2Port::Port2 => (ports.port2, PS2_CONF_PORT2_INT),
+        };
+
+        if !is_present {
+            return Err(Error::Failed("PS/2 port is not present"));
+        }
+
+        let mut conf = self.read_conf()?;
+        if enabled {
+            conf |= int_bit;
+        } else {
+            conf &= !int_bit;
+        }
+        self.write_conf(conf);
+
+        Ok(())
+    }
+
+    fn test_port(&self, cmd: u8) -> Result<bool> {
+        self.write_cmd(cmd);
+        let res = self.read_data()?;
+        if res != PS2_CTRL_PORT_TEST_PASSED {
+            warn!("ps2: Port test failed (cmd: 0x{:x}, res: 0x{:x})", cmd, res);
+        }
+
+        Ok(res == PS2_CTRL_PORT_TEST_PASSED)
+    }
+
+    fn read_conf(&self) -> Result<u8> {
+        self.write_cmd(PS2_CTRL_CMD_READ_CONF);
+        self.read_data()
+    }
+
+    fn write_conf(&self, conf: u8) {
+        self.write_cmd(PS2_CTRL_CMD_WRITE_CONF);
+        PS2_DATA_REG_ADDR.out8(conf);
+        self.wait_ready();
+    }
+
+    fn write_cmd(&self, cmd: u8) {
+        self.wait_ready();
+        PS2_CMD_AND_STATE_REG_ADDR.out8(cmd);
+        self.wait_ready();
+    }
+
+    // polling, must be called with interrupts disabled
+    fn read_data(&self) -> Result<u8> {
+        for _ in 0..READ_TIMEOUT_LOOP_CNT {
+            if PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x1 != 0 {
+                return Ok(PS2_DATA_REG_ADDR.in8());
+            }
+        }
+
+        Err(Error::Failed("Timed out reading PS/2 data"))
+    }
+
+    // drop stale bytes such as the self-test result of the devices
+    fn flush_output_buf(&self) {
+        for _ in 0..FLUSH_MAX_LEN {
+            if PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x1 == 0 {
+                break;
+            }
+            let _ = PS2_DATA_REG_ADDR.in8();
+        }
+    }
+
+    fn wait_ready(&self) {
+        while PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x2 != 0 {
+            continue;
+        }
+    }
+}
+
+// returns the ports which passed the interface test
+pub fn init() -> Result<Ps2Ports> {
+    let ports = arch::disabled_int(|| unsafe { PS2_CTRL.try_lock() }?.init())?;
+    info!(
+        "ps2: Controller initialized (port1: {}, port2: {})",
+        ports.port1, ports.port2
+    );
+    Ok(ports)
+}
+
+pub fn ports() -> Result<Ps2Ports> {
+    unsafe { PS2_CTRL.try_lock() }?.ports()
+}
+
+// must be called with interrupts disabled
+pub fn set_int(port: Ps2Port, enabled: bool) -> Result<()> {
+    unsafe { PS2_CTRL.try_lock() }?.set_int(port, enabled)
+}
@@ -5,6 +5,22 @@ pub type KeyMapArray<const LEN: usize> = [ScanCode; LEN];
 
 pub enum KeyMap {
     AnsiUs104(KeyMapArray<104>),
+    Jis109(KeyMapArray<109>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyLayout {
+    AnsiUs104,
+    Jis109,
+}
+
+impl KeyLayout {
+    pub fn key_map(&self) -> KeyMap {
+        match self {
+            Self::AnsiUs104 => ANSI_US_104_KEY_MAP,
+            Self::Jis109 => JIS_109_KEY_MAP,
+        }
+    }
 }
 
 pub const ANSI_US_104_KEY_MAP: KeyMap = KeyMap::AnsiUs104([
@@ -737,3 +753,770 @@ pub const ANSI_US_104_KEY_MAP: KeyMap = KeyMap::AnsiUs104([
         released: [0xb5, 0x00, 0x00, 0x00, 0x00, 0x00],
     },
 ]);
+
+// symbols are placed by the JIS layout, the yen key is mapped to backslash
+pub const JIS_109_KEY_MAP: KeyMap = KeyMap::Jis109([
+    ScanCode {
+        key_code: KeyCode::Esc,
+        ascii_code: Some(AsciiCode::Escape),
+        on_shift_ascii_code: None,
+        pressed: [0x01, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x81, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F1,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xbb, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F2,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3c, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xbc, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F3,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xbd, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F4,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3e, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xbe, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F5,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3f, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xbf, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F6,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x40, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F7,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x41, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc1, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F8,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x42, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc2, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F9,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x43, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc3, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F10,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x44, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc4, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F11,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x57, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd7, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F12,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x58, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd8, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::PrintScreen,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x1d, 0x46, 0x9d, 0xc5, 0x00],
+        released: [0xe0, 0xb7, 0xe0, 0xaa, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::ScrollLock,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x46, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc6, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Pause,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe1, 0x1d, 0x45, 0x9d, 0xc5, 0x00],
+        released: [0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Insert,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x52, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xd2, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Home,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x47, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xc7, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::PageUp,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x49, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xc9, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Delete,
+        ascii_code: Some(AsciiCode::Delete),
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x53, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xd3, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::End,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x4f, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xcf, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::PageDown,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x51, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xd1, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::CursorRight,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x4d, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xcd, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::CursorLeft,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x4b, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xcb, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::CursorDown,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x50, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xd0, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::CursorUp,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x48, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xc8, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::NumLock,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x45, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc5, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpDivide,
+        ascii_code: Some(AsciiCode::Solidius),
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x35, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xb5, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpMultiply,
+        ascii_code: Some(AsciiCode::Asterisk),
+        on_shift_ascii_code: None,
+        pressed: [0x37, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb7, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpSubtract,
+        ascii_code: Some(AsciiCode::Hyphen),
+        on_shift_ascii_code: None,
+        pressed: [0x4a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xca, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpAdd,
+        ascii_code: Some(AsciiCode::Plus),
+        on_shift_ascii_code: None,
+        pressed: [0x4e, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xce, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpEnter,
+        ascii_code: Some(AsciiCode::NewLine),
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x1c, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0x9c, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp1,
+        ascii_code: Some(AsciiCode::Num1),
+        on_shift_ascii_code: None,
+        pressed: [0x4f, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xcf, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp2,
+        ascii_code: Some(AsciiCode::Num2),
+        on_shift_ascii_code: None,
+        pressed: [0x50, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd0, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp3,
+        ascii_code: Some(AsciiCode::Num3),
+        on_shift_ascii_code: None,
+        pressed: [0x51, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd1, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp4,
+        ascii_code: Some(AsciiCode::Num4),
+        on_shift_ascii_code: None,
+        pressed: [0x4b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xcb, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp5,
+        ascii_code: Some(AsciiCode::Num5),
+        on_shift_ascii_code: None,
+        pressed: [0x4c, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xcc, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp6,
+        ascii_code: Some(AsciiCode::Num6),
+        on_shift_ascii_code: None,
+        pressed: [0x4d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xcd, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp7,
+        ascii_code: Some(AsciiCode::Num7),
+        on_shift_ascii_code: None,
+        pressed: [0x47, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc7, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp8,
+        ascii_code: Some(AsciiCode::Num8),
+        on_shift_ascii_code: None,
+        pressed: [0x48, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc8, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp9,
+        ascii_code: Some(AsciiCode::Num9),
+        on_shift_ascii_code: None,
+        pressed: [0x49, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xc9, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Kp0,
+        ascii_code: Some(AsciiCode::Num0),
+        on_shift_ascii_code: None,
+        pressed: [0x52, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd2, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KpPeriod,
+        ascii_code: Some(AsciiCode::FullStop),
+        on_shift_ascii_code: None,
+        pressed: [0x53, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xd3, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::LCtrl,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x1d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9d, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::LGui,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x5b, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xdb, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::LAlt,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x38, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb8, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Space,
+        ascii_code: Some(AsciiCode::Space),
+        on_shift_ascii_code: None,
+        pressed: [0x39, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb9, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::RGui,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x5c, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xdc, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::RAlt,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x38, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xb8, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Apps,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x6d, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0xdd, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::RCtrl,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0xe0, 0x1d, 0x00, 0x00, 0x00, 0x00],
+        released: [0xe0, 0x9d, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::LShift,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x2a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xaa, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::CapsLock,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x3a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xba, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Tab,
+        ascii_code: Some(AsciiCode::HorizontalTab),
+        on_shift_ascii_code: None,
+        pressed: [0x0f, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8f, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Backspace,
+        ascii_code: Some(AsciiCode::Backspace),
+        on_shift_ascii_code: None,
+        pressed: [0x0e, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8e, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Enter,
+        ascii_code: Some(AsciiCode::NewLine),
+        on_shift_ascii_code: None,
+        pressed: [0x1c, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9c, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::RShift,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x36, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb6, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num1,
+        ascii_code: Some(AsciiCode::Num1),
+        on_shift_ascii_code: Some(AsciiCode::Exclamation),
+        pressed: [0x02, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x82, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num2,
+        ascii_code: Some(AsciiCode::Num2),
+        on_shift_ascii_code: Some(AsciiCode::Quotation),
+        pressed: [0x03, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x83, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num3,
+        ascii_code: Some(AsciiCode::Num3),
+        on_shift_ascii_code: Some(AsciiCode::Hash),
+        pressed: [0x04, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x84, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num4,
+        ascii_code: Some(AsciiCode::Num4),
+        on_shift_ascii_code: Some(AsciiCode::Doll),
+        pressed: [0x05, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x85, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num5,
+        ascii_code: Some(AsciiCode::Num5),
+        on_shift_ascii_code: Some(AsciiCode::Percent),
+        pressed: [0x06, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x86, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num6,
+        ascii_code: Some(AsciiCode::Num6),
+        on_shift_ascii_code: Some(AsciiCode::Ampersand),
+        pressed: [0x07, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x87, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num7,
+        ascii_code: Some(AsciiCode::Num7),
+        on_shift_ascii_code: Some(AsciiCode::Apostrophe),
+        pressed: [0x08, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x88, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num8,
+        ascii_code: Some(AsciiCode::Num8),
+        on_shift_ascii_code: Some(AsciiCode::LParenthesis),
+        pressed: [0x09, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x89, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num9,
+        ascii_code: Some(AsciiCode::Num9),
+        on_shift_ascii_code: Some(AsciiCode::RParenthesis),
+        pressed: [0x0a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8a, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Num0,
+        ascii_code: Some(AsciiCode::Num0),
+        on_shift_ascii_code: None,
+        pressed: [0x0b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8b, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::A,
+        ascii_code: Some(AsciiCode::SmallA),
+        on_shift_ascii_code: Some(AsciiCode::LargeA),
+        pressed: [0x1e, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9e, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::B,
+        ascii_code: Some(AsciiCode::SmallB),
+        on_shift_ascii_code: Some(AsciiCode::LargeB),
+        pressed: [0x30, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb0, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::C,
+        ascii_code: Some(AsciiCode::SmallC),
+        on_shift_ascii_code: Some(AsciiCode::LargeC),
+        pressed: [0x2e, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xae, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::D,
+        ascii_code: Some(AsciiCode::SmallD),
+        on_shift_ascii_code: Some(AsciiCode::LargeD),
+        pressed: [0x20, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa0, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::E,
+        ascii_code: Some(AsciiCode::SmallE),
+        on_shift_ascii_code: Some(AsciiCode::LargeE),
+        pressed: [0x12, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x92, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::F,
+        ascii_code: Some(AsciiCode::SmallF),
+        on_shift_ascii_code: Some(AsciiCode::LargeF),
+        pressed: [0x21, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa1, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::G,
+        ascii_code: Some(AsciiCode::SmallG),
+        on_shift_ascii_code: Some(AsciiCode::LargeG),
+        pressed: [0x22, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa2, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::H,
+        ascii_code: Some(AsciiCode::SmallH),
+        on_shift_ascii_code: Some(AsciiCode::LargeH),
+        pressed: [0x23, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa3, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::I,
+        ascii_code: Some(AsciiCode::SmallI),
+        on_shift_ascii_code: Some(AsciiCode::LargeI),
+        pressed: [0x17, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x97, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::J,
+        ascii_code: Some(AsciiCode::SmallJ),
+        on_shift_ascii_code: Some(AsciiCode::LargeJ),
+        pressed: [0x24, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa4, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::K,
+        ascii_code: Some(AsciiCode::SmallK),
+        on_shift_ascii_code: Some(AsciiCode::LargeK),
+        pressed: [0x25, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa5, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::L,
+        ascii_code: Some(AsciiCode::SmallL),
+        on_shift_ascii_code: Some(AsciiCode::LargeL),
+        pressed: [0x26, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa6, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::M,
+        ascii_code: Some(AsciiCode::SmallM),
+        on_shift_ascii_code: Some(AsciiCode::LargeM),
+        pressed: [0x32, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb2, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::N,
+        ascii_code: Some(AsciiCode::SmallN),
+        on_shift_ascii_code: Some(AsciiCode::LargeN),
+        pressed: [0x31, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb1, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::O,
+        ascii_code: Some(AsciiCode::SmallO),
+        on_shift_ascii_code: Some(AsciiCode::LargeO),
+        pressed: [0x18, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x98, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::P,
+        ascii_code: Some(AsciiCode::SmallP),
+        on_shift_ascii_code: Some(AsciiCode::LargeP),
+        pressed: [0x19, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x99, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Q,
+        ascii_code: Some(AsciiCode::SmallQ),
+        on_shift_ascii_code: Some(AsciiCode::LargeQ),
+        pressed: [0x10, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x90, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::R,
+        ascii_code: Some(AsciiCode::SmallR),
+        on_shift_ascii_code: Some(AsciiCode::LargeR),
+        pressed: [0x13, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x93, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::S,
+        ascii_code: Some(AsciiCode::SmallS),
+        on_shift_ascii_code: Some(AsciiCode::LargeS),
+        pressed: [0x1f, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9f, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::T,
+        ascii_code: Some(AsciiCode::SmallT),
+        on_shift_ascii_code: Some(AsciiCode::LargeT),
+        pressed: [0x14, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x94, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::U,
+        ascii_code: Some(AsciiCode::SmallU),
+        on_shift_ascii_code: Some(AsciiCode::LargeU),
+        pressed: [0x16, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x96, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::V,
+        ascii_code: Some(AsciiCode::SmallV),
+        on_shift_ascii_code: Some(AsciiCode::LargeV),
+        pressed: [0x2f, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xaf, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::W,
+        ascii_code: Some(AsciiCode::SmallW),
+        on_shift_ascii_code: Some(AsciiCode::LargeW),
+        pressed: [0x11, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x91, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::X,
+        ascii_code: Some(AsciiCode::SmallX),
+        on_shift_ascii_code: Some(AsciiCode::LargeX),
+        pressed: [0x2d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xad, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Y,
+        ascii_code: Some(AsciiCode::SmallY),
+        on_shift_ascii_code: Some(AsciiCode::LargeY),
+        pressed: [0x15, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x95, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Z,
+        ascii_code: Some(AsciiCode::SmallZ),
+        on_shift_ascii_code: Some(AsciiCode::LargeZ),
+        pressed: [0x2c, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xac, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::HankakuZenkaku,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x29, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa9, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Subtract,
+        ascii_code: Some(AsciiCode::Hyphen),
+        on_shift_ascii_code: Some(AsciiCode::Equal),
+        pressed: [0x0c, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8c, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Equal,
+        ascii_code: Some(AsciiCode::CircumflexAccent),
+        on_shift_ascii_code: Some(AsciiCode::Tilde),
+        pressed: [0x0d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x8d, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::BracketLeft,
+        ascii_code: Some(AsciiCode::At),
+        on_shift_ascii_code: Some(AsciiCode::GraveAccent),
+        pressed: [0x1a, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9a, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::BracketRight,
+        ascii_code: Some(AsciiCode::LSquareBracket),
+        on_shift_ascii_code: Some(AsciiCode::LCurlyBracket),
+        pressed: [0x1b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0x9b, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Backslash,
+        ascii_code: Some(AsciiCode::RSquareBracket),
+        on_shift_ascii_code: Some(AsciiCode::RCurlyBracket),
+        pressed: [0x2b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xab, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Semicolon,
+        ascii_code: Some(AsciiCode::Semiclon),
+        on_shift_ascii_code: Some(AsciiCode::Plus),
+        pressed: [0x27, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa7, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Quote,
+        ascii_code: Some(AsciiCode::Colon),
+        on_shift_ascii_code: Some(AsciiCode::Asterisk),
+        pressed: [0x28, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xa8, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Comma,
+        ascii_code: Some(AsciiCode::Comma),
+        on_shift_ascii_code: Some(AsciiCode::LessThan),
+        pressed: [0x33, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb3, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Period,
+        ascii_code: Some(AsciiCode::FullStop),
+        on_shift_ascii_code: Some(AsciiCode::GreaterThan),
+        pressed: [0x34, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb4, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Slash,
+        ascii_code: Some(AsciiCode::Solidius),
+        on_shift_ascii_code: Some(AsciiCode::Question),
+        pressed: [0x35, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xb5, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Yen,
+        ascii_code: Some(AsciiCode::ReverseSolidus),
+        on_shift_ascii_code: Some(AsciiCode::VerticalLine),
+        pressed: [0x7d, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xfd, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Ro,
+        ascii_code: Some(AsciiCode::ReverseSolidus),
+        on_shift_ascii_code: Some(AsciiCode::LowLine),
+        pressed: [0x73, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xf3, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Henkan,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x79, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xf9, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::Muhenkan,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x7b, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xfb, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+    ScanCode {
+        key_code: KeyCode::KatakanaHiragana,
+        ascii_code: None,
+        on_shift_ascii_code: None,
+        pressed: [0x70, 0x00, 0x00, 0x00, 0x00, 0x00],
+        released: [0xf0, 0x00, 0x00, 0x00, 0x00, 0x00],
+    },
+]);
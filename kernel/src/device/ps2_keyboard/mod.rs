@@ -1,5 +1,13 @@
-use self::{key_event::KeyEvent, key_map::KeyMap};
-use super::{console, DeviceDriverFunction, DeviceDriverInfo};
+use self::{
+    key_event::KeyEvent,
+    key_map::{KeyLayout, KeyMap},
+    scan_code::{KeyCode, ScanCode},
+};
+use super::{
+    console, local_apic_timer,
+    ps2::controller::{self, Ps2Port},
+    DeviceDriverFunction, DeviceDriverInfo,
+};
 use crate::{
     arch::{self, addr::IoPortAddress},
     device::ps2_keyboard::{
@@ -7,18 +15,23 @@ use crate::{
         key_map::ANSI_US_104_KEY_MAP,
     },
     error::{Error, Result},
-    idt, print, println,
-    util::{ascii::AsciiCode, fifo::Fifo, mutex::Mutex},
+    graphics::{frame_buf_console, simple_window_manager},
+    idt,
+    util::{
+        fifo::{Fifo, FifoError},
+        mutex::Mutex,
+    },
 };
 use alloc::vec::Vec;
 use log::info;
 
 pub mod key_event;
-mod key_map;
-mod scan_code;
+pub mod key_map;
+pub mod scan_code;
 
 const PS2_DATA_REG_ADDR: IoPortAddress = IoPortAddress::new(0x60);
-const PS2_CMD_AND_STATE_REG_ADDR: IoPortAddress = IoPortAddress::new(0x64);
+const DEFAULT_TYPEMATIC_DELAY_MS: usize = 500;
+const DEFAULT_TYPEMATIC_RATE_HZ: usize = 20;
 
 static mut PS2_KBD_DRIVER: Mutex<Ps2KeyboardDriver> =
     Mutex::new(Ps2KeyboardDriver::new(ANSI_US_104_KEY_MAP));
@@ -28,6 +41,10 @@ struct Ps2KeyboardDriver {
     key_map: KeyMap,
     mod_keys_state: ModifierKeysState,
     data_buf: Fifo<u8, 128>,
+    typematic_delay_ms: usize,
+    typematic_rate_hz: usize,
+    held_key: Option<KeyEvent>,
+    next_repeat_ms: usize,
     data_0: Option<u8>,
     data_1: Option<u8>,
     data_2: Option<u8>,
@@ -48,6 +65,10 @@ impl Ps2KeyboardDriver {
                 alt: false,
             },
             data_buf: Fifo::new(0),
+            typematic_delay_ms: DEFAULT_TYPEMATIC_DELAY_MS,
+            typematic_rate_hz: DEFAULT_TYPEMATIC_RATE_HZ,
+            held_key: None,
+            next_repeat_ms: 0,
             data_0: None,
             data_1: None,
             data_2: None,
@@ -57,15 +78,9 @@ impl Ps2KeyboardDriver {
         }
     }
 
+    // drop the data if the buffer is full
     fn input(&mut self, data: u8) -> Result<()> {
-        if self.data_buf.enqueue(data).is_err() {
-            self.data_buf.reset_ptr();
-            self.data_buf.enqueue(data)?;
-        }
-
-        //println!("{:?}", self.data_buf.get_buf_ref());
-
-        Ok(())
+        self.data_buf.enqueue(data)
     }
 
     fn get_event(&mut self) -> Result<Option<KeyEvent>> {
@@ -96,8 +111,9 @@ impl Ps2KeyboardDriver {
             self.data_4.unwrap_or(0),
             self.data_5.unwrap_or(0),
         ];
-        let key_map = match self.key_map {
+        let key_map: &[ScanCode] = match &self.key_map {
             KeyMap::AnsiUs104(map) => map,
+            KeyMap::Jis109(map) => map,
         };
 
         for scan_code in key_map {
@@ -152,6 +168,46 @@ impl Ps2KeyboardDriver {
         Ok(None)
     }
 
+    // hardware repeats of the held key are dropped, the driver repeats it instead
+    fn update_held_key(&mut self, key_event: KeyEvent) -> Option<KeyEvent> {
+        let is_held = self.held_key.is_some_and(|e| e.code == key_event.code);
+
+        match key_event.state {
+            KeyState::Pressed if is_held => return None,
+            KeyState::Pressed if key_event.ascii.is_some() => {
+                self.held_key = Some(key_event);
+                self.next_repeat_ms =
+                    local_apic_timer::get_current_ms().unwrap_or(0) + self.typematic_delay_ms;
+            }
+            KeyState::Released if is_held => self.held_key = None,
+            _ => (),
+        }
+
+        Some(key_event)
+    }
+
+    fn repeat_held_key(&mut self) -> Option<KeyEvent> {
+        let key_event = self.held_key?;
+        let current_ms = local_apic_timer::get_current_ms()?;
+        if self.typematic_rate_hz == 0 || current_ms < self.next_repeat_ms {
+            return None;
+        }
+
+        self.next_repeat_ms = current_ms + 1000 / self.typematic_rate_hz;
+        Some(key_event)
+    }
+
+    fn set_typematic(&mut self, delay_ms: usize, rate_hz: usize) {
+        self.typematic_delay_ms = delay_ms;
+        self.typematic_rate_hz = rate_hz;
+    }
+
+    fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_map = key_map;
+        self.mod_keys_state = ModifierKeysState::default();
+        self.clear_data();
+    }
+
     fn clear_data(&mut self) {
         self.data_0 = None;
         self.data_1 = None;
@@ -160,12 +216,6 @@ impl Ps2KeyboardDriver {
         self.data_4 = None;
         self.data_5 = None;
     }
-
-    fn wait_ready(&self) {
-        while PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x2 != 0 {
-            continue;
-        }
-    }
 }
 
 impl DeviceDriverFunction for Ps2KeyboardDriver {
@@ -178,17 +228,21 @@ impl DeviceDriverFunction for Ps2KeyboardDriver {
     }
 
     fn probe(&mut self) -> Result<()> {
+        if !controller::ports()?.port1 {
+            return Err(Error::Failed("PS/2 keyboard port was not found"));
+        }
+
         Ok(())
     }
 
     fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
-        PS2_CMD_AND_STATE_REG_ADDR.out8(0x60); // write configuration byte
-        self.wait_ready();
-        PS2_DATA_REG_ADDR.out8(0x47); // enable interrupt
-        self.wait_ready();
+        idt::set_irq_handler(
+            idt::IRQ_PS2_KBD,
+            idt::InterruptHandler::Normal(poll_int_ps2_kbd_driver),
+            idt::GateType::Interrupt,
+        )?;
 
-        PS2_CMD_AND_STATE_REG_ADDR.out8(0x20); // read configuration byte
-        self.wait_ready();
+        controller::set_int(Ps2Port::Port1, true)?;
 
         self.device_driver_info.attached = true;
         Ok(())
@@ -199,7 +253,12 @@ impl DeviceDriverFunction for Ps2KeyboardDriver {
             return Err(Error::Failed("Device driver is not attached"));
         }
 
-        self.get_event()
+        match self.get_event() {
+            Ok(Some(key_event)) => Ok(self.update_held_key(key_event)),
+            Ok(None) => Ok(None),
+            Err(Error::FifoError(FifoError::BufferIsEmpty)) => Ok(self.repeat_held_key()),
+            Err(err) => Err(err),
+        }
     }
 
     fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
@@ -237,10 +296,27 @@ pub fn probe_and_attach() -> Result<()> {
     })
 }
 
+pub fn set_layout(layout: KeyLayout) -> Result<()> {
+    arch::disabled_int(|| {
+        unsafe { PS2_KBD_DRIVER.try_lock() }?.set_key_map(layout.key_map());
+        Result::Ok(())
+    })?;
+    info!("ps2-kbd: Set keyboard layout to {:?}", layout);
+    Ok(())
+}
+
+// rate_hz 0 disables auto-repeat
+pub fn set_typematic(delay_ms: usize, rate_hz: usize) -> Result<()> {
+    arch::disabled_int(|| {
+        unsafe { PS2_KBD_DRIVER.try_lock() }?.set_typematic(delay_ms, rate_hz);
+        Result::Ok(())
+    })
+}
+
 pub fn poll_normal() -> Result<()> {
-    let key_event = arch::disabled_int(|| {
+    let (key_event, mod_keys_state) = arch::disabled_int(|| {
         let mut driver = unsafe { PS2_KBD_DRIVER.try_lock() }?;
-        driver.poll_normal()
+        Result::Ok((driver.poll_normal()?, driver.mod_keys_state))
     })?;
     let key_event = match key_event {
         Some(e) => e,
@@ -251,19 +327,33 @@ pub fn poll_normal() -> Result<()> {
         return Ok(());
     }
 
+    // console scrollback
+    if mod_keys_state.shift {
+        match key_event.code {
+            KeyCode::PageUp => return frame_buf_console::scroll_page_up(),
+            KeyCode::PageDown => return frame_buf_console::scroll_page_down(),
+            _ => (),
+        }
+    }
+
+    // consumed by the focused component
+    if let Ok(true) = simple_window_manager::key_event(key_event) {
+        return Ok(());
+    }
+
+    // cursor movement and history of the console line editor
+    if let Ok(true) = console::input_key(key_event.code) {
+        let _ = frame_buf_console::scroll_to_tail();
+        return Ok(());
+    }
+
     let ascii_code = match key_event.ascii {
         Some(c) => c,
         None => return Ok(()),
     };
 
-    match ascii_code {
-        AsciiCode::CarriageReturn => {
-            println!();
-        }
-        code => {
-            print!("{}", code as u8 as char);
-        }
-    }
+    // back to the live tail on new input
+    let _ = frame_buf_console::scroll_to_tail();
 
     console::input(ascii_code)
 }
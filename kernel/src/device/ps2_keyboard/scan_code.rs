@@ -107,6 +107,13 @@ pub enum KeyCode {
     Comma,        // , p: 0x33, r: 0xb3
     Period,       // . p: 0x34, r: 0xb4
     Slash,        // / p: 0x35, r: 0xb5
+    // JIS keyboard only
+    HankakuZenkaku,   // p: 0x29, r: 0xa9
+    Yen,              // p: 0x7d, r: 0xfd
+    Ro,               // p: 0x73, r: 0xf3
+    Henkan,           // p: 0x79, r: 0xf9
+    Muhenkan,         // p: 0x7b, r: 0xfb
+    KatakanaHiragana, // p: 0x70, r: 0xf0
 }
 
 impl KeyCode {
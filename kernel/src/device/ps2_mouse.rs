@@ -1,4 +1,7 @@
-use super::{DeviceDriverFunction, DeviceDriverInfo};
+use super::{
+    ps2::controller::{self, Ps2Port},
+    DeviceDriverFunction, DeviceDriverInfo,
+};
 use crate::{
     arch::{self, addr::IoPortAddress},
     error::{Error, Result},
@@ -6,28 +9,41 @@ use crate::{
     util::{fifo::Fifo, mutex::Mutex},
 };
 use alloc::vec::Vec;
-use log::info;
+use log::{info, warn};
 
 const PS2_DATA_REG_ADDR: IoPortAddress = IoPortAddress::new(0x60);
 const PS2_CMD_AND_STATE_REG_ADDR: IoPortAddress = IoPortAddress::new(0x64);
 
+const PS2_CMD_RESET: u8 = 0xff;
+const PS2_CMD_SET_SAMPLE_RATE: u8 = 0xf3;
+const PS2_CMD_GET_DEVICE_ID: u8 = 0xf2;
+const PS2_CMD_ENABLE_STREAMING: u8 = 0xf4;
+const PS2_ACK: u8 = 0xfa;
+const DEVICE_ID_INTELLI_MOUSE: u8 = 3;
+// sample rate sequence to enable the wheel
+const INTELLI_MOUSE_MAGIC_SEQ: [u8; 3] = [200, 100, 80];
+const READ_TIMEOUT_LOOP_CNT: usize = 1_000_000;
+
 static mut PS2_MOUSE_DRIVER: Mutex<Ps2MouseDriver> = Mutex::new(Ps2MouseDriver::new());
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Copy)]
 pub struct MouseEvent {
     pub middle: bool,
     pub right: bool,
     pub left: bool,
     pub rel_x: i16,
     pub rel_y: i16,
+    pub wheel: i8, // positive is scrolling down
 }
 
 struct Ps2MouseDriver {
     device_driver_info: DeviceDriverInfo,
     data_buf: Fifo<u8, 128>,
+    has_wheel: bool,
     data_0: Option<u8>,
     data_1: Option<u8>,
     data_2: Option<u8>,
+    data_3: Option<u8>,
 }
 
 impl Ps2MouseDriver {
@@ -35,9 +51,11 @@ impl Ps2MouseDriver {
         Self {
             device_driver_info: DeviceDriverInfo::new("ps2-mouse"),
             data_buf: Fifo::new(0),
+            has_wheel: false,
             data_0: None,
             data_1: None,
             data_2: None,
+            data_3: None,
         }
     }
 
@@ -52,64 +70,105 @@ impl Ps2MouseDriver {
 
         let data = self.data_buf.dequeue()?;
 
-        if data == 0xfa {
-            self.data_0 = None;
-            self.data_1 = None;
-            self.data_2 = None;
+        // ACK is only expected at the start of a packet
+        if self.data_0.is_none() && data == PS2_ACK {
             return Ok(None);
         }
 
         if self.data_0.is_none() && is_data0_valid(data) {
             self.data_0 = Some(data);
+        } else if self.data_0.is_none() {
+            return Ok(None);
         } else if self.data_1.is_none() {
             self.data_1 = Some(data);
         } else if self.data_2.is_none() {
             self.data_2 = Some(data);
-        } else if is_data0_valid(data) {
-            self.data_0 = Some(data);
-            self.data_1 = None;
-            self.data_2 = None;
-        }
-
-        if let (Some(data_0), Some(data_1), Some(data_2)) = (self.data_0, self.data_1, self.data_2)
-        {
-            let button_m = data_0 & 0x4 != 0;
-            let button_r = data_0 & 0x2 != 0;
-            let button_l = data_0 & 0x1 != 0;
-            let x_of = data_0 & 0x40 != 0;
-            let y_of = data_0 & 0x80 != 0;
-            let x_sign = data_0 & 0x10 != 0;
-            let y_sign = data_0 & 0x20 != 0;
-
-            if x_of || y_of {
-                return Ok(None);
-            }
+        } else if self.has_wheel && self.data_3.is_none() {
+            self.data_3 = Some(data);
+        }
 
-            let mut rel_x = data_1 as i16;
-            let mut rel_y = data_2 as i16;
+        let is_completed = self.data_2.is_some() && (!self.has_wheel || self.data_3.is_some());
+        if !is_completed {
+            return Ok(None);
+        }
 
-            if x_sign {
-                rel_x |= !0xff;
-            }
+        let data_0 = self.data_0.take().unwrap();
+        let data_1 = self.data_1.take().unwrap();
+        let data_2 = self.data_2.take().unwrap();
+        let data_3 = self.data_3.take().unwrap_or(0);
 
-            if y_sign {
-                rel_y |= !0xff;
-            }
+        let button_m = data_0 & 0x4 != 0;
+        let button_r = data_0 & 0x2 != 0;
+        let button_l = data_0 & 0x1 != 0;
+        let x_of = data_0 & 0x40 != 0;
+        let y_of = data_0 & 0x80 != 0;
+        let x_sign = data_0 & 0x10 != 0;
+        let y_sign = data_0 & 0x20 != 0;
 
-            rel_y = -rel_y;
+        if x_of || y_of {
+            return Ok(None);
+        }
 
-            let e = MouseEvent {
-                middle: button_m,
-                right: button_r,
-                left: button_l,
-                rel_x,
-                rel_y,
-            };
+        let mut rel_x = data_1 as i16;
+        let mut rel_y = data_2 as i16;
 
-            return Ok(Some(e));
+        if x_sign {
+            rel_x |= !0xff;
         }
 
-        Ok(None)
+        if y_sign {
+            rel_y |= !0xff;
+        }
+
+        rel_y = -rel_y;
+
+        let e = MouseEvent {
+            middle: button_m,
+            right: button_r,
+            left: button_l,
+            rel_x,
+            rel_y,
+            wheel: data_3 as i8,
+        };
+
+        Ok(Some(e))
+    }
+
+    fn send_cmd(&self, cmd: u8) -> Result<()> {
+        // send next wrote byte to ps/2 secondary port
+        PS2_CMD_AND_STATE_REG_ADDR.out8(0xd4);
+        self.wait_ready();
+
+        PS2_DATA_REG_ADDR.out8(cmd);
+        self.wait_ready();
+
+        if self.read_data()? != PS2_ACK {
+            return Err(Error::Failed("Command was not acknowledged"));
+        }
+
+        Ok(())
+    }
+
+    // polling, must be called with interrupts disabled
+    fn read_data(&self) -> Result<u8> {
+        for _ in 0..READ_TIMEOUT_LOOP_CNT {
+            if PS2_CMD_AND_STATE_REG_ADDR.in8() & 0x1 != 0 {
+                return Ok(PS2_DATA_REG_ADDR.in8());
+            }
+        }
+
+        Err(Error::Failed("Timed out reading PS/2 data"))
+    }
+
+    fn enable_wheel(&mut self) -> Result<()> {
+        for rate in INTELLI_MOUSE_MAGIC_SEQ {
+            self.send_cmd(PS2_CMD_SET_SAMPLE_RATE)?;
+            self.send_cmd(rate)?;
+        }
+
+        self.send_cmd(PS2_CMD_GET_DEVICE_ID)?;
+        self.has_wheel = self.read_data()? == DEVICE_ID_INTELLI_MOUSE;
+        Ok(())
     }
 
     fn wait_ready(&self) {
@@ -129,24 +188,35 @@ impl DeviceDriverFunction for Ps2MouseDriver {
     }
 
     fn probe(&mut self) -> Result<()> {
+        if !controller::ports()?.port2 {
+            return Err(Error::Failed("PS/2 mouse port was not found"));
+        }
+
         Ok(())
     }
 
     fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
-        // send next wrote byte to ps/2 secondary port
-        PS2_CMD_AND_STATE_REG_ADDR.out8(0xd4);
-        self.wait_ready();
-
-        // init mouse
-        PS2_DATA_REG_ADDR.out8(0xff);
-        self.wait_ready();
-
-        PS2_CMD_AND_STATE_REG_ADDR.out8(0xd4);
-        self.wait_ready();
+        idt::set_irq_handler(
+            idt::IRQ_PS2_MOUSE,
+            idt::InterruptHandler::Normal(poll_int_ps2_mouse_driver),
+            idt::GateType::Interrupt,
+        )?;
+
+        // init mouse (ACK, self-test passed, device id)
+        self.send_cmd(PS2_CMD_RESET)?;
+        let _ = self.read_data();
+        let _ = self.read_data();
+
+        // fall back to the standard 3 bytes packet
+        if let Err(err) = self.enable_wheel() {
+            warn!("ps2-mouse: Failed to enable wheel: {:?}", err);
+            self.has_wheel = false;
+        }
+        info!("ps2-mouse: Wheel enabled: {}", self.has_wheel);
 
         // start streaming
-        PS2_DATA_REG_ADDR.out8(0xf4);
-        self.wait_ready();
+        self.send_cmd(PS2_CMD_ENABLE_STREAMING)?;
+        controller::set_int(Ps2Port::Port2, true)?;
 
         self.device_driver_info.attached = true;
         Ok(())
@@ -0,0 +1,269 @@
+use super::{DeviceDriverFunction, DeviceDriverInfo};
+use crate::{
+    arch::{self, addr::IoPortAddress},
+    error::{Error, Result},
+    util::mutex::Mutex,
+};
+use alloc::vec::Vec;
+use core::fmt;
+use log::info;
+
+const CMOS_ADDR_REG_ADDR: IoPortAddress = IoPortAddress::new(0x70);
+const CMOS_DATA_REG_ADDR: IoPortAddress = IoPortAddress::new(0x71);
+
+const CMOS_REG_SEC: u8 = 0x00;
+const CMOS_REG_MIN: u8 = 0x02;
+const CMOS_REG_HOUR: u8 = 0x04;
+const CMOS_REG_DAY: u8 = 0x07;
+const CMOS_REG_MONTH: u8 = 0x08;
+const CMOS_REG_YEAR: u8 = 0x09;
+const CMOS_REG_STATUS_A: u8 = 0x0a;
+const CMOS_REG_STATUS_B: u8 = 0x0b;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+const HOUR_PM: u8 = 1 << 7;
+
+// the century register is not standardized
+const CENTURY: u16 = 2000;
+const READ_RETRY_MAX: usize = 10;
+
+static mut RTC_DRIVER: Mutex<RtcDriver> = Mutex::new(RtcDriver::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub min: u8,
+    pub sec: u8,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.min, self.sec
+        )
+    }
+}
+
+impl DateTime {
+    // seconds since 1970-01-01 00:00:00 UTC
+    pub fn unix_timestamp(&self) -> u64 {
+        // days from civil (March based year)
+        let (year, month) = if self.month <= 2 {
+            (self.year as i64 - 1, self.month as i64 + 9)
+        } else {
+            (self.year as i64, self.month as i64 - 3)
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era - 719468;
+
+        (days * 86400 + self.hour as i64 * 3600 + self.min as i64 * 60 + self.sec as i64) as u64
+    }
+}
+
+// raw register values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CmosTime {
+    sec: u8,
+    min: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+}
+
+impl CmosTime {
+    fn to_date_time(&self, status_b: u8) -> DateTime {
+        let is_binary = status_b & STATUS_B_BINARY != 0;
+        let conv = |value: u8| {
+            if is_binary {
+                value
+            } else {
+                bcd_to_binary(value)
+            }
+        };
+
+        let is_pm = self.hour & HOUR_PM != 0;
+        let mut hour = conv(self.hour & !HOUR_PM);
+        if status_b & STATUS_B_24_HOUR == 0 {
+            // 12 AM is 0:00, 12 PM is 12:00
+            hour %= 12;
+            if is_pm {
+                hour += 12;
+            }
+        }
+
+        DateTime {
+            year: CENTURY + conv(self.year) as u16,
+            month: conv(self.month),
+            day: conv(self.day),
+            hour,
+            min: conv(self.min),
+            sec: conv(self.sec),
+        }
+    }
+}
+
+struct RtcDriver {
+    device_driver_info: DeviceDriverInfo,
+}
+
+impl RtcDriver {
+    const fn new() -> Self {
+        Self {
+            device_driver_info: DeviceDriverInfo::new("rtc"),
+        }
+    }
+
+    fn read_reg(&self, reg: u8) -> u8 {
+        CMOS_ADDR_REG_ADDR.out8(reg);
+        CMOS_DATA_REG_ADDR.in8()
+    }
+
+    fn is_updating(&self) -> bool {
+        self.read_reg(CMOS_REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0
+    }
+
+    fn read_cmos_time(&self) -> CmosTime {
+        while self.is_updating() {}
+
+        CmosTime {
+            sec: self.read_reg(CMOS_REG_SEC),
+            min: self.read_reg(CMOS_REG_MIN),
+            hour: self.read_reg(CMOS_REG_HOUR),
+            day: self.read_reg(CMOS_REG_DAY),
+            month: self.read_reg(CMOS_REG_MONTH),
+            year: self.read_reg(CMOS_REG_YEAR),
+        }
+    }
+
+    fn now(&self) -> Result<DateTime> {
+        if !self.device_driver_info.attached {
+            return Err(Error::Failed("Device driver is not attached"));
+        }
+
+        // read until the same values are read twice in a row to avoid an update in the middle
+        let mut prev = self.read_cmos_time();
+        for _ in 0..READ_RETRY_MAX {
+            let current = self.read_cmos_time();
+            if current == prev {
+                return Ok(current.to_date_time(self.read_reg(CMOS_REG_STATUS_B)));
+            }
+            prev = current;
+        }
+
+        Err(Error::Failed("Failed to read consistent RTC values"))
+    }
+}
+
+impl DeviceDriverFunction for RtcDriver {
+    type AttachInput = ();
+    type PollNormalOutput = ();
+    type PollInterruptOutput = ();
+
+    fn get_device_driver_info(&self) -> Result<DeviceDriverInfo> {
+        Ok(self.device_driver_info.clone())
+    }
+
+    fn probe(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
+        self.device_driver_info.attached = true;
+        Ok(())
+    }
+
+    fn poll_normal(&mut self) -> Result<Self::PollNormalOutput> {
+        unimplemented!()
+    }
+
+    fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
+        unimplemented!()
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>> {
+        unimplemented!()
+    }
+
+    fn write(&mut self, _data: &[u8]) -> Result<()> {
+        unimplemented!()
+    }
+}
+
+pub fn get_device_driver_info() -> Result<DeviceDriverInfo> {
+    let driver = unsafe { RTC_DRIVER.try_lock() }?;
+    driver.get_device_driver_info()
+}
+
+pub fn probe_and_attach() -> Result<()> {
+    arch::disabled_int(|| {
+        let mut driver = unsafe { RTC_DRIVER.try_lock() }?;
+        driver.probe()?;
+        driver.attach(())?;
+        info!("{}: Attached!", driver.get_device_driver_info()?.name);
+        Result::Ok(())
+    })
+}
+
+pub fn now() -> Result<DateTime> {
+    arch::disabled_int(|| unsafe { RTC_DRIVER.try_lock() }?.now())
+}
+
+fn bcd_to_binary(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0f)
+}
+
+#[test_case]
+fn test_cmos_time_to_date_time() {
+    // 2024-02-29 23:59:58, BCD, 24 hour
+    let cmos_time = CmosTime {
+        sec: 0x58,
+        min: 0x59,
+        hour: 0x23,
+        day: 0x29,
+        month: 0x02,
+        year: 0x24,
+    };
+    let date_time = cmos_time.to_date_time(STATUS_B_24_HOUR);
+    assert_eq!(
+        date_time,
+        DateTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 23,
+            min: 59,
+            sec: 58,
+        }
+    );
+    assert_eq!(date_time.unix_timestamp(), 1709251198);
+
+    // 12:30 AM, binary, 12 hour
+    let cmos_time = CmosTime {
+        sec: 0,
+        min: 30,
+        hour: 12,
+        day: 1,
+        month: 1,
+        year: 70,
+    };
+    let date_time = cmos_time.to_date_time(STATUS_B_BINARY);
+    assert_eq!(date_time.hour, 0);
+
+    // 12:30 PM
+    let cmos_time = CmosTime {
+        hour: 12 | HOUR_PM,
+        ..cmos_time
+    };
+    assert_eq!(cmos_time.to_date_time(STATUS_B_BINARY).hour, 12);
+}
@@ -5,14 +5,26 @@ use crate::{
     error::{Error, Result},
     net::{
         self,
-        eth::{EtherType, EthernetAddress, EthernetFrame, EthernetPayload},
+        eth::{EthernetAddress, EthernetFrame},
     },
     util::mutex::Mutex,
 };
-use alloc::{boxed::Box, vec::Vec};
-use log::{debug, info};
+use alloc::vec::Vec;
+use log::{debug, info, warn};
 
 const RX_BUF_SIZE: usize = 8192;
+const RX_BUF_PAD_SIZE: usize = 16;
+const RX_HEADER_SIZE: usize = 4;
+const TX_BUF_SIZE: usize = 1792;
+const TX_DESC_NUM: usize = 4;
+
+// transmit status register bits
+const TSD_OWN: u32 = 1 << 13;
+const TSD_TUN: u32 = 1 << 14;
+const TSD_TOK: u32 = 1 << 15;
+const TSD_TABT: u32 = 1 << 30;
+
+const TX_TIMEOUT_CNT: usize = 0x10_0000;
 
 static mut RTL8139_DRIVER: Mutex<Rtl8139Driver> = Mutex::new(Rtl8139Driver::new());
 
@@ -43,6 +55,10 @@ impl IoRegister {
         multicast_addr
     }
 
+    fn read_tx_status(&self, index: usize) -> u32 {
+        self.io_port_base().offset(0x10 + 4 * index).in32()
+    }
+
     fn write_tx_status(&self, data: u32, index: usize) {
         self.io_port_base().offset(0x10 + 4 * index).out32(data);
     }
@@ -55,6 +71,14 @@ impl IoRegister {
         self.io_port_base().offset(0x30).out32(addr);
     }
 
+    fn write_cur_addr_of_packet_read(&self, capr: u16) {
+        self.io_port_base().offset(0x38).out16(capr);
+    }
+
+    fn read_cur_buf_addr(&self) -> u16 {
+        self.io_port_base().offset(0x3a).in16()
+    }
+
     fn read_cmd(&self) -> u8 {
         self.io_port_base().offset(0x37).in8()
     }
@@ -86,14 +110,14 @@ impl IoRegister {
 
 #[repr(C, align(16))]
 struct RxBuffer {
-    buf: [u8; RX_BUF_SIZE],
+    buf: [u8; RX_BUF_SIZE + RX_BUF_PAD_SIZE],
     packet_ptr: usize,
 }
 
 impl RxBuffer {
     const fn new() -> Self {
         Self {
-            buf: [0; RX_BUF_SIZE],
+            buf: [0; RX_BUF_SIZE + RX_BUF_PAD_SIZE],
             packet_ptr: 0,
         }
     }
@@ -102,52 +126,59 @@ impl RxBuffer {
         self.buf.as_ptr()
     }
 
-    fn pop_eth_frame(&mut self) -> Result<EthernetFrame> {
-        let packet = &self.buf[self.packet_ptr..];
+    // the ring is RX_BUF_SIZE bytes long, a packet may straddle its end
+    fn read_wrapped(&self, offset: usize, len: usize) -> Vec<u8> {
+        let start = (self.packet_ptr + offset) % RX_BUF_SIZE;
+        let first_len = len.min(RX_BUF_SIZE - start);
+
+        let mut data = Vec::with_capacity(len);
+        data.extend_from_slice(&self.buf[start..start + first_len]);
+        data.extend_from_slice(&self.buf[..len - first_len]);
+        data
+    }
+
+    // CAPR is kept 16 bytes behind the actual read pointer
+    fn capr(&self) -> u16 {
+        self.packet_ptr.wrapping_sub(RX_BUF_PAD_SIZE) as u16
+    }
 
+    fn pop_packet(&mut self) -> Result<Vec<u8>> {
         // RTL8139 metadata
-        let rtl8139_status = u16::from_le_bytes([packet[0], packet[1]]);
-        let rtl8139_len = u16::from_le_bytes([packet[2], packet[3]]);
+        let header = self.read_wrapped(0, RX_HEADER_SIZE);
+        let rtl8139_status = u16::from_le_bytes([header[0], header[1]]);
+        let rtl8139_len = u16::from_le_bytes([header[2], header[3]]) as usize;
 
-        if rtl8139_status & 0xe03f == 0 {
+        // ROK
+        if rtl8139_status & 0x1 == 0 || rtl8139_len == 0 || rtl8139_len > RX_BUF_SIZE {
             return Err(Error::Failed("Invalid packet"));
         }
 
-        self.packet_ptr = (self.packet_ptr + rtl8139_len as usize + 4) % RX_BUF_SIZE;
+        // including the FCS
+        let packet = self.read_wrapped(RX_HEADER_SIZE, rtl8139_len);
+
+        // 4 bytes align
+        self.packet_ptr = ((self.packet_ptr + rtl8139_len + RX_HEADER_SIZE + 3) & !3) % RX_BUF_SIZE;
 
-        let frame = &packet[4..rtl8139_len as usize];
-        Ok(EthernetFrame::new(frame))
+        Ok(packet)
     }
 }
 
+#[repr(C, align(16))]
 struct TxBuffer {
-    buf: Option<Vec<Box<[u8]>>>,
-    buf_len: usize,
-    packet_ptr: usize,
+    buf: [[u8; TX_BUF_SIZE]; TX_DESC_NUM],
+    desc_ptr: usize,
 }
 
 impl TxBuffer {
     const fn new() -> Self {
         Self {
-            buf: None,
-            buf_len: 4,
-            packet_ptr: 0,
+            buf: [[0; TX_BUF_SIZE]; TX_DESC_NUM],
+            desc_ptr: 0,
         }
     }
 
-    fn push(&mut self, packet: Box<[u8]>) {
-        if self.buf.is_none() {
-            self.buf = Some(Vec::with_capacity(self.buf_len));
-        }
-
-        let buf = self.buf.as_mut().unwrap();
-        if buf.len() < self.buf_len {
-            buf.push(packet);
-        } else {
-            buf[self.packet_ptr] = packet;
-        }
-
-        self.packet_ptr = (self.packet_ptr + 1) % self.buf_len;
+    fn buf_ptr(&self, index: usize) -> *const u8 {
+        self.buf[index].as_ptr()
     }
 }
 
@@ -181,23 +212,74 @@ impl Rtl8139Driver {
         Ok(self.io_register()?.read_mac_addr().into())
     }
 
-    fn receive_packet(&mut self) -> Result<EthernetFrame> {
-        self.rx_buf.pop_eth_frame()
+    fn is_rx_buf_empty(&self) -> Result<bool> {
+        // BUFE
+        Ok(self.io_register()?.read_cmd() & 0x1 != 0)
     }
 
-    fn send_packet(&mut self, eth_frame: EthernetFrame) -> Result<()> {
+    // the read pointer is lost, so restart the receiver from the start of the ring
+    fn reset_receiver(&mut self) -> Result<()> {
+        self.rx_buf.packet_ptr = 0;
+
         let io_register = self.io_register()?;
-        let tx_packet_ptr = self.tx_buf.packet_ptr;
+        io_register.write_cmd(0x04); // TE only
+        io_register.write_rx_buf_addr(self.rx_buf.buf_ptr() as u32);
+        io_register.write_rx_conf(0xf); // AB+AM+APM+AAP, 8K+16 bytes ring
+        io_register.write_cmd(0x0c); // TE, RE
+        io_register.write_cur_addr_of_packet_read(self.rx_buf.capr());
 
-        let boxed_eth_frame = eth_frame.to_vec().into_boxed_slice();
-        let packet_len = boxed_eth_frame.len();
+        warn!("{}: Reset receiver", self.device_driver_info.name);
+        Ok(())
+    }
 
-        io_register.write_tx_start_addr(boxed_eth_frame.as_ptr() as u32, tx_packet_ptr);
-        io_register.write_tx_status(packet_len as u32, tx_packet_ptr);
-        self.tx_buf.push(boxed_eth_frame);
+    fn receive_packet(&mut self) -> Result<Vec<u8>> {
+        let packet = match self.rx_buf.pop_packet() {
+            Ok(packet) => packet,
+            Err(err) => {
+                self.reset_receiver()?;
+                return Err(err);
+            }
+        };
+        self.io_register()?
+            .write_cur_addr_of_packet_read(self.rx_buf.capr());
+
+        Ok(packet)
+    }
+
+    fn receive_packets(&mut self) -> Result<()> {
+        let name = self.device_driver_info.name;
+
+        while !self.is_rx_buf_empty()? {
+            let packet = self.receive_packet()?;
+            let eth_frame = EthernetFrame::parse(&packet, true)?;
+            debug!(
+                "{}: Received packet (CBR: 0x{:x}): {:?}",
+                name,
+                self.io_register()?.read_cur_buf_addr(),
+                eth_frame
+            );
+
+            if let Some(reply_frame) = net::receive_eth_frame(&eth_frame)? {
+                self.write(&reply_frame)?;
+            }
+        }
 
         Ok(())
     }
+
+    fn send_packet(&mut self, eth_frame: EthernetFrame) -> Result<()> {
+        self.write(&eth_frame.to_vec())
+    }
+
+    // find a TX descriptor owned by the driver, starting from the next one in round-robin order
+    fn find_free_tx_desc(&self) -> Result<usize> {
+        let io_register = self.io_register()?;
+
+        (0..TX_DESC_NUM)
+            .map(|i| (self.tx_buf.desc_ptr + i) % TX_DESC_NUM)
+            .find(|&i| io_register.read_tx_status(i) & TSD_OWN != 0)
+            .ok_or(Error::Failed("All TX descriptors are busy"))
+    }
 }
 
 impl DeviceDriverFunction for Rtl8139Driver {
@@ -221,10 +303,9 @@ impl DeviceDriverFunction for Rtl8139Driver {
     fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
         let (bus, device, func) = self.pci_device_bdf.ok_or("Device driver is not probed")?;
 
-        device::pci_bus::configure_device(bus, device, func, |d| {
-            // enable PCI bus mastering and disable interrupt
+        device::pci_bus::attach_device(bus, device, func, |d| {
+            // disable interrupt
             let mut conf_space_header = d.read_conf_space_header()?;
-            conf_space_header.command.write_bus_master_enable(true);
             conf_space_header.command.write_int_disable(true);
             d.write_conf_space_header(conf_space_header)?;
 
@@ -270,7 +351,7 @@ impl DeviceDriverFunction for Rtl8139Driver {
             io_register.write_int_mask(0x5); // TOK, ROK
 
             // configure RX buffer
-            io_register.write_rx_conf(0xf | (1 << 7)); // AB+AM+APM+AAP, WRAP
+            io_register.write_rx_conf(0xf); // AB+AM+APM+AAP, 8K+16 bytes ring
 
             // enable rx/tx
             io_register.write_cmd(0x0c); // TE, RE
@@ -317,41 +398,67 @@ impl DeviceDriverFunction for Rtl8139Driver {
             io_register.write_int_status(0x01);
 
             debug!("{}: ROK", name);
-            let eth_frame = self.receive_packet()?;
-
-            if let Some(reply_payload) = net::receive_eth_payload(eth_frame.payload())? {
-                let payload_vec = reply_payload.to_vec();
-
-                match reply_payload {
-                    EthernetPayload::Arp(_) => {
-                        let reply_eth_frame = EthernetFrame::new_with(
-                            eth_frame.src_mac_addr,
-                            net::my_mac_addr()?,
-                            EtherType::Arp,
-                            &payload_vec,
-                        );
-                        self.send_packet(reply_eth_frame)?;
-                    }
-                    _ => {
-                        unimplemented!()
-                    }
-                }
-            }
+            self.receive_packets()?;
+        }
+
+        // frames queued by the network stack
+        while let Some(frame) = net::pop_eth_tx_frame()? {
+            self.write(&frame)?;
         }
 
         Ok(())
     }
 
     fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
-        unimplemented!()
+        let io_register = self.io_register()?;
+        let status = io_register.read_int_status();
+
+        // clear TOK, ROK
+        io_register.write_int_status(status & 0x5);
+
+        if status & 1 != 0 {
+            self.receive_packets()?;
+        }
+
+        Ok(())
     }
 
     fn read(&mut self) -> Result<Vec<u8>> {
         unimplemented!()
     }
 
-    fn write(&mut self, _data: &[u8]) -> Result<()> {
-        unimplemented!()
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() > TX_BUF_SIZE {
+            return Err(Error::Failed("Ethernet frame is too long"));
+        }
+
+        let index = self.find_free_tx_desc()?;
+        self.tx_buf.buf[index][..data.len()].copy_from_slice(data);
+        self.tx_buf.desc_ptr = (index + 1) % TX_DESC_NUM;
+
+        let tx_buf_addr = self.tx_buf.buf_ptr(index) as u64;
+        if tx_buf_addr > u32::MAX as u64 {
+            return Err(Error::Failed("TX buffer address is too large"));
+        }
+
+        let io_register = self.io_register()?;
+        io_register.write_tx_start_addr(tx_buf_addr as u32, index);
+        // writing the size clears the OWN bit and starts transmission
+        io_register.write_tx_status(data.len() as u32, index);
+
+        for _ in 0..TX_TIMEOUT_CNT {
+            let status = io_register.read_tx_status(index);
+
+            if status & (TSD_TUN | TSD_TABT) != 0 {
+                return Err(Error::Failed("Transmission aborted"));
+            }
+
+            if status & TSD_TOK != 0 {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Failed("Transmission timed out"))
     }
 }
 
@@ -375,6 +482,11 @@ pub fn poll_normal() -> Result<()> {
     Ok(())
 }
 
+pub fn send_eth_frame(eth_frame: EthernetFrame) -> Result<()> {
+    let mut driver = unsafe { RTL8139_DRIVER.try_lock() }?;
+    driver.send_packet(eth_frame)
+}
+
 // extern "x86-interrupt" fn poll_int_rt8139_driver() {
 //     if let Ok(mut driver) = unsafe { RTL8139_DRIVER.try_lock() } {
 //         let _ = driver.poll_int();
@@ -1,10 +1,24 @@
-use super::{DeviceDriverFunction, DeviceDriverInfo};
-use crate::{arch, error::Result, util::sleep};
+use super::{local_apic_timer, DeviceDriverFunction, DeviceDriverInfo};
+use crate::{
+    arch::{self, task},
+    error::Result,
+    util::sleep,
+};
 use alloc::vec::Vec;
-use core::num::NonZeroU8;
+use core::{
+    num::NonZeroU8,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 use log::info;
 
 static mut SPEAKER_DRIVER: SpeakerDriver = SpeakerDriver::new();
+// incremented to stop the melody currently playing
+static MELODY_ID: AtomicUsize = AtomicUsize::new(0);
+
+// (freq_hz, duration_ms), freq 0 is a rest
+pub const STARTUP_JINGLE: &[(u32, usize)] = &[(523, 100), (659, 100), (784, 100), (1047, 200)];
+pub const ERROR_BEEP: &[(u32, usize)] = &[(220, 150), (0, 50), (220, 150)];
+pub const WARNING_BEEP: &[(u32, usize)] = &[(880, 100)];
 
 #[repr(u32)]
 #[derive(Copy, Clone)]
@@ -44,6 +58,11 @@ impl SpeakerDriver {
     }
 
     fn play(&self, freq: u32) {
+        if freq == 0 {
+            self.stop();
+            return;
+        }
+
         let div = 1193180 / freq;
 
         arch::out8(0x43, 0xb6);
@@ -138,3 +157,41 @@ pub fn probe_and_attach() -> Result<()> {
 pub fn beep() {
     unsafe { SPEAKER_DRIVER.beep() };
 }
+
+pub fn tone(freq: u32) {
+    unsafe { SPEAKER_DRIVER.play(freq) };
+}
+
+pub fn off() {
+    unsafe { SPEAKER_DRIVER.stop() };
+}
+
+// play notes in the background, a new melody stops the previous one
+pub fn play(notes: &[(u32, usize)]) -> Result<()> {
+    let notes = notes.to_vec();
+    let id = MELODY_ID.fetch_add(1, Ordering::SeqCst) + 1;
+
+    task::spawn(async move {
+        for (freq, duration_ms) in notes {
+            if MELODY_ID.load(Ordering::SeqCst) != id {
+                return;
+            }
+
+            tone(freq);
+
+            let start_ms = match local_apic_timer::get_current_ms() {
+                Some(ms) => ms,
+                None => break,
+            };
+            while local_apic_timer::get_current_ms()
+                .is_some_and(|ms| ms.saturating_sub(start_ms) < duration_ms)
+            {
+                task::exec_yield().await;
+            }
+        }
+
+        if MELODY_ID.load(Ordering::SeqCst) == id {
+            off();
+        }
+    })
+}
@@ -2,10 +2,10 @@ use super::{console, DeviceDriverFunction, DeviceDriverInfo};
 use crate::{
     arch::{self, addr::IoPortAddress},
     error::{Error, Result},
-    print, println,
     util::{ascii::AsciiCode, mutex::Mutex},
 };
 use alloc::vec::Vec;
+use core::fmt;
 use log::info;
 
 static mut UART_DRIVER: Mutex<UartDriver> = Mutex::new(UartDriver::new());
@@ -26,6 +26,7 @@ pub enum ComPort {
 struct UartDriver {
     device_driver_info: DeviceDriverInfo,
     io_port_addr: Option<IoPortAddress>,
+    is_prev_cr: bool,
 }
 
 impl UartDriver {
@@ -33,6 +34,7 @@ impl UartDriver {
         Self {
             device_driver_info: DeviceDriverInfo::new("uart"),
             io_port_addr: None,
+            is_prev_cr: false,
         }
     }
 
@@ -48,6 +50,20 @@ impl UartDriver {
         Some(data)
     }
 
+    // serial terminals send CR (or CR LF) on enter and DEL on backspace
+    fn translate(&mut self, data: u8) -> Option<AsciiCode> {
+        let is_prev_cr = self.is_prev_cr;
+        self.is_prev_cr = data == AsciiCode::CarriageReturn as u8;
+
+        let ascii_code: AsciiCode = data.try_into().ok()?;
+        match ascii_code {
+            AsciiCode::CarriageReturn => Some(AsciiCode::NewLine),
+            AsciiCode::NewLine if is_prev_cr => None,
+            AsciiCode::Delete => Some(AsciiCode::Backspace),
+            code => Some(code),
+        }
+    }
+
     fn send_data(&self, data: u8) {
         // TODO: loop infinity on VirtualBox and actual device
         //while !self.is_transmit_empty() {}
@@ -80,7 +96,7 @@ impl UartDriver {
 
 impl DeviceDriverFunction for UartDriver {
     type AttachInput = ();
-    type PollNormalOutput = Option<u8>;
+    type PollNormalOutput = Option<AsciiCode>;
     type PollInterruptOutput = ();
 
     fn get_device_driver_info(&self) -> Result<DeviceDriverInfo> {
@@ -121,7 +137,13 @@ impl DeviceDriverFunction for UartDriver {
             return Err(Error::Failed("Device driver is not attached"));
         }
 
-        Ok(self.receive_data())
+        while let Some(data) = self.receive_data() {
+            if let Some(ascii_code) = self.translate(data) {
+                return Ok(Some(ascii_code));
+            }
+        }
+
+        Ok(None)
     }
 
     fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
@@ -153,26 +175,28 @@ pub fn probe_and_attach() -> Result<()> {
 }
 
 pub fn poll_normal() -> Result<()> {
-    let received_data = match arch::disabled_int(|| {
+    // drain all received bytes
+    while let Some(ascii_code) = arch::disabled_int(|| {
         let mut driver = unsafe { UART_DRIVER.try_lock() }?;
         driver.poll_normal()
     })? {
-        Some(data) => data,
-        None => return Ok(()),
-    };
+        console::input_serial(ascii_code)?;
+    }
 
-    let ascii_code = received_data.try_into()?;
+    Ok(())
+}
 
-    match ascii_code {
-        AsciiCode::CarriageReturn => {
-            println!();
-        }
-        code => {
-            print!("{}", code as u8 as char);
+// log sink, nothing is sent until the driver is attached
+pub struct UartWriter;
+
+impl fmt::Write for UartWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for data in s.bytes() {
+            send_data(data);
         }
-    }
 
-    console::input(ascii_code)
+        Ok(())
+    }
 }
 
 pub fn send_data(data: u8) {
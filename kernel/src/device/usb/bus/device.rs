@@ -4,11 +4,13 @@ use super::descriptor::{
     DescriptorHeader, DescriptorType,
 };
 use crate::{
-    addr::VirtualAddress,
+    addr::{PhysicalAddress, VirtualAddress},
     device::{
         self,
         usb::{
-            hid_keyboard::InputData,
+            hid_keyboard, hid_mouse,
+            hub::Hub,
+            mass_storage::MassStorage,
             trb::*,
             xhc::{
                 context::{endpoint::*, input::InputControlContext},
@@ -17,25 +19,61 @@ use crate::{
         },
     },
     error::Result,
-    mem::bitmap::{self, MemoryFrameInfo},
-    println,
+    mem::{
+        bitmap::{self, MemoryFrameInfo},
+        paging::PAGE_SIZE,
+    },
 };
 use alloc::vec::Vec;
-use core::mem::size_of;
+use core::{mem::size_of, ptr};
 
 const RING_BUF_LEN: usize = 16;
 const DEFAULT_CTRL_PIPE_ID: u8 = 1;
+const CTRL_TRANSFER_TIMEOUT_MS: usize = 1000;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UsbDeviceError {
     XhcPortNotFoundError,
     InvalidTransferRequestBlockTypeError,
     InvalidRequestError,
+    EndpointNotConfiguredError(usize),
+    TransferTimeoutError,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SetupData {
+    pub direction: RequestTypeDirection,
+    pub ty: RequestType,
+    pub recipient: RequestTypeRecipient,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidBootProtocol {
+    Keyboard,
+    Mouse,
+}
+
+impl HidBootProtocol {
+    pub fn from_interface_protocol(protocol: u8) -> Option<Self> {
+        match protocol {
+            1 => Some(Self::Keyboard),
+            2 => Some(Self::Mouse),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+// not cloneable, the buffers are owned by a single device
+#[derive(Debug)]
 pub struct UsbDevice {
     pub is_configured: bool,
+    pub hid_boot_protocol: Option<HidBootProtocol>,
+    pub mass_storage: Option<MassStorage>,
+    pub hub: Option<Hub>,
 
     slot_id: usize,
     transfer_ring_bufs: [Option<RingBuffer<RING_BUF_LEN>>; 32],
@@ -48,6 +86,7 @@ pub struct UsbDevice {
     current_conf_index: usize,
     dev_desc: DeviceDescriptor,
     conf_descs: Vec<Descriptor>,
+    prev_kbd_input_data: hid_keyboard::InputData,
 }
 
 impl UsbDevice {
@@ -68,6 +107,9 @@ impl UsbDevice {
 
         let device = Self {
             is_configured: false,
+            hid_boot_protocol: None,
+            mass_storage: None,
+            hub: None,
             slot_id,
             transfer_ring_bufs,
             dev_desc_buf_mem_info,
@@ -77,6 +119,7 @@ impl UsbDevice {
             current_conf_index: 0,
             dev_desc: DeviceDescriptor::default(),
             conf_descs: Vec::new(),
+            prev_kbd_input_data: hid_keyboard::InputData::default(),
         };
 
         Ok(device)
@@ -86,6 +129,22 @@ impl UsbDevice {
         self.request_to_get_desc(DescriptorType::Device, 0)
     }
 
+    // free memory frames owned by the device (e.g. after disconnected)
+    pub fn release(mut self) -> Result<()> {
+        bitmap::dealloc_mem_frame(self.dev_desc_buf_mem_info)?;
+        bitmap::dealloc_mem_frame(self.conf_desc_buf_mem_info)?;
+
+        for ring_buf in self.transfer_ring_bufs.iter_mut().flatten() {
+            ring_buf.dealloc_data_bufs()?;
+        }
+
+        if let Some(mass_storage) = self.mass_storage.as_ref() {
+            mass_storage.release()?;
+        }
+
+        Ok(())
+    }
+
     pub fn slot_id(&self) -> usize {
         self.slot_id
     }
@@ -230,7 +289,14 @@ impl UsbDevice {
             .collect()
     }
 
-    pub fn configure_endpoint(&mut self, endpoint_type: EndpointType) -> Result<()> {
+    pub fn find_endpoint_dci(&self, endpoint_type: EndpointType) -> Option<usize> {
+        self.get_endpoint_descs()
+            .iter()
+            .find(|d| EndpointType::new(d.endpoint_addr, d.bitmap_attrs) == endpoint_type)
+            .map(|d| d.dci())
+    }
+
+    pub fn configure_endpoint(&mut self, endpoint_types: &[EndpointType]) -> Result<()> {
         let port = device::usb::xhc::find_port_by_slot_id(self.slot_id)?
             .ok_or(UsbDeviceError::XhcPortNotFoundError)?;
 
@@ -239,6 +305,11 @@ impl UsbDevice {
         let device_context = device::usb::xhc::read_device_context(self.slot_id)?.unwrap();
         let mut input_context = port.read_input_context();
         input_context.device_context.slot_context = device_context.slot_context;
+        if let Some(hub) = &self.hub {
+            let slot_context = &mut input_context.device_context.slot_context;
+            slot_context.set_hub(true);
+            slot_context.set_num_of_ports(hub.num_of_ports());
+        }
         let mut input_ctrl_context = InputControlContext::default();
         input_ctrl_context.set_add_context_flag(0, true).unwrap();
 
@@ -249,20 +320,21 @@ impl UsbDevice {
 
             let mut endpoint_context = EndpointContext::default();
             let desc_endpoint_type = EndpointType::new(endpoint_addr, endpoint_desc.bitmap_attrs);
-            if desc_endpoint_type != endpoint_type {
+            if !endpoint_types.contains(&desc_endpoint_type) {
                 continue;
             }
+            let max_packet_size = endpoint_desc.max_packet_size;
 
             let mut transfer_ring_buf = RingBuffer::new(RingBufferType::TransferRing, true)?;
             transfer_ring_buf.set_link_trb()?;
 
-            endpoint_context.set_endpoint_type(endpoint_type);
-            endpoint_context.set_max_packet_size(self.max_packet_size);
-            endpoint_context.set_max_endpoint_service_interval_payload_low(self.max_packet_size);
+            endpoint_context.set_endpoint_type(desc_endpoint_type);
+            endpoint_context.set_max_packet_size(max_packet_size);
+            endpoint_context.set_max_endpoint_service_interval_payload_low(max_packet_size);
             endpoint_context.set_max_burst_size(0);
             endpoint_context.set_dequeue_cycle_state(true); // initial cycle state of transfer ring buffer
             endpoint_context.set_tr_dequeue_ptr(transfer_ring_buf.buf_ptr() as u64);
-            endpoint_context.set_interval(endpoint_desc.interval - 1);
+            endpoint_context.set_interval(endpoint_desc.interval.saturating_sub(1)); // 0 for bulk
             endpoint_context.set_max_primary_streams(0);
             endpoint_context.set_mult(0);
             endpoint_context.set_error_cnt(3);
@@ -347,15 +419,152 @@ impl UsbDevice {
 
             let data_trb_ptr = transfer_event_trb.param as *const TransferRequestBlock;
             let data_trb = unsafe { data_trb_ptr.read() };
-            let data_ptr = data_trb.param as *const InputData;
-            //println!("data_trb: {:p}, data: {:p}", data_trb_ptr, data_ptr);
-            let data = unsafe { data_ptr.read() };
-            println!("{:?}", data);
+            //println!("data_trb: {:p}, data: {:p}", data_trb_ptr, data_trb.param);
+
+            match self.hid_boot_protocol {
+                Some(HidBootProtocol::Keyboard) => {
+                    let data_ptr = data_trb.param as *const hid_keyboard::InputData;
+                    let data = unsafe { data_ptr.read() };
+                    let _ = hid_keyboard::input(&self.prev_kbd_input_data, &data);
+                    self.prev_kbd_input_data = data;
+                }
+                Some(HidBootProtocol::Mouse) => {
+                    let data_ptr = data_trb.param as *const hid_mouse::InputData;
+                    let data = unsafe { data_ptr.read() };
+                    let _ = hid_mouse::input(&data);
+                }
+                None => match self.hub.as_mut() {
+                    // port change bitmap from the status change endpoint
+                    Some(hub) => {
+                        let data_ptr = data_trb.param as *const [u8; 8];
+                        let data = unsafe { data_ptr.read() };
+                        hub.set_port_change_bitmap(&data);
+                    }
+                    // bulk transfers are waited by the caller
+                    None => return,
+                },
+            }
 
             ring_buf.enqueue().unwrap();
         }
     }
 
+    pub fn bulk_transfer(
+        &mut self,
+        endpoint_id: usize,
+        buf_phys_addr: PhysicalAddress,
+        buf_size: u32,
+    ) -> Result<()> {
+        let ring_buf = self.transfer_ring_bufs[endpoint_id]
+            .as_mut()
+            .ok_or(UsbDeviceError::EndpointNotConfiguredError(endpoint_id))?;
+
+        let mut trb = TransferRequestBlock::default();
+        trb.set_trb_type(TransferRequestBlockType::Normal);
+        trb.param = buf_phys_addr.get();
+        trb.status = buf_size; // TRB Transfer Length
+        trb.set_other_flags(0x12); // IOC, ISP bit
+        ring_buf.push(trb)?;
+
+        device::usb::xhc::ring_doorbell(self.slot_id, endpoint_id as u8)
+    }
+
+    // generic control transfer on the default control pipe, waits for the status stage
+    // buf length must be equal to setup.length (up to 4KiB)
+    pub fn control_transfer(&mut self, setup: SetupData, buf: Option<&mut [u8]>) -> Result<()> {
+        let buf_len = buf.as_ref().map(|b| b.len()).unwrap_or(0);
+        if buf_len != setup.length as usize || buf_len > PAGE_SIZE {
+            return Err(UsbDeviceError::InvalidRequestError.into());
+        }
+
+        let mut setup_stage_trb = TransferRequestBlock::default();
+        setup_stage_trb.set_trb_type(TransferRequestBlockType::SetupStage);
+
+        let mut setup_req_type = SetupRequestType::default();
+        setup_req_type.set_direction(setup.direction);
+        setup_req_type.set_ty(setup.ty);
+        setup_req_type.set_recipient(setup.recipient);
+
+        setup_stage_trb.set_setup_request_type(setup_req_type);
+        setup_stage_trb.set_setup_request_raw(setup.request);
+        setup_stage_trb.set_setup_index(setup.index);
+        setup_stage_trb.set_setup_value(setup.value);
+        setup_stage_trb.set_setup_length(setup.length);
+        setup_stage_trb.status = 8; // TRB transfer length
+        setup_stage_trb.set_other_flags(1 << 5); // IDT bit
+
+        let is_in = matches!(setup.direction, RequestTypeDirection::In);
+        let mut data_buf_mem_frame_info = None;
+        let data_stage_trb = if buf_len > 0 {
+            let mem_frame_info = bitmap::alloc_mem_frame(1)?;
+            bitmap::mem_clear(&mem_frame_info)?;
+            if !is_in {
+                mem_frame_info
+                    .frame_start_virt_addr()?
+                    .copy_from_nonoverlapping(buf.as_ref().unwrap().as_ptr(), buf_len);
+            }
+            data_buf_mem_frame_info = Some(mem_frame_info);
+
+            setup_stage_trb.set_transfer_type(if is_in {
+                TransferType::InDataStage
+            } else {
+                TransferType::OutDataStage
+            });
+            let mut trb = TransferRequestBlock::default();
+            trb.set_trb_type(TransferRequestBlockType::DataStage);
+            trb.param = mem_frame_info.frame_start_phys_addr.get();
+            trb.status = buf_len as u32;
+            trb.ctrl_regs = is_in as u16; // DIR bit
+            Some(trb)
+        } else {
+            setup_stage_trb.set_transfer_type(TransferType::NoDataStage);
+            None
+        };
+
+        // the status stage is in the opposite direction of the data stage,
+        // IN if there is no data stage
+        let mut status_stage_trb = TransferRequestBlock::default();
+        status_stage_trb.set_trb_type(TransferRequestBlockType::StatusStage);
+        status_stage_trb.ctrl_regs = !(data_stage_trb.is_some() && is_in) as u16; // DIR bit
+        status_stage_trb.set_other_flags(1 << 4); // IOC bit
+
+        let dcp_transfer_ring = self.transfer_ring_bufs[1].as_mut().unwrap();
+        dcp_transfer_ring.push(setup_stage_trb)?;
+        if let Some(trb) = data_stage_trb {
+            dcp_transfer_ring.push(trb)?;
+        }
+        let status_stage_trb_ptr = dcp_transfer_ring.push(status_stage_trb)?;
+        device::usb::xhc::ring_doorbell(self.slot_id, DEFAULT_CTRL_PIPE_ID)?;
+
+        let start_ms = device::local_apic_timer::get_current_ms().unwrap_or(0);
+        let res = loop {
+            if device::usb::xhc::pop_completed_ctrl_transfer(status_stage_trb_ptr)? {
+                break Ok(());
+            }
+
+            let current_ms = device::local_apic_timer::get_current_ms().unwrap_or(0);
+            if current_ms.saturating_sub(start_ms) > CTRL_TRANSFER_TIMEOUT_MS {
+                break Err(UsbDeviceError::TransferTimeoutError.into());
+            }
+        };
+
+        if let Some(mem_frame_info) = data_buf_mem_frame_info {
+            if res.is_ok() && is_in {
+                let buf = buf.unwrap();
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        mem_frame_info.frame_start_virt_addr()?.as_ptr::<u8>(),
+                        buf.as_mut_ptr(),
+                        buf_len,
+                    );
+                }
+            }
+            bitmap::dealloc_mem_frame(mem_frame_info)?;
+        }
+
+        res
+    }
+
     fn ctrl_out(
         &mut self,
         req_type: RequestType,
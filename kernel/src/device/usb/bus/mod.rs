@@ -1,4 +1,8 @@
-use super::xhc::{self, context::endpoint::EndpointType};
+use super::{
+    hub::{self, DownstreamPort, Hub, PortChange},
+    mass_storage::{self, MassStorage},
+    xhc::{self, context::endpoint::EndpointType, port::Port},
+};
 use crate::{
     arch,
     device::{DeviceDriverFunction, DeviceDriverInfo},
@@ -7,7 +11,7 @@ use crate::{
 };
 use alloc::{boxed::Box, vec::Vec};
 use descriptor::*;
-use device::UsbDevice;
+use device::{HidBootProtocol, UsbDevice};
 use log::{info, warn};
 
 mod descriptor;
@@ -19,6 +23,7 @@ static mut USB_BUS_DRIVER: Mutex<UsbBusDriver> = Mutex::new(UsbBusDriver::new())
 pub enum UsbBusDriverError {
     UsbDeviceError { slot_id: usize, err: Box<Error> },
     UsbDeviceNotExitstError,
+    MassStorageNotFoundError,
 }
 
 struct UsbBusDriver {
@@ -34,28 +39,210 @@ impl UsbBusDriver {
         }
     }
 
-    fn find_device_by_slot_id(&self, slot_id: usize) -> Option<UsbDevice> {
+    fn find_device_by_slot_id_mut(&mut self, slot_id: usize) -> Option<&mut UsbDevice> {
+        self.usb_devices.iter_mut().find(|d| d.slot_id() == slot_id)
+    }
+
+    fn find_mass_storage_device_mut(&mut self) -> Option<&mut UsbDevice> {
         self.usb_devices
-            .iter()
-            .find(|d| d.slot_id() == slot_id)
-            .cloned()
+            .iter_mut()
+            .find(|d| d.is_configured && d.mass_storage.is_some())
     }
 
-    fn update_device(&mut self, device: UsbDevice) -> Result<()> {
-        let device_mut = self
+    fn remove_device(&mut self, slot_id: usize) -> Result<()> {
+        let index = self
             .usb_devices
-            .iter_mut()
-            .find(|d| d.slot_id() == device.slot_id())
+            .iter()
+            .position(|d| d.slot_id() == slot_id)
             .ok_or(UsbBusDriverError::UsbDeviceNotExitstError)?;
 
-        *device_mut = device;
+        self.usb_devices.remove(index).release()
+    }
+
+    // devices behind hubs are appended to the queue while configuring
+    fn configure_devices(&mut self, mut devices: Vec<UsbDevice>) -> Result<()> {
+        while !devices.is_empty() {
+            let mut device = devices.remove(0);
+            let downstream_ports = Self::configure_device(&mut device)?;
+            self.usb_devices.push(device);
+
+            for downstream_port in downstream_ports {
+                match xhc::alloc_address_to_downstream_device(downstream_port) {
+                    Ok(device) => devices.push(device),
+                    Err(err) => warn!("usb: Failed to address downstream device: {:?}", err),
+                }
+            }
+        }
+
         Ok(())
     }
+
+    // returns ports to be enumerated if the device is a hub
+    fn configure_device(device: &mut UsbDevice) -> Result<Vec<DownstreamPort>> {
+        let slot_id = device.slot_id();
+        arch::disabled_int(|| device.init()).map_err(|err| UsbBusDriverError::UsbDeviceError {
+            slot_id,
+            err: Box::new(err),
+        })?;
+
+        device.read_dev_desc();
+
+        arch::disabled_int(|| device.request_to_get_desc(DescriptorType::Configration, 0))
+            .map_err(|err| UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            })?;
+
+        device.read_conf_descs();
+
+        // mass storage (bulk-only transport)
+        if let Some(interface) = device
+            .get_interface_descs()
+            .iter()
+            .find(|d| mass_storage::is_mass_storage_interface(d.class, d.sub_class, d.protocol))
+            .map(|d| **d)
+        {
+            let conf_desc = match device.get_conf_descs()[0].clone() {
+                Descriptor::Configuration(desc) => desc,
+                _ => unreachable!(),
+            };
+
+            arch::disabled_int(|| {
+                device.configure_endpoint(&[EndpointType::BulkIn, EndpointType::BulkOut])?;
+                device.request_to_set_conf(conf_desc.conf_value)?;
+                device.request_to_set_interface(interface)
+            })
+            .map_err(|err| UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            })?;
+
+            let (bulk_in_dci, bulk_out_dci) = match (
+                device.find_endpoint_dci(EndpointType::BulkIn),
+                device.find_endpoint_dci(EndpointType::BulkOut),
+            ) {
+                (Some(i), Some(o)) => (i, o),
+                _ => {
+                    warn!("usb: Bulk endpoints were not found (slot id: {})", slot_id);
+                    return Ok(Vec::new());
+                }
+            };
+
+            // SCSI commands are issued at the first access
+            device.mass_storage = Some(MassStorage::new(bulk_in_dci, bulk_out_dci)?);
+            device.is_configured = true;
+            return Ok(Vec::new());
+        }
+
+        // hub
+        if device
+            .get_interface_descs()
+            .iter()
+            .any(|d| hub::is_hub_interface(d.class))
+        {
+            return Self::configure_hub(device).map_err(|err| {
+                UsbBusDriverError::UsbDeviceError {
+                    slot_id,
+                    err: Box::new(err),
+                }
+                .into()
+            });
+        }
+
+        // HID boot interface (keyboard or mouse)
+        let boot_interface = match device.get_interface_descs().iter().find(|d| {
+            d.class == 3
+                && d.sub_class == 1
+                && HidBootProtocol::from_interface_protocol(d.protocol).is_some()
+        }) {
+            Some(d) => **d,
+            None => {
+                warn!(
+                    "usb: Unsupported device, skip configuring... (slot id: {})",
+                    slot_id
+                );
+                return Ok(Vec::new());
+            }
+        };
+
+        let conf_desc = match device.get_conf_descs()[0].clone() {
+            Descriptor::Configuration(desc) => desc,
+            _ => unreachable!(),
+        };
+
+        arch::disabled_int(|| device.configure_endpoint(&[EndpointType::InterruptIn])).map_err(
+            |err| UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            },
+        )?;
+
+        arch::disabled_int(|| device.request_to_set_conf(conf_desc.conf_value)).map_err(|err| {
+            UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            }
+        })?;
+
+        arch::disabled_int(|| device.request_to_set_interface(boot_interface)).map_err(|err| {
+            UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            }
+        })?;
+
+        arch::disabled_int(|| device.request_to_set_protocol(boot_interface, 0)).map_err(
+            |err| UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            },
+        )?;
+
+        arch::disabled_int(|| device.configure_endpoint_transfer_ring()).map_err(|err| {
+            UsbBusDriverError::UsbDeviceError {
+                slot_id,
+                err: Box::new(err),
+            }
+        })?;
+
+        device.hid_boot_protocol =
+            HidBootProtocol::from_interface_protocol(boot_interface.protocol);
+        device.is_configured = true;
+
+        Ok(Vec::new())
+    }
+
+    // hub class requests wait for the transfer events, so interrupts must be enabled
+    fn configure_hub(device: &mut UsbDevice) -> Result<Vec<DownstreamPort>> {
+        let conf_desc = match device.get_conf_descs()[0].clone() {
+            Descriptor::Configuration(desc) => desc,
+            _ => unreachable!(),
+        };
+
+        arch::disabled_int(|| device.request_to_set_conf(conf_desc.conf_value))?;
+
+        // the slot context is updated with the number of ports
+        device.hub = Some(Hub::new(device)?);
+        arch::disabled_int(|| device.configure_endpoint(&[EndpointType::InterruptIn]))?;
+
+        let hub = device.hub.take().unwrap();
+        let res = hub
+            .power_on_ports(device)
+            .and_then(|_| hub.enum_ports(device));
+        device.hub = Some(hub);
+        let downstream_ports = res?;
+
+        // start polling the status change endpoint
+        arch::disabled_int(|| device.configure_endpoint_transfer_ring())?;
+        device.is_configured = true;
+
+        Ok(downstream_ports)
+    }
 }
 
 impl DeviceDriverFunction for UsbBusDriver {
     type AttachInput = ();
-    type PollNormalOutput = ();
+    type PollNormalOutput = Vec<usize>; // port ids to be detached
     type PollInterruptOutput = ();
 
     fn get_device_driver_info(&self) -> Result<DeviceDriverInfo> {
@@ -70,95 +257,60 @@ impl DeviceDriverFunction for UsbBusDriver {
         // XHC driver mut be started
         let port_ids = arch::disabled_int(|| xhc::scan_ports())?;
 
+        let mut devices = Vec::new();
         for port_id in port_ids {
             arch::disabled_int(|| xhc::reset_port(port_id))?;
             let device = arch::disabled_int(|| xhc::alloc_address_to_device(port_id))?;
-            self.usb_devices.push(device);
+            devices.push(device);
         }
 
-        for device in self.usb_devices.iter_mut() {
-            let slot_id = device.slot_id();
-            arch::disabled_int(|| device.init()).map_err(|err| {
-                UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                }
-            })?;
+        self.configure_devices(devices)?;
+
+        self.device_driver_info.attached = true;
+        Ok(())
+    }
 
-            device.read_dev_desc();
+    fn poll_normal(&mut self) -> Result<Self::PollNormalOutput> {
+        if !self.device_driver_info.attached {
+            return Ok(Vec::new());
+        }
 
-            arch::disabled_int(|| device.request_to_get_desc(DescriptorType::Configration, 0))
-                .map_err(|err| UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                })?;
-
-            device.read_conf_descs();
-
-            let boot_interface = match device
-                .get_interface_descs()
-                .iter()
-                .find(|d| d.class == 3 && d.sub_class == 1 && d.protocol == 1)
-            {
-                Some(d) => **d,
-                None => {
-                    warn!(
-                        "usb: Unsupported device, skip configuring... (slot id: {})",
-                        slot_id
-                    );
+        let mut detached_port_ids = Vec::new();
+        let mut connected_ports = Vec::new();
+
+        for device in self.usb_devices.iter_mut().filter(|d| d.is_configured) {
+            let mut hub = match device.hub.take() {
+                Some(hub) if hub.has_port_changes() => hub,
+                hub => {
+                    device.hub = hub;
                     continue;
                 }
             };
-
-            let conf_desc = match device.get_conf_descs()[0].clone() {
-                Descriptor::Configuration(desc) => desc,
-                _ => unreachable!(),
-            };
-
-            arch::disabled_int(|| device.configure_endpoint(EndpointType::InterruptIn)).map_err(
-                |err| UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                },
-            )?;
-
-            arch::disabled_int(|| device.request_to_set_conf(conf_desc.conf_value)).map_err(
-                |err| UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                },
-            )?;
-
-            arch::disabled_int(|| device.request_to_set_interface(boot_interface)).map_err(
-                |err| UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                },
-            )?;
-
-            arch::disabled_int(|| device.request_to_set_protocol(boot_interface, 0)).map_err(
-                |err| UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
-                },
-            )?;
-
-            arch::disabled_int(|| device.configure_endpoint_transfer_ring()).map_err(|err| {
-                UsbBusDriverError::UsbDeviceError {
-                    slot_id,
-                    err: Box::new(err),
+            let res = hub.handle_port_changes(device);
+            device.hub = Some(hub);
+
+            for change in res? {
+                match change {
+                    PortChange::Connected(port) => connected_ports.push(port),
+                    PortChange::Disconnected {
+                        root_hub_port_id,
+                        route_string,
+                    } => detached_port_ids
+                        .push(Port::downstream_port_id(root_hub_port_id, route_string)),
                 }
-            })?;
-
-            device.is_configured = true;
+            }
         }
 
-        self.device_driver_info.attached = true;
-        Ok(())
-    }
+        let mut devices = Vec::new();
+        for port in connected_ports {
+            match xhc::alloc_address_to_downstream_device(port) {
+                Ok(device) => devices.push(device),
+                Err(err) => warn!("usb: Failed to address downstream device: {:?}", err),
+            }
+        }
+        self.configure_devices(devices)?;
 
-    fn poll_normal(&mut self) -> Result<Self::PollNormalOutput> {
-        unimplemented!()
+        Ok(detached_port_ids)
     }
 
     fn poll_int(&mut self) -> Result<Self::PollInterruptOutput> {
@@ -189,12 +341,60 @@ pub fn probe_and_attach() -> Result<()> {
     Ok(())
 }
 
-pub fn find_device_by_slot_id(slot_id: usize) -> Result<Option<UsbDevice>> {
-    let driver = unsafe { USB_BUS_DRIVER.try_lock() }?;
-    Ok(driver.find_device_by_slot_id(slot_id))
+pub fn poll_normal() -> Result<()> {
+    let detached_port_ids = unsafe { USB_BUS_DRIVER.try_lock() }?.poll_normal()?;
+
+    // devices are removed from the bus by the xhc driver
+    for port_id in detached_port_ids {
+        arch::disabled_int(|| xhc::detach_port(port_id))?;
+    }
+
+    Ok(())
+}
+
+// devices are owned by the bus driver and never copied,
+// because their buffers are released when removed
+pub fn with_device_by_slot_id<T>(
+    slot_id: usize,
+    f: impl FnOnce(&mut UsbDevice) -> T,
+) -> Result<Option<T>> {
+    let mut driver = unsafe { USB_BUS_DRIVER.try_lock() }?;
+    Ok(driver.find_device_by_slot_id_mut(slot_id).map(f))
+}
+
+pub fn remove_device(slot_id: usize) -> Result<()> {
+    let mut driver = unsafe { USB_BUS_DRIVER.try_lock() }?;
+    driver.remove_device(slot_id)
+}
+
+// block size, block count
+pub fn mass_storage_capacity() -> Result<(usize, usize)> {
+    let mut driver = unsafe { USB_BUS_DRIVER.try_lock() }?;
+    let device = driver
+        .find_mass_storage_device_mut()
+        .ok_or(UsbBusDriverError::MassStorageNotFoundError)?;
+
+    let mut mass_storage = device.mass_storage.take().unwrap();
+    let res = if mass_storage.is_init() {
+        Ok(())
+    } else {
+        mass_storage.init(device)
+    };
+    let capacity = (mass_storage.block_size(), mass_storage.block_count());
+    device.mass_storage = Some(mass_storage);
+
+    res.map(|_| capacity)
 }
 
-pub fn update_device(device: UsbDevice) -> Result<()> {
+pub fn read_mass_storage_blocks(lba: usize, count: usize) -> Result<Vec<u8>> {
     let mut driver = unsafe { USB_BUS_DRIVER.try_lock() }?;
-    driver.update_device(device)
+    let device = driver
+        .find_mass_storage_device_mut()
+        .ok_or(UsbBusDriverError::MassStorageNotFoundError)?;
+
+    let mut mass_storage = device.mass_storage.take().unwrap();
+    let res = mass_storage.read_blocks(device, lba, count);
+    device.mass_storage = Some(mass_storage);
+
+    res
 }
@@ -1,4 +1,14 @@
-#[derive(Debug)]
+use crate::{
+    device::{console, ps2_keyboard::scan_code::KeyCode},
+    error::Result,
+    util::ascii::AsciiCode,
+};
+
+const MOD_LEFT_SHIFT: u8 = 1 << 1;
+const MOD_RIGHT_SHIFT: u8 = 1 << 5;
+
+// boot protocol input report
+#[derive(Debug, Default, Clone, Copy)]
 #[repr(C)]
 pub struct InputData {
     modifier_key: u8,
@@ -10,3 +20,110 @@ pub struct InputData {
     key_code5: u8,
     key_code6: u8,
 }
+
+impl InputData {
+    fn key_codes(&self) -> [u8; 6] {
+        [
+            self.key_code1,
+            self.key_code2,
+            self.key_code3,
+            self.key_code4,
+            self.key_code5,
+            self.key_code6,
+        ]
+    }
+
+    fn is_shift(&self) -> bool {
+        self.modifier_key & (MOD_LEFT_SHIFT | MOD_RIGHT_SHIFT) != 0
+    }
+}
+
+// HID usage ID (keyboard/keypad page) to ASCII code, US layout
+fn usage_to_ascii_code(usage: u8, shift: bool) -> Option<AsciiCode> {
+    let c = match usage {
+        // a - z
+        0x04..=0x1d => {
+            let c = b'a' + (usage - 0x04);
+            if shift {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        }
+        // 1 - 9, 0
+        0x1e..=0x27 => {
+            let i = (usage - 0x1e) as usize;
+            if shift {
+                b"!@#$%^&*()"[i]
+            } else {
+                b"1234567890"[i]
+            }
+        }
+        0x28 => b'\n', // enter
+        0x29 => 0x1b,  // escape
+        0x2a => 0x08,  // backspace
+        0x2b => b'\t', // tab
+        0x2c => b' ',  // space
+        0x2d..=0x38 => {
+            let i = (usage - 0x2d) as usize;
+            if shift {
+                b"_+{}|~:\"~<>?"[i]
+            } else {
+                b"-=[]\\#;'`,./"[i]
+            }
+        }
+        _ => return None,
+    };
+
+    AsciiCode::try_from(c).ok()
+}
+
+// HID usage ID of the line editing keys
+fn usage_to_key_code(usage: u8) -> Option<KeyCode> {
+    let key_code = match usage {
+        0x4a => KeyCode::Home,
+        0x4d => KeyCode::End,
+        0x4f => KeyCode::CursorRight,
+        0x50 => KeyCode::CursorLeft,
+        0x51 => KeyCode::CursorDown,
+        0x52 => KeyCode::CursorUp,
+        _ => return None,
+    };
+
+    Some(key_code)
+}
+
+// input keys pressed in data but not in prev_data to the console
+pub fn input(prev_data: &InputData, data: &InputData) -> Result<()> {
+    let prev_key_codes = prev_data.key_codes();
+
+    for key_code in data.key_codes() {
+        // 0: no event, 1: rollover error
+        if key_code <= 1 || prev_key_codes.contains(&key_code) {
+            continue;
+        }
+
+        if let Some(key_code) = usage_to_key_code(key_code) {
+            console::input_key(key_code)?;
+            continue;
+        }
+
+        let ascii_code = match usage_to_ascii_code(key_code, data.is_shift()) {
+            Some(c) => c,
+            None => continue,
+        };
+
+        console::input(ascii_code)?;
+    }
+
+    Ok(())
+}
+
+#[test_case]
+fn test_usage_to_ascii_code() {
+    assert_eq!(usage_to_ascii_code(0x04, false), Some(AsciiCode::SmallA));
+    assert_eq!(usage_to_ascii_code(0x04, true), Some(AsciiCode::LargeA));
+    assert_eq!(usage_to_ascii_code(0x27, false), Some(AsciiCode::Num0));
+    assert_eq!(usage_to_ascii_code(0x28, false), Some(AsciiCode::NewLine));
+    assert_eq!(usage_to_ascii_code(0x3a, false), None); // F1
+}
@@ -0,0 +1,63 @@
+use crate::{
+    arch,
+    device::ps2_mouse::MouseEvent,
+    error::Result,
+    util::{fifo::Fifo, mutex::Mutex},
+};
+
+static mut MOUSE_EVENT_BUF: Mutex<Fifo<MouseEvent, 64>> = Mutex::new(Fifo::new(MouseEvent {
+    middle: false,
+    right: false,
+    left: false,
+    rel_x: 0,
+    rel_y: 0,
+    wheel: 0,
+}));
+
+// boot protocol input report
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct InputData {
+    buttons: u8,
+    rel_x: i8,
+    rel_y: i8,
+}
+
+impl InputData {
+    fn to_mouse_event(&self) -> MouseEvent {
+        MouseEvent {
+            middle: self.buttons & 0x4 != 0,
+            right: self.buttons & 0x2 != 0,
+            left: self.buttons & 0x1 != 0,
+            rel_x: self.rel_x as i16,
+            rel_y: self.rel_y as i16,
+            wheel: 0, // not reported by the boot protocol
+        }
+    }
+}
+
+// called from the xhc interrupt
+pub fn input(data: &InputData) -> Result<()> {
+    let mut buf = unsafe { MOUSE_EVENT_BUF.try_lock() }?;
+    buf.enqueue(data.to_mouse_event())
+}
+
+pub fn poll_normal() -> Result<Option<MouseEvent>> {
+    arch::disabled_int(|| {
+        let mut buf = unsafe { MOUSE_EVENT_BUF.try_lock() }?;
+        Ok(buf.dequeue().ok())
+    })
+}
+
+#[test_case]
+fn test_to_mouse_event() {
+    let data = InputData {
+        buttons: 0x1,
+        rel_x: -3,
+        rel_y: 5,
+    };
+    let e = data.to_mouse_event();
+    assert!(e.left && !e.right && !e.middle);
+    assert_eq!(e.rel_x, -3);
+    assert_eq!(e.rel_y, 5);
+}
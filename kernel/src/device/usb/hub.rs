@@ -0,0 +1,380 @@
+use super::{
+    bus::device::{SetupData, UsbDevice},
+    trb::{RequestType, RequestTypeDirection, RequestTypeRecipient, SetupRequest},
+    xhc::{self, register::PortSpeedIdValue},
+};
+use crate::{
+    device::local_apic_timer,
+    error::{Error, Result},
+};
+use alloc::vec::Vec;
+use core::{mem::size_of, ptr};
+use log::info;
+
+const CLASS_HUB: u8 = 0x09;
+const DESC_TYPE_HUB: u8 = 0x29;
+
+// hub class feature selectors
+const FEAT_PORT_RESET: u16 = 4;
+const FEAT_PORT_POWER: u16 = 8;
+const FEAT_C_PORT_CONNECTION: u16 = 16;
+const FEAT_C_PORT_RESET: u16 = 20;
+
+// route string has 5 tiers of 4 bits
+const MAX_ROUTE_DEPTH: usize = 5;
+const MAX_ROUTE_PORT_NUM: u8 = 15;
+const PORT_RESET_TIMEOUT_MS: usize = 500;
+const PORT_RESET_RECOVERY_MS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HubError {
+    UnsupportedHubError,
+    InvalidHubDescriptorError,
+    RouteStringDepthExceededError,
+    RouteStringPortNumberError(u8),
+    PortResetTimeoutError(u8),
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C, packed)]
+pub struct HubDescriptor {
+    pub len: u8,
+    pub ty: u8,
+    pub num_of_ports: u8,
+    pub characteristics: u16,
+    pub power_on_to_power_good: u8, // 2ms units
+    pub ctrl_current: u8,
+}
+
+// wPortStatus (low 16 bits) and wPortChange (high 16 bits)
+#[derive(Debug, Clone, Copy)]
+pub struct PortStatus(u32);
+
+impl PortStatus {
+    pub fn connection(&self) -> bool {
+        (self.0 & 0x1) != 0
+    }
+
+    pub fn enable(&self) -> bool {
+        (self.0 & 0x2) != 0
+    }
+
+    pub fn low_speed(&self) -> bool {
+        (self.0 & 0x200) != 0
+    }
+
+    pub fn high_speed(&self) -> bool {
+        (self.0 & 0x400) != 0
+    }
+
+    pub fn connection_change(&self) -> bool {
+        (self.0 & 0x1_0000) != 0
+    }
+
+    pub fn reset_change(&self) -> bool {
+        (self.0 & 0x10_0000) != 0
+    }
+
+    pub fn speed(&self) -> PortSpeedIdValue {
+        if self.low_speed() {
+            PortSpeedIdValue::LowSpeed
+        } else if self.high_speed() {
+            PortSpeedIdValue::HighSpeed
+        } else {
+            PortSpeedIdValue::FullSpeed
+        }
+    }
+}
+
+// device connected to a hub port, addressed by xhc::alloc_address_to_downstream_device
+#[derive(Debug, Clone, Copy)]
+pub struct DownstreamPort {
+    pub root_hub_port_id: usize,
+    pub route_string: u32,
+    pub speed: PortSpeedIdValue,
+    // transaction translator for low/full-speed devices behind a high-speed hub
+    pub tt_hub_slot_id: u8,
+    pub tt_port_num: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PortChange {
+    Connected(DownstreamPort),
+    Disconnected {
+        root_hub_port_id: usize,
+        route_string: u32,
+    },
+}
+
+pub fn is_hub_interface(class: u8) -> bool {
+    class == CLASS_HUB
+}
+
+pub fn route_string(parent_route_string: u32, port_num: u8) -> Result<u32> {
+    let depth = (0..MAX_ROUTE_DEPTH)
+        .take_while(|i| (parent_route_string >> (i * 4)) & 0xf != 0)
+        .count();
+    if depth >= MAX_ROUTE_DEPTH {
+        return Err(HubError::RouteStringDepthExceededError.into());
+    }
+
+    // each tier is a 4-bit port number, 0 terminates the route string
+    if port_num == 0 || port_num > MAX_ROUTE_PORT_NUM {
+        return Err(HubError::RouteStringPortNumberError(port_num).into());
+    }
+
+    Ok(parent_route_string | ((port_num as u32) << (depth * 4)))
+}
+
+// USB 2.0 hub only
+#[derive(Debug, Clone)]
+pub struct Hub {
+    slot_id: usize,
+    desc: HubDescriptor,
+    root_hub_port_id: usize,
+    route_string: u32,
+    speed: PortSpeedIdValue,
+    parent_tt: (u8, u8), // hub slot id, port num
+    port_change_bitmap: u64,
+}
+
+impl Hub {
+    pub fn new(device: &mut UsbDevice) -> Result<Self> {
+        let slot_id = device.slot_id();
+        let slot_context = xhc::read_device_context(slot_id)?
+            .ok_or(Error::Failed("Device context was not found"))?
+            .slot_context;
+
+        let speed = slot_context.speed();
+        if speed == PortSpeedIdValue::SuperSpeed {
+            return Err(HubError::UnsupportedHubError.into());
+        }
+
+        let mut buf = [0; size_of::<HubDescriptor>()];
+        device.control_transfer(
+            SetupData {
+                direction: RequestTypeDirection::In,
+                ty: RequestType::Class,
+                recipient: RequestTypeRecipient::Device,
+                request: SetupRequest::GetDescriptor as u8,
+                value: (DESC_TYPE_HUB as u16) << 8,
+                index: 0,
+                length: buf.len() as u16,
+            },
+            Some(&mut buf),
+        )?;
+        let desc: HubDescriptor = unsafe { ptr::read_unaligned(buf.as_ptr() as *const _) };
+
+        if desc.ty != DESC_TYPE_HUB || desc.num_of_ports == 0 {
+            return Err(HubError::InvalidHubDescriptorError.into());
+        }
+
+        info!(
+            "usb: Found hub (slot id: {}, ports: {})",
+            slot_id, desc.num_of_ports
+        );
+
+        Ok(Self {
+            slot_id,
+            desc,
+            root_hub_port_id: slot_context.root_hub_port_num() as usize,
+            route_string: slot_context.route_string(),
+            speed,
+            parent_tt: (
+                slot_context.parent_hub_slot_id(),
+                slot_context.parent_port_num(),
+            ),
+            port_change_bitmap: 0,
+        })
+    }
+
+    pub fn num_of_ports(&self) -> u8 {
+        self.desc.num_of_ports
+    }
+
+    pub fn has_port_changes(&self) -> bool {
+        self.port_change_bitmap != 0
+    }
+
+    // data from the status change endpoint, bit 0 is the hub itself
+    pub fn set_port_change_bitmap(&mut self, data: &[u8]) {
+        for (i, b) in data.iter().take(size_of::<u64>()).enumerate() {
+            self.port_change_bitmap |= (*b as u64) << (i * 8);
+        }
+    }
+
+    pub fn power_on_ports(&self, device: &mut UsbDevice) -> Result<()> {
+        for port_num in 1..=self.num_of_ports() {
+            self.set_port_feature(device, port_num, FEAT_PORT_POWER)?;
+        }
+
+        spin_wait_ms(self.desc.power_on_to_power_good as usize * 2);
+        Ok(())
+    }
+
+    pub fn enum_ports(&self, device: &mut UsbDevice) -> Result<Vec<DownstreamPort>> {
+        let mut ports = Vec::new();
+
+        for port_num in 1..=self.num_of_ports() {
+            if let Some(port) = self.reset_port(device, port_num)? {
+                ports.push(port);
+            }
+        }
+
+        Ok(ports)
+    }
+
+    pub fn handle_port_changes(&mut self, device: &mut UsbDevice) -> Result<Vec<PortChange>> {
+        let bitmap = self.port_change_bitmap;
+        self.port_change_bitmap = 0;
+
+        let mut changes = Vec::new();
+        for port_num in 1..=self.num_of_ports().min(63) {
+            if bitmap & (1 << port_num) == 0 {
+                continue;
+            }
+
+            let status = self.get_port_status(device, port_num)?;
+            if !status.connection_change() {
+                continue;
+            }
+
+            // a reconnected device is detached first
+            changes.push(PortChange::Disconnected {
+                root_hub_port_id: self.root_hub_port_id,
+                route_string: route_string(self.route_string, port_num)?,
+            });
+
+            if let Some(port) = self.reset_port(device, port_num)? {
+                changes.push(PortChange::Connected(port));
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn reset_port(&self, device: &mut UsbDevice, port_num: u8) -> Result<Option<DownstreamPort>> {
+        let status = self.get_port_status(device, port_num)?;
+        if status.connection_change() {
+            self.clear_port_feature(device, port_num, FEAT_C_PORT_CONNECTION)?;
+        }
+
+        if !status.connection() {
+            return Ok(None);
+        }
+
+        self.set_port_feature(device, port_num, FEAT_PORT_RESET)?;
+
+        let start_ms = local_apic_timer::get_current_ms().unwrap_or(0);
+        let status = loop {
+            let status = self.get_port_status(device, port_num)?;
+            if status.reset_change() {
+                break status;
+            }
+
+            let current_ms = local_apic_timer::get_current_ms().unwrap_or(0);
+            if current_ms.saturating_sub(start_ms) > PORT_RESET_TIMEOUT_MS {
+                return Err(HubError::PortResetTimeoutError(port_num).into());
+            }
+        };
+
+        self.clear_port_feature(device, port_num, FEAT_C_PORT_RESET)?;
+        spin_wait_ms(PORT_RESET_RECOVERY_MS);
+
+        if !status.enable() {
+            return Ok(None);
+        }
+
+        let speed = status.speed();
+        let (tt_hub_slot_id, tt_port_num) = match speed {
+            PortSpeedIdValue::HighSpeed | PortSpeedIdValue::SuperSpeed => (0, 0),
+            _ if self.speed == PortSpeedIdValue::HighSpeed => (self.slot_id as u8, port_num),
+            _ => self.parent_tt,
+        };
+
+        info!(
+            "usb: Reset hub port (slot id: {}, port: {}, speed: {:?})",
+            self.slot_id, port_num, speed
+        );
+
+        Ok(Some(DownstreamPort {
+            root_hub_port_id: self.root_hub_port_id,
+            route_string: route_string(self.route_string, port_num)?,
+            speed,
+            tt_hub_slot_id,
+            tt_port_num,
+        }))
+    }
+
+    fn get_port_status(&self, device: &mut UsbDevice, port_num: u8) -> Result<PortStatus> {
+        let mut buf = [0; 4];
+        device.control_transfer(
+            SetupData {
+                direction: RequestTypeDirection::In,
+                ty: RequestType::Class,
+                recipient: RequestTypeRecipient::Other,
+                request: SetupRequest::GetStatus as u8,
+                value: 0,
+                index: port_num as u16,
+                length: buf.len() as u16,
+            },
+            Some(&mut buf),
+        )?;
+
+        Ok(PortStatus(u32::from_le_bytes(buf)))
+    }
+
+    fn set_port_feature(&self, device: &mut UsbDevice, port_num: u8, feature: u16) -> Result<()> {
+        device.control_transfer(
+            SetupData {
+                direction: RequestTypeDirection::Out,
+                ty: RequestType::Class,
+                recipient: RequestTypeRecipient::Other,
+                request: SetupRequest::SetFeature as u8,
+                value: feature,
+                index: port_num as u16,
+                length: 0,
+            },
+            None,
+        )
+    }
+
+    fn clear_port_feature(&self, device: &mut UsbDevice, port_num: u8, feature: u16) -> Result<()> {
+        device.control_transfer(
+            SetupData {
+                direction: RequestTypeDirection::Out,
+                ty: RequestType::Class,
+                recipient: RequestTypeRecipient::Other,
+                request: SetupRequest::ClearFeature as u8,
+                value: feature,
+                index: port_num as u16,
+                length: 0,
+            },
+            None,
+        )
+    }
+}
+
+fn spin_wait_ms(ms: usize) {
+    let start_ms = match local_apic_timer::get_current_ms() {
+        Some(ms) => ms,
+        None => return,
+    };
+
+    while let Some(current_ms) = local_apic_timer::get_current_ms() {
+        if current_ms.saturating_sub(start_ms) >= ms {
+            break;
+        }
+    }
+}
+
+#[test_case]
+fn test_route_string() {
+    assert_eq!(size_of::<HubDescriptor>(), 7);
+    assert_eq!(route_string(0, 3), Ok(0x3));
+    assert_eq!(route_string(0x3, 2), Ok(0x23));
+    assert_eq!(route_string(0x23, 15), Ok(0xf23));
+    assert!(route_string(0x23, 16).is_err());
+    assert!(route_string(0x23, 0).is_err());
+    assert!(route_string(0x1_1111, 1).is_err());
+}
@@ -0,0 +1,231 @@
+use super::bus::device::UsbDevice;
+use crate::{
+    device::hpet,
+    error::Result,
+    mem::bitmap::{self, MemoryFrameInfo},
+    mem::paging::PAGE_SIZE,
+};
+use alloc::vec::Vec;
+use core::{mem::size_of, ptr};
+
+const CLASS_MASS_STORAGE: u8 = 0x08;
+const SUB_CLASS_SCSI: u8 = 0x06;
+const PROTOCOL_BULK_ONLY: u8 = 0x50;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355; // "USBC"
+const CSW_SIGNATURE: u32 = 0x5342_5355; // "USBS"
+const CBW_FLAGS_DATA_IN: u8 = 0x80;
+
+const SCSI_READ_CAPACITY_10: u8 = 0x25;
+const SCSI_READ_10: u8 = 0x28;
+
+// CBW and CSW are placed at the first frame, the data buffer follows
+const CSW_OFFSET: usize = 64;
+const DATA_BUF_LEN: usize = 16; // frames
+const TRANSFER_TIMEOUT_MS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MassStorageError {
+    NotInitialized,
+    TransferTimeoutError,
+    InvalidCommandStatusWrapperError,
+    CommandFailedError(u8),
+    InvalidBlockRangeError { lba: usize, count: usize },
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C, packed)]
+struct CommandBlockWrapper {
+    signature: u32,
+    tag: u32,
+    data_transfer_len: u32,
+    flags: u8,
+    lun: u8,
+    cb_len: u8,
+    cb: [u8; 16],
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C, packed)]
+struct CommandStatusWrapper {
+    signature: u32,
+    tag: u32,
+    data_residue: u32,
+    status: u8,
+}
+
+pub fn is_mass_storage_interface(class: u8, sub_class: u8, protocol: u8) -> bool {
+    class == CLASS_MASS_STORAGE && sub_class == SUB_CLASS_SCSI && protocol == PROTOCOL_BULK_ONLY
+}
+
+// bulk-only transport, LUN 0 only
+#[derive(Debug)]
+pub struct MassStorage {
+    bulk_in_dci: usize,
+    bulk_out_dci: usize,
+    buf_mem_frame_info: MemoryFrameInfo,
+    tag: u32,
+    block_size: usize,
+    block_count: usize,
+}
+
+impl MassStorage {
+    pub fn new(bulk_in_dci: usize, bulk_out_dci: usize) -> Result<Self> {
+        let buf_mem_frame_info = bitmap::alloc_mem_frame(1 + DATA_BUF_LEN)?;
+        bitmap::mem_clear(&buf_mem_frame_info)?;
+
+        Ok(Self {
+            bulk_in_dci,
+            bulk_out_dci,
+            buf_mem_frame_info,
+            tag: 0,
+            block_size: 0,
+            block_count: 0,
+        })
+    }
+
+    pub fn is_init(&self) -> bool {
+        self.block_size != 0
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.block_count
+    }
+
+    pub fn release(&self) -> Result<()> {
+        bitmap::dealloc_mem_frame(self.buf_mem_frame_info)
+    }
+
+    pub fn init(&mut self, device: &mut UsbDevice) -> Result<()> {
+        let mut cb = [0; 10];
+        cb[0] = SCSI_READ_CAPACITY_10;
+        let data = self.send_cmd(device, &cb, 8)?;
+
+        let last_lba = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        self.block_size = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        self.block_count = last_lba + 1;
+
+        Ok(())
+    }
+
+    pub fn read_blocks(
+        &mut self,
+        device: &mut UsbDevice,
+        lba: usize,
+        count: usize,
+    ) -> Result<Vec<u8>> {
+        if !self.is_init() {
+            return Err(MassStorageError::NotInitialized.into());
+        }
+
+        if lba + count > self.block_count {
+            return Err(MassStorageError::InvalidBlockRangeError { lba, count }.into());
+        }
+
+        let max_blocks = DATA_BUF_LEN * PAGE_SIZE / self.block_size;
+        let mut data = Vec::with_capacity(count * self.block_size);
+        let mut offset = 0;
+
+        while offset < count {
+            let len = (count - offset).min(max_blocks);
+            let lba_bytes = ((lba + offset) as u32).to_be_bytes();
+            let len_bytes = (len as u16).to_be_bytes();
+
+            let mut cb = [0; 10];
+            cb[0] = SCSI_READ_10;
+            cb[2..6].copy_from_slice(&lba_bytes);
+            cb[7..9].copy_from_slice(&len_bytes);
+            data.extend(self.send_cmd(device, &cb, len * self.block_size)?);
+
+            offset += len;
+        }
+
+        Ok(data)
+    }
+
+    // CBW (bulk out) -> data (bulk in) -> CSW (bulk in)
+    fn send_cmd(&mut self, device: &mut UsbDevice, cb: &[u8], data_len: usize) -> Result<Vec<u8>> {
+        self.tag = self.tag.wrapping_add(1);
+
+        let buf_phys_addr = self.buf_mem_frame_info.frame_start_phys_addr;
+        let buf_virt_addr = self.buf_mem_frame_info.frame_start_virt_addr()?;
+        let cbw_virt_addr = buf_virt_addr;
+        let csw_virt_addr = buf_virt_addr.offset(CSW_OFFSET);
+        let data_virt_addr = buf_virt_addr.offset(PAGE_SIZE);
+
+        let mut cbw = CommandBlockWrapper {
+            signature: CBW_SIGNATURE,
+            tag: self.tag,
+            data_transfer_len: data_len as u32,
+            flags: CBW_FLAGS_DATA_IN,
+            lun: 0,
+            cb_len: cb.len() as u8,
+            cb: [0; 16],
+        };
+        cbw.cb[..cb.len()].copy_from_slice(cb);
+        unsafe {
+            ptr::write_volatile(cbw_virt_addr.as_ptr_mut(), cbw);
+            ptr::write_volatile(csw_virt_addr.as_ptr_mut(), CommandStatusWrapper::default());
+        }
+
+        device.bulk_transfer(
+            self.bulk_out_dci,
+            buf_phys_addr,
+            size_of::<CommandBlockWrapper>() as u32,
+        )?;
+        if data_len > 0 {
+            device.bulk_transfer(
+                self.bulk_in_dci,
+                buf_phys_addr.offset(PAGE_SIZE),
+                data_len as u32,
+            )?;
+        }
+        device.bulk_transfer(
+            self.bulk_in_dci,
+            buf_phys_addr.offset(CSW_OFFSET),
+            size_of::<CommandStatusWrapper>() as u32,
+        )?;
+
+        // the CSW is written last by the device
+        // poll every 1ms up to the bounded count, the timer may not be ready
+        let mut csw = None;
+        for _ in 0..TRANSFER_TIMEOUT_MS {
+            let value: CommandStatusWrapper = unsafe { ptr::read_volatile(csw_virt_addr.as_ptr()) };
+            if value.signature == CSW_SIGNATURE {
+                csw = Some(value);
+                break;
+            }
+
+            if hpet::wait_ms(1).is_err() {
+                core::hint::spin_loop();
+            }
+        }
+        let csw = csw.ok_or(MassStorageError::TransferTimeoutError)?;
+
+        if csw.tag != self.tag {
+            return Err(MassStorageError::InvalidCommandStatusWrapperError.into());
+        }
+
+        if csw.status != 0 {
+            return Err(MassStorageError::CommandFailedError(csw.status).into());
+        }
+
+        let mut data = Vec::with_capacity(data_len);
+        unsafe {
+            data.set_len(data_len);
+            ptr::copy_nonoverlapping(data_virt_addr.as_ptr::<u8>(), data.as_mut_ptr(), data_len);
+        }
+
+        Ok(data)
+    }
+}
+
+#[test_case]
+fn test_wrapper_size() {
+    assert_eq!(size_of::<CommandBlockWrapper>(), 31);
+    assert_eq!(size_of::<CommandStatusWrapper>(), 13);
+}
@@ -1,4 +1,7 @@
 pub mod bus;
 pub mod hid_keyboard;
+pub mod hid_mouse;
+pub mod hub;
+pub mod mass_storage;
 pub mod trb;
 pub mod xhc;
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum RequestTypeRecipient {
     Device = 0,
@@ -19,7 +19,7 @@ impl From<u8> for RequestTypeRecipient {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum RequestType {
     Standard = 0,
@@ -38,7 +38,7 @@ impl From<u8> for RequestType {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum RequestTypeDirection {
     Out = 0,
@@ -87,7 +87,7 @@ impl SetupRequestType {
 
     pub fn set_ty(&mut self, value: RequestType) {
         let value = value as u8;
-        self.0 = (self.0 & !0x60) | (value << 2);
+        self.0 = (self.0 & !0x60) | (value << 5);
     }
 
     pub fn direction(&self) -> RequestTypeDirection {
@@ -457,6 +457,16 @@ impl TransferRequestBlock {
         self.param = param;
     }
 
+    // for class or vendor specific requests
+    pub fn set_setup_request_raw(&mut self, new_val: u8) {
+        if self.trb_type() != TransferRequestBlockType::SetupStage {
+            return;
+        }
+
+        let param = (self.param & !0xff00) | ((new_val as u64) << 8);
+        self.param = param;
+    }
+
     pub fn setup_request(&self) -> Option<SetupRequest> {
         if self.trb_type() != TransferRequestBlockType::SetupStage {
             return None;
@@ -541,6 +551,12 @@ impl TransferRequestBlock {
         Some((self.status & 0xfff) as usize)
     }
 
+    // transfer TRB, the interrupter which receives the transfer event
+    pub fn set_intr_target(&mut self, intr_index: usize) {
+        let value = (intr_index as u32) & 0x3ff;
+        self.status = (self.status & !0xffc0_0000) | (value << 22);
+    }
+
     pub fn endpoint_id(&self) -> Option<usize> {
         if self.trb_type() != TransferRequestBlockType::TransferEvent {
             return None;
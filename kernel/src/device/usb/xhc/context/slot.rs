@@ -5,16 +5,56 @@ use crate::device::usb::xhc::register::PortSpeedIdValue;
 pub struct SlotContext([u32; 8]);
 
 impl SlotContext {
+    pub fn route_string(&self) -> u32 {
+        self.0[0] & 0xf_ffff
+    }
+
+    pub fn set_route_string(&mut self, value: u32) {
+        self.0[0] = (self.0[0] & !0xf_ffff) | (value & 0xf_ffff);
+    }
+
+    pub fn speed(&self) -> PortSpeedIdValue {
+        PortSpeedIdValue::from(((self.0[0] >> 20) & 0xf) as u8)
+    }
+
     pub fn set_speed(&mut self, value: PortSpeedIdValue) {
         self.0[0] = (self.0[0] & !0xf0_0000) | ((value as u32) << 20);
     }
 
     pub fn set_context_entries(&mut self, value: u8) {
         let value = value & 0x1f; // 5 bits
-        self.0[0] = (self.0[0] & !0xf800_0000) | ((value as u32) << 26);
+        self.0[0] = (self.0[0] & !0xf800_0000) | ((value as u32) << 27);
+    }
+
+    pub fn set_hub(&mut self, value: bool) {
+        self.0[0] = (self.0[0] & !0x400_0000) | ((value as u32) << 26);
+    }
+
+    pub fn root_hub_port_num(&self) -> u8 {
+        (self.0[1] >> 16) as u8
     }
 
     pub fn set_root_hub_port_num(&mut self, value: u8) {
         self.0[1] = (self.0[1] & !0xff_0000) | ((value as u32) << 16);
     }
+
+    pub fn set_num_of_ports(&mut self, value: u8) {
+        self.0[1] = (self.0[1] & !0xff00_0000) | ((value as u32) << 24);
+    }
+
+    pub fn parent_hub_slot_id(&self) -> u8 {
+        self.0[2] as u8
+    }
+
+    pub fn set_parent_hub_slot_id(&mut self, value: u8) {
+        self.0[2] = (self.0[2] & !0xff) | value as u32;
+    }
+
+    pub fn parent_port_num(&self) -> u8 {
+        (self.0[2] >> 8) as u8
+    }
+
+    pub fn set_parent_port_num(&mut self, value: u8) {
+        self.0[2] = (self.0[2] & !0xff00) | ((value as u32) << 8);
+    }
 }
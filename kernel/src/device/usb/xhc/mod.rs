@@ -1,14 +1,21 @@
 use super::{
-    bus::{device::UsbDevice, find_device_by_slot_id, update_device},
+    bus::{device::UsbDevice, remove_device, with_device_by_slot_id},
+    hub::DownstreamPort,
     trb::*,
 };
 use crate::{
     addr::{PhysicalAddress, VirtualAddress},
-    apic,
-    device::{self, pci_bus::conf_space::BaseAddress, DeviceDriverFunction, DeviceDriverInfo},
+    apic, arch,
+    device::{
+        self, hpet, local_apic_timer, pci_bus::conf_space::BaseAddress, DeviceDriverFunction,
+        DeviceDriverInfo,
+    },
     error::{Error, Result},
     idt,
-    mem::bitmap,
+    mem::{
+        bitmap::{self, MemoryFrameInfo},
+        paging::PAGE_SIZE,
+    },
     register::msi::*,
     util::mutex::Mutex,
 };
@@ -34,6 +41,16 @@ static mut XHC_DRIVER: Mutex<XhcDriver> = Mutex::new(XhcDriver::new());
 
 const PORT_REG_SETS_START_VIRT_ADDR_OFFSET: usize = 1024;
 const RING_BUF_LEN: usize = 16;
+// segments of the primary event ring, reduced to the number supported by the controller
+const EVENT_RING_SEG_NUM: usize = 4;
+const EVENT_RING_BUF_LEN: usize = RING_BUF_LEN * EVENT_RING_SEG_NUM;
+const COMPLETED_CTRL_TRANSFER_LEN: usize = 32;
+const COMPLETED_CMD_LEN: usize = 32;
+const CMD_TIMEOUT_MS: usize = 1000;
+const OWNERSHIP_TIMEOUT_MS: usize = 1000;
+const LOCK_TIMEOUT_SPINS: usize = 100_000;
+// consecutive TRB errors before the controller is disabled
+const TRB_ERROR_MAX: usize = 3;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum XhcDriverError {
@@ -48,6 +65,10 @@ pub enum XhcDriverError {
     NotRunning,
     PortWasNotFoundError(usize),
     PortIsNotEnabledError(usize),
+    CommandTimeoutError,
+    CommandFailedError(CompletionCode),
+    InterrupterIsFullError,
+    MsixIsNotAvailableError,
 }
 
 struct XhcDriver {
@@ -63,13 +84,24 @@ struct XhcDriver {
     device_context_arr_virt_addr: Option<VirtualAddress>,
     num_of_ports: Option<usize>,
     num_of_slots: Option<usize>,
-    primary_event_ring_buf: Option<RingBuffer<RING_BUF_LEN>>,
+    primary_event_ring_buf: Option<RingBuffer<EVENT_RING_BUF_LEN>>,
+    // event rings of the interrupter 1, 2, ...
+    secondary_event_ring_bufs: Vec<RingBuffer<EVENT_RING_BUF_LEN>>,
     cmd_ring_buf: Option<RingBuffer<RING_BUF_LEN>>,
 
     ports: Vec<Port>,
 
     configuring_port_id: Option<usize>,
     root_hub_port_id: Option<usize>,
+    // addresses of completed TRBs on default control pipes
+    completed_ctrl_transfer_trb_ptrs: Vec<u64>,
+    // command TRB address, completion code, slot id
+    completed_cmds: Vec<(u64, CompletionCode, Option<usize>)>,
+    trb_error_cnt: usize,
+    // command TRB address, endpoint to ring after the completion (slot id, endpoint id)
+    recovery_cmds: Vec<(u64, Option<(usize, usize)>)>,
+    // command TRB address, slot id and device context frames released after the completion
+    disable_slot_cmds: Vec<(u64, usize, Vec<MemoryFrameInfo>)>,
 }
 
 impl XhcDriver {
@@ -87,10 +119,16 @@ impl XhcDriver {
             num_of_ports: None,
             num_of_slots: None,
             primary_event_ring_buf: None,
+            secondary_event_ring_bufs: Vec::new(),
             cmd_ring_buf: None,
             ports: Vec::new(),
             configuring_port_id: None,
             root_hub_port_id: None,
+            completed_ctrl_transfer_trb_ptrs: Vec::new(),
+            completed_cmds: Vec::new(),
+            trb_error_cnt: 0,
+            recovery_cmds: Vec::new(),
+            disable_slot_cmds: Vec::new(),
         }
     }
 
@@ -217,6 +255,75 @@ impl XhcDriver {
             return Err(XhcDriverError::PortIsNotEnabledError(port_id).into());
         }
 
+        let mut port = port.clone();
+        port.config_state = ConfigState::AddressingDevice;
+        self.write_port(port);
+
+        self.configuring_port_id = Some(port_id);
+
+        let port_speed = self
+            .read_port_reg_set(self.root_hub_port_id.unwrap())
+            .unwrap()
+            .port_status_and_ctrl
+            .port_speed();
+
+        let mut slot_context = SlotContext::default();
+        slot_context.set_speed(port_speed);
+        slot_context.set_context_entries(1);
+        slot_context.set_root_hub_port_num(self.root_hub_port_id.unwrap() as u8);
+
+        let (trb, device) = self.init_input_context(port_id, slot_context)?;
+        self.push_cmd_ring(trb).unwrap();
+
+        Ok(device)
+    }
+
+    // slot for the downstream port must be enabled, returns an Address Device Command TRB
+    fn alloc_address_to_downstream_device(
+        &mut self,
+        slot_id: usize,
+        downstream_port: DownstreamPort,
+    ) -> Result<(TransferRequestBlock, UsbDevice)> {
+        if !self.device_driver_info.attached {
+            return Err(XhcDriverError::NotInitialized.into());
+        }
+
+        let DownstreamPort {
+            root_hub_port_id,
+            route_string,
+            speed,
+            tt_hub_slot_id,
+            tt_port_num,
+        } = downstream_port;
+
+        let port_id = Port::downstream_port_id(root_hub_port_id, route_string);
+        self.ports.retain(|p| p.port_id() != port_id);
+        self.ports.push(Port::new(port_id));
+        self.alloc_slot(port_id, slot_id)?;
+
+        let mut slot_context = SlotContext::default();
+        slot_context.set_route_string(route_string);
+        slot_context.set_speed(speed);
+        slot_context.set_context_entries(1);
+        slot_context.set_root_hub_port_num(root_hub_port_id as u8);
+        slot_context.set_parent_hub_slot_id(tt_hub_slot_id);
+        slot_context.set_parent_port_num(tt_port_num);
+
+        let mut port = *self.read_port(port_id).unwrap();
+        port.config_state = ConfigState::AddressingDevice;
+        self.write_port(port);
+
+        self.init_input_context(port_id, slot_context)
+    }
+
+    fn init_input_context(
+        &mut self,
+        port_id: usize,
+        slot_context: SlotContext,
+    ) -> Result<(TransferRequestBlock, UsbDevice)> {
+        let port = self
+            .read_port(port_id)
+            .ok_or(XhcDriverError::PortWasNotFoundError(port_id))?;
         let slot_id = port.slot_id.unwrap();
 
         let input_context_mem_frame_info = bitmap::alloc_mem_frame(1)?;
@@ -224,12 +331,10 @@ impl XhcDriver {
         let input_context_base_virt_addr = input_context_mem_frame_info.frame_start_virt_addr()?;
 
         let mut port = port.clone();
-        port.config_state = ConfigState::AddressingDevice;
         port.input_context_base_virt_addr = input_context_base_virt_addr;
+        port.input_context_mem_frame_info = Some(input_context_mem_frame_info);
         self.write_port(port);
 
-        self.configuring_port_id = Some(port_id);
-
         // initialize input control context
         let mut input_context = InputContext::default();
         input_context
@@ -241,19 +346,7 @@ impl XhcDriver {
             .set_add_context_flag(1, true)
             .unwrap();
 
-        let port_speed = self
-            .read_port_reg_set(self.root_hub_port_id.unwrap())
-            .unwrap()
-            .port_status_and_ctrl
-            .port_speed();
-
-        let max_packet_size = port_speed.get_max_packet_size();
-
-        let mut slot_context = SlotContext::default();
-        slot_context.set_speed(port_speed);
-        slot_context.set_context_entries(1);
-        slot_context.set_root_hub_port_num(self.root_hub_port_id.unwrap() as u8);
-
+        let max_packet_size = slot_context.speed().get_max_packet_size();
         input_context.device_context.slot_context = slot_context;
 
         let mut endpoint_context_0 = EndpointContext::default();
@@ -276,9 +369,9 @@ impl XhcDriver {
         trb.set_trb_type(TransferRequestBlockType::AddressDeviceCommand);
         trb.param = input_context_base_virt_addr.get_phys_addr().unwrap().get();
         trb.ctrl_regs = (slot_id as u16) << 8;
-        self.push_cmd_ring(trb).unwrap();
 
-        return UsbDevice::new(slot_id, max_packet_size, transfer_ring_buf);
+        let device = UsbDevice::new(slot_id, max_packet_size, transfer_ring_buf)?;
+        Ok((trb, device))
     }
 
     fn is_running(&self) -> bool {
@@ -306,6 +399,7 @@ impl XhcDriver {
         port.slot_id = Some(slot_id);
         port.config_state = ConfigState::Enabled;
         port.output_context_base_virt_addr = device_context_base_virt_addr;
+        port.output_context_mem_frame_info = Some(device_context_mem_frame_info);
         self.write_port(port);
 
         self.write_device_context_base_addr(
@@ -320,6 +414,74 @@ impl XhcDriver {
         Ok(())
     }
 
+    fn detach_port(&mut self, port_id: usize) -> Result<()> {
+        let name = self.device_driver_info.name;
+
+        // clear connect status change (RW1C)
+        if let Some(mut port_reg_set) = self.read_port_reg_set(port_id) {
+            port_reg_set
+                .port_status_and_ctrl
+                .set_connect_status_change(true);
+            self.write_port_reg_set(port_id, port_reg_set)?;
+        }
+
+        let port = match self.read_port(port_id) {
+            Some(port) => *port,
+            None => return Ok(()),
+        };
+
+        // devices behind hubs on the root hub port
+        if !port.is_downstream() {
+            let downstream_port_ids: Vec<usize> = self
+                .ports
+                .iter()
+                .filter(|p| p.is_downstream() && p.root_hub_port_id() == port_id)
+                .map(|p| p.port_id())
+                .collect();
+
+            for downstream_port_id in downstream_port_ids {
+                self.detach_port(downstream_port_id)?;
+            }
+        }
+
+        let mem_frame_infos: Vec<MemoryFrameInfo> = [
+            port.input_context_mem_frame_info,
+            port.output_context_mem_frame_info,
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        match port.slot_id {
+            // the xHC may access the device context until the slot is disabled
+            Some(slot_id) => {
+                let mut trb = TransferRequestBlock::default();
+                trb.set_trb_type(TransferRequestBlockType::DisableSlotCommand);
+                trb.ctrl_regs = (slot_id as u16) << 8;
+                let trb_ptr = self.push_cmd_ring(trb)?;
+                self.disable_slot_cmds
+                    .push((trb_ptr, slot_id, mem_frame_infos));
+            }
+            None => {
+                for mem_frame_info in mem_frame_infos {
+                    bitmap::dealloc_mem_frame(mem_frame_info)?;
+                }
+            }
+        }
+
+        if port.is_downstream() {
+            self.ports.retain(|p| p.port_id() != port_id);
+        } else {
+            self.write_port(Port::new(port_id));
+        }
+        if self.configuring_port_id == Some(port_id) {
+            self.configuring_port_id = None;
+        }
+
+        info!("{}: Detached port (port id: {})", name, port_id);
+        Ok(())
+    }
+
     fn read_port(&self, port_id: usize) -> Option<&Port> {
         self.ports.iter().find(|p| p.port_id() == port_id)
     }
@@ -476,29 +638,315 @@ impl XhcDriver {
         self.write_doorbell_reg(index, doorbell_reg).unwrap();
     }
 
-    fn push_cmd_ring(&mut self, trb: TransferRequestBlock) -> Result<()> {
-        self.cmd_ring_buf.as_mut().unwrap().push(trb)?;
+    fn push_cmd_ring(&mut self, trb: TransferRequestBlock) -> Result<u64> {
+        let trb_ptr = self.cmd_ring_buf.as_mut().unwrap().push(trb)?;
         self.ring_doorbell(0, 0);
+        Ok(trb_ptr)
+    }
+
+    fn pop_completed_cmd(&mut self, trb_ptr: u64) -> Option<(CompletionCode, Option<usize>)> {
+        let i = self.completed_cmds.iter().position(|c| c.0 == trb_ptr)?;
+        let (_, comp_code, slot_id) = self.completed_cmds.remove(i);
+        Some((comp_code, slot_id))
+    }
+
+    fn handle_trb_error(&mut self, event_trb: TransferRequestBlock) {
+        let name = self.device_driver_info.name;
+        let trb_ptr = event_trb.param;
+        if trb_ptr == 0 {
+            warn!("{}: TRB error (event: {:?})", name, event_trb);
+        } else {
+            let trb = unsafe { (trb_ptr as *const TransferRequestBlock).read_volatile() };
+            warn!(
+                "{}: TRB error (event: {:?}, TRB at 0x{:x}: {:?})",
+                name, event_trb, trb_ptr, trb
+            );
+        }
+
+        self.trb_error_cnt += 1;
+        if self.trb_error_cnt >= TRB_ERROR_MAX {
+            error!(
+                "{}: Disabled after {} consecutive TRB errors",
+                name, self.trb_error_cnt
+            );
+            self.device_driver_info.attached = false;
+            return;
+        }
+
+        // a failed command does not stop the command ring
+        if event_trb.trb_type() != TransferRequestBlockType::TransferEvent {
+            return;
+        }
+
+        let (slot_id, endpoint_id) = match (event_trb.slot_id(), event_trb.endpoint_id()) {
+            (Some(slot_id), Some(endpoint_id)) if trb_ptr != 0 => (slot_id, endpoint_id),
+            _ => return,
+        };
+
+        if let Err(err) = self.recover_endpoint(slot_id, endpoint_id, trb_ptr) {
+            warn!(
+                "{}: Failed to recover endpoint (slot id: {}, endpoint id: {}): {:?}",
+                name, slot_id, endpoint_id, err
+            );
+        }
+    }
+
+    // the endpoint is halted by the TRB error, restart it from the next TRB
+    fn recover_endpoint(&mut self, slot_id: usize, endpoint_id: usize, trb_ptr: u64) -> Result<()> {
+        let ctrl_regs = ((slot_id as u16) << 8) | endpoint_id as u16;
+
+        let mut reset_endpoint_trb = TransferRequestBlock::default();
+        reset_endpoint_trb.set_trb_type(TransferRequestBlockType::ResetEndpointCommand);
+        reset_endpoint_trb.ctrl_regs = ctrl_regs;
+        let reset_endpoint_trb_ptr = self.push_cmd_ring(reset_endpoint_trb)?;
+        self.recovery_cmds.push((reset_endpoint_trb_ptr, None));
+
+        let (dequeue_ptr, cycle_state) = next_trb_ptr(trb_ptr);
+        let mut set_dequeue_ptr_trb = TransferRequestBlock::default();
+        set_dequeue_ptr_trb.set_trb_type(TransferRequestBlockType::SetTrDequeuePointerCommand);
+        set_dequeue_ptr_trb.param = dequeue_ptr | cycle_state as u64; // DCS bit
+        set_dequeue_ptr_trb.ctrl_regs = ctrl_regs;
+        let set_dequeue_ptr_trb_ptr = self.push_cmd_ring(set_dequeue_ptr_trb)?;
+        self.recovery_cmds
+            .push((set_dequeue_ptr_trb_ptr, Some((slot_id, endpoint_id))));
+
+        info!(
+            "{}: Recovering endpoint (slot id: {}, endpoint id: {}, dequeue: 0x{:x})",
+            self.device_driver_info.name, slot_id, endpoint_id, dequeue_ptr
+        );
+
         Ok(())
     }
 
-    fn pop_primary_event_ring(&mut self) -> Option<TransferRequestBlock> {
-        let mut intr_reg_sets_0 = self.read_intr_reg_sets(0)?;
-        match self
-            .primary_event_ring_buf
-            .as_mut()?
-            .pop(&mut intr_reg_sets_0)
+    // returns true if the command was pushed by detaching the port
+    fn complete_disable_slot_cmd(&mut self, trb_ptr: u64, comp_code: CompletionCode) -> bool {
+        let name = self.device_driver_info.name;
+        let i = match self.disable_slot_cmds.iter().position(|c| c.0 == trb_ptr) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let (_, slot_id, mem_frame_infos) = self.disable_slot_cmds.remove(i);
+        if comp_code != CompletionCode::Success {
+            // the slot may be still in use, so the device context is not released
+            warn!("{}: Failed to disable slot: {}", name, slot_id);
+            return true;
+        }
+
+        if let Err(err) = self.write_device_context_base_addr(slot_id, PhysicalAddress::default()) {
+            warn!("{}: {:?}", name, err);
+        }
+
+        if let Err(err) = remove_device(slot_id) {
+            warn!("{}: Failed to remove USB device: {:?}", name, err);
+        }
+
+        for mem_frame_info in mem_frame_infos {
+            if let Err(err) = bitmap::dealloc_mem_frame(mem_frame_info) {
+                warn!("{}: {:?}", name, err);
+            }
+        }
+
+        info!("{}: Disabled slot: {}", name, slot_id);
+        true
+    }
+
+    // returns true if the command was pushed by the endpoint recovery
+    fn complete_recovery_cmd(&mut self, trb_ptr: u64, comp_code: CompletionCode) -> bool {
+        let i = match self.recovery_cmds.iter().position(|c| c.0 == trb_ptr) {
+            Some(i) => i,
+            None => return false,
+        };
+
+        let (_, doorbell_target) = self.recovery_cmds.remove(i);
+        if let (CompletionCode::Success, Some((slot_id, endpoint_id))) =
+            (comp_code, doorbell_target)
         {
+            info!(
+                "{}: Recovered endpoint (slot id: {}, endpoint id: {})",
+                self.device_driver_info.name, slot_id, endpoint_id
+            );
+            self.ring_doorbell(slot_id, endpoint_id as u8);
+        }
+
+        true
+    }
+
+    // BIOS to OS handover by the USB legacy support capability
+    fn request_ownership(&self) {
+        let driver_name = self.device_driver_info.name;
+        let cap_reg_virt_addr = self.cap_reg_virt_addr.unwrap();
+
+        // find the capability from the extended capabilities list
+        let mut ext_cap_ptr = self.read_cap_reg().cap_params1.ext_caps_ptr();
+        let legsup_virt_addr = loop {
+            if ext_cap_ptr == 0 {
+                // owned by the OS
+                return;
+            }
+
+            let virt_addr = cap_reg_virt_addr.offset(ext_cap_ptr * 4);
+            let header: ExtendedCapabilityHeader = virt_addr.read_volatile();
+            if header.cap_id() == EXT_CAP_ID_USB_LEGACY_SUPPORT {
+                break virt_addr;
+            }
+
+            ext_cap_ptr = match header.next_ptr() {
+                0 => 0,
+                next_ptr => ext_cap_ptr + next_ptr,
+            };
+        };
+
+        let mut legsup: UsbLegacySupportCapability = legsup_virt_addr.read_volatile();
+        if legsup.bios_owned() || !legsup.os_owned() {
+            legsup.set_os_owned(true);
+            legsup_virt_addr.write_volatile(legsup);
+
+            // the local APIC timer may not be ready yet,
+            // so poll every 1ms up to the bounded count
+            let mut is_acquired = false;
+            for _ in 0..OWNERSHIP_TIMEOUT_MS {
+                legsup = legsup_virt_addr.read_volatile();
+                if !legsup.bios_owned() && legsup.os_owned() {
+                    info!("{}: Host controller ownership acquired", driver_name);
+                    is_acquired = true;
+                    break;
+                }
+
+                if hpet::wait_ms(1).is_err() {
+                    core::hint::spin_loop();
+                }
+            }
+
+            if !is_acquired {
+                // the BIOS didn't respond, take the ownership forcibly
+                warn!("{}: BIOS handover timed out", driver_name);
+                legsup.set_bios_owned(false);
+                legsup.set_os_owned(true);
+                legsup_virt_addr.write_volatile(legsup);
+            }
+        }
+
+        // USBLEGCTLSTS follows USBLEGSUP
+        let ctrl_sts_virt_addr = legsup_virt_addr.offset(size_of::<UsbLegacySupportCapability>());
+        let mut ctrl_sts: UsbLegacySupportControlStatus = ctrl_sts_virt_addr.read_volatile();
+        ctrl_sts.disable_smi();
+        ctrl_sts_virt_addr.write_volatile(ctrl_sts);
+    }
+
+    fn pop_event_ring(&mut self, intr_index: usize) -> Option<TransferRequestBlock> {
+        let mut intr_reg_set = self.read_intr_reg_sets(intr_index)?;
+        let event_ring_buf = match intr_index {
+            0 => self.primary_event_ring_buf.as_mut()?,
+            i => self.secondary_event_ring_bufs.get_mut(i - 1)?,
+        };
+
+        match event_ring_buf.pop(&mut intr_reg_set) {
             Ok(trb) => {
-                self.write_intr_reg_sets(0, intr_reg_sets_0).unwrap();
+                self.write_intr_reg_sets(intr_index, intr_reg_set).unwrap();
                 Some(trb)
             }
+            // no event, the interrupters share the handler
+            Err(Error::RingBufferError(RingBufferError::InvalidTransferRequestBlockError(_))) => {
+                None
+            }
             Err(err) => {
                 warn!("{}: {:?}", self.device_driver_info.name, err);
                 None
             }
         }
     }
+
+    // allocate an event ring and its segment table, and register them to the interrupter
+    fn init_event_ring(&self, intr_index: usize) -> Result<RingBuffer<EVENT_RING_BUF_LEN>> {
+        let seg_table_virt_addr = bitmap::alloc_mem_frame(1)?.frame_start_virt_addr()?;
+
+        let max_seg_num = self
+            .read_cap_reg()
+            .structural_params2
+            .max_event_ring_seg_table_len();
+        let seg_num = EVENT_RING_SEG_NUM.min(max_seg_num);
+        // the consumer cycle state of an event ring starts from 1
+        let mut event_ring_buf = RingBuffer::new(RingBufferType::EventRing, true)?;
+        event_ring_buf.set_seg_num(seg_num)?;
+
+        for (i, seg_table_entry) in event_ring_buf.seg_table_entries()?.iter().enumerate() {
+            seg_table_virt_addr
+                .offset(i * size_of::<EventRingSegmentTableEntry>())
+                .write_volatile(*seg_table_entry);
+        }
+
+        let mut intr_reg_set = self.read_intr_reg_sets(intr_index).ok_or(
+            XhcDriverError::InvalidInterrupterRegisterSetIndexError(intr_index),
+        )?;
+        intr_reg_set.set_event_ring_seg_table_base_addr(seg_table_virt_addr.get_phys_addr()?.get());
+        intr_reg_set.set_event_ring_seg_table_size(seg_num as u16);
+        intr_reg_set.set_dequeue_erst_seg_index(0);
+        intr_reg_set.set_event_ring_dequeue_ptr(event_ring_buf.buf_ptr() as u64);
+        self.write_intr_reg_sets(intr_index, intr_reg_set)?;
+
+        Ok(event_ring_buf)
+    }
+
+    fn enable_interrupter(&self, intr_index: usize) -> Result<()> {
+        let mut intr_reg_set = self.read_intr_reg_sets(intr_index).ok_or(
+            XhcDriverError::InvalidInterrupterRegisterSetIndexError(intr_index),
+        )?;
+        intr_reg_set.set_int_mod_interval(4000);
+        intr_reg_set.set_int_pending(false);
+        intr_reg_set.set_int_enable(true);
+        self.write_intr_reg_sets(intr_index, intr_reg_set)
+    }
+
+    // add an interrupter with its own event ring and MSI-X vector, returns the interrupter index
+    pub fn add_interrupter(&mut self) -> Result<usize> {
+        let DeviceDriverInfo { name, attached } = self.device_driver_info;
+
+        if !attached {
+            return Err(XhcDriverError::NotInitialized.into());
+        }
+
+        let intr_index = self.secondary_event_ring_bufs.len() + 1;
+        let max_intrs = self.read_cap_reg().structural_params1.max_intrs();
+        if intr_index >= max_intrs.min(INTR_REG_SET_MAX_LEN) {
+            return Err(XhcDriverError::InterrupterIsFullError.into());
+        }
+
+        // MSI can't assign a vector to each interrupter
+        let (bus, device, func) = self.pci_device_bdf.unwrap();
+        device::pci_bus::configure_device(bus, device, func, |d| {
+            if !d.is_available_msix_int() {
+                return Err(XhcDriverError::MsixIsNotAvailableError.into());
+            }
+
+            let vec_num = idt::set_handler_dyn_vec(
+                idt::InterruptHandler::Normal(poll_int_xhc_driver),
+                idt::GateType::Interrupt,
+            )?;
+
+            let msg_addr = MsiMessageAddressField::new(false, false, apic::local_apic_id());
+            let msg_data = MsiMessageDataField::new(
+                vec_num,
+                DeliveryMode::Fixed,
+                Level::Assert,
+                TriggerMode::Level,
+            );
+            if let Err(err) = d.set_msix_vector(intr_index, msg_addr, msg_data) {
+                idt::remove_handler(vec_num as usize)?;
+                return Err(err);
+            }
+
+            Ok(())
+        })?;
+
+        let event_ring_buf = self.init_event_ring(intr_index)?;
+        self.secondary_event_ring_bufs.push(event_ring_buf);
+
+        self.enable_interrupter(intr_index)?;
+        info!("{}: Interrupter {} added", name, intr_index);
+
+        Ok(intr_index)
+    }
 }
 
 impl DeviceDriverFunction for XhcDriver {
@@ -535,7 +983,7 @@ impl DeviceDriverFunction for XhcDriver {
         let driver_name = self.device_driver_info.name;
 
         let (bus, device, func) = self.pci_device_bdf.unwrap();
-        device::pci_bus::configure_device(bus, device, func, |d| {
+        device::pci_bus::attach_device(bus, device, func, |d| {
             // read base address registers
             let conf_space_non_bridge_field = d.read_conf_space_non_bridge_field()?;
             let bars = conf_space_non_bridge_field.get_bars()?;
@@ -582,7 +1030,8 @@ impl DeviceDriverFunction for XhcDriver {
                     .offset(cap_reg.doorbell_offset as usize),
             );
 
-            // TODO: request host controller ownership
+            // request host controller ownership
+            self.request_ownership();
 
             // stop controller
             if !self.read_ope_reg().usb_status.hchalted() {
@@ -622,29 +1071,33 @@ impl DeviceDriverFunction for XhcDriver {
             );
 
             // initialize scratchpad
-            // let cap_reg = self.read_cap_reg();
-            // let sp2 = cap_reg.structural_params2();
-            // let num_of_bufs =
-            //     (sp2.max_scratchpad_bufs_high() << 5 | sp2.max_scratchpad_bufs_low()) as usize;
-
-            // let scratchpad_buf_arr_virt_addr = match BITMAP_MEM_MAN.try_lock().unwrap().alloc_single_mem_frame() {
-            //     Ok(mem_info) => mem_info,
-            //     Err(err) => return Err(XhcDriverError::BitmapMemoryManagerError(err)),
-            // }
-            // .get_frame_start_virt_addr();
-
-            // let arr: &mut [u64] = scratchpad_buf_arr_virt_addr.read_volatile();
-
-            // for i in 0..num_of_bufs {
-            //     let mem_frame_info = match BITMAP_MEM_MAN.try_lock().unwrap().alloc_single_mem_frame() {
-            //         Ok(mem_info) => mem_info,
-            //         Err(err) => return Err(XhcDriverError::BitmapMemoryManagerError(err)),
-            //     };
-
-            //     arr[i] = mem_frame_info.get_frame_start_phys_addr().get();
-            // }
+            let cap_reg = self.read_cap_reg();
+            let num_of_scratchpad_bufs = cap_reg.structural_params2.max_scratchpad_bufs();
+            let mut scratchpad_buf_arr_phys_addr = None;
+            if num_of_scratchpad_bufs > 0 {
+                let arr_mem_frame_info = bitmap::alloc_mem_frame(
+                    (num_of_scratchpad_bufs * size_of::<u64>()).div_ceil(PAGE_SIZE),
+                )?;
+                bitmap::mem_clear(&arr_mem_frame_info)?;
+                let bufs_mem_frame_info = bitmap::alloc_mem_frame(num_of_scratchpad_bufs)?;
+                bitmap::mem_clear(&bufs_mem_frame_info)?;
+
+                let arr_virt_addr = arr_mem_frame_info.frame_start_virt_addr()?;
+                for i in 0..num_of_scratchpad_bufs {
+                    let buf_phys_addr = bufs_mem_frame_info
+                        .frame_start_phys_addr
+                        .offset(i * PAGE_SIZE);
+                    arr_virt_addr
+                        .offset(i * size_of::<u64>())
+                        .write_volatile(buf_phys_addr.get());
+                }
 
-            // scratchpad_buf_arr_virt_addr.write_volatile(arr);
+                scratchpad_buf_arr_phys_addr = Some(arr_mem_frame_info.frame_start_phys_addr);
+                debug!(
+                    "{}: Scratchpad buffers: {}",
+                    driver_name, num_of_scratchpad_bufs
+                );
+            }
 
             // initialize device context
             let device_context_arr_mem_frame_info = bitmap::alloc_mem_frame(1)?;
@@ -652,16 +1105,10 @@ impl DeviceDriverFunction for XhcDriver {
             self.device_context_arr_virt_addr =
                 Some(device_context_arr_mem_frame_info.frame_start_virt_addr()?);
 
-            // initialize device context array
-            // for i in 0..(self.num_of_slots + 1) {
-            //     let entry = if i == 0 {
-            //         //scratchpad_buf_arr_virt_addr
-            //         VirtualAddress::default()
-            //     } else {
-            //         VirtualAddress::default()
-            //     };
-            //     self.write_device_context_base_addr(i, entry)?;
-            // }
+            // device context base address array entry 0 is the scratchpad buffer array
+            if let Some(phys_addr) = scratchpad_buf_arr_phys_addr {
+                self.write_device_context_base_addr(0, phys_addr)?;
+            }
 
             let mut ope_reg = self.read_ope_reg();
             ope_reg.device_context_base_addr_array_ptr = self
@@ -691,36 +1138,13 @@ impl DeviceDriverFunction for XhcDriver {
             info!("{}: Command ring initialized", driver_name);
 
             // register event ring (primary)
-            let primary_event_ring_seg_table_virt_addr =
-                bitmap::alloc_mem_frame(1)?.frame_start_virt_addr()?;
-
-            // initialized event ring buffer (support only segment table length is 1)
-            let primary_event_ring_buf = RingBuffer::new(RingBufferType::EventRing, pcs)?;
-            self.primary_event_ring_buf = Some(primary_event_ring_buf);
-
-            // initialize event ring segment table entry
-            let mut seg_table_entry = EventRingSegmentTableEntry::default();
-            seg_table_entry.ring_seg_base_addr =
-                self.primary_event_ring_buf.as_ref().unwrap().buf_ptr() as u64;
-            seg_table_entry.ring_seg_size = RING_BUF_LEN as u16;
-            primary_event_ring_seg_table_virt_addr.write_volatile(seg_table_entry);
-
-            // initialize first interrupter register sets entry
-            let mut intr_reg_sets_0 = self.read_intr_reg_sets(0).unwrap();
-            intr_reg_sets_0.set_event_ring_seg_table_base_addr(
-                primary_event_ring_seg_table_virt_addr
-                    .get_phys_addr()
-                    .unwrap()
-                    .get(),
-            );
-            intr_reg_sets_0.set_event_ring_seg_table_size(1);
-            intr_reg_sets_0.set_dequeue_erst_seg_index(0);
-            intr_reg_sets_0.set_event_ring_dequeue_ptr(
-                self.primary_event_ring_buf.as_ref().unwrap().buf_ptr() as u64,
+            let primary_event_ring_buf = self.init_event_ring(0)?;
+            info!(
+                "{}: Event ring initialized (segments: {})",
+                driver_name,
+                primary_event_ring_buf.seg_num()
             );
-            self.write_intr_reg_sets(0, intr_reg_sets_0).unwrap();
-
-            info!("{}: Event ring initialized", driver_name);
+            self.primary_event_ring_buf = Some(primary_event_ring_buf);
 
             // setting up msi
             let vec_num = idt::set_handler_dyn_vec(
@@ -736,17 +1160,21 @@ impl DeviceDriverFunction for XhcDriver {
                 TriggerMode::Level,
             );
 
-            match d.set_msi_cap(msg_addr, msg_data) {
-                Ok(_) => info!("{}: MSI interrupt initialized", driver_name),
-                Err(err) => warn!("{}: {:?}", driver_name, err),
+            // prefer MSI-X, the primary interrupter uses the first vector
+            if d.is_available_msix_int() {
+                match d.set_msix_vector(0, msg_addr, msg_data) {
+                    Ok(_) => info!("{}: MSI-X interrupt initialized", driver_name),
+                    Err(err) => warn!("{}: {:?}", driver_name, err),
+                }
+            } else {
+                match d.set_msi_cap(msg_addr, msg_data) {
+                    Ok(_) => info!("{}: MSI interrupt initialized", driver_name),
+                    Err(err) => warn!("{}: {:?}", driver_name, err),
+                }
             }
 
             // enable interrupt
-            let mut intr_reg_set_0 = self.read_intr_reg_sets(0).unwrap();
-            intr_reg_set_0.set_int_mod_interval(4000);
-            intr_reg_set_0.set_int_pending(false);
-            intr_reg_set_0.set_int_enable(true);
-            self.write_intr_reg_sets(0, intr_reg_set_0)?;
+            self.enable_interrupter(0)?;
 
             let mut ope_reg = self.read_ope_reg();
             ope_reg.usb_cmd.set_intr_enable(true);
@@ -770,15 +1198,29 @@ impl DeviceDriverFunction for XhcDriver {
             return Ok(());
         }
 
-        let trb = match self.pop_primary_event_ring() {
+        let intr_num = self.secondary_event_ring_bufs.len() + 1;
+        let trb = match (0..intr_num).find_map(|i| self.pop_event_ring(i)) {
             Some(trb) => trb,
             None => return Ok(()),
         };
 
         match trb.trb_type() {
             TransferRequestBlockType::PortStatusChangeEvent => {
+                let port_id = trb.port_id().unwrap();
+
+                // disconnected
+                if let Some(port_reg_set) = self.read_port_reg_set(port_id) {
+                    let sc_reg = port_reg_set.port_status_and_ctrl;
+                    if sc_reg.connect_status_change() && !sc_reg.current_connect_status() {
+                        if let Err(err) = self.detach_port(port_id) {
+                            warn!("{}: {:?}", name, err);
+                        }
+                        return Ok(());
+                    }
+                }
+
                 // get root hub port id
-                self.root_hub_port_id = Some(trb.port_id().unwrap());
+                self.root_hub_port_id = Some(port_id);
 
                 if let Some(port_id) = self.configuring_port_id {
                     match self.read_port(port_id).unwrap().config_state {
@@ -793,20 +1235,34 @@ impl DeviceDriverFunction for XhcDriver {
             }
             TransferRequestBlockType::CommandCompletionEvent => {
                 let comp_code = trb.completion_code().unwrap();
+
+                if self.completed_cmds.len() >= COMPLETED_CMD_LEN {
+                    self.completed_cmds.remove(0);
+                }
+                self.completed_cmds
+                    .push((trb.param, comp_code, trb.slot_id()));
+
+                let is_recovery_cmd = self.complete_recovery_cmd(trb.param, comp_code);
+                let is_disable_slot_cmd = self.complete_disable_slot_cmd(trb.param, comp_code);
+
                 if comp_code != CompletionCode::Success {
                     warn!(
                         "{}: Failed to process command (completion code: {:?})",
                         name, comp_code
                     );
 
-                    // TODO
                     if comp_code == CompletionCode::TrbError {
-                        self.device_driver_info.attached = false;
+                        self.handle_trb_error(trb);
                     }
 
                     return Ok(());
                 }
 
+                self.trb_error_cnt = 0;
+                if is_recovery_cmd || is_disable_slot_cmd {
+                    return Ok(());
+                }
+
                 if let (Some(port_id), Some(slot_id)) = (self.configuring_port_id, trb.slot_id()) {
                     match self.read_port(port_id).unwrap().config_state {
                         ConfigState::Reset => {
@@ -834,31 +1290,39 @@ impl DeviceDriverFunction for XhcDriver {
                         name, comp_code
                     );
 
-                    // TODO
                     if comp_code == CompletionCode::TrbError {
-                        self.device_driver_info.attached = false;
+                        self.handle_trb_error(trb);
                     }
 
                     return Ok(());
                 }
 
+                self.trb_error_cnt = 0;
                 let slot_id = trb.slot_id().unwrap();
                 let endpoint_id = trb.endpoint_id().unwrap();
 
                 //info!("slot id: {}, endpoint id: {}", slot_id, endpoint_id);
 
-                if let Some(mut device) = find_device_by_slot_id(slot_id).unwrap_or(None) {
+                if endpoint_id == 1 {
+                    if self.completed_ctrl_transfer_trb_ptrs.len() >= COMPLETED_CTRL_TRANSFER_LEN {
+                        self.completed_ctrl_transfer_trb_ptrs.remove(0);
+                    }
+                    self.completed_ctrl_transfer_trb_ptrs.push(trb.param);
+                }
+
+                let res = with_device_by_slot_id(slot_id, |device| {
                     if !device.is_configured {
-                        return Ok(());
+                        return false;
                     }
 
                     device.update(endpoint_id, trb);
+                    true
+                });
 
-                    if update_device(device).is_ok() {
-                        self.ring_doorbell(slot_id, endpoint_id as u8);
-                    } else {
-                        error!("{}: Failed to update USB device", name);
-                    }
+                match res {
+                    Ok(Some(true)) => self.ring_doorbell(slot_id, endpoint_id as u8),
+                    Ok(_) => (),
+                    Err(_) => error!("{}: Failed to update USB device", name),
                 }
             }
             TransferRequestBlockType::HostControllerEvent => {
@@ -869,11 +1333,6 @@ impl DeviceDriverFunction for XhcDriver {
                         name, comp_code
                     );
 
-                    // TODO
-                    // if comp_code == CompletionCode::TrbError {
-                    //     self.is_init = false;
-                    // }
-
                     return Ok(());
                 }
             }
@@ -892,6 +1351,21 @@ impl DeviceDriverFunction for XhcDriver {
     }
 }
 
+// the TRB following the given one and its cycle state, the Link TRB is followed
+fn next_trb_ptr(trb_ptr: u64) -> (u64, bool) {
+    let trb = unsafe { (trb_ptr as *const TransferRequestBlock).read_volatile() };
+    if let Some(toggle_cycle) = trb.toggle_cycle() {
+        return (trb.param, trb.cycle_bit() ^ toggle_cycle);
+    }
+
+    let next_ptr = trb_ptr + size_of::<TransferRequestBlock>() as u64;
+    let next_trb = unsafe { (next_ptr as *const TransferRequestBlock).read_volatile() };
+    match next_trb.toggle_cycle() {
+        Some(toggle_cycle) => (next_trb.param, trb.cycle_bit() ^ toggle_cycle),
+        None => (next_ptr, trb.cycle_bit()),
+    }
+}
+
 pub fn get_device_driver_info() -> Result<DeviceDriverInfo> {
     unsafe { XHC_DRIVER.try_lock() }?.get_device_driver_info()
 }
@@ -908,6 +1382,11 @@ pub fn probe_and_attach() -> Result<()> {
     Ok(())
 }
 
+pub fn add_interrupter() -> Result<usize> {
+    // prevent the xhc interrupt from failing to lock the driver
+    arch::disabled_int(|| unsafe { XHC_DRIVER.try_lock() }?.add_interrupter())
+}
+
 pub fn find_port_by_slot_id(slot_id: usize) -> Result<Option<Port>> {
     Ok(unsafe { XHC_DRIVER.try_lock() }?.find_port_by_slot_id(slot_id))
 }
@@ -917,12 +1396,57 @@ pub fn read_device_context(slot_id: usize) -> Result<Option<DeviceContext>> {
 }
 
 pub fn push_cmd_ring(trb: TransferRequestBlock) -> Result<()> {
-    unsafe { XHC_DRIVER.try_lock() }?.push_cmd_ring(trb)
+    unsafe { XHC_DRIVER.try_lock() }?.push_cmd_ring(trb)?;
+    Ok(())
+}
+
+// push a command and wait for the completion event, returns the slot id
+fn exec_cmd(trb: TransferRequestBlock) -> Result<Option<usize>> {
+    // prevent the xhc interrupt from failing to lock the driver
+    let trb_ptr = arch::disabled_int(|| unsafe { XHC_DRIVER.try_lock() }?.push_cmd_ring(trb))?;
+
+    let start_ms = local_apic_timer::get_current_ms().unwrap_or(0);
+    loop {
+        let completed = arch::disabled_int(|| {
+            Ok::<_, Error>(unsafe { XHC_DRIVER.try_lock() }?.pop_completed_cmd(trb_ptr))
+        })?;
+
+        match completed {
+            Some((CompletionCode::Success, slot_id)) => return Ok(slot_id),
+            Some((comp_code, _)) => {
+                return Err(XhcDriverError::CommandFailedError(comp_code).into())
+            }
+            None => (),
+        }
+
+        let current_ms = local_apic_timer::get_current_ms().unwrap_or(0);
+        if current_ms.saturating_sub(start_ms) > CMD_TIMEOUT_MS {
+            return Err(XhcDriverError::CommandTimeoutError.into());
+        }
+    }
 }
 
 pub fn ring_doorbell(index: usize, value: u8) -> Result<()> {
-    unsafe { XHC_DRIVER.try_lock() }?.ring_doorbell(index, value);
-    Ok(())
+    // prevent the xhc interrupt from taking the driver while spinning
+    arch::disabled_int(|| {
+        unsafe { XHC_DRIVER.lock_timeout(LOCK_TIMEOUT_SPINS) }?.ring_doorbell(index, value);
+        Ok(())
+    })
+}
+
+pub fn pop_completed_ctrl_transfer(trb_ptr: u64) -> Result<bool> {
+    // prevent the xhc interrupt from failing to lock the driver
+    arch::disabled_int(|| {
+        let mut driver = unsafe { XHC_DRIVER.try_lock() }?;
+        let ptrs = &mut driver.completed_ctrl_transfer_trb_ptrs;
+        match ptrs.iter().position(|p| *p == trb_ptr) {
+            Some(i) => {
+                ptrs.remove(i);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })
 }
 
 pub fn scan_ports() -> Result<Vec<usize>> {
@@ -937,6 +1461,25 @@ pub fn alloc_address_to_device(port_id: usize) -> Result<UsbDevice> {
     unsafe { XHC_DRIVER.try_lock() }?.alloc_address_to_device(port_id)
 }
 
+// interrupts must be enabled to receive the command completion events
+pub fn alloc_address_to_downstream_device(downstream_port: DownstreamPort) -> Result<UsbDevice> {
+    let mut trb = TransferRequestBlock::default();
+    trb.set_trb_type(TransferRequestBlockType::EnableSlotCommand);
+    let slot_id = exec_cmd(trb)?.ok_or(Error::Failed("Slot ID was not assigned"))?;
+
+    let (trb, device) = arch::disabled_int(|| {
+        unsafe { XHC_DRIVER.try_lock() }?
+            .alloc_address_to_downstream_device(slot_id, downstream_port)
+    })?;
+    exec_cmd(trb)?;
+
+    Ok(device)
+}
+
+pub fn detach_port(port_id: usize) -> Result<()> {
+    unsafe { XHC_DRIVER.try_lock() }?.detach_port(port_id)
+}
+
 extern "x86-interrupt" fn poll_int_xhc_driver() {
     if let Ok(mut driver) = unsafe { XHC_DRIVER.try_lock() } {
         let _ = driver.poll_int();
@@ -1,5 +1,5 @@
 use super::context::input::InputContext;
-use crate::arch::addr::*;
+use crate::{arch::addr::*, mem::bitmap::MemoryFrameInfo};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ConfigState {
@@ -19,6 +19,8 @@ pub struct Port {
     pub config_state: ConfigState,
     pub input_context_base_virt_addr: VirtualAddress,
     pub output_context_base_virt_addr: VirtualAddress,
+    pub input_context_mem_frame_info: Option<MemoryFrameInfo>,
+    pub output_context_mem_frame_info: Option<MemoryFrameInfo>,
 }
 
 impl Port {
@@ -29,13 +31,28 @@ impl Port {
             config_state: ConfigState::NotConnected,
             input_context_base_virt_addr: VirtualAddress::default(),
             output_context_base_virt_addr: VirtualAddress::default(),
+            input_context_mem_frame_info: None,
+            output_context_mem_frame_info: None,
         }
     }
 
+    // root hub ports use 1..=255, devices behind hubs put the route string above them
+    pub fn downstream_port_id(root_hub_port_id: usize, route_string: u32) -> usize {
+        root_hub_port_id | ((route_string as usize) << 8)
+    }
+
     pub fn port_id(&self) -> usize {
         self.port_id
     }
 
+    pub fn root_hub_port_id(&self) -> usize {
+        self.port_id & 0xff
+    }
+
+    pub fn is_downstream(&self) -> bool {
+        self.port_id > 0xff
+    }
+
     pub fn read_input_context(&self) -> InputContext {
         self.input_context_base_virt_addr.read_volatile()
     }
@@ -12,6 +12,12 @@ pub struct StructuralParameters1 {
     pub num_of_ports: u8,
 }
 
+impl StructuralParameters1 {
+    pub fn max_intrs(&self) -> usize {
+        (self.num_of_intrs & 0x7ff) as usize
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
 pub struct StructuralParameters2(u32);
@@ -47,6 +53,25 @@ pub struct CapabilityRegisters {
     pub cap_params2: CapabilityParameters2,
 }
 
+impl CapabilityParameters1 {
+    // offset of the first extended capability from the capability registers (in dwords)
+    pub fn ext_caps_ptr(&self) -> usize {
+        (self.0 >> 16) as usize
+    }
+}
+
+impl StructuralParameters2 {
+    pub fn max_scratchpad_bufs(&self) -> usize {
+        let high = (self.0 >> 21) & 0x1f;
+        let low = (self.0 >> 27) & 0x1f;
+        (high << 5 | low) as usize
+    }
+
+    pub fn max_event_ring_seg_table_len(&self) -> usize {
+        1 << ((self.0 >> 4) & 0xf)
+    }
+}
+
 impl CapabilityRegisters {
     pub fn read(base_addr: VirtualAddress) -> Self {
         let mut data = [0; 8];
@@ -227,7 +252,7 @@ impl RuntimeRegitsers {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct InterrupterRegisterSet([u64; 4]);
 
@@ -329,6 +354,58 @@ impl InterrupterRegisterSet {
     }
 }
 
+pub const EXT_CAP_ID_USB_LEGACY_SUPPORT: u8 = 1;
+
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct ExtendedCapabilityHeader(u32);
+
+impl ExtendedCapabilityHeader {
+    pub fn cap_id(&self) -> u8 {
+        self.0 as u8
+    }
+
+    // offset of the next extended capability (in dwords), 0 is the end of the list
+    pub fn next_ptr(&self) -> usize {
+        ((self.0 >> 8) & 0xff) as usize
+    }
+}
+
+// USBLEGSUP
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct UsbLegacySupportCapability(u32);
+
+impl UsbLegacySupportCapability {
+    pub fn bios_owned(&self) -> bool {
+        (self.0 & 0x1_0000) != 0
+    }
+
+    pub fn set_bios_owned(&mut self, value: bool) {
+        self.0 = (self.0 & !0x1_0000) | ((value as u32) << 16);
+    }
+
+    pub fn os_owned(&self) -> bool {
+        (self.0 & 0x100_0000) != 0
+    }
+
+    pub fn set_os_owned(&mut self, value: bool) {
+        self.0 = (self.0 & !0x100_0000) | ((value as u32) << 24);
+    }
+}
+
+// USBLEGCTLSTS
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct UsbLegacySupportControlStatus(u32);
+
+impl UsbLegacySupportControlStatus {
+    // disable all SMIs and clear the SMI events (RW1C)
+    pub fn disable_smi(&mut self) {
+        self.0 = (self.0 & !0xe011) | 0xe000_0000;
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct EventRingSegmentTableEntry {
@@ -337,7 +414,7 @@ pub struct EventRingSegmentTableEntry {
     reserved1: [u8; 6],
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub enum PortSpeedIdValue {
     FullSpeed = 1,
@@ -473,3 +550,23 @@ impl DoorbellRegister {
         base_addr.write_volatile(*self);
     }
 }
+
+#[test_case]
+fn test_usb_legacy_support_capability() {
+    // capability id: 1, next: 0x10, BIOS owned
+    let header = ExtendedCapabilityHeader(0x0001_1001);
+    assert_eq!(header.cap_id(), EXT_CAP_ID_USB_LEGACY_SUPPORT);
+    assert_eq!(header.next_ptr(), 0x10);
+
+    let mut legsup = UsbLegacySupportCapability(0x0001_1001);
+    assert!(legsup.bios_owned());
+    assert!(!legsup.os_owned());
+    legsup.set_os_owned(true);
+    legsup.set_bios_owned(false);
+    assert_eq!(legsup.0, 0x0100_1001);
+
+    // SMI enables are cleared, SMI events are written 1 to clear
+    let mut ctrl_sts = UsbLegacySupportControlStatus(0x2000_e011);
+    ctrl_sts.disable_smi();
+    assert_eq!(ctrl_sts.0, 0xe000_0000);
+}
@@ -1,12 +1,19 @@
-use super::register::InterrupterRegisterSet;
-use crate::{device::usb::trb::*, error::Result, mem::bitmap, println};
-use alloc::boxed::Box;
+use super::register::{EventRingSegmentTableEntry, InterrupterRegisterSet};
+use crate::{
+    device::usb::trb::*,
+    error::Result,
+    mem::bitmap::{self, MemoryFrameInfo},
+    println,
+};
+use alloc::{boxed::Box, vec::Vec};
 use core::mem::size_of;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RingBufferError {
     UnsupportedRingBufferTypeError(RingBufferType),
     UnsupportedEventRingSegmentTableLengthError,
+    InvalidEventRingSegmentNumError(usize),
+    InvalidDequeuePointerError(u64),
     InvalidTransferRequestBlockError(usize),
     RingBufferSizeIsTooSmallError(usize),
 }
@@ -28,6 +35,8 @@ pub struct RingBuffer<const N: usize> {
     buf_type: RingBufferType,
     cycle_state: bool,
     enqueue_index: usize,
+    seg_num: usize,
+    data_buf_mem_frame_infos: Vec<MemoryFrameInfo>,
 }
 
 impl<const N: usize> RingBuffer<N> {
@@ -41,6 +50,8 @@ impl<const N: usize> RingBuffer<N> {
             buf_type,
             cycle_state: cycle_state_bit,
             enqueue_index: 0,
+            seg_num: 1,
+            data_buf_mem_frame_infos: Vec::new(),
         })
     }
 
@@ -48,14 +59,65 @@ impl<const N: usize> RingBuffer<N> {
         self.buf.as_ref().0.as_ptr()
     }
 
+    pub fn seg_num(&self) -> usize {
+        self.seg_num
+    }
+
+    pub fn seg_len(&self) -> usize {
+        self.buf_len() / self.seg_num
+    }
+
+    // split the event ring buffer into segments of the same length
+    pub fn set_seg_num(&mut self, seg_num: usize) -> Result<()> {
+        if self.buf_type != RingBufferType::EventRing {
+            return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
+        }
+
+        // a segment has at least 16 TRBs
+        if seg_num == 0 || self.buf_len() % seg_num != 0 || self.buf_len() / seg_num < 16 {
+            return Err(RingBufferError::InvalidEventRingSegmentNumError(seg_num).into());
+        }
+
+        self.seg_num = seg_num;
+
+        Ok(())
+    }
+
+    pub fn seg_table_entries(&self) -> Result<Vec<EventRingSegmentTableEntry>> {
+        if self.buf_type != RingBufferType::EventRing {
+            return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
+        }
+
+        let seg_size = self.seg_len() * size_of::<TransferRequestBlock>();
+        let mut entries = Vec::new();
+        for i in 0..self.seg_num {
+            let seg_base_addr = self.buf_ptr() as u64 + (i * seg_size) as u64;
+
+            // a segment must not cross a 64KiB boundary
+            if (seg_base_addr ^ (seg_base_addr + seg_size as u64 - 1)) >> 16 != 0 {
+                return Err(RingBufferError::InvalidEventRingSegmentNumError(self.seg_num).into());
+            }
+
+            let mut entry = EventRingSegmentTableEntry::default();
+            entry.ring_seg_base_addr = seg_base_addr;
+            entry.ring_seg_size = self.seg_len() as u16;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
     pub fn set_link_trb(&mut self) -> Result<()> {
         if self.buf_type == RingBufferType::EventRing {
             return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
         }
 
+        // the cycle bit is set when the producer reaches the Link TRB
         let mut trb = TransferRequestBlock::default();
         trb.set_trb_type(TransferRequestBlockType::Link);
         trb.param = self.buf_ptr() as u64;
+        trb.set_cycle_bit(!self.cycle_state);
+        trb.set_toggle_cycle(true);
 
         let buf_len = self.buf_len();
         self.buf_mut()[buf_len - 1] = trb;
@@ -78,15 +140,16 @@ impl<const N: usize> RingBuffer<N> {
         }
 
         let enqueue_index = self.enqueue_index;
-        let trb = &mut self.buf_mut()[enqueue_index];
-        trb.set_cycle_bit(!trb.cycle_bit());
+        let cycle_state = self.cycle_state;
+        self.buf_mut()[enqueue_index].set_cycle_bit(cycle_state);
 
         self.enqueue_index += 1;
 
         Ok(())
     }
 
-    pub fn push(&mut self, trb: TransferRequestBlock) -> Result<()> {
+    // returns the address of the pushed TRB
+    pub fn push(&mut self, trb: TransferRequestBlock) -> Result<u64> {
         if self.buf_type == RingBufferType::EventRing {
             return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
         }
@@ -103,7 +166,7 @@ impl<const N: usize> RingBuffer<N> {
         self.buf_mut()[enqueue_index] = trb;
         self.enqueue_index += 1;
 
-        Ok(())
+        Ok(self.buf_ptr() as u64 + (enqueue_index * size_of::<TransferRequestBlock>()) as u64)
     }
 
     pub fn pop(
@@ -114,14 +177,18 @@ impl<const N: usize> RingBuffer<N> {
             return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
         }
 
-        if int_reg_set.event_ring_seg_table_size() != 1 || int_reg_set.dequeue_erst_seg_index() != 0
-        {
+        if int_reg_set.event_ring_seg_table_size() as usize != self.seg_num {
             return Err(RingBufferError::UnsupportedEventRingSegmentTableLengthError.into());
         }
 
+        // the segments are contiguous, so the dequeue pointer moves to the next segment as is
         let trb_size = size_of::<TransferRequestBlock>();
         let mut dequeue_ptr = int_reg_set.event_ring_dequeue_ptr();
-        let mut index = (dequeue_ptr as usize - self.buf_ptr() as usize) / trb_size;
+        let mut index = (dequeue_ptr as usize)
+            .checked_sub(self.buf_ptr() as usize)
+            .map(|offset| offset / trb_size)
+            .filter(|i| *i < self.buf_len())
+            .ok_or(RingBufferError::InvalidDequeuePointerError(dequeue_ptr))?;
         let trb = self.buf_mut()[index];
 
         if trb.cycle_bit() != self.cycle_state {
@@ -138,6 +205,7 @@ impl<const N: usize> RingBuffer<N> {
         //println!("{:p}, index: {}", self.buf_ptr(), index);
         dequeue_ptr = self.buf_ptr() as u64 + (index * trb_size) as u64;
         int_reg_set.set_event_ring_dequeue_ptr(dequeue_ptr);
+        int_reg_set.set_dequeue_erst_seg_index((index / self.seg_len()) as u8);
         int_reg_set.set_event_handler_busy(false);
 
         Ok(trb)
@@ -156,6 +224,7 @@ impl<const N: usize> RingBuffer<N> {
             let data_buf_mem_frame_info = bitmap::alloc_mem_frame(1)?;
             bitmap::mem_clear(&data_buf_mem_frame_info)?;
             fill_trb.param = data_buf_mem_frame_info.frame_start_virt_addr()?.get();
+            self.data_buf_mem_frame_infos.push(data_buf_mem_frame_info);
             fill_trb.set_cycle_bit(if i < self.buf_len() - 3 {
                 self.cycle_state
             } else {
@@ -170,6 +239,14 @@ impl<const N: usize> RingBuffer<N> {
         Ok(())
     }
 
+    pub fn dealloc_data_bufs(&mut self) -> Result<()> {
+        for mem_frame_info in self.data_buf_mem_frame_infos.drain(..) {
+            bitmap::dealloc_mem_frame(mem_frame_info)?;
+        }
+
+        Ok(())
+    }
+
     pub fn debug(&mut self) {
         println!(
             "{:?}:, current: {}, start: 0x{:x}",
@@ -192,15 +269,140 @@ impl<const N: usize> RingBuffer<N> {
             return Err(RingBufferError::UnsupportedRingBufferTypeError(self.buf_type).into());
         }
 
+        // hand the Link TRB over with the current cycle state, the consumer follows it
+        // and toggles its cycle state (a single segment ring always toggles)
         let link_trb_index = self.buf_len() - 1;
         let cycle_state = self.cycle_state;
         let link_trb = &mut self.buf_mut()[link_trb_index];
-        link_trb.set_cycle_bit(!link_trb.cycle_bit());
-        // true -> toggle, false -> reset
-        link_trb.set_toggle_cycle(cycle_state);
+        link_trb.set_cycle_bit(cycle_state);
+        link_trb.set_toggle_cycle(true);
 
         self.cycle_state = !self.cycle_state;
 
         Ok(())
     }
 }
+
+#[test_case]
+fn test_transfer_ring_wrap() {
+    // walk the ring as the xHC does, returns the params of the consumed TRBs
+    fn consume_transfer_ring(
+        dequeue_ptr: &mut u64,
+        cycle_state: &mut bool,
+        link_trb_cnt: &mut usize,
+    ) -> Vec<u64> {
+        let mut params = Vec::new();
+        loop {
+            let trb = unsafe { (*dequeue_ptr as *const TransferRequestBlock).read_volatile() };
+            if trb.cycle_bit() != *cycle_state {
+                return params;
+            }
+
+            match trb.toggle_cycle() {
+                Some(toggle_cycle) => {
+                    *dequeue_ptr = trb.param;
+                    *cycle_state ^= toggle_cycle;
+                    *link_trb_cnt += 1;
+                }
+                None => {
+                    params.push(trb.param);
+                    *dequeue_ptr += size_of::<TransferRequestBlock>() as u64;
+                }
+            }
+        }
+    }
+
+    const N: usize = 16;
+    let mut ring_buf: RingBuffer<N> = RingBuffer::new(RingBufferType::TransferRing, true).unwrap();
+    ring_buf.set_link_trb().unwrap();
+
+    let buf_ptr = ring_buf.buf_ptr() as u64;
+    let mut dequeue_ptr = buf_ptr;
+    let mut cycle_state = true;
+    let mut link_trb_cnt = 0;
+
+    for i in 0..N * 2 {
+        let mut trb = TransferRequestBlock::default();
+        trb.set_trb_type(TransferRequestBlockType::Normal);
+        trb.param = i as u64;
+        let trb_ptr = ring_buf.push(trb).unwrap();
+        assert_ne!(
+            trb_ptr,
+            buf_ptr + ((N - 1) * size_of::<TransferRequestBlock>()) as u64
+        );
+
+        let params = consume_transfer_ring(&mut dequeue_ptr, &mut cycle_state, &mut link_trb_cnt);
+        assert_eq!(params, [i as u64]);
+        assert_eq!(
+            dequeue_ptr,
+            trb_ptr + size_of::<TransferRequestBlock>() as u64
+        );
+    }
+
+    // 2 * N TRBs wrap the ring of N - 1 TRBs twice
+    assert_eq!(link_trb_cnt, 2);
+    assert_eq!(cycle_state, ring_buf.cycle_state);
+    assert!(ring_buf.cycle_state);
+    assert_eq!(
+        dequeue_ptr,
+        buf_ptr + (ring_buf.enqueue_index * size_of::<TransferRequestBlock>()) as u64
+    );
+}
+
+#[test_case]
+fn test_event_ring_segments() {
+    const N: usize = 64;
+    let mut ring_buf: RingBuffer<N> = RingBuffer::new(RingBufferType::EventRing, true).unwrap();
+    assert!(ring_buf.set_seg_num(3).is_err());
+    assert!(ring_buf.set_seg_num(8).is_err());
+    ring_buf.set_seg_num(4).unwrap();
+
+    let buf_ptr = ring_buf.buf_ptr() as u64;
+    let trb_size = size_of::<TransferRequestBlock>() as u64;
+    let entries = ring_buf.seg_table_entries().unwrap();
+    assert_eq!(entries.len(), 4);
+    for (i, entry) in entries.iter().enumerate() {
+        assert_eq!(
+            entry.ring_seg_base_addr,
+            buf_ptr + (i * 16) as u64 * trb_size
+        );
+        assert_eq!(entry.ring_seg_size, 16);
+    }
+
+    let mut int_reg_set = InterrupterRegisterSet::default();
+    int_reg_set.set_event_ring_seg_table_size(4);
+    int_reg_set.set_event_ring_dequeue_ptr(buf_ptr);
+
+    // the xHC writes an event and the driver pops it, twice around the ring
+    let mut cycle_state = true;
+    for i in 0..N * 2 {
+        let mut trb = TransferRequestBlock::default();
+        trb.set_trb_type(TransferRequestBlockType::TransferEvent);
+        trb.set_cycle_bit(cycle_state);
+        trb.param = i as u64;
+        ring_buf.buf_mut()[i % N] = trb;
+
+        assert_eq!(ring_buf.pop(&mut int_reg_set).unwrap().param, i as u64);
+        // no new event
+        assert!(ring_buf.pop(&mut int_reg_set).is_err());
+
+        let next_index = (i + 1) % N;
+        assert_eq!(
+            int_reg_set.event_ring_dequeue_ptr(),
+            buf_ptr + next_index as u64 * trb_size
+        );
+        assert_eq!(
+            int_reg_set.dequeue_erst_seg_index() as usize,
+            next_index / 16
+        );
+
+        if next_index == 0 {
+            cycle_state = !cycle_state;
+        }
+    }
+
+    assert_eq!(ring_buf.cycle_state, cycle_state);
+
+    int_reg_set.set_event_ring_seg_table_size(1);
+    assert!(ring_buf.pop(&mut int_reg_set).is_err());
+}
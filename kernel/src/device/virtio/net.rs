@@ -9,9 +9,11 @@ use crate::{
     error::{Error, Result},
     idt,
     mem::{bitmap, paging::PAGE_SIZE},
+    net::{self, eth::EthernetFrame},
     util::mutex::Mutex,
 };
 use alloc::vec::Vec;
+use core::mem::size_of;
 use log::{debug, info};
 
 static mut VIRTIO_NET_DRIVER: Mutex<VirtioNetDriver> = Mutex::new(VirtioNetDriver::new());
@@ -63,13 +65,15 @@ impl ConfigurationField {
 
 // 5.1.6 Device Operation
 #[derive(Default)]
+#[repr(C, packed)]
 struct PacketHeader {
     flags: u8,
     gso_type: u8,
     hdr_len: u16,
     gso_size: u16,
+    csum_start: u16,
     csum_offset: u16,
-    num_buffers: u16,
+    // num_buffers: u16,      // VIRTIO_NET_F_MRG_RXBUF or VIRTIO 1.0
     // hash_value: u32,       // VIRTIO_NET_F_HASH_REPORT
     // hash_report: u32,      // VIRTIO_NET_F_HASH_REPORT
     // padding_reserved: u16, // VIRTIO_NET_F_HASH_REPORT
@@ -130,13 +134,37 @@ impl VirtioNetDriver {
             .ok_or(Error::Failed("TX queue is not initialized"))
     }
 
-    fn send_packet(&mut self) -> Result<()> {
-        let tx_queue = self.tx_queue()?;
-        let mut header = PacketHeader::default();
-        header.gso_type = PacketHeader::GSO_NONE;
+    fn send_packet(&mut self, eth_frame: EthernetFrame) -> Result<()> {
+        self.write(&eth_frame.to_vec())
+    }
 
-        tx_queue.write_data(&header)?;
-        self.io_register()?.write_queue_notify(Self::TX_QUEUE_INDEX);
+    fn receive_packets(&mut self) -> Result<Vec<Vec<u8>>> {
+        let rx_queue = self.rx_queue()?;
+        let mut packets = Vec::new();
+
+        while let Some((desc_index, len)) = rx_queue.pop_used() {
+            let desc = &rx_queue.descs_mut()[desc_index as usize];
+
+            if len > size_of::<PacketHeader>() {
+                let data = desc.data_slice(len);
+                packets.push(data[size_of::<PacketHeader>()..].to_vec());
+            }
+
+            // give the buffer back to the device
+            rx_queue.push_available(desc_index);
+        }
+
+        if !packets.is_empty() {
+            self.io_register()?.write_queue_notify(Self::RX_QUEUE_INDEX);
+        }
+
+        Ok(packets)
+    }
+
+    fn receive_eth_frame(&mut self, eth_frame: EthernetFrame) -> Result<()> {
+        if let Some(reply_frame) = net::receive_eth_frame(&eth_frame)? {
+            self.write(&reply_frame)?;
+        }
 
         Ok(())
     }
@@ -190,7 +218,7 @@ impl DeviceDriverFunction for VirtioNetDriver {
     fn attach(&mut self, _arg: Self::AttachInput) -> Result<()> {
         let (bus, device, func) = self.pci_device_bdf.ok_or("Device driver is not probed")?;
 
-        device::pci_bus::configure_device(bus, device, func, |d| {
+        device::pci_bus::attach_device(bus, device, func, |d| {
             let conf_space = d.read_conf_space_non_bridge_field()?;
             let bars = conf_space.get_bars()?;
             let (_, mmio_bar) = bars
@@ -211,10 +239,12 @@ impl DeviceDriverFunction for VirtioNetDriver {
                 self.io_register()?.read_device_status() | DeviceStatus::Driver as u8,
             );
 
-            // enable device supported features + VIRTIO_NET_F_MAC
-            self.io_register()?.write_driver_features(
-                self.io_register()?.read_device_features() | NetworkDeviceFeature::Mac as u32,
-            );
+            // negotiate VIRTIO_NET_F_MAC only
+            let mac_feature = 1 << NetworkDeviceFeature::Mac as u32;
+            if self.io_register()?.read_device_features() & mac_feature == 0 {
+                return Err(Error::Failed("VIRTIO_NET_F_MAC is not supported"));
+            }
+            self.io_register()?.write_driver_features(mac_feature);
             self.io_register()?.write_device_status(
                 self.io_register()?.read_device_status() | DeviceStatus::FeaturesOk as u8,
             );
@@ -238,15 +268,15 @@ impl DeviceDriverFunction for VirtioNetDriver {
 
             // configure rx virtqueue
             let rx_queue = self.rx_queue.as_mut().unwrap();
-            rx_queue.available_header_mut().index = rx_queue.queue_size() as u16;
 
-            for (i, desc) in rx_queue.descs_mut().iter_mut().enumerate() {
+            for i in 0..rx_queue.queue_size() {
                 let mem_frame_info = bitmap::alloc_mem_frame(1)?;
-                desc.addr = mem_frame_info.frame_start_virt_addr()?.get();
+                let desc = &mut rx_queue.descs_mut()[i];
+                desc.addr = mem_frame_info.frame_start_phys_addr.get();
                 desc.len = mem_frame_info.frame_size as u32;
                 desc.flags = 2; // device write only
                 desc.next = 0;
-                rx_queue.available_elements_mut()[i] = i as u16;
+                rx_queue.push_available(i as u16);
             }
             self.io_register()?.write_queue_notify(Self::RX_QUEUE_INDEX);
 
@@ -255,18 +285,23 @@ impl DeviceDriverFunction for VirtioNetDriver {
 
             for desc in tx_queue.descs_mut() {
                 let mem_frame_info = bitmap::alloc_mem_frame(1)?;
-                desc.addr = mem_frame_info.frame_start_virt_addr()?.get();
+                desc.addr = mem_frame_info.frame_start_phys_addr.get();
                 desc.len = mem_frame_info.frame_size as u32;
                 desc.flags = 0; // device read only
                 desc.next = 0;
             }
 
-            self.send_packet()?;
             debug!("{}: {:?}", self.device_driver_info.name, self.conf_field);
 
             Ok(())
         })?;
 
+        let conf_field = self
+            .conf_field
+            .as_ref()
+            .ok_or(Error::Failed("Configuration field is not read"))?;
+        net::set_my_mac_addr(conf_field.mac.into())?;
+
         self.device_driver_info.attached = true;
         Ok(())
     }
@@ -278,21 +313,21 @@ impl DeviceDriverFunction for VirtioNetDriver {
             return Err(Error::Failed("Device driver is not attached"));
         }
 
-        match self.io_register()?.interrupt_type() {
-            Some(InterruptType::DeviceConfiguration) => {
-                info!("{}: device configuration updated", name);
-            }
-            Some(InterruptType::Queue) => {
-                // TODO
-                arch::disabled_int(|| {
-                    let rx_queue = self.rx_queue()?;
-                    let data = rx_queue.read_data()?;
-                    info!("{}: data: {:?}", name, data);
-
-                    Result::Ok(())
-                })?;
-            }
-            None => (),
+        // reading ISR status also acknowledges the interrupt
+        if let Some(InterruptType::DeviceConfiguration) = self.io_register()?.interrupt_type() {
+            info!("{}: device configuration updated", name);
+        }
+
+        for packet in self.receive_packets()? {
+            // the device does not pass the FCS
+            let eth_frame = EthernetFrame::parse(&packet, false)?;
+            debug!("{}: Received packet: {:?}", name, eth_frame);
+            self.receive_eth_frame(eth_frame)?;
+        }
+
+        // frames queued by the network stack
+        while let Some(frame) = net::pop_eth_tx_frame()? {
+            self.write(&frame)?;
         }
 
         Ok(())
@@ -306,8 +341,37 @@ impl DeviceDriverFunction for VirtioNetDriver {
         unimplemented!()
     }
 
-    fn write(&mut self, _data: &[u8]) -> Result<()> {
-        unimplemented!()
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        let header = PacketHeader {
+            gso_type: PacketHeader::GSO_NONE,
+            ..Default::default()
+        };
+        let header_len = size_of::<PacketHeader>();
+
+        if header_len + data.len() > PAGE_SIZE {
+            return Err(Error::Failed("Data too long"));
+        }
+
+        // reclaim transmitted buffers
+        let tx_queue = self.tx_queue()?;
+        while tx_queue.pop_used().is_some() {}
+
+        // descriptors are used in order, so the next one is free
+        // unless all of them are still owned by the device
+        if tx_queue.in_flight_len() >= tx_queue.queue_size() {
+            return Err(Error::Failed("All TX descriptors are in use"));
+        }
+
+        let desc_index = tx_queue.next_available_desc_index();
+        let desc = &mut tx_queue.descs_mut()[desc_index];
+
+        desc.write_data(&header);
+        desc.data_slice_mut(header_len + data.len())[header_len..].copy_from_slice(data);
+        desc.len = (header_len + data.len()) as u32;
+        tx_queue.push_available(desc_index as u16);
+
+        self.io_register()?.write_queue_notify(Self::TX_QUEUE_INDEX);
+        Ok(())
     }
 }
 
@@ -320,6 +384,16 @@ pub fn probe_and_attach() -> Result<()> {
     arch::disabled_int(|| {
         let mut driver = unsafe { VIRTIO_NET_DRIVER.try_lock() }?;
         driver.probe()?;
+
+        // the device is optional, so not an error
+        if driver.pci_device_bdf.is_none() {
+            info!(
+                "{}: Device was not found",
+                driver.get_device_driver_info()?.name
+            );
+            return Ok(());
+        }
+
         driver.attach(())?;
         info!("{}: Attached!", driver.get_device_driver_info()?.name);
         Result::Ok(())
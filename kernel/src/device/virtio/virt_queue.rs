@@ -8,7 +8,9 @@ use crate::{
 };
 use core::{
     mem::size_of,
+    ptr::read_volatile,
     slice::{from_raw_parts, from_raw_parts_mut},
+    sync::atomic::{fence, Ordering},
 };
 
 #[derive(Debug, Default)]
@@ -31,6 +33,14 @@ impl QueueDescriptor {
             .copy_from_nonoverlapping(data as *const T, 1);
     }
 
+    pub fn data_slice(&self, len: usize) -> &[u8] {
+        unsafe { from_raw_parts(self.virt_addr().as_ptr(), len) }
+    }
+
+    pub fn data_slice_mut(&self, len: usize) -> &mut [u8] {
+        unsafe { from_raw_parts_mut(self.virt_addr().as_ptr_mut(), len) }
+    }
+
     fn virt_addr(&self) -> VirtualAddress {
         self.addr.into()
     }
@@ -59,6 +69,7 @@ pub struct Queue {
     mem_frame_info: MemoryFrameInfo,
     base_virt_addr: VirtualAddress,
     queue_size: usize,
+    last_used_index: u16,
 }
 
 impl Drop for Queue {
@@ -91,32 +102,44 @@ impl Queue {
             mem_frame_info,
             base_virt_addr: mem_frame_info.frame_start_virt_addr()?,
             queue_size,
+            last_used_index: 0,
         })
     }
 
-    pub fn write_data<T>(&mut self, data: &T) -> Result<()> {
-        let index = self.available_header_mut().index as usize % self.queue_size;
-        let desc = &mut self.descs_mut()[index];
+    // index of the descriptor that will be made available next
+    pub fn next_available_desc_index(&self) -> usize {
+        self.available_header_mut().index as usize % self.queue_size
+    }
 
-        if size_of::<T>() > desc.len as usize {
-            return Err(Error::Failed("Data too long"));
-        }
+    // descriptors made available and not returned to the used ring yet
+    pub fn in_flight_len(&self) -> usize {
+        self.available_header_mut()
+            .index
+            .wrapping_sub(self.last_used_index) as usize
+    }
 
-        desc.write_data(data);
-        self.available_elements_mut()[index] = index as u16;
-        self.available_header_mut().index = (index + 1) as u16;
-        Ok(())
+    pub fn push_available(&mut self, desc_index: u16) {
+        let index = self.available_header_mut().index;
+        self.available_elements_mut()[index as usize % self.queue_size] = desc_index;
+
+        // publish the element before updating the index
+        fence(Ordering::SeqCst);
+        self.available_header_mut().index = index.wrapping_add(1);
     }
 
-    pub fn read_data(&mut self) -> Result<&[u8]> {
-        let index = self.used_header_mut().index as usize % self.queue_size;
-        let desc = &mut self.descs_mut()[self.used_elements_mut()[index].id as usize];
+    // descriptor index, written length
+    pub fn pop_used(&mut self) -> Option<(u16, usize)> {
+        let used_index = unsafe { read_volatile(&self.used_header_mut().index) };
+        if used_index == self.last_used_index {
+            return None;
+        }
 
-        let data_len = self.used_elements_mut()[index].len as usize;
-        let data_ptr = desc.virt_addr().as_ptr();
+        fence(Ordering::SeqCst);
+        let elem = &self.used_elements_mut()[self.last_used_index as usize % self.queue_size];
+        let used = (elem.id as u16, elem.len as usize);
+        self.last_used_index = self.last_used_index.wrapping_add(1);
 
-        self.used_header_mut().index = (index + 1) as u16;
-        Ok(unsafe { from_raw_parts(data_ptr, data_len) })
+        Some(used)
     }
 
     pub fn descs_mut(&self) -> &mut [QueueDescriptor] {
@@ -4,16 +4,20 @@ use crate::{
         console::ConsoleError,
         usb::{
             bus::{device::UsbDeviceError, UsbBusDriverError},
+            hub::HubError,
+            mass_storage::MassStorageError,
             xhc::{ringbuf::RingBufferError, XhcDriverError},
         },
     },
-    fs::vfs::VirtualFileSystemError,
+    fs::{file::png::PngImageError, vfs::VirtualFileSystemError},
     graphics::{
         font::FontError, frame_buf::FrameBufferError, frame_buf_console::FrameBufferConsoleError,
         multi_layer::LayerError, simple_window_manager::SimpleWindowManagerError,
     },
     mem::{bitmap::BitmapMemoryManagerError, paging::PageManagerError},
-    util::{ascii::AsciiCodeError, fifo::FifoError, lifo::LifoError, mutex::MutexError},
+    util::{
+        ascii::AsciiCodeError, fifo::FifoError, lifo::LifoError, mutex::MutexError, zlib::ZlibError,
+    },
 };
 use common::elf::Elf64Error;
 
@@ -31,6 +35,8 @@ pub enum Error {
     ConsoleError(ConsoleError),
     UsbBusDriverError(UsbBusDriverError),
     UsbDeviceError(UsbDeviceError),
+    MassStorageError(MassStorageError),
+    HubError(HubError),
     XhcDriverError(XhcDriverError),
     RingBufferError(RingBufferError),
     FifoError(FifoError),
@@ -40,6 +46,8 @@ pub enum Error {
     Elf64Error(Elf64Error),
     SimpleWindowManagerError(SimpleWindowManagerError),
     AcpiError(AcpiError),
+    ZlibError(ZlibError),
+    PngImageError(PngImageError),
 }
 
 impl From<&'static str> for Error {
@@ -114,6 +122,18 @@ impl From<UsbDeviceError> for Error {
     }
 }
 
+impl From<MassStorageError> for Error {
+    fn from(err: MassStorageError) -> Self {
+        Self::MassStorageError(err)
+    }
+}
+
+impl From<HubError> for Error {
+    fn from(err: HubError) -> Self {
+        Self::HubError(err)
+    }
+}
+
 impl From<XhcDriverError> for Error {
     fn from(err: XhcDriverError) -> Self {
         Self::XhcDriverError(err)
@@ -162,4 +182,16 @@ impl From<AcpiError> for Error {
     }
 }
 
+impl From<ZlibError> for Error {
+    fn from(err: ZlibError) -> Self {
+        Self::ZlibError(err)
+    }
+}
+
+impl From<PngImageError> for Error {
+    fn from(err: PngImageError) -> Self {
+        Self::PngImageError(err)
+    }
+}
+
 pub type Result<T> = core::result::Result<T, Error>;
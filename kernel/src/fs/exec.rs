@@ -1,20 +1,62 @@
-use super::vfs;
-use crate::{arch::task, error::Result};
+use super::vfs::{self, file_desc::FileDescriptorNumber};
+use crate::{
+    arch::task::{self, ProcessInfo},
+    error::Result,
+};
+use alloc::vec::Vec;
 use common::elf::Elf64;
 use log::info;
 
-pub fn exec_elf(elf_path: &str, args: &[&str]) -> Result<()> {
-    let fd_num = vfs::open_file(elf_path)?;
+fn read_elf_data(elf_path: &str) -> Result<Vec<u8>> {
+    let fd_num = vfs::open_file(&task::resolve_path(elf_path)?)?;
     let elf_data = vfs::read_file(&fd_num)?;
+    vfs::close_file(&fd_num)?;
+
+    Ok(elf_data)
+}
+
+// return the exit code
+pub fn exec_elf(elf_path: &str, args: &[&str]) -> Result<u64> {
+    let elf_data = read_elf_data(elf_path)?;
     let elf64 = match Elf64::new(&elf_data) {
         Ok(e) => e,
         Err(err) => return Err(err.into()),
     };
 
-    vfs::close_file(&fd_num)?;
-
     let exit_code = task::exec_user_task(elf64, elf_path, args)?;
     info!("exec: Exited (code: {})", exit_code);
 
-    Ok(())
+    Ok(exit_code)
+}
+
+// run the program in the background and return the process id
+// stdin / stdout can be redirected to the file descriptors (e.g. pipe ends)
+pub fn spawn_elf(
+    elf_path: &str,
+    args: &[&str],
+    stdin: Option<FileDescriptorNumber>,
+    stdout: Option<FileDescriptorNumber>,
+) -> Result<usize> {
+    let elf_data = read_elf_data(elf_path)?;
+    let elf64 = match Elf64::new(&elf_data) {
+        Ok(e) => e,
+        Err(err) => return Err(err.into()),
+    };
+
+    let pid = task::spawn_user_task(elf64, elf_path, args, stdin, stdout)?;
+    info!("exec: Spawned (pid: {})", pid);
+
+    Ok(pid)
+}
+
+// wait for the background process to exit and return the exit code
+pub fn wait(pid: usize) -> Result<u64> {
+    let exit_code = task::wait_process(pid)?;
+    info!("exec: Exited (pid: {}, code: {})", pid, exit_code);
+
+    Ok(exit_code)
+}
+
+pub fn processes() -> Vec<ProcessInfo> {
+    task::processes()
 }
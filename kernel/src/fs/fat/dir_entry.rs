@@ -1,5 +1,11 @@
 use alloc::{string::String, vec::Vec};
 
+const LFN_CHARS_PER_ENTRY: usize = 13;
+const LFN_LAST_ENTRY_FLAG: u8 = 0x40;
+
+pub const DOT_SF_NAME: [u8; 11] = *b".          ";
+pub const DOT_DOT_SF_NAME: [u8; 11] = *b"..         ";
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Attribute {
@@ -35,10 +41,82 @@ pub trait LongFileNameEntry {
 pub struct DirectoryEntry([u8; 32]);
 
 impl DirectoryEntry {
+    pub fn new_sf(
+        sf_name: [u8; 11],
+        attr: Attribute,
+        first_cluster_num: usize,
+        file_size: usize,
+    ) -> Self {
+        let mut raw = [0; 32];
+        raw[0..11].copy_from_slice(&sf_name);
+        raw[11] = attr as u8;
+
+        let mut entry = Self(raw);
+        entry.set_first_cluster_num(first_cluster_num);
+        entry.set_file_size(file_size);
+        entry
+    }
+
+    // long file name entries are placed before the short file name entry in reverse order
+    pub fn new_lfn_entries(name: &str, sf_name: &[u8; 11]) -> Vec<Self> {
+        let checksum = sf_name_checksum(sf_name);
+        let mut utf16_name: Vec<u16> = name.encode_utf16().collect();
+
+        if utf16_name.len() % LFN_CHARS_PER_ENTRY != 0 {
+            utf16_name.push(0x0);
+        }
+
+        while utf16_name.len() % LFN_CHARS_PER_ENTRY != 0 {
+            utf16_name.push(0xffff);
+        }
+
+        let entries_len = utf16_name.len() / LFN_CHARS_PER_ENTRY;
+        let mut entries = Vec::with_capacity(entries_len);
+
+        for (i, chars) in utf16_name.chunks(LFN_CHARS_PER_ENTRY).enumerate() {
+            let mut raw = [0; 32];
+            raw[0] = (i + 1) as u8;
+            if i + 1 == entries_len {
+                raw[0] |= LFN_LAST_ENTRY_FLAG;
+            }
+            raw[11] = Attribute::LongFileName as u8;
+            raw[13] = checksum;
+
+            let offsets = (1..11)
+                .step_by(2)
+                .chain((14..26).step_by(2))
+                .chain((28..32).step_by(2));
+            for (c, offset) in chars.iter().zip(offsets) {
+                raw[offset..offset + 2].copy_from_slice(&c.to_le_bytes());
+            }
+
+            entries.push(Self(raw));
+        }
+
+        entries.reverse();
+        entries
+    }
+
     pub fn raw(&self) -> [u8; 32] {
         self.0
     }
 
+    pub fn sf_name_raw(&self) -> [u8; 11] {
+        let mut sf_name = [0; 11];
+        sf_name.copy_from_slice(&self.0[0..11]);
+        sf_name
+    }
+
+    pub fn set_first_cluster_num(&mut self, cluster_num: usize) {
+        let cluster_num = cluster_num as u32;
+        self.0[20..22].copy_from_slice(&((cluster_num >> 16) as u16).to_le_bytes());
+        self.0[26..28].copy_from_slice(&(cluster_num as u16).to_le_bytes());
+    }
+
+    pub fn set_file_size(&mut self, file_size: usize) {
+        self.0[28..32].copy_from_slice(&(file_size as u32).to_le_bytes());
+    }
+
     pub fn attr(&self) -> Option<Attribute> {
         match self.raw()[11] {
             0x01 => Some(Attribute::ReadOnly),
@@ -92,6 +170,12 @@ impl DirectoryEntry {
     }
 }
 
+impl From<[u8; 32]> for DirectoryEntry {
+    fn from(raw: [u8; 32]) -> Self {
+        Self(raw)
+    }
+}
+
 impl ShortFileNameEntry for DirectoryEntry {
     fn sf_name(&self) -> Option<String> {
         match self.attr() {
@@ -150,3 +234,42 @@ impl LongFileNameEntry for DirectoryEntry {
         Some(String::from_utf16_lossy(&utf16_buf).replace("\0", ""))
     }
 }
+
+pub fn sf_name_checksum(sf_name: &[u8; 11]) -> u8 {
+    sf_name.iter().fold(0u8, |sum, &c| {
+        ((sum & 1) << 7).wrapping_add(sum >> 1).wrapping_add(c)
+    })
+}
+
+// generate "BASENA~N.EXT" style short file name
+pub fn gen_sf_name(name: &str, num: usize) -> [u8; 11] {
+    fn to_sf_char(c: char) -> u8 {
+        match c.to_ascii_uppercase() {
+            c @ ('A'..='Z' | '0'..='9' | '_' | '-') => c as u8,
+            _ => b'_',
+        }
+    }
+
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) if !base.is_empty() => (base, ext),
+        _ => (name, ""),
+    };
+
+    let mut sf_name = [b' '; 11];
+    let tail = format!("~{}", num);
+    let base: Vec<u8> = base
+        .chars()
+        .filter(|c| *c != ' ' && *c != '.')
+        .map(to_sf_char)
+        .take(8 - tail.len())
+        .collect();
+
+    sf_name[..base.len()].copy_from_slice(&base);
+    sf_name[base.len()..base.len() + tail.len()].copy_from_slice(tail.as_bytes());
+
+    for (i, c) in ext.chars().map(to_sf_char).take(3).enumerate() {
+        sf_name[8 + i] = c;
+    }
+
+    sf_name
+}
@@ -9,3 +9,23 @@ pub struct FsInfoSector {
     reserved1: [u8; 12],
     sign2: u32,
 }
+
+impl FsInfoSector {
+    pub const UNKNOWN: u32 = 0xffff_ffff;
+
+    pub fn free_cnt(&self) -> u32 {
+        u32::from_le_bytes(self.free_cnt)
+    }
+
+    pub fn set_free_cnt(&mut self, free_cnt: u32) {
+        self.free_cnt = free_cnt.to_le_bytes();
+    }
+
+    pub fn next_free(&self) -> u32 {
+        u32::from_le_bytes(self.next_free)
+    }
+
+    pub fn set_next_free(&mut self, next_free: u32) {
+        self.next_free = next_free.to_le_bytes();
+    }
+}
@@ -1,11 +1,19 @@
 use self::{
-    boot_sector::BootSector, dir_entry::DirectoryEntry, file_allocation_table::ClusterType,
+    boot_sector::BootSector,
+    dir_entry::{Attribute, DirectoryEntry, EntryType, LongFileNameEntry, ShortFileNameEntry},
+    file_allocation_table::ClusterType,
     fs_info_sector::FsInfoSector,
 };
-use crate::arch::addr::VirtualAddress;
-use alloc::vec::Vec;
+use crate::{
+    arch::addr::VirtualAddress,
+    error::{Error, Result},
+};
+use alloc::{collections::VecDeque, string::String, vec::Vec};
 use core::mem::size_of;
 
+const FAT32_ENTRY_MASK: u32 = 0x0fff_ffff;
+const FAT32_END_OF_CHAIN: u32 = 0x0fff_ffff;
+
 pub mod boot_sector;
 pub mod dir_entry;
 pub mod file_allocation_table;
@@ -35,20 +43,23 @@ impl FatVolume {
     }
 
     pub fn fs_info_sector(&self) -> Option<&FsInfoSector> {
+        let virt_addr = self.fs_info_sector_virt_addr()?;
+        Some(unsafe { &*(virt_addr.as_ptr() as *const FsInfoSector) })
+    }
+
+    fn fs_info_sector_mut(&mut self) -> Option<&mut FsInfoSector> {
+        let virt_addr = self.fs_info_sector_virt_addr()?;
+        Some(unsafe { &mut *(virt_addr.as_ptr_mut() as *mut FsInfoSector) })
+    }
+
+    fn fs_info_sector_virt_addr(&self) -> Option<VirtualAddress> {
         match self.fat_type() {
             FatType::Fat32 => {
                 let boot_sector = self.boot_sector();
                 let fat32_other_field = boot_sector.fat32_other_field().unwrap();
-                let fs_info_sector = unsafe {
-                    &*(self
-                        .volume_start_virt_addr
-                        .offset(
-                            fat32_other_field.fs_info_sector_num() * boot_sector.bytes_per_sector(),
-                        )
-                        .as_ptr() as *const FsInfoSector)
-                };
-
-                Some(fs_info_sector)
+                Some(self.volume_start_virt_addr.offset(
+                    fat32_other_field.fs_info_sector_num() * boot_sector.bytes_per_sector(),
+                ))
             }
             _ => None,
         }
@@ -92,6 +103,53 @@ impl FatVolume {
         entries
     }
 
+    // read up to len bytes from the offset in the cluster chain
+    pub fn read_chained_clusters(
+        &self,
+        start_cluster_num: usize,
+        offset: usize,
+        len: usize,
+    ) -> Vec<u8> {
+        let cluster_size = self.cluster_size();
+        let mut bytes = Vec::with_capacity(len);
+        let mut current_cluster_num = start_cluster_num;
+        let mut cluster_offset = offset;
+
+        // skip the clusters before the offset
+        while cluster_offset >= cluster_size {
+            match self.next_cluster_num(current_cluster_num) {
+                Some(ClusterType::Data(next_cluster_num)) => current_cluster_num = next_cluster_num,
+                _ => return bytes,
+            }
+            cluster_offset -= cluster_size;
+        }
+
+        while bytes.len() < len {
+            if current_cluster_num < 2 || current_cluster_num >= self.clusters_cnt() {
+                break;
+            }
+
+            let read_len = (cluster_size - cluster_offset).min(len - bytes.len());
+            let data = unsafe {
+                core::slice::from_raw_parts(
+                    self.volume_start_virt_addr
+                        .offset(self.cluster_offset(current_cluster_num) + cluster_offset)
+                        .as_ptr::<u8>(),
+                    read_len,
+                )
+            };
+            bytes.extend_from_slice(data);
+            cluster_offset = 0;
+
+            match self.next_cluster_num(current_cluster_num) {
+                Some(ClusterType::Data(next_cluster_num)) => current_cluster_num = next_cluster_num,
+                _ => break,
+            }
+        }
+
+        bytes
+    }
+
     fn dir_entries(&self, cluster_num: usize) -> Vec<&DirectoryEntry> {
         let boot_sector = self.boot_sector();
         let mut entries = Vec::with_capacity(self.dir_entries_per_cluster());
@@ -122,6 +180,311 @@ impl FatVolume {
         entries
     }
 
+    pub fn alloc_cluster(&mut self) -> Result<usize> {
+        let max_cluster_num = self.max_cluster_num();
+
+        // start scanning from the FSInfo hint
+        let hint = match self.fs_info_sector() {
+            Some(fs_info_sector) => fs_info_sector.next_free() as usize,
+            None => 2,
+        };
+        let hint = if (2..max_cluster_num).contains(&hint) {
+            hint
+        } else {
+            2
+        };
+
+        let cluster_num = (hint..max_cluster_num)
+            .chain(2..hint)
+            .find(|&n| self.next_cluster_num(n) == Some(ClusterType::Free))
+            .ok_or(Error::Failed("No free cluster"))?;
+
+        self.write_fat_entry(cluster_num, FAT32_END_OF_CHAIN);
+
+        if let Some(fs_info_sector) = self.fs_info_sector_mut() {
+            let free_cnt = fs_info_sector.free_cnt();
+            if free_cnt != FsInfoSector::UNKNOWN && free_cnt > 0 {
+                fs_info_sector.set_free_cnt(free_cnt - 1);
+            }
+            fs_info_sector.set_next_free(cluster_num as u32 + 1);
+        }
+
+        // clear cluster
+        let cluster_size = self.cluster_size();
+        unsafe {
+            self.volume_start_virt_addr
+                .offset(self.cluster_offset(cluster_num))
+                .as_ptr_mut::<u8>()
+                .write_bytes(0, cluster_size);
+        }
+
+        Ok(cluster_num)
+    }
+
+    // write entries into free slots of the directory, extending its cluster chain if needed
+    pub fn append_dir_entries(
+        &mut self,
+        dir_cluster_num: usize,
+        entries: &[DirectoryEntry],
+    ) -> Result<()> {
+        let entries_per_cluster = self.dir_entries_per_cluster();
+        let mut clusters = self.cluster_chain(dir_cluster_num);
+        let mut slots: Vec<(usize, usize)> = Vec::new();
+
+        'scan: for &cluster_num in clusters.iter() {
+            for (i, entry) in self.dir_entries(cluster_num).iter().enumerate() {
+                match entry.entry_type() {
+                    EntryType::Null | EntryType::Unused => slots.push((cluster_num, i)),
+                    _ => slots.clear(),
+                }
+
+                if slots.len() == entries.len() {
+                    break 'scan;
+                }
+            }
+        }
+
+        while slots.len() < entries.len() {
+            let last_cluster_num = *clusters.last().unwrap();
+            let new_cluster_num = self.alloc_cluster()?;
+            self.write_fat_entry(last_cluster_num, new_cluster_num as u32);
+            clusters.push(new_cluster_num);
+
+            for i in 0..entries_per_cluster.min(entries.len() - slots.len()) {
+                slots.push((new_cluster_num, i));
+            }
+        }
+
+        for (&(cluster_num, index), entry) in slots.iter().zip(entries) {
+            self.write_dir_entry(cluster_num, index, *entry);
+        }
+
+        Ok(())
+    }
+
+    // write entries across the cluster chain, extending it if needed
+    pub fn write_chained_dir_entries(
+        &mut self,
+        start_cluster_num: usize,
+        entries: &[DirectoryEntry],
+    ) -> Result<()> {
+        let entries_per_cluster = self.dir_entries_per_cluster();
+        let mut clusters = self.cluster_chain(start_cluster_num);
+
+        for (i, chunk) in entries.chunks(entries_per_cluster).enumerate() {
+            if i == clusters.len() {
+                let new_cluster_num = self.alloc_cluster()?;
+                self.write_fat_entry(clusters[i - 1], new_cluster_num as u32);
+                clusters.push(new_cluster_num);
+            }
+
+            for (j, entry) in chunk.iter().enumerate() {
+                self.write_dir_entry(clusters[i], j, *entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    // returns first cluster number of the file data
+    pub fn create_file(
+        &mut self,
+        dir_cluster_num: usize,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<usize> {
+        if self.find_dir_entry(dir_cluster_num, name).is_some() {
+            return Err(Error::Failed("The file or directory already exists"));
+        }
+
+        let cluster_num = if bytes.is_empty() {
+            0
+        } else {
+            let cluster_num = self.alloc_cluster()?;
+            let data_entries: Vec<DirectoryEntry> = bytes
+                .chunks(size_of::<DirectoryEntry>())
+                .map(|chunk| {
+                    let mut raw = [0; 32];
+                    raw[..chunk.len()].copy_from_slice(chunk);
+                    raw.into()
+                })
+                .collect();
+            self.write_chained_dir_entries(cluster_num, &data_entries)?;
+            cluster_num
+        };
+
+        let sf_name = self.unique_sf_name(dir_cluster_num, name);
+        let mut entries = DirectoryEntry::new_lfn_entries(name, &sf_name);
+        entries.push(DirectoryEntry::new_sf(
+            sf_name,
+            Attribute::Archive,
+            cluster_num,
+            bytes.len(),
+        ));
+        self.append_dir_entries(dir_cluster_num, &entries)?;
+
+        Ok(cluster_num)
+    }
+
+    // mark entries as unused and free the data clusters
+    pub fn remove_dir_entry(&mut self, dir_cluster_num: usize, name: &str) -> Result<()> {
+        let (slots, sf_entry) = self
+            .find_dir_entry(dir_cluster_num, name)
+            .ok_or(Error::Failed("The file or directory does not exist"))?;
+
+        for (cluster_num, index) in slots {
+            let mut raw = self.dir_entries(cluster_num)[index].raw();
+            raw[0] = 0xe5;
+            self.write_dir_entry(cluster_num, index, raw.into());
+        }
+
+        let first_cluster_num = sf_entry.first_cluster_num();
+        if first_cluster_num >= 2 {
+            self.free_cluster_chain(first_cluster_num);
+        }
+
+        Ok(())
+    }
+
+    pub fn free_cluster_chain(&mut self, start_cluster_num: usize) {
+        let clusters = self.cluster_chain(start_cluster_num);
+
+        for &cluster_num in clusters.iter() {
+            self.write_fat_entry(cluster_num, 0);
+        }
+
+        if let Some(fs_info_sector) = self.fs_info_sector_mut() {
+            let free_cnt = fs_info_sector.free_cnt();
+            if free_cnt != FsInfoSector::UNKNOWN {
+                fs_info_sector.set_free_cnt(free_cnt + clusters.len() as u32);
+            }
+        }
+    }
+
+    pub fn unique_sf_name(&self, dir_cluster_num: usize, name: &str) -> [u8; 11] {
+        let sf_names: Vec<[u8; 11]> = self
+            .read_chained_dir_entries(dir_cluster_num)
+            .iter()
+            .filter(|de| de.entry_type() == EntryType::Data)
+            .map(|de| de.sf_name_raw())
+            .collect();
+
+        let mut num = 1;
+        loop {
+            let sf_name = dir_entry::gen_sf_name(name, num);
+            if !sf_names.contains(&sf_name) {
+                return sf_name;
+            }
+            num += 1;
+        }
+    }
+
+    // returns slots (cluster number, index) of the entries including long file name entries
+    fn find_dir_entry(
+        &self,
+        dir_cluster_num: usize,
+        name: &str,
+    ) -> Option<(Vec<(usize, usize)>, DirectoryEntry)> {
+        let mut lf_name_buf = VecDeque::new();
+        let mut slots = Vec::new();
+
+        for cluster_num in self.cluster_chain(dir_cluster_num) {
+            for (i, dir_entry) in self.dir_entries(cluster_num).iter().enumerate() {
+                match dir_entry.entry_type() {
+                    EntryType::Null => return None,
+                    EntryType::Unused => {
+                        lf_name_buf.clear();
+                        slots.clear();
+                        continue;
+                    }
+                    _ => (),
+                }
+
+                slots.push((cluster_num, i));
+
+                if let Some(lf_name) = dir_entry.lf_name() {
+                    lf_name_buf.push_front(lf_name);
+                    continue;
+                }
+
+                let entry_name = if lf_name_buf.len() > 0 {
+                    lf_name_buf.iter().fold(String::new(), |acc, s| acc + s)
+                } else {
+                    dir_entry.sf_name().unwrap_or_default()
+                };
+
+                if entry_name.trim() == name {
+                    return Some((slots, **dir_entry));
+                }
+
+                lf_name_buf.clear();
+                slots.clear();
+            }
+        }
+
+        None
+    }
+
+    pub fn write_dir_entry(&mut self, cluster_num: usize, index: usize, entry: DirectoryEntry) {
+        let offset = self.cluster_offset(cluster_num) + size_of::<DirectoryEntry>() * index;
+        unsafe {
+            *self
+                .volume_start_virt_addr
+                .offset(offset)
+                .as_ptr_mut::<DirectoryEntry>() = entry;
+        }
+    }
+
+    fn cluster_chain(&self, start_cluster_num: usize) -> Vec<usize> {
+        let mut clusters = Vec::new();
+        let mut current_cluster_num = start_cluster_num;
+
+        loop {
+            clusters.push(current_cluster_num);
+
+            match self.next_cluster_num(current_cluster_num) {
+                Some(ClusterType::Data(next_cluster_num)) => current_cluster_num = next_cluster_num,
+                _ => break,
+            }
+        }
+
+        clusters
+    }
+
+    // update all FAT copies
+    fn write_fat_entry(&mut self, cluster_num: usize, value: u32) {
+        let boot_sector = self.boot_sector();
+        let fat_size = boot_sector.fat32_other_field().unwrap().fat_size();
+
+        for i in 0..boot_sector.num_fats() {
+            let offset = (boot_sector.reserved_sectors() + fat_size * i)
+                * boot_sector.bytes_per_sector()
+                + size_of::<u32>() * cluster_num;
+            let ptr: *mut u32 = self.volume_start_virt_addr.offset(offset).as_ptr_mut();
+
+            unsafe {
+                let reserved_bits = ptr.read_unaligned() & !FAT32_ENTRY_MASK;
+                ptr.write_unaligned(reserved_bits | (value & FAT32_ENTRY_MASK));
+            }
+        }
+    }
+
+    fn cluster_offset(&self, cluster_num: usize) -> usize {
+        let boot_sector = self.boot_sector();
+        boot_sector.data_start_sector32().unwrap() * boot_sector.bytes_per_sector()
+            + self.cluster_size() * (cluster_num - 2)
+    }
+
+    fn cluster_size(&self) -> usize {
+        let boot_sector = self.boot_sector();
+        boot_sector.bytes_per_sector() * boot_sector.sectors_per_cluster()
+    }
+
+    fn max_cluster_num(&self) -> usize {
+        let boot_sector = self.boot_sector();
+        boot_sector.data_sectors32().unwrap() / boot_sector.sectors_per_cluster() + 2
+    }
+
     // read file allocation table
     fn next_cluster_num(&self, cluster_num: usize) -> Option<ClusterType> {
         let boot_sector = self.boot_sector();
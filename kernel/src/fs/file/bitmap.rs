@@ -1,6 +1,6 @@
 use crate::graphics::color::ColorCode;
 use alloc::vec::Vec;
-use core::mem::size_of;
+use core::{mem::size_of, slice};
 
 const MAGIC: [u8; 2] = *b"BM";
 
@@ -80,3 +80,71 @@ impl<'a> BitmapImage<'a> {
         data
     }
 }
+
+// encode to 24 bits bottom-up bitmap
+pub fn rgb_color_code_to_bitmap(width: usize, height: usize, data: &[ColorCode]) -> Vec<u8> {
+    let bits_per_pixel = 3;
+    let padding = (4 - (width * bits_per_pixel) % 4) % 4;
+    let image_size = (width * bits_per_pixel + padding) * height;
+    let offset = size_of::<ImageHeader>() + size_of::<InfoHeader>();
+
+    let header = ImageHeader {
+        magic: MAGIC,
+        file_size: (offset + image_size) as u32,
+        reserved: [0; 2],
+        offset: offset as u32,
+    };
+    let info_header = InfoHeader {
+        header_size: size_of::<InfoHeader>() as u32,
+        width: width as i32,
+        height: height as i32,
+        planes: 1,
+        bits_per_pixel: (bits_per_pixel * 8) as u16,
+        compression: 0,
+        image_size: image_size as u32,
+        x_pixels_per_meter: 0,
+        y_pixels_per_meter: 0,
+        colors_used: 0,
+        colors_important: 0,
+    };
+
+    let mut bitmap = Vec::with_capacity(offset + image_size);
+    bitmap.extend_from_slice(unsafe {
+        slice::from_raw_parts(
+            &header as *const ImageHeader as *const u8,
+            size_of::<ImageHeader>(),
+        )
+    });
+    bitmap.extend_from_slice(unsafe {
+        slice::from_raw_parts(
+            &info_header as *const InfoHeader as *const u8,
+            size_of::<InfoHeader>(),
+        )
+    });
+
+    for y in (0..height).rev() {
+        for color_code in &data[y * width..(y + 1) * width] {
+            bitmap.extend_from_slice(&[color_code.b, color_code.g, color_code.r]);
+        }
+        bitmap.extend_from_slice(&[0; 3][..padding]);
+    }
+
+    bitmap
+}
+
+#[test_case]
+fn test_rgb_color_code_to_bitmap() {
+    let data = [
+        ColorCode::new_rgb(0xff, 0, 0),
+        ColorCode::new_rgb(0, 0xff, 0),
+        ColorCode::new_rgb(0, 0, 0xff),
+        ColorCode::new_rgb(0xff, 0xff, 0xff),
+    ];
+    let bitmap = rgb_color_code_to_bitmap(2, 2, &data);
+    // 2 pixels + 2 bytes padding per line
+    assert_eq!(bitmap.len(), 14 + 40 + 8 * 2);
+
+    let image = BitmapImage::new(&bitmap);
+    assert!(image.is_valid());
+    assert_eq!(image.bitmap_to_rgb_color_code(), data);
+}
@@ -1,2 +1,3 @@
 pub mod bitmap;
 pub mod gif;
+pub mod png;
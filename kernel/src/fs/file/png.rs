@@ -0,0 +1,332 @@
+use crate::{error::Result, graphics::color::ColorCode, util::zlib};
+use alloc::vec::Vec;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+const CHUNK_TYPE_IHDR: [u8; 4] = *b"IHDR";
+const CHUNK_TYPE_IDAT: [u8; 4] = *b"IDAT";
+const CHUNK_TYPE_IEND: [u8; 4] = *b"IEND";
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PngImageError {
+    InvalidSignatureError,
+    InvalidChunkError,
+    InvalidChunkCrcError { ty: [u8; 4] },
+    InvalidHeaderError,
+    UnsupportedFormatError { bit_depth: u8, color_type: u8 },
+    UnsupportedInterlaceMethodError,
+    InvalidFilterTypeError(u8),
+    InvalidImageDataLengthError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+
+impl ColorType {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Grayscale => 1,
+            Self::Rgb => 3,
+            Self::GrayscaleAlpha => 2,
+            Self::Rgba => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageHeader {
+    pub width: u32,
+    pub height: u32,
+    pub bit_depth: u8,
+    pub color_type: ColorType,
+    pub compression_method: u8,
+    pub filter_method: u8,
+    pub interlace_method: u8,
+}
+
+struct Chunk<'a> {
+    ty: [u8; 4],
+    data: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    // ancillary chunks have lowercase first letter
+    fn is_critical(&self) -> bool {
+        self.ty[0].is_ascii_uppercase()
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(0xffff_ffff, |crc, b| {
+        CRC32_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+// TODO: supported 8 bits depth, non-interlaced PNG only
+pub struct PngImage<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PngImage<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.data.starts_with(&SIGNATURE)
+    }
+
+    pub fn header(&self) -> Result<ImageHeader> {
+        let chunk = self
+            .chunks()?
+            .into_iter()
+            .next()
+            .filter(|c| c.ty == CHUNK_TYPE_IHDR && c.data.len() == 13)
+            .ok_or(PngImageError::InvalidHeaderError)?;
+        let data = chunk.data;
+
+        let bit_depth = data[8];
+        let color_type = match data[9] {
+            0 => ColorType::Grayscale,
+            2 => ColorType::Rgb,
+            4 => ColorType::GrayscaleAlpha,
+            6 => ColorType::Rgba,
+            color_type => {
+                return Err(PngImageError::UnsupportedFormatError {
+                    bit_depth,
+                    color_type,
+                }
+                .into())
+            }
+        };
+
+        if bit_depth != 8 {
+            return Err(PngImageError::UnsupportedFormatError {
+                bit_depth,
+                color_type: color_type as u8,
+            }
+            .into());
+        }
+
+        Ok(ImageHeader {
+            width: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            height: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            bit_depth,
+            color_type,
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        })
+    }
+
+    pub fn has_alpha(&self) -> Result<bool> {
+        Ok(matches!(
+            self.header()?.color_type,
+            ColorType::GrayscaleAlpha | ColorType::Rgba
+        ))
+    }
+
+    pub fn png_to_rgba_color_code(&self) -> Result<Vec<ColorCode>> {
+        let header = self.header()?;
+        if header.interlace_method != 0 {
+            return Err(PngImageError::UnsupportedInterlaceMethodError.into());
+        }
+        if header.compression_method != 0 || header.filter_method != 0 {
+            return Err(PngImageError::InvalidHeaderError.into());
+        }
+
+        let mut compressed = Vec::new();
+        for chunk in self.chunks()? {
+            if chunk.ty == CHUNK_TYPE_IDAT {
+                compressed.extend_from_slice(chunk.data);
+            }
+        }
+
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let bytes_per_pixel = header.color_type.bytes_per_pixel();
+        let stride = width * bytes_per_pixel;
+
+        // each scanline is prefixed with the filter type
+        let data_len = (stride + 1)
+            .checked_mul(height)
+            .ok_or(PngImageError::InvalidHeaderError)?;
+        let mut data = zlib::decompress(&compressed, data_len)?;
+        if data.len() < data_len {
+            return Err(PngImageError::InvalidImageDataLengthError.into());
+        }
+        unfilter(&mut data, stride, height, bytes_per_pixel)?;
+
+        let mut color_codes = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let line = &data[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+            for p in line.chunks_exact(bytes_per_pixel) {
+                color_codes.push(match header.color_type {
+                    ColorType::Grayscale => ColorCode::new_rgb(p[0], p[0], p[0]),
+                    ColorType::Rgb => ColorCode::new_rgb(p[0], p[1], p[2]),
+                    ColorType::GrayscaleAlpha => ColorCode::new_rgba(p[0], p[0], p[0], p[1]),
+                    ColorType::Rgba => ColorCode::new_rgba(p[0], p[1], p[2], p[3]),
+                });
+            }
+        }
+
+        Ok(color_codes)
+    }
+
+    fn chunks(&self) -> Result<Vec<Chunk<'a>>> {
+        if !self.is_valid() {
+            return Err(PngImageError::InvalidSignatureError.into());
+        }
+
+        let mut chunks = Vec::new();
+        let mut offset = SIGNATURE.len();
+
+        loop {
+            // length, type, data, crc
+            let len_bytes = self
+                .data
+                .get(offset..offset + 4)
+                .ok_or(PngImageError::InvalidChunkError)?;
+            let len = u32::from_be_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]])
+                as usize;
+            let ty_and_data = self
+                .data
+                .get(offset + 4..offset + 8 + len)
+                .ok_or(PngImageError::InvalidChunkError)?;
+            let crc_bytes = self
+                .data
+                .get(offset + 8 + len..offset + 12 + len)
+                .ok_or(PngImageError::InvalidChunkError)?;
+
+            let chunk = Chunk {
+                ty: [
+                    ty_and_data[0],
+                    ty_and_data[1],
+                    ty_and_data[2],
+                    ty_and_data[3],
+                ],
+                data: &ty_and_data[4..],
+            };
+
+            let crc = u32::from_be_bytes([crc_bytes[0], crc_bytes[1], crc_bytes[2], crc_bytes[3]]);
+            if chunk.is_critical() && crc != crc32(ty_and_data) {
+                return Err(PngImageError::InvalidChunkCrcError { ty: chunk.ty }.into());
+            }
+
+            offset += 12 + len;
+            let is_end = chunk.ty == CHUNK_TYPE_IEND;
+            chunks.push(chunk);
+
+            if is_end {
+                break;
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+// reconstruct scanlines in place
+fn unfilter(data: &mut [u8], stride: usize, height: usize, bytes_per_pixel: usize) -> Result<()> {
+    for y in 0..height {
+        let line_offset = y * (stride + 1);
+        let filter_type = data[line_offset];
+
+        for x in 0..stride {
+            let i = line_offset + 1 + x;
+            let a = if x >= bytes_per_pixel {
+                data[i - bytes_per_pixel]
+            } else {
+                0
+            };
+            let b = if y > 0 { data[i - (stride + 1)] } else { 0 };
+            let c = if y > 0 && x >= bytes_per_pixel {
+                data[i - (stride + 1) - bytes_per_pixel]
+            } else {
+                0
+            };
+
+            data[i] = data[i].wrapping_add(match filter_type {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth_predictor(a, b, c),
+                _ => return Err(PngImageError::InvalidFilterTypeError(filter_type).into()),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[test_case]
+fn test_png_image() {
+    // 2x2 RGBA, second scanline uses up filter
+    let mut data = [
+        0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x72,
+        0xb6, 0x0d, 0x24, 0x00, 0x00, 0x00, 0x16, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8,
+        0xcf, 0xc0, 0xf0, 0x1f, 0x08, 0x1b, 0x98, 0x80, 0x34, 0x90, 0xcd, 0xc0, 0x00, 0x00, 0x3a,
+        0xee, 0x05, 0x7e, 0xd6, 0xf1, 0x1c, 0x2e, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+        0xae, 0x42, 0x60, 0x82,
+    ];
+
+    let png = PngImage::new(&data);
+    assert!(png.is_valid());
+    assert_eq!(png.has_alpha(), Ok(true));
+    assert_eq!(
+        png.png_to_rgba_color_code(),
+        Ok(vec![
+            ColorCode::new_rgba(255, 0, 0, 255),
+            ColorCode::new_rgba(0, 255, 0, 128),
+            ColorCode::new_rgba(255, 0, 255, 254),
+            ColorCode::new_rgba(0, 255, 0, 128),
+        ])
+    );
+
+    // broken IHDR crc
+    data[29] ^= 0xff;
+    assert!(PngImage::new(&data).header().is_err());
+}
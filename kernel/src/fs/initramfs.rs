@@ -1,5 +1,5 @@
 use super::fat::{
-    dir_entry::{Attribute, EntryType, ShortFileNameEntry},
+    dir_entry::{self, Attribute, DirectoryEntry, EntryType, ShortFileNameEntry},
     FatType, FatVolume,
 };
 use crate::{
@@ -8,6 +8,10 @@ use crate::{
 };
 use alloc::{collections::VecDeque, string::String, vec::Vec};
 
+// FAT has no symbolic links, they are stored as a file with the marker and the target path
+const SYMLINK_MARKER: &[u8] = b"!<symlink>";
+const SYMLINK_FILE_MAX_SIZE: usize = 4096;
+
 #[derive(Debug, Clone)]
 pub struct FileMetaData {
     pub name: String,
@@ -66,21 +70,154 @@ impl Initramfs {
     }
 
     pub fn get_file(&self, file_name: &str) -> Result<(FileMetaData, Vec<u8>)> {
-        let files = self.scan_current_dir();
-        let file = files
-            .iter()
-            .find(|f| f.attr == Attribute::Archive && f.name.trim() == file_name);
+        let file = self.get_file_metadata(file_name)?;
+
+        let fat_volume = self.fat_volume.as_ref().unwrap();
+        let dir_entries = fat_volume.read_chained_dir_entries(file.target_cluster_num);
+        let mut bytes: Vec<u8> = dir_entries.iter().flat_map(|de| de.raw()).collect();
+        bytes.resize(file.size, 0);
 
-        if file.is_none() {
-            return Err(Error::Failed("The file does not exist"));
+        Ok((file, bytes))
+    }
+
+    // read up to max_len bytes from the offset
+    pub fn read_file_at(&self, file_name: &str, offset: usize, max_len: usize) -> Result<Vec<u8>> {
+        let file = self.get_file_metadata(file_name)?;
+        let len = file.size.saturating_sub(offset).min(max_len);
+        if len == 0 {
+            return Ok(Vec::new());
         }
 
         let fat_volume = self.fat_volume.as_ref().unwrap();
-        let dir_entries = fat_volume.read_chained_dir_entries(file.unwrap().target_cluster_num);
-        let mut bytes: Vec<u8> = dir_entries.iter().flat_map(|de| de.raw()).collect();
-        bytes.resize(file.unwrap().size, 0);
+        Ok(fat_volume.read_chained_clusters(file.target_cluster_num, offset, len))
+    }
+
+    pub fn get_file_metadata(&self, file_name: &str) -> Result<FileMetaData> {
+        self.scan_current_dir()
+            .into_iter()
+            .find(|f| f.attr == Attribute::Archive && f.name.trim() == file_name)
+            .ok_or(Error::Failed("The file does not exist"))
+    }
+
+    pub fn mkdir(&mut self, dir_name: &str) -> Result<()> {
+        if self
+            .scan_current_dir()
+            .iter()
+            .any(|f| f.name.trim() == dir_name)
+        {
+            return Err(Error::Failed("The file or directory already exists"));
+        }
+
+        let parent_cluster_num = if self.current_cluster_num == self.root_cluster_num {
+            0
+        } else {
+            self.current_cluster_num
+        };
+        let current_cluster_num = self.current_cluster_num;
+
+        let fat_volume = self
+            .fat_volume
+            .as_mut()
+            .ok_or(Error::Failed("FAT volume is not initialized"))?;
+        let sf_name = fat_volume.unique_sf_name(current_cluster_num, dir_name);
+        let cluster_num = fat_volume.alloc_cluster()?;
+
+        // "." and ".." entries
+        fat_volume.write_dir_entry(
+            cluster_num,
+            0,
+            DirectoryEntry::new_sf(dir_entry::DOT_SF_NAME, Attribute::Directory, cluster_num, 0),
+        );
+        fat_volume.write_dir_entry(
+            cluster_num,
+            1,
+            DirectoryEntry::new_sf(
+                dir_entry::DOT_DOT_SF_NAME,
+                Attribute::Directory,
+                parent_cluster_num,
+                0,
+            ),
+        );
+
+        let mut entries = DirectoryEntry::new_lfn_entries(dir_name, &sf_name);
+        entries.push(DirectoryEntry::new_sf(
+            sf_name,
+            Attribute::Directory,
+            cluster_num,
+            0,
+        ));
+        fat_volume.append_dir_entries(current_cluster_num, &entries)
+    }
+
+    pub fn create_file(&mut self, file_name: &str, bytes: &[u8]) -> Result<()> {
+        let current_cluster_num = self.current_cluster_num;
+        let fat_volume = self
+            .fat_volume
+            .as_mut()
+            .ok_or(Error::Failed("FAT volume is not initialized"))?;
+
+        fat_volume.create_file(current_cluster_num, file_name, bytes)?;
+        Ok(())
+    }
+
+    pub fn create_symlink(&mut self, link_name: &str, target: &str) -> Result<()> {
+        self.create_file(link_name, &[SYMLINK_MARKER, target.as_bytes()].concat())
+    }
+
+    pub fn read_link(&self, link_name: &str) -> Result<String> {
+        let metadata = self.get_file_metadata(link_name)?;
+        if metadata.size < SYMLINK_MARKER.len() || metadata.size > SYMLINK_FILE_MAX_SIZE {
+            return Err(Error::Failed("The file is not a symbolic link"));
+        }
+
+        let (_, bytes) = self.get_file(link_name)?;
+        let target = bytes
+            .strip_prefix(SYMLINK_MARKER)
+            .ok_or(Error::Failed("The file is not a symbolic link"))?;
+        Ok(String::from_utf8_lossy(target).into_owned())
+    }
+
+    // replace whole file data
+    pub fn write_file(&mut self, file_name: &str, bytes: &[u8]) -> Result<()> {
+        self.get_file_metadata(file_name)?;
+
+        let current_cluster_num = self.current_cluster_num;
+        let fat_volume = self
+            .fat_volume
+            .as_mut()
+            .ok_or(Error::Failed("FAT volume is not initialized"))?;
+
+        fat_volume.remove_dir_entry(current_cluster_num, file_name)?;
+        fat_volume.create_file(current_cluster_num, file_name, bytes)?;
+        Ok(())
+    }
+
+    // remove the file or the directory recursively
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        let file = self
+            .scan_current_dir()
+            .into_iter()
+            .find(|f| f.name.trim() == name)
+            .ok_or(Error::Failed("The file or directory does not exist"))?;
+
+        if file.attr == Attribute::Directory {
+            self.cd(name)?;
+            let res = self
+                .scan_current_dir()
+                .iter()
+                .filter(|f| !matches!(f.name.trim(), "." | ".."))
+                .try_for_each(|f| self.remove(f.name.trim()));
+            self.cd("..")?;
+            res?;
+        }
+
+        let current_cluster_num = self.current_cluster_num;
+        let fat_volume = self
+            .fat_volume
+            .as_mut()
+            .ok_or(Error::Failed("FAT volume is not initialized"))?;
 
-        Ok((file.unwrap().clone(), bytes))
+        fat_volume.remove_dir_entry(current_cluster_num, name)
     }
 
     pub fn scan_current_dir(&self) -> Vec<FileMetaData> {
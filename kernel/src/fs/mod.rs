@@ -1,6 +1,9 @@
 use crate::{
     arch::addr::VirtualAddress,
-    fs::{fat::FatVolume, initramfs::Initramfs, vfs::FileSystem},
+    device::usb,
+    error::{Error, Result},
+    fs::{fat::FatVolume, initramfs::Initramfs, tmpfs::Tmpfs, vfs::FileSystem},
+    mem::{bitmap, paging::PAGE_SIZE},
 };
 use common::kernel_config::KernelConfig;
 use log::{error, info};
@@ -9,8 +12,13 @@ pub mod exec;
 pub mod fat;
 pub mod file;
 pub mod initramfs;
+pub mod path;
+pub mod tmpfs;
 pub mod vfs;
 
+// volumes are loaded onto memory because FatVolume is memory-backed
+const USB_MASS_STORAGE_MAX_SIZE: usize = 64 * 1024 * 1024; // 64MiB
+
 pub fn init(initramfs_virt_addr: VirtualAddress, kernel_config: &KernelConfig) {
     if let Err(err) = vfs::init() {
         error!("fs: Failed to initialized VFS: {:?}", err);
@@ -30,8 +38,44 @@ pub fn init(initramfs_virt_addr: VirtualAddress, kernel_config: &KernelConfig) {
     }
     info!("fs: Mounted initramfs to VFS");
 
+    if let Err(err) = vfs::mount("/tmp", FileSystem::Tmpfs(Tmpfs::new())) {
+        error!("fs: Failed to mount tmpfs to VFS: {:?}", err);
+    }
+    info!("fs: Mounted tmpfs to VFS");
+
     let dirname = kernel_config.init_cwd_path;
     if let Err(err) = vfs::chdir(&dirname) {
         error!("fs: Failed to chdir to {}: {:?}", dirname, err);
     }
 }
+
+pub fn mount_usb_mass_storage(path: &str) -> Result<()> {
+    let (block_size, block_count) = usb::bus::mass_storage_capacity()?;
+    let size = block_size * block_count;
+    if size > USB_MASS_STORAGE_MAX_SIZE {
+        return Err(Error::Failed("USB mass storage is too large to load"));
+    }
+
+    let mem_frame_info = bitmap::alloc_mem_frame(size.div_ceil(PAGE_SIZE))?;
+    let volume_virt_addr = mem_frame_info.frame_start_virt_addr()?;
+    let blocks_per_read = PAGE_SIZE * 16 / block_size;
+    let mut lba = 0;
+    while lba < block_count {
+        let count = (block_count - lba).min(blocks_per_read);
+        let data = usb::bus::read_mass_storage_blocks(lba, count)?;
+        volume_virt_addr
+            .offset(lba * block_size)
+            .copy_from_nonoverlapping(data.as_ptr(), data.len());
+        lba += count;
+    }
+
+    let mut fs = Initramfs::new(2);
+    if let Err(err) = fs.init(FatVolume::new(volume_virt_addr)) {
+        bitmap::dealloc_mem_frame(mem_frame_info)?;
+        return Err(err);
+    }
+
+    vfs::mount(path, FileSystem::Initramfs(fs))?;
+    info!("fs: Mounted USB mass storage to {}", path);
+    Ok(())
+}
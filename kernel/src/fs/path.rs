@@ -0,0 +1,155 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+pub const PATH_SEPARATOR: char = '/';
+
+const CURRENT_DIR_NAME: &str = ".";
+const PARENT_DIR_NAME: &str = "..";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    path: String,
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+impl Path {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.path.starts_with(PATH_SEPARATOR)
+    }
+
+    // names without empty names, "." and ".." are kept
+    pub fn names(&self) -> Vec<&str> {
+        self.path
+            .split(PATH_SEPARATOR)
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    // remove empty names and ".", resolve ".." lexically
+    // ".." above the root is clamped at "/", leading ".." of relative paths are kept
+    pub fn normalize(&self) -> Self {
+        let mut names: Vec<&str> = Vec::new();
+
+        for name in self.names() {
+            match name {
+                CURRENT_DIR_NAME => (),
+                PARENT_DIR_NAME => match names.last() {
+                    Some(&last) if last != PARENT_DIR_NAME => {
+                        names.pop();
+                    }
+                    _ if self.is_absolute() => (),
+                    _ => names.push(name),
+                },
+                _ => names.push(name),
+            }
+        }
+
+        let path = names.join("/");
+        if self.is_absolute() {
+            Self::new(&format!("{}{}", PATH_SEPARATOR, path))
+        } else if path.is_empty() {
+            Self::new(CURRENT_DIR_NAME)
+        } else {
+            Self::new(&path)
+        }
+    }
+
+    // the parent of "/" is "/"
+    pub fn parent(&self) -> Self {
+        self.normalize().join(PARENT_DIR_NAME).normalize()
+    }
+
+    // last name, none for "/", "." and ".." (normalize before to resolve them)
+    pub fn name(&self) -> Option<&str> {
+        match self.names().last() {
+            Some(&CURRENT_DIR_NAME) | Some(&PARENT_DIR_NAME) | None => None,
+            Some(&name) => Some(name),
+        }
+    }
+
+    // relative paths are resolved from self
+    pub fn join(&self, path: &str) -> Self {
+        if path.starts_with(PATH_SEPARATOR) {
+            return Self::new(path);
+        }
+
+        Self::new(&format!(
+            "{}{}{}",
+            self.path.trim_end_matches(PATH_SEPARATOR),
+            PATH_SEPARATOR,
+            path
+        ))
+    }
+}
+
+#[test_case]
+fn test_normalize() {
+    let normalize = |path: &str| Path::new(path).normalize().to_string();
+
+    assert_eq!(normalize("/"), "/");
+    assert_eq!(normalize(""), ".");
+    assert_eq!(
+        normalize("/mnt/initramfs/../initramfs/./sys"),
+        "/mnt/initramfs/sys"
+    );
+    assert_eq!(normalize("/mnt/initramfs/"), "/mnt/initramfs");
+    assert_eq!(normalize("//a//b/../"), "/a");
+    // escape above the root
+    assert_eq!(normalize("/../.."), "/");
+    assert_eq!(normalize("/a/../../b"), "/b");
+    // relative
+    assert_eq!(normalize("./a/./b/"), "a/b");
+    assert_eq!(normalize("a/.."), ".");
+    assert_eq!(normalize("a/../../b"), "../b");
+    assert_eq!(normalize("../../a"), "../../a");
+}
+
+#[test_case]
+fn test_parent_and_name() {
+    let path = Path::new("/mnt/initramfs/apps/");
+    assert_eq!(path.parent().to_string(), "/mnt/initramfs");
+    assert_eq!(path.name(), Some("apps"));
+
+    let path = Path::new("/");
+    assert_eq!(path.parent().to_string(), "/");
+    assert_eq!(path.name(), None);
+
+    let path = Path::new("file");
+    assert_eq!(path.parent().to_string(), ".");
+    assert_eq!(path.name(), Some("file"));
+
+    assert_eq!(Path::new("a/..").name(), None);
+    assert_eq!(Path::new("a/b/..").normalize().name(), Some("a"));
+    assert_eq!(Path::new(".").parent().to_string(), "..");
+    assert_eq!(Path::new("..").parent().to_string(), "../..");
+}
+
+#[test_case]
+fn test_join() {
+    let path = Path::new("/mnt/initramfs");
+    assert_eq!(
+        path.join("apps/sh.elf").as_str(),
+        "/mnt/initramfs/apps/sh.elf"
+    );
+    assert_eq!(path.join("/mnt").as_str(), "/mnt");
+    assert_eq!(Path::new("/").join("mnt").as_str(), "/mnt");
+    assert_eq!(Path::new("/mnt/").join("../mnt").as_str(), "/mnt/../mnt");
+}
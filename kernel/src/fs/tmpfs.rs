@@ -0,0 +1,158 @@
+use crate::error::{Error, Result};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+const PATH_SEPARATOR: char = '/';
+
+// in-memory filesystem, paths are relative to the mount point
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Tmpfs {
+    files: BTreeMap<String, Vec<u8>>,
+    dirs: BTreeSet<String>,
+    symlinks: BTreeMap<String, String>, // link path, target path
+}
+
+impl Tmpfs {
+    pub const fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+            symlinks: BTreeMap::new(),
+        }
+    }
+
+    pub fn create_file(&mut self, path: &str) -> Result<()> {
+        if self.exists(path) {
+            return Err(Error::Failed("The file or directory already exists"));
+        }
+
+        self.files.insert(path.to_string(), Vec::new());
+        Ok(())
+    }
+
+    pub fn mkdir(&mut self, path: &str) -> Result<()> {
+        if self.exists(path) {
+            return Err(Error::Failed("The file or directory already exists"));
+        }
+
+        self.dirs.insert(path.to_string());
+        Ok(())
+    }
+
+    pub fn symlink(&mut self, target: &str, path: &str) -> Result<()> {
+        if self.exists(path) {
+            return Err(Error::Failed("The file or directory already exists"));
+        }
+
+        self.symlinks.insert(path.to_string(), target.to_string());
+        Ok(())
+    }
+
+    pub fn read_link(&self, path: &str) -> Result<String> {
+        self.symlinks
+            .get(path)
+            .cloned()
+            .ok_or(Error::Failed("The symbolic link does not exist"))
+    }
+
+    pub fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or(Error::Failed("The file does not exist"))
+    }
+
+    // read up to max_len bytes from the offset
+    pub fn read_file_at(&self, path: &str, offset: usize, max_len: usize) -> Result<Vec<u8>> {
+        let data = self
+            .files
+            .get(path)
+            .ok_or(Error::Failed("The file does not exist"))?;
+        let data = data.get(offset..).unwrap_or(&[]);
+        Ok(data[..data.len().min(max_len)].to_vec())
+    }
+
+    // replace whole file data
+    pub fn write_file(&mut self, path: &str, bytes: &[u8]) -> Result<()> {
+        let data = self
+            .files
+            .get_mut(path)
+            .ok_or(Error::Failed("The file does not exist"))?;
+        *data = bytes.to_vec();
+        Ok(())
+    }
+
+    pub fn file_size(&self, path: &str) -> Result<usize> {
+        self.files
+            .get(path)
+            .map(|data| data.len())
+            .ok_or(Error::Failed("The file does not exist"))
+    }
+
+    // remove the file or the directory recursively
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        if !self.exists(path) {
+            return Err(Error::Failed("The file or directory does not exist"));
+        }
+
+        let prefix = format!("{}{}", path, PATH_SEPARATOR);
+        self.files
+            .retain(|p, _| p.as_str() != path && !p.starts_with(&prefix));
+        self.dirs
+            .retain(|p| p.as_str() != path && !p.starts_with(&prefix));
+        self.symlinks
+            .retain(|p, _| p.as_str() != path && !p.starts_with(&prefix));
+
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+            || self.dirs.contains(path)
+            || self.symlinks.contains_key(path)
+    }
+}
+
+#[test_case]
+fn test_create_write_read() {
+    let mut tmpfs = Tmpfs::new();
+    assert!(tmpfs.create_file("a").is_ok());
+    assert!(tmpfs.create_file("a").is_err());
+    assert!(tmpfs.write_file("a", &[1, 2, 3]).is_ok());
+    assert_eq!(tmpfs.read_file("a"), Ok(vec![1, 2, 3]));
+    assert_eq!(tmpfs.file_size("a"), Ok(3));
+    assert!(tmpfs.read_file("b").is_err());
+    assert_eq!(tmpfs.read_file_at("a", 1, 1), Ok(vec![2]));
+    assert_eq!(tmpfs.read_file_at("a", 2, 8), Ok(vec![3]));
+    assert_eq!(tmpfs.read_file_at("a", 4, 8), Ok(vec![]));
+}
+
+#[test_case]
+fn test_remove_recursive() {
+    let mut tmpfs = Tmpfs::new();
+    tmpfs.mkdir("dir").unwrap();
+    tmpfs.create_file("dir/a").unwrap();
+    tmpfs.create_file("dir2").unwrap();
+    assert!(tmpfs.remove("dir").is_ok());
+    assert!(tmpfs.read_file("dir/a").is_err());
+    assert!(tmpfs.read_file("dir2").is_ok());
+    assert!(tmpfs.remove("dir").is_err());
+}
+
+#[test_case]
+fn test_symlink() {
+    let mut tmpfs = Tmpfs::new();
+    tmpfs.mkdir("dir").unwrap();
+    assert!(tmpfs.symlink("/mnt/initramfs/sys", "dir/link").is_ok());
+    assert!(tmpfs.symlink("/", "dir/link").is_err());
+    assert_eq!(
+        tmpfs.read_link("dir/link"),
+        Ok("/mnt/initramfs/sys".to_string())
+    );
+    assert!(tmpfs.read_file("dir/link").is_err());
+    assert!(tmpfs.remove("dir").is_ok());
+    assert!(tmpfs.read_link("dir/link").is_err());
+}
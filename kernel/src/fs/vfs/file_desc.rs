@@ -34,9 +34,17 @@ pub enum Status {
     Close,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Whence {
+    Set,
+    Current,
+    End,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileDescriptor {
     pub num: FileDescriptorNumber,
     pub status: Status,
     pub file_id: FileId,
+    pub offset: usize,
 }
@@ -1,6 +1,18 @@
-use self::file_desc::{FileDescriptor, FileDescriptorNumber};
-use super::initramfs::Initramfs;
-use crate::{error::Result, fs::fat::dir_entry::Attribute, util::mutex::Mutex};
+use self::{
+    file_desc::{FileDescriptor, FileDescriptorNumber, Whence},
+    pipe::Pipe,
+};
+use super::{
+    initramfs::Initramfs,
+    path::{Path, PATH_SEPARATOR},
+    tmpfs::Tmpfs,
+};
+use crate::{
+    arch::task::{self, ProcessState},
+    error::{Error, Result},
+    fs::fat::dir_entry::Attribute,
+    util::{mutex::Mutex, random},
+};
 use alloc::{
     string::{String, ToString},
     vec::Vec,
@@ -8,8 +20,15 @@ use alloc::{
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 pub mod file_desc;
+pub mod pipe;
 
-const PATH_SEPARATOR: char = '/';
+// bound of nested symbolic links to detect cycles
+const SYMLINK_FOLLOW_MAX: usize = 8;
+// one directory per process, synced with the process table
+const PROC_DIR_PATH: &str = "/proc";
+const PROC_STATUS_FILE_NAME: &str = "status";
+// bytes returned by a read of random devices
+const RANDOM_READ_LEN: usize = 512;
 
 static mut VFS: Mutex<Option<VirtualFileSystem>> = Mutex::new(None);
 
@@ -37,6 +56,47 @@ enum SpecialFile {
     StdOut,
     StdErr,
     DeviceZero,
+    ProcessStatus(usize), // pid
+    Random,               // /dev/random and /dev/urandom
+}
+
+impl SpecialFile {
+    // no offset, each read returns new data
+    fn is_stream(&self) -> bool {
+        matches!(self, Self::Random)
+    }
+
+    // generated on each read
+    fn read_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Random => {
+                let mut bytes = vec![0; RANDOM_READ_LEN];
+                random::fill_bytes(&mut bytes);
+                Ok(bytes)
+            }
+            Self::ProcessStatus(pid) => {
+                let process = task::processes()
+                    .into_iter()
+                    .find(|p| p.pid == *pid)
+                    .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+                let mut status = format!(
+                    "pid: {}\nname: {}\nbackground: {}\nbrk: 0x{:x}\n",
+                    process.pid,
+                    process.name,
+                    process.is_background,
+                    process.program_break.get()
+                );
+                match process.state {
+                    ProcessState::Running => status.push_str("state: running\n"),
+                    ProcessState::Exited(exit_code) => {
+                        status.push_str(&format!("state: exited\nexit_code: {}\n", exit_code))
+                    }
+                }
+                Ok(status.into_bytes())
+            }
+            _ => Err(VirtualFileSystemError::NotFileError.into()),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -44,11 +104,34 @@ enum FileType {
     File,
     Directory,
     Special(SpecialFile),
+    Symlink(String), // target path
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum FileSystem {
     Initramfs(Initramfs),
+    Tmpfs(Tmpfs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystemKind {
+    Vfs,
+    Initramfs,
+    Tmpfs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub size: usize,
+    pub is_dir: bool,
+    pub fs_kind: FileSystemKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: usize,
 }
 
 #[derive(Debug)]
@@ -68,8 +151,13 @@ pub enum VirtualFileSystemError {
     NoSuchFileOrDirectoryError,
     NotDirectoryError,
     NotFileError,
+    AlreadyExistsError,
+    DirectoryNotEmptyError,
+    ResourceBusyError,
     BlockingFileResourceError(FileDescriptorNumber),
     ReleasedFileResourceError(FileDescriptorNumber),
+    WouldBlockError(FileDescriptorNumber),
+    BrokenPipeError(FileDescriptorNumber),
 }
 
 struct VirtualFileSystem {
@@ -77,6 +165,7 @@ struct VirtualFileSystem {
     root_id: FileId,
     files: Vec<FileInfo>,
     file_descs: Vec<FileDescriptor>,
+    pipes: Vec<Pipe>,
 }
 
 impl VirtualFileSystem {
@@ -85,6 +174,8 @@ impl VirtualFileSystem {
 
         let rootfs_id = FileId::new();
         let mnt_dir_id = FileId::new();
+        let proc_dir_id = FileId::new();
+        let dev_dir_id = FileId::new();
 
         let root_fs = FileInfo {
             id: rootfs_id,
@@ -104,15 +195,63 @@ impl VirtualFileSystem {
             name: String::from("mnt"),
             parent: Some(rootfs_id),
             child: None,
-            next: None,
+            next: Some(proc_dir_id),
         };
         files.push(mnt_dir);
 
+        let proc_dir = FileInfo {
+            id: proc_dir_id,
+            ty: FileType::Directory,
+            fs: None,
+            name: String::from(&PROC_DIR_PATH[1..]),
+            parent: Some(rootfs_id),
+            child: None,
+            next: Some(dev_dir_id),
+        };
+        files.push(proc_dir);
+
+        let dev_random_id = FileId::new();
+        let dev_urandom_id = FileId::new();
+        let dev_dir = FileInfo {
+            id: dev_dir_id,
+            ty: FileType::Directory,
+            fs: None,
+            name: String::from("dev"),
+            parent: Some(rootfs_id),
+            child: Some(dev_random_id),
+            next: None,
+        };
+        files.push(dev_dir);
+
+        // both never block
+        let dev_random = FileInfo {
+            id: dev_random_id,
+            ty: FileType::Special(SpecialFile::Random),
+            fs: None,
+            name: String::from("random"),
+            parent: Some(dev_dir_id),
+            child: None,
+            next: Some(dev_urandom_id),
+        };
+        files.push(dev_random);
+
+        let dev_urandom = FileInfo {
+            id: dev_urandom_id,
+            ty: FileType::Special(SpecialFile::Random),
+            fs: None,
+            name: String::from("urandom"),
+            parent: Some(dev_dir_id),
+            child: None,
+            next: None,
+        };
+        files.push(dev_urandom);
+
         Self {
             cwd_id: rootfs_id,
             root_id: rootfs_id,
             files,
             file_descs: Vec::new(),
+            pipes: Vec::new(),
         }
     }
 
@@ -125,51 +264,64 @@ impl VirtualFileSystem {
     }
 
     fn find_file_by_path(&self, path: &str) -> Option<&FileInfo> {
-        let mut file_ref = self.find_file(&self.cwd_id)?;
+        self.find_file_by_path_with(path, true)
+    }
 
-        if path.starts_with(PATH_SEPARATOR) {
-            file_ref = self.find_file(&self.root_id)?;
-        }
+    // follow_last: resolve the last name too if it is a symbolic link
+    fn find_file_by_path_with(&self, path: &str, follow_last: bool) -> Option<&FileInfo> {
+        let mut path = Path::new(path).normalize();
+        let mut follow_count = 0;
 
-        for name in path.split(PATH_SEPARATOR) {
-            match name {
-                "" | "." => continue,
-                ".." => {
-                    if !self.is_directory(file_ref) {
-                        return None;
-                    }
+        'resolve: loop {
+            let mut file_ref = if path.is_absolute() {
+                self.find_file(&self.root_id)?
+            } else {
+                self.find_file(&self.cwd_id)?
+            };
 
-                    let parent_file_id = file_ref.parent?;
-                    file_ref = self.find_file(&parent_file_id)?;
-                    continue;
+            let names = path.names();
+            for (i, name) in names.iter().enumerate() {
+                if !self.is_directory(file_ref) {
+                    return None;
                 }
-                _ => (),
-            }
 
-            if let Some(child_file_id) = file_ref.child {
-                file_ref = self.find_file(&child_file_id)?;
-            }
+                // only leading names of relative paths, clamped at the root
+                if *name == ".." {
+                    if file_ref.id != self.root_id {
+                        let parent_file_id = file_ref.parent?;
+                        file_ref = self.find_file(&parent_file_id)?;
+                    }
+                    continue;
+                }
 
-            if file_ref.name == name {
-                continue;
-            }
+                file_ref = self
+                    .dir_files(&file_ref.id)
+                    .into_iter()
+                    .find(|f| f.name == *name)?;
 
-            let mut found = false;
-            while let Some(next_file_id) = file_ref.next {
-                file_ref = self.find_file(&next_file_id)?;
+                let target = match &file_ref.ty {
+                    FileType::Symlink(target) if i < names.len() - 1 || follow_last => target,
+                    _ => continue,
+                };
 
-                if file_ref.name == name {
-                    found = true;
-                    break;
+                follow_count += 1;
+                if follow_count > SYMLINK_FOLLOW_MAX {
+                    return None;
                 }
-            }
 
-            if !found {
-                return None;
+                // relative targets are resolved from the directory of the link
+                let link_dir_path = self.file_path(&file_ref.parent?).ok()?;
+                let resolved_path = names[i + 1..]
+                    .iter()
+                    .fold(Path::new(&link_dir_path).join(target), |p, name| {
+                        p.join(name)
+                    });
+                path = resolved_path.normalize();
+                continue 'resolve;
             }
-        }
 
-        Some(file_ref)
+            return Some(file_ref);
+        }
     }
 
     fn find_file_by_path_mut(&mut self, path: &str) -> Option<&mut FileInfo> {
@@ -204,14 +356,29 @@ impl VirtualFileSystem {
         Ok(())
     }
 
-    fn cwd_files(&mut self) -> Vec<&FileInfo> {
+    fn entry_names(&self, path: &str) -> Result<Vec<String>> {
+        let dir_ref = self
+            .find_file_by_path(path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        Ok(self
+            .dir_files(&dir_ref.id)
+            .iter()
+            .map(|f| f.name.clone())
+            .collect())
+    }
+
+    fn dir_files(&self, dir_id: &FileId) -> Vec<&FileInfo> {
         let mut files = Vec::new();
-        let cwd_ref = match self.find_file(&self.cwd_id) {
+        let dir_ref = match self.find_file(dir_id) {
             Some(f) => f,
             None => return files,
         };
 
-        if let Some(child_file_id) = cwd_ref.child {
+        if let Some(child_file_id) = dir_ref.child {
             let mut file_ref = match self.find_file(&child_file_id) {
                 Some(f) => f,
                 None => return files,
@@ -230,10 +397,48 @@ impl VirtualFileSystem {
         files
     }
 
+    fn readdir(&mut self, path: &str) -> Result<Vec<DirEntry>> {
+        let dir_ref = self
+            .find_file_by_path(path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        let files: Vec<(FileId, String)> = self
+            .dir_files(&dir_ref.id)
+            .iter()
+            .map(|f| (f.id, f.name.clone()))
+            .collect();
+
+        let mut entries = Vec::new();
+        for (file_id, name) in files {
+            let stat = self.stat_file(&file_id)?;
+            entries.push(DirEntry {
+                name,
+                is_dir: stat.is_dir,
+                size: stat.size,
+            });
+        }
+
+        Ok(entries)
+    }
+
     fn cwd_path(&self) -> Result<String> {
+        self.file_path(&self.cwd_id)
+    }
+
+    fn abs_path(&self, path: &str) -> Result<String> {
+        let file_ref = self
+            .find_file_by_path(path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        self.file_path(&file_ref.id)
+    }
+
+    fn file_path(&self, id: &FileId) -> Result<String> {
         let mut path = String::new();
         let mut file_ref = self
-            .find_file(&self.cwd_id)
+            .find_file(id)
             .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
         path = format!("{}", file_ref.name);
 
@@ -281,7 +486,10 @@ impl VirtualFileSystem {
                         id: FileId::new(),
                         ty: match metadata.attr {
                             Attribute::Directory => FileType::Directory,
-                            _ => FileType::File,
+                            _ => match initramfs_ref.read_link(metadata.name.trim()) {
+                                Ok(target) => FileType::Symlink(target),
+                                Err(_) => FileType::File,
+                            },
                         },
                         fs: None,
                         name: metadata.name,
@@ -332,10 +540,28 @@ impl VirtualFileSystem {
             files
         }
 
-        let path_parts: Vec<&str> = path.split(PATH_SEPARATOR).collect();
-        let mount_name = path_parts
-            .last()
+        let path = Path::new(path).normalize();
+        let parent_dir_path = path.parent();
+        let parent_dir_path = parent_dir_path.as_str();
+        let mount_name = path
+            .name()
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        let parent_dir_ref = self
+            .find_file_by_path(parent_dir_path)
             .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(parent_dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        let parent_dir_id = parent_dir_ref.id;
+        if self
+            .dir_files(&parent_dir_id)
+            .iter()
+            .any(|f| f.name == mount_name)
+        {
+            return Err(VirtualFileSystemError::AlreadyExistsError.into());
+        }
 
         let mut mount_fs = FileInfo {
             id: FileId::new(),
@@ -347,18 +573,53 @@ impl VirtualFileSystem {
             next: None,
         };
 
-        let mapped_files = match fs {
-            FileSystem::Initramfs(_) => map_initramfs(&mut mount_fs),
+        let mapped_files = match mount_fs.fs {
+            Some(FileSystem::Initramfs(_)) => map_initramfs(&mut mount_fs),
+            Some(FileSystem::Tmpfs(_)) => Vec::new(),
+            None => unreachable!(),
         };
         self.files.extend(mapped_files);
 
-        let parent_dir_path = &path[0..path.len() - (mount_name.len() + 1)];
         self.add_file_into_directory(parent_dir_path, &mut mount_fs)?;
         self.files.push(mount_fs);
 
         Ok(())
     }
 
+    fn umount(&mut self, path: &str) -> Result<FileSystem> {
+        let mount_fs_ref = self
+            .find_file_by_path(path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        let mount_fs_id = mount_fs_ref.id;
+
+        if mount_fs_ref.fs.is_none() {
+            return Err(Error::Failed("Not a mount point"));
+        }
+
+        let removed_ids = self.subtree_file_ids(&mount_fs_id);
+        if removed_ids.contains(&self.cwd_id) || removed_ids.contains(&self.root_id) {
+            return Err(Error::Failed("Mount point is in use"));
+        }
+
+        if let Some(fd) = self
+            .file_descs
+            .iter()
+            .find(|fd| fd.status == file_desc::Status::Open && removed_ids.contains(&fd.file_id))
+        {
+            return Err(VirtualFileSystemError::BlockingFileResourceError(fd.num).into());
+        }
+
+        self.unlink_file(&mount_fs_id)?;
+
+        let fs = self
+            .find_file_mut(&mount_fs_id)
+            .and_then(|f| f.fs.take())
+            .unwrap();
+        self.files.retain(|f| !removed_ids.contains(&f.id));
+
+        Ok(fs)
+    }
+
     fn open_file(&mut self, path: &str) -> Result<FileDescriptorNumber> {
         let file_ref = self
             .find_file_by_path(path)
@@ -368,7 +629,7 @@ impl VirtualFileSystem {
             return Err(VirtualFileSystemError::BlockingFileResourceError(fd.num).into());
         }
 
-        if file_ref.ty != FileType::File {
+        if !matches!(file_ref.ty, FileType::File | FileType::Special(_)) {
             return Err(VirtualFileSystemError::NotFileError.into());
         }
 
@@ -377,6 +638,7 @@ impl VirtualFileSystem {
             num: fd_num,
             status: file_desc::Status::Open,
             file_id: file_ref.id,
+            offset: 0,
         };
         self.file_descs.push(fd);
 
@@ -384,7 +646,19 @@ impl VirtualFileSystem {
     }
 
     fn close_file(&mut self, fd_num: &FileDescriptorNumber) -> Result<()> {
-        if self.find_fd(fd_num).is_none() {
+        if let Some(pipe) = self.find_pipe_mut(fd_num) {
+            if pipe.read_fd == Some(*fd_num) {
+                pipe.read_fd = None;
+            } else {
+                pipe.write_fd = None;
+            }
+
+            self.pipes.retain(|p| !p.is_closed());
+            return Ok(());
+        }
+
+        // file descriptors are released by removing the file
+        if !self.file_descs.iter().any(|fd| fd.num == *fd_num) {
             return Err(VirtualFileSystemError::ReleasedFileResourceError(fd_num.clone()).into());
         }
 
@@ -394,35 +668,135 @@ impl VirtualFileSystem {
         Ok(())
     }
 
-    fn read_file(&mut self, fd_num: &FileDescriptorNumber) -> Result<Vec<u8>> {
+    // read up to max_len bytes from the current offset
+    fn read_file(&mut self, fd_num: &FileDescriptorNumber, max_len: usize) -> Result<Vec<u8>> {
         let fd = self
             .find_fd(fd_num)
             .ok_or(VirtualFileSystemError::ReleasedFileResourceError(
                 fd_num.clone(),
             ))?;
+        let file_id = fd.file_id;
+        let offset = fd.offset;
+
+        // stream files have no offset, the rest of the data is discarded
+        if self.is_stream_file(&file_id) {
+            let mut bytes = self.read_file_bytes(&file_id)?;
+            bytes.truncate(max_len);
+            return Ok(bytes);
+        }
+        let bytes = self.read_file_bytes_at(&file_id, offset, max_len)?;
+
+        // advance offset
+        let fd = self.find_fd_mut(fd_num).unwrap();
+        fd.offset = offset + bytes.len();
+
+        Ok(bytes)
+    }
 
+    fn seek(&mut self, fd_num: &FileDescriptorNumber, pos: i64, whence: Whence) -> Result<usize> {
+        let fd = self
+            .find_fd(fd_num)
+            .ok_or(VirtualFileSystemError::ReleasedFileResourceError(
+                fd_num.clone(),
+            ))?;
+        let file_id = fd.file_id;
+
+        if self.is_stream_file(&file_id) {
+            return Ok(0);
+        }
+
+        let base = match whence {
+            Whence::Set => 0,
+            Whence::Current => fd.offset,
+            Whence::End => self.read_file_bytes(&file_id)?.len(),
+        };
+
+        let offset = base
+            .checked_add_signed(pos as isize)
+            .ok_or(Error::Failed("Invalid offset"))?;
+
+        let fd = self.find_fd_mut(fd_num).unwrap();
+        fd.offset = offset;
+
+        Ok(offset)
+    }
+
+    // return (read fd, write fd)
+    fn pipe(&mut self) -> (FileDescriptorNumber, FileDescriptorNumber) {
+        let pipe = Pipe::new();
+        let fds = (pipe.read_fd.unwrap(), pipe.write_fd.unwrap());
+        self.pipes.push(pipe);
+        fds
+    }
+
+    // return empty data if the write end was closed
+    fn read_pipe(&mut self, fd_num: &FileDescriptorNumber, max_len: usize) -> Result<Vec<u8>> {
+        let pipe = self
+            .pipes
+            .iter_mut()
+            .find(|p| p.read_fd == Some(*fd_num))
+            .ok_or(VirtualFileSystemError::ReleasedFileResourceError(*fd_num))?;
+
+        if pipe.is_empty() && pipe.write_fd.is_some() {
+            return Err(VirtualFileSystemError::WouldBlockError(*fd_num).into());
+        }
+
+        Ok(pipe.read(max_len))
+    }
+
+    // return written length
+    fn write_pipe(&mut self, fd_num: &FileDescriptorNumber, data: &[u8]) -> Result<usize> {
+        let pipe = self
+            .pipes
+            .iter_mut()
+            .find(|p| p.write_fd == Some(*fd_num))
+            .ok_or(VirtualFileSystemError::ReleasedFileResourceError(*fd_num))?;
+
+        if pipe.read_fd.is_none() {
+            return Err(VirtualFileSystemError::BrokenPipeError(*fd_num).into());
+        }
+
+        Ok(pipe.write(data))
+    }
+
+    fn find_pipe_mut(&mut self, fd_num: &FileDescriptorNumber) -> Option<&mut Pipe> {
+        self.pipes
+            .iter_mut()
+            .find(|p| p.read_fd == Some(*fd_num) || p.write_fd == Some(*fd_num))
+    }
+
+    fn read_file_bytes(&mut self, file_id: &FileId) -> Result<Vec<u8>> {
+        self.read_file_bytes_at(file_id, 0, usize::MAX)
+    }
+
+    // read up to max_len bytes from the offset
+    fn read_file_bytes_at(
+        &mut self,
+        file_id: &FileId,
+        offset: usize,
+        max_len: usize,
+    ) -> Result<Vec<u8>> {
         let file_ref = self
-            .find_file_mut(&fd.file_id.clone())
+            .find_file_mut(file_id)
             .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
 
+        if let FileType::Special(special_file) = &file_ref.ty {
+            let bytes = special_file.read_bytes()?;
+            let bytes = bytes.get(offset..).unwrap_or(&[]);
+            return Ok(bytes[..bytes.len().min(max_len)].to_vec());
+        }
+
         if file_ref.ty != FileType::File {
             return Err(VirtualFileSystemError::NotFileError.into());
         }
 
-        let mut file_names_to_root_file = Vec::new();
-        let mut fs_root_file_ref = file_ref;
-        // TODO: if fs not found, loop infinity
-        while let Some(parent_file_id) = fs_root_file_ref.parent {
-            file_names_to_root_file.push(fs_root_file_ref.name.clone());
-            fs_root_file_ref = self
-                .find_file_mut(&parent_file_id)
-                .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
-
-            if fs_root_file_ref.fs.is_some() {
-                break;
-            }
-        }
-        file_names_to_root_file.reverse();
+        let file_id = file_ref.id;
+        let (fs_root_file_id, file_names_to_root_file) = self
+            .find_fs_root(&file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        let fs_root_file_ref = self
+            .find_file_mut(&fs_root_file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
 
         match &mut fs_root_file_ref.fs {
             Some(FileSystem::Initramfs(initramfs)) => {
@@ -433,26 +807,536 @@ impl VirtualFileSystem {
                     }
                 }
 
-                let (_, bytes) = initramfs.get_file(file_names_to_root_file.last().unwrap())?;
+                let bytes = initramfs.read_file_at(
+                    file_names_to_root_file.last().unwrap(),
+                    offset,
+                    max_len,
+                );
+                initramfs.reset_cwd();
+                return bytes;
+            }
+            Some(FileSystem::Tmpfs(tmpfs)) => {
+                tmpfs.read_file_at(&file_names_to_root_file.join("/"), offset, max_len)
+            }
+            None => unreachable!(),
+        }
+    }
+
+    fn write_file(&mut self, fd_num: &FileDescriptorNumber, data: &[u8]) -> Result<()> {
+        let fd = self
+            .find_fd(fd_num)
+            .ok_or(VirtualFileSystemError::ReleasedFileResourceError(
+                fd_num.clone(),
+            ))?;
+        let file_id = fd.file_id;
+        let offset = fd.offset;
+
+        let file_ref = self
+            .find_file(&file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        if file_ref.ty != FileType::File {
+            return Err(VirtualFileSystemError::NotFileError.into());
+        }
+
+        let (fs_root_file_id, file_names_to_root_file) = self
+            .find_fs_root(&file_id)
+            .ok_or(Error::Failed("No filesystem is mounted"))?;
+
+        // write data at the current offset
+        let mut bytes = self.read_file_bytes(&file_id)?;
+        if bytes.len() < offset + data.len() {
+            bytes.resize(offset + data.len(), 0);
+        }
+        bytes[offset..offset + data.len()].copy_from_slice(data);
+
+        let fs_root_file_ref = self
+            .find_file_mut(&fs_root_file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        match &mut fs_root_file_ref.fs {
+            Some(FileSystem::Initramfs(initramfs)) => {
+                let (file_name, dir_names) = file_names_to_root_file.split_last().unwrap();
+                initramfs.reset_cwd();
+                let res = dir_names
+                    .iter()
+                    .try_for_each(|name| initramfs.cd(name.as_str()))
+                    .and_then(|_| initramfs.write_file(file_name, &bytes));
+                initramfs.reset_cwd();
+                res?;
+            }
+            Some(FileSystem::Tmpfs(tmpfs)) => {
+                tmpfs.write_file(&file_names_to_root_file.join("/"), &bytes)?;
+            }
+            None => unreachable!(),
+        }
+
+        // advance offset
+        let fd = self.find_fd_mut(fd_num).unwrap();
+        fd.offset = offset + data.len();
+
+        Ok(())
+    }
+
+    fn create_file(&mut self, path: &str) -> Result<()> {
+        let path = Path::new(path).normalize();
+        let parent_dir_path = path.parent();
+        let parent_dir_path = parent_dir_path.as_str();
+        let file_name = path
+            .name()
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        let parent_dir_ref = self
+            .find_file_by_path(parent_dir_path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(parent_dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        let parent_dir_id = parent_dir_ref.id;
+        if self
+            .dir_files(&parent_dir_id)
+            .iter()
+            .any(|f| f.name == file_name)
+        {
+            return Err(VirtualFileSystemError::AlreadyExistsError.into());
+        }
+
+        // nodes outside of any mounted filesystem only live in the VFS tree
+        if let Some((fs_root_file_id, dir_names_to_root_file)) = self.find_fs_root(&parent_dir_id) {
+            let fs_root_file_ref = self
+                .find_file_mut(&fs_root_file_id)
+                .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+            match &mut fs_root_file_ref.fs {
+                Some(FileSystem::Initramfs(initramfs)) => {
+                    initramfs.reset_cwd();
+                    let res = dir_names_to_root_file
+                        .iter()
+                        .try_for_each(|name| initramfs.cd(name.as_str()))
+                        .and_then(|_| initramfs.create_file(file_name, &[]));
+                    initramfs.reset_cwd();
+                    res?;
+                }
+                Some(FileSystem::Tmpfs(tmpfs)) => {
+                    let mut names = dir_names_to_root_file;
+                    names.push(file_name.to_string());
+                    tmpfs.create_file(&names.join("/"))?;
+                }
+                None => unreachable!(),
+            }
+        }
+
+        let mut file = FileInfo {
+            id: FileId::new(),
+            ty: FileType::File,
+            fs: None,
+            name: file_name.to_string(),
+            parent: None,
+            child: None,
+            next: None,
+        };
+        self.add_file_into_directory(parent_dir_path, &mut file)?;
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    fn mkdir(&mut self, path: &str) -> Result<()> {
+        let path = Path::new(path).normalize();
+        let parent_dir_path = path.parent();
+        let parent_dir_path = parent_dir_path.as_str();
+        let dir_name = path
+            .name()
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        let parent_dir_ref = self
+            .find_file_by_path(parent_dir_path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(parent_dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        let parent_dir_id = parent_dir_ref.id;
+        if self
+            .dir_files(&parent_dir_id)
+            .iter()
+            .any(|f| f.name == dir_name)
+        {
+            return Err(VirtualFileSystemError::AlreadyExistsError.into());
+        }
+
+        if let Some((fs_root_file_id, dir_names_to_root_file)) = self.find_fs_root(&parent_dir_id) {
+            let fs_root_file_ref = self
+                .find_file_mut(&fs_root_file_id)
+                .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+            match &mut fs_root_file_ref.fs {
+                Some(FileSystem::Initramfs(initramfs)) => {
+                    initramfs.reset_cwd();
+                    let res = dir_names_to_root_file
+                        .iter()
+                        .try_for_each(|name| initramfs.cd(name.as_str()))
+                        .and_then(|_| initramfs.mkdir(dir_name));
+                    initramfs.reset_cwd();
+                    res?;
+                }
+                Some(FileSystem::Tmpfs(tmpfs)) => {
+                    let mut names = dir_names_to_root_file;
+                    names.push(dir_name.to_string());
+                    tmpfs.mkdir(&names.join("/"))?;
+                }
+                None => unreachable!(),
+            }
+        }
+
+        let mut dir = FileInfo {
+            id: FileId::new(),
+            ty: FileType::Directory,
+            fs: None,
+            name: dir_name.to_string(),
+            parent: None,
+            child: None,
+            next: None,
+        };
+        self.add_file_into_directory(parent_dir_path, &mut dir)?;
+        self.files.push(dir);
+
+        Ok(())
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        let link_path = Path::new(link_path).normalize();
+        let parent_dir_path = link_path.parent();
+        let parent_dir_path = parent_dir_path.as_str();
+        let link_name = link_path
+            .name()
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        let parent_dir_ref = self
+            .find_file_by_path(parent_dir_path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        if !self.is_directory(parent_dir_ref) {
+            return Err(VirtualFileSystemError::NotDirectoryError.into());
+        }
+
+        let parent_dir_id = parent_dir_ref.id;
+        if self
+            .dir_files(&parent_dir_id)
+            .iter()
+            .any(|f| f.name == link_name)
+        {
+            return Err(VirtualFileSystemError::AlreadyExistsError.into());
+        }
+
+        if let Some((fs_root_file_id, dir_names_to_root_file)) = self.find_fs_root(&parent_dir_id) {
+            let fs_root_file_ref = self
+                .find_file_mut(&fs_root_file_id)
+                .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+            match &mut fs_root_file_ref.fs {
+                Some(FileSystem::Initramfs(initramfs)) => {
+                    initramfs.reset_cwd();
+                    let res = dir_names_to_root_file
+                        .iter()
+                        .try_for_each(|name| initramfs.cd(name.as_str()))
+                        .and_then(|_| initramfs.create_symlink(link_name, target));
+                    initramfs.reset_cwd();
+                    res?;
+                }
+                Some(FileSystem::Tmpfs(tmpfs)) => {
+                    let mut names = dir_names_to_root_file;
+                    names.push(link_name.to_string());
+                    tmpfs.symlink(target, &names.join("/"))?;
+                }
+                None => unreachable!(),
+            }
+        }
+
+        let mut link = FileInfo {
+            id: FileId::new(),
+            ty: FileType::Symlink(target.to_string()),
+            fs: None,
+            name: link_name.to_string(),
+            parent: None,
+            child: None,
+            next: None,
+        };
+        self.add_file_into_directory(parent_dir_path, &mut link)?;
+        self.files.push(link);
+
+        Ok(())
+    }
+
+    // symbolic links are removed instead of their targets
+    fn remove(&mut self, path: &str, recursive: bool) -> Result<()> {
+        let file_ref = self
+            .find_file_by_path_with(path, false)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        let file_id = file_ref.id;
+        let file_name = file_ref.name.clone();
+
+        if file_ref.fs.is_some() {
+            return Err(Error::Failed("Mounted filesystem can not be removed"));
+        }
+
+        let parent_id = match file_ref.parent {
+            Some(id) if id != file_id => id,
+            _ => return Err(Error::Failed("Root directory can not be removed")),
+        };
+
+        if self.is_directory(file_ref) && file_ref.child.is_some() && !recursive {
+            return Err(VirtualFileSystemError::DirectoryNotEmptyError.into());
+        }
+
+        let removed_ids = self.subtree_file_ids(&file_id);
+        if removed_ids.contains(&self.cwd_id) || removed_ids.contains(&self.root_id) {
+            return Err(Error::Failed("Directory is in use"));
+        }
+
+        // the mounted filesystem would be dropped with the directory
+        if removed_ids
+            .iter()
+            .any(|id| self.find_file(id).is_some_and(|f| f.fs.is_some()))
+        {
+            return Err(VirtualFileSystemError::ResourceBusyError.into());
+        }
+
+        if let Some((fs_root_file_id, dir_names_to_root_file)) = self.find_fs_root(&parent_id) {
+            let fs_root_file_ref = self
+                .find_file_mut(&fs_root_file_id)
+                .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+            match &mut fs_root_file_ref.fs {
+                Some(FileSystem::Initramfs(initramfs)) => {
+                    initramfs.reset_cwd();
+                    let res = dir_names_to_root_file
+                        .iter()
+                        .try_for_each(|name| initramfs.cd(name.as_str()))
+                        .and_then(|_| initramfs.remove(&file_name));
+                    initramfs.reset_cwd();
+                    res?;
+                }
+                Some(FileSystem::Tmpfs(tmpfs)) => {
+                    let mut names = dir_names_to_root_file;
+                    names.push(file_name.clone());
+                    tmpfs.remove(&names.join("/"))?;
+                }
+                None => unreachable!(),
+            }
+        }
+
+        self.unlink_file(&file_id)?;
+
+        // release file descriptors, closing them later returns an error
+        self.file_descs
+            .retain(|fd| !removed_ids.contains(&fd.file_id));
+
+        self.files.retain(|f| !removed_ids.contains(&f.id));
+
+        Ok(())
+    }
+
+    // unlink from parent directory
+    fn unlink_file(&mut self, file_id: &FileId) -> Result<()> {
+        let file_ref = self
+            .find_file(file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        let next_id = file_ref.next;
+        let parent_id = file_ref
+            .parent
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        let parent_ref = self
+            .find_file_mut(&parent_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        if parent_ref.child == Some(*file_id) {
+            parent_ref.child = next_id;
+        } else if let Some(prev_ref) = self.files.iter_mut().find(|f| f.next == Some(*file_id)) {
+            prev_ref.next = next_id;
+        }
+
+        Ok(())
+    }
+
+    fn subtree_file_ids(&self, file_id: &FileId) -> Vec<FileId> {
+        let mut ids = Vec::new();
+        let mut stack = vec![*file_id];
+
+        while let Some(id) = stack.pop() {
+            ids.push(id);
+            stack.extend(self.dir_files(&id).iter().map(|f| f.id));
+        }
+
+        ids
+    }
+
+    fn stat(&mut self, path: &str) -> Result<FileStat> {
+        let file_id = self
+            .find_file_by_path(path)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?
+            .id;
+        self.stat_file(&file_id)
+    }
+
+    fn fstat(&mut self, fd_num: &FileDescriptorNumber) -> Result<FileStat> {
+        let file_id = self
+            .find_fd(fd_num)
+            .ok_or(VirtualFileSystemError::ReleasedFileResourceError(
+                fd_num.clone(),
+            ))?
+            .file_id;
+        self.stat_file(&file_id)
+    }
+
+    fn stat_file(&mut self, file_id: &FileId) -> Result<FileStat> {
+        let file_ref = self
+            .find_file(file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+        let is_dir = self.is_directory(file_ref);
+        let is_symlink = matches!(file_ref.ty, FileType::Symlink(_));
+
+        let (fs_root_file_id, file_names_to_root_file) = match self.find_fs_root(file_id) {
+            Some(r) => r,
+            None => {
+                let size = match &file_ref.ty {
+                    FileType::Symlink(target) => target.len(),
+                    FileType::Special(special_file) if !special_file.is_stream() => {
+                        special_file.read_bytes().map(|b| b.len()).unwrap_or(0)
+                    }
+                    _ => 0,
+                };
+
+                return Ok(FileStat {
+                    size,
+                    is_dir,
+                    fs_kind: FileSystemKind::Vfs,
+                });
+            }
+        };
+        let fs_root_file_ref = self
+            .find_file_mut(&fs_root_file_id)
+            .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
+
+        match &mut fs_root_file_ref.fs {
+            Some(FileSystem::Initramfs(initramfs)) => {
+                if is_dir {
+                    return Ok(FileStat {
+                        size: 0,
+                        is_dir,
+                        fs_kind: FileSystemKind::Initramfs,
+                    });
+                }
+
+                initramfs.reset_cwd();
+                for i in 0..file_names_to_root_file.len() - 1 {
+                    initramfs.cd(file_names_to_root_file[i].as_str())?;
+                }
+
+                // size of the target path like lstat
+                let file_name = file_names_to_root_file.last().unwrap();
+                let size = if is_symlink {
+                    initramfs.read_link(file_name).map(|target| target.len())
+                } else {
+                    initramfs.get_file_metadata(file_name).map(|m| m.size)
+                };
                 initramfs.reset_cwd();
-                return Ok(bytes);
+
+                Ok(FileStat {
+                    size: size?,
+                    is_dir,
+                    fs_kind: FileSystemKind::Initramfs,
+                })
+            }
+            Some(FileSystem::Tmpfs(tmpfs)) => {
+                let path = file_names_to_root_file.join("/");
+                let size = if is_dir {
+                    0
+                } else if is_symlink {
+                    tmpfs.read_link(&path)?.len()
+                } else {
+                    tmpfs.file_size(&path)?
+                };
+
+                Ok(FileStat {
+                    size,
+                    is_dir,
+                    fs_kind: FileSystemKind::Tmpfs,
+                })
             }
             None => unreachable!(),
         }
     }
 
+    // "/proc/<pid>/status"
+    fn add_process_entry(&mut self, pid: usize) -> Result<()> {
+        let dir_path = format!("{}/{}", PROC_DIR_PATH, pid);
+        self.mkdir(&dir_path)?;
+
+        let mut status_file = FileInfo {
+            id: FileId::new(),
+            ty: FileType::Special(SpecialFile::ProcessStatus(pid)),
+            fs: None,
+            name: PROC_STATUS_FILE_NAME.to_string(),
+            parent: None,
+            child: None,
+            next: None,
+        };
+        self.add_file_into_directory(&dir_path, &mut status_file)?;
+        self.files.push(status_file);
+
+        Ok(())
+    }
+
+    fn remove_process_entry(&mut self, pid: usize) -> Result<()> {
+        self.remove(&format!("{}/{}", PROC_DIR_PATH, pid), true)
+    }
+
+    // returns file id of the mounted filesystem root and file names from it
+    fn find_fs_root(&self, file_id: &FileId) -> Option<(FileId, Vec<String>)> {
+        let mut file_names_to_root_file = Vec::new();
+        let mut file_ref = self.find_file(file_id)?;
+
+        while file_ref.fs.is_none() {
+            let parent_file_id = file_ref.parent?;
+
+            // reached VFS root
+            if parent_file_id == file_ref.id {
+                return None;
+            }
+
+            file_names_to_root_file.push(file_ref.name.clone());
+            file_ref = self.find_file(&parent_file_id)?;
+        }
+        file_names_to_root_file.reverse();
+
+        Some((file_ref.id, file_names_to_root_file))
+    }
+
     fn find_fd(&self, num: &FileDescriptorNumber) -> Option<&FileDescriptor> {
-        self.file_descs.iter().find(|fd| fd.num == *num)
+        self.file_descs
+            .iter()
+            .find(|fd| fd.num == *num && fd.status == file_desc::Status::Open)
     }
 
     fn find_fd_mut(&mut self, num: &FileDescriptorNumber) -> Option<&mut FileDescriptor> {
-        self.file_descs.iter_mut().find(|fd| fd.num == *num)
+        self.file_descs
+            .iter_mut()
+            .find(|fd| fd.num == *num && fd.status == file_desc::Status::Open)
     }
 
     fn find_fd_by_file_id(&self, file_id: &FileId) -> Option<&FileDescriptor> {
         self.file_descs.iter().find(|fd| fd.file_id == *file_id)
     }
 
+    fn is_stream_file(&self, file_id: &FileId) -> bool {
+        matches!(
+            self.find_file(file_id).map(|f| &f.ty),
+            Some(FileType::Special(special_file)) if special_file.is_stream()
+        )
+    }
+
     fn is_directory(&self, file_ref: &FileInfo) -> bool {
         file_ref.ty == FileType::Directory || file_ref.fs.is_some()
     }
@@ -465,7 +1349,7 @@ impl VirtualFileSystem {
         let parent_dir_file_ref = self
             .find_file_by_path_mut(parent_dir_path)
             .ok_or(VirtualFileSystemError::NoSuchFileOrDirectoryError)?;
-        if parent_dir_file_ref.ty != FileType::Directory {
+        if parent_dir_file_ref.ty != FileType::Directory && parent_dir_file_ref.fs.is_none() {
             return Err(VirtualFileSystemError::NotDirectoryError.into());
         }
         target_file_ref.parent = Some(parent_dir_file_ref.id);
@@ -516,14 +1400,18 @@ pub fn mount(path: &str, fs: FileSystem) -> Result<()> {
         .mount(path, fs)
 }
 
-pub fn cwd_entry_names() -> Result<Vec<String>> {
-    Ok(unsafe { VFS.try_lock() }?
+pub fn umount(path: &str) -> Result<FileSystem> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .umount(path)
+}
+
+pub fn entry_names(path: &str) -> Result<Vec<String>> {
+    unsafe { VFS.try_lock() }?
         .as_mut()
         .ok_or(VirtualFileSystemError::NotInitialized)?
-        .cwd_files()
-        .iter()
-        .map(|f| f.name.clone())
-        .collect())
+        .entry_names(path)
 }
 
 pub fn cwd_path() -> Result<String> {
@@ -533,6 +1421,29 @@ pub fn cwd_path() -> Result<String> {
         .cwd_path()
 }
 
+pub fn readdir(path: &str) -> Result<Vec<DirEntry>> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .readdir(path)
+}
+
+// create a symbolic link at link_path pointing to target
+pub fn symlink(target: &str, link_path: &str) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .symlink(target, link_path)
+}
+
+// absolute path of an existing file, "." and ".." are resolved
+pub fn abs_path(path: &str) -> Result<String> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .abs_path(path)
+}
+
 pub fn open_file(path: &str) -> Result<FileDescriptorNumber> {
     let fd_num = unsafe { VFS.try_lock() }?
         .as_mut()
@@ -549,9 +1460,106 @@ pub fn close_file(fd_num: &FileDescriptorNumber) -> Result<()> {
     Ok(())
 }
 
+// read the rest of the file from the current offset
 pub fn read_file(fd_num: &FileDescriptorNumber) -> Result<Vec<u8>> {
+    read_file_max(fd_num, usize::MAX)
+}
+
+pub fn read_file_max(fd_num: &FileDescriptorNumber, max_len: usize) -> Result<Vec<u8>> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .read_file(fd_num, max_len)
+}
+
+pub fn mkdir(path: &str) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .mkdir(path)
+}
+
+pub fn remove(path: &str, recursive: bool) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .remove(path, recursive)
+}
+
+pub fn stat(path: &str) -> Result<FileStat> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .stat(path)
+}
+
+pub fn fstat(fd_num: &FileDescriptorNumber) -> Result<FileStat> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .fstat(fd_num)
+}
+
+pub fn seek(fd_num: &FileDescriptorNumber, pos: i64, whence: Whence) -> Result<usize> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .seek(fd_num, pos, whence)
+}
+
+pub fn write_file(fd_num: &FileDescriptorNumber, data: &[u8]) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .write_file(fd_num, data)
+}
+
+pub fn create_file(path: &str) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .create_file(path)
+}
+
+pub fn pipe() -> Result<(FileDescriptorNumber, FileDescriptorNumber)> {
+    Ok(unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .pipe())
+}
+
+pub fn is_pipe(fd_num: &FileDescriptorNumber) -> Result<bool> {
+    Ok(unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .find_pipe_mut(fd_num)
+        .is_some())
+}
+
+pub fn read_pipe(fd_num: &FileDescriptorNumber, max_len: usize) -> Result<Vec<u8>> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .read_pipe(fd_num, max_len)
+}
+
+pub fn write_pipe(fd_num: &FileDescriptorNumber, data: &[u8]) -> Result<usize> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .write_pipe(fd_num, data)
+}
+
+pub fn add_process_entry(pid: usize) -> Result<()> {
+    unsafe { VFS.try_lock() }?
+        .as_mut()
+        .ok_or(VirtualFileSystemError::NotInitialized)?
+        .add_process_entry(pid)
+}
+
+pub fn remove_process_entry(pid: usize) -> Result<()> {
     unsafe { VFS.try_lock() }?
         .as_mut()
         .ok_or(VirtualFileSystemError::NotInitialized)?
-        .read_file(fd_num)
+        .remove_process_entry(pid)
 }
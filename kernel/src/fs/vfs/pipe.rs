@@ -0,0 +1,60 @@
+use super::file_desc::FileDescriptorNumber;
+use crate::util::fifo::Fifo;
+use alloc::{boxed::Box, vec::Vec};
+
+pub const PIPE_BUF_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub struct Pipe {
+    // None if the end was closed
+    pub read_fd: Option<FileDescriptorNumber>,
+    pub write_fd: Option<FileDescriptorNumber>,
+    buf: Box<Fifo<u8, PIPE_BUF_SIZE>>,
+}
+
+impl Pipe {
+    pub fn new() -> Self {
+        Self {
+            read_fd: Some(FileDescriptorNumber::new()),
+            write_fd: Some(FileDescriptorNumber::new()),
+            buf: Box::new(Fifo::new(0)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.read_fd.is_none() && self.write_fd.is_none()
+    }
+
+    // read up to max_len bytes
+    pub fn read(&mut self, max_len: usize) -> Vec<u8> {
+        let mut data = vec![0; max_len.min(self.buf.len())];
+        let len = self.buf.dequeue_slice(&mut data).unwrap_or(0);
+        data.truncate(len);
+        data
+    }
+
+    // return written length, less than data.len() if the buffer is full
+    pub fn write(&mut self, data: &[u8]) -> usize {
+        self.buf.enqueue_slice(data).unwrap_or(0)
+    }
+}
+
+#[test_case]
+fn test_read_write() {
+    let mut pipe = Pipe::new();
+    assert!(pipe.is_empty());
+
+    assert_eq!(pipe.write(b"hello"), 5);
+    assert_eq!(pipe.read(3), b"hel");
+    assert_eq!(pipe.read(10), b"lo");
+    assert!(pipe.is_empty());
+
+    let data = [0xaa; PIPE_BUF_SIZE];
+    assert_eq!(pipe.write(&data), PIPE_BUF_SIZE - 1);
+    assert_eq!(pipe.write(&data), 0);
+    assert_eq!(pipe.read(PIPE_BUF_SIZE).len(), PIPE_BUF_SIZE - 1);
+}
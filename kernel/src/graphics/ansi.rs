@@ -0,0 +1,277 @@
+use super::color::ColorCode;
+use alloc::vec::Vec;
+
+const ESC: char = '\x1b';
+const CSI_START: char = '[';
+const CSI_PARAMS_MAX_LEN: usize = 16;
+// "ESC[?25l" etc., private parameters are not interpreted
+const CSI_PRIVATE_PARAM_PREFIXES: [char; 4] = ['<', '=', '>', '?'];
+const SGR_EXTENDED_FORE_COLOR: usize = 38;
+
+// standard colors (30-37) followed by bright colors (90-97)
+pub const ANSI_COLORS: [ColorCode; 16] = [
+    ColorCode::new_rgb(0x00, 0x00, 0x00),
+    ColorCode::new_rgb(0xaa, 0x00, 0x00),
+    ColorCode::new_rgb(0x00, 0xaa, 0x00),
+    ColorCode::new_rgb(0xaa, 0x55, 0x00),
+    ColorCode::new_rgb(0x00, 0x00, 0xaa),
+    ColorCode::new_rgb(0xaa, 0x00, 0xaa),
+    ColorCode::new_rgb(0x00, 0xaa, 0xaa),
+    ColorCode::new_rgb(0xaa, 0xaa, 0xaa),
+    ColorCode::new_rgb(0x55, 0x55, 0x55),
+    ColorCode::new_rgb(0xff, 0x55, 0x55),
+    ColorCode::new_rgb(0x55, 0xff, 0x55),
+    ColorCode::new_rgb(0xff, 0xff, 0x55),
+    ColorCode::new_rgb(0x55, 0x55, 0xff),
+    ColorCode::new_rgb(0xff, 0x55, 0xff),
+    ColorCode::new_rgb(0x55, 0xff, 0xff),
+    ColorCode::new_rgb(0xff, 0xff, 0xff),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SgrParam {
+    Reset,
+    ForeColor(ColorCode),
+    DefaultForeColor,
+    Unsupported(usize),
+}
+
+impl SgrParam {
+    pub fn new(param: usize) -> Self {
+        match param {
+            0 => Self::Reset,
+            30..=37 => Self::ForeColor(ANSI_COLORS[param - 30]),
+            39 => Self::DefaultForeColor,
+            90..=97 => Self::ForeColor(ANSI_COLORS[param - 90 + 8]),
+            p => Self::Unsupported(p),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiOutput {
+    Char(char),
+    Sgr(Vec<SgrParam>),
+    CursorUp(usize),
+    CursorDown(usize),
+    CursorForward(usize),
+    CursorBack(usize),
+    // 0-based
+    CursorPosition { x: usize, y: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AnsiParserState {
+    Normal,
+    Escape,
+    Csi,
+}
+
+#[derive(Debug)]
+pub struct AnsiParser {
+    state: AnsiParserState,
+    params: Vec<Option<usize>>,
+    is_private: bool,
+}
+
+impl AnsiParser {
+    pub const fn new() -> Self {
+        Self {
+            state: AnsiParserState::Normal,
+            params: Vec::new(),
+            is_private: false,
+        }
+    }
+
+    // none while the char is a part of an escape sequence
+    pub fn input(&mut self, c: char) -> Option<AnsiOutput> {
+        match self.state {
+            AnsiParserState::Normal if c == ESC => {
+                self.state = AnsiParserState::Escape;
+                None
+            }
+            AnsiParserState::Normal => Some(AnsiOutput::Char(c)),
+            AnsiParserState::Escape if c == CSI_START => {
+                self.state = AnsiParserState::Csi;
+                self.params.clear();
+                self.is_private = false;
+                None
+            }
+            // unsupported escape sequence
+            AnsiParserState::Escape => {
+                self.state = AnsiParserState::Normal;
+                None
+            }
+            AnsiParserState::Csi => self.input_csi(c),
+        }
+    }
+
+    fn input_csi(&mut self, c: char) -> Option<AnsiOutput> {
+        if CSI_PRIVATE_PARAM_PREFIXES.contains(&c) && self.params.is_empty() && !self.is_private {
+            self.is_private = true;
+            return None;
+        }
+
+        if let Some(digit) = c.to_digit(10) {
+            if self.params.is_empty() {
+                self.params.push(None);
+            }
+
+            let param = self.params.last_mut().unwrap();
+            *param = Some(
+                param
+                    .unwrap_or(0)
+                    .saturating_mul(10)
+                    .saturating_add(digit as usize),
+            );
+            return None;
+        }
+
+        if c == ';' {
+            if self.params.is_empty() {
+                self.params.push(None);
+            }
+
+            if self.params.len() >= CSI_PARAMS_MAX_LEN {
+                self.state = AnsiParserState::Normal;
+                return None;
+            }
+
+            self.params.push(None);
+            return None;
+        }
+
+        self.state = AnsiParserState::Normal;
+
+        if self.is_private {
+            return None;
+        }
+
+        // cursor moves are at least 1
+        let count = self.param(0).unwrap_or(1).max(1);
+        match c {
+            'm' if self.params.is_empty() => Some(AnsiOutput::Sgr(vec![SgrParam::Reset])),
+            'm' => Some(AnsiOutput::Sgr(self.sgr_params())),
+            'A' => Some(AnsiOutput::CursorUp(count)),
+            'B' => Some(AnsiOutput::CursorDown(count)),
+            'C' => Some(AnsiOutput::CursorForward(count)),
+            'D' => Some(AnsiOutput::CursorBack(count)),
+            'H' | 'f' => Some(AnsiOutput::CursorPosition {
+                x: self.param(1).unwrap_or(1).max(1) - 1,
+                y: self.param(0).unwrap_or(1).max(1) - 1,
+            }),
+            // unsupported control sequence
+            _ => None,
+        }
+    }
+
+    fn sgr_params(&self) -> Vec<SgrParam> {
+        let mut sgr_params = Vec::new();
+        let mut params = self.params.iter().map(|p| p.unwrap_or(0));
+
+        while let Some(param) = params.next() {
+            // 24-bit color: 38;2;r;g;b
+            if param == SGR_EXTENDED_FORE_COLOR {
+                let mut next = || params.next().unwrap_or(0);
+                let sgr_param = match next() {
+                    2 => {
+                        let (r, g, b) = (next(), next(), next());
+                        SgrParam::ForeColor(ColorCode::new_rgb(
+                            r.min(0xff) as u8,
+                            g.min(0xff) as u8,
+                            b.min(0xff) as u8,
+                        ))
+                    }
+                    // 256 colors: 38;5;n
+                    5 => {
+                        next();
+                        SgrParam::Unsupported(param)
+                    }
+                    _ => SgrParam::Unsupported(param),
+                };
+                sgr_params.push(sgr_param);
+                continue;
+            }
+
+            sgr_params.push(SgrParam::new(param));
+        }
+
+        sgr_params
+    }
+
+    fn param(&self, index: usize) -> Option<usize> {
+        self.params.get(index).copied().flatten()
+    }
+}
+
+#[test_case]
+fn test_parse_chars() {
+    let mut parser = AnsiParser::new();
+    assert_eq!(parser.input('a'), Some(AnsiOutput::Char('a')));
+    assert_eq!(parser.input('\n'), Some(AnsiOutput::Char('\n')));
+
+    // unsupported escape sequence is dropped
+    assert_eq!(parser.input(ESC), None);
+    assert_eq!(parser.input('c'), None);
+    assert_eq!(parser.input('b'), Some(AnsiOutput::Char('b')));
+}
+
+#[test_case]
+fn test_parse_sgr() {
+    let parse = |s: &str| {
+        let mut parser = AnsiParser::new();
+        s.chars()
+            .filter_map(|c| parser.input(c))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(parse("\x1b[m"), [AnsiOutput::Sgr(vec![SgrParam::Reset])]);
+    assert_eq!(
+        parse("\x1b[31mx\x1b[0m"),
+        [
+            AnsiOutput::Sgr(vec![SgrParam::ForeColor(ANSI_COLORS[1])]),
+            AnsiOutput::Char('x'),
+            AnsiOutput::Sgr(vec![SgrParam::Reset]),
+        ]
+    );
+    assert_eq!(
+        parse("\x1b[1;97;39m"),
+        [AnsiOutput::Sgr(vec![
+            SgrParam::Unsupported(1),
+            SgrParam::ForeColor(ANSI_COLORS[15]),
+            SgrParam::DefaultForeColor,
+        ])]
+    );
+    assert_eq!(
+        parse("\x1b[38;2;255;128;0;0m"),
+        [AnsiOutput::Sgr(vec![
+            SgrParam::ForeColor(ColorCode::new_rgb(255, 128, 0)),
+            SgrParam::Reset,
+        ])]
+    );
+}
+
+#[test_case]
+fn test_parse_cursor() {
+    let parse = |s: &str| {
+        let mut parser = AnsiParser::new();
+        s.chars()
+            .filter_map(|c| parser.input(c))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(parse("\x1b[A"), [AnsiOutput::CursorUp(1)]);
+    assert_eq!(parse("\x1b[12B"), [AnsiOutput::CursorDown(12)]);
+    assert_eq!(parse("\x1b[0C"), [AnsiOutput::CursorForward(1)]);
+    assert_eq!(parse("\x1b[3D"), [AnsiOutput::CursorBack(3)]);
+    assert_eq!(parse("\x1b[H"), [AnsiOutput::CursorPosition { x: 0, y: 0 }]);
+    assert_eq!(
+        parse("\x1b[5;10f"),
+        [AnsiOutput::CursorPosition { x: 9, y: 4 }]
+    );
+    // unsupported control sequence
+    assert_eq!(parse("\x1b[2Jz"), [AnsiOutput::Char('z')]);
+    // private parameters
+    assert_eq!(parse("\x1b[?25lz\x1b[?25h"), [AnsiOutput::Char('z')]);
+    assert_eq!(parse("\x1b[?1;2Az"), [AnsiOutput::Char('z')]);
+}
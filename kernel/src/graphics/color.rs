@@ -6,12 +6,12 @@ pub struct ColorCode {
     pub r: u8,
     pub g: u8,
     pub b: u8,
-    pub a: u8,
+    pub a: u8, // 0xff is opaque, only used by alpha enabled layers
 }
 
 impl ColorCode {
     pub const fn new_rgb(r: u8, g: u8, b: u8) -> Self {
-        Self { r, g, b, a: 0 }
+        Self { r, g, b, a: 0xff }
     }
 
     pub const fn new_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
@@ -24,13 +24,13 @@ impl ColorCode {
                 r: (data >> 16) as u8,
                 g: (data >> 8) as u8,
                 b: (data >> 0) as u8,
-                a: 0,
+                a: 0xff,
             },
             PixelFormat::Rgb => Self {
                 r: (data >> 0) as u8,
                 g: (data >> 8) as u8,
                 b: (data >> 16) as u8,
-                a: 0,
+                a: 0xff,
             },
             PixelFormat::Bgra => Self {
                 r: (data >> 16) as u8,
@@ -41,6 +41,19 @@ impl ColorCode {
         }
     }
 
+    // source-over compositing onto an opaque destination
+    pub fn blend_over(&self, dst: ColorCode) -> ColorCode {
+        let a = self.a as u32;
+        let blend = |s: u8, d: u8| ((s as u32 * a + d as u32 * (0xff - a) + 0x7f) / 0xff) as u8;
+
+        Self {
+            r: blend(self.r, dst.r),
+            g: blend(self.g, dst.g),
+            b: blend(self.b, dst.b),
+            a: dst.a,
+        }
+    }
+
     pub fn to_color_code(&self, pixel_format: PixelFormat) -> u32 {
         match pixel_format {
             PixelFormat::Bgr => (self.r as u32) << 16 | (self.g as u32) << 8 | (self.b as u32) << 0,
@@ -54,3 +67,17 @@ impl ColorCode {
         }
     }
 }
+
+#[test_case]
+fn test_blend_over() {
+    let dst = ColorCode::new_rgb(0, 0, 0);
+    assert_eq!(
+        ColorCode::new_rgb(10, 20, 30).blend_over(dst),
+        ColorCode::new_rgb(10, 20, 30)
+    );
+    assert_eq!(ColorCode::new_rgba(10, 20, 30, 0).blend_over(dst), dst);
+    assert_eq!(
+        ColorCode::new_rgba(255, 255, 255, 128).blend_over(dst),
+        ColorCode::new_rgb(128, 128, 128)
+    );
+}
@@ -1,109 +1,160 @@
 use crate::error::Result;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    ops::Deref,
+    ptr::null_mut,
+    str,
+    sync::atomic::{AtomicPtr, Ordering},
+};
 
 //PSF font v2
 const FONT_BIN: &'static [u8] = include_bytes!("../../../third-party/font.psf");
-const FONT_MAGIC_NUM: u32 = 0x864ab572;
-const UNICODE_TABLE_SEPARATOR: u8 = 0xff;
+const PSF1_MAGIC_NUM: u16 = 0x0436;
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TAB: u8 = 0x02;
+const PSF1_MODE_SEQ: u8 = 0x04;
+const PSF1_HEADER_SIZE: usize = 4;
+const PSF1_UNICODE_TABLE_SEPARATOR: u16 = 0xffff;
+const PSF1_UNICODE_TABLE_SEQ_START: u16 = 0xfffe;
+const PSF2_MAGIC_NUM: u32 = 0x864ab572;
+const PSF2_FLAG_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_UNICODE_TABLE_SEPARATOR: u8 = 0xff;
+const PSF2_UNICODE_TABLE_SEQ_START: u8 = 0xfe;
 pub const TAB_DISP_STR: &str = "    ";
 
-pub static FONT: PsfFont = PsfFont::new();
+static DEFAULT_FONT: PsfFont = PsfFont::new();
+pub static FONT: ActiveFont = ActiveFont::new();
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FontError {
     FontGlyphError,
+    InvalidFontBinaryError,
+    UnsupportedFontVersionError,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnicodeTable {
+    None,
+    Psf1 { offset: usize },
+    Psf2 { offset: usize },
+}
+
+#[derive(Debug)]
 pub struct PsfFont {
-    binary_len: usize,
+    binary: &'static [u8],
     height: usize,
     width: usize,
     glyphs_len: usize,
     glyph_size: usize,
-    has_unicode_table: bool,
+    unicode_table: UnicodeTable,
     header_size: usize,
-    unicode_table_offset: usize,
 }
 
 impl PsfFont {
+    // built-in font
     pub const fn new() -> Self {
-        const fn get_magic_num() -> u32 {
-            (FONT_BIN[3] as u32) << 24
-                | (FONT_BIN[2] as u32) << 16
-                | (FONT_BIN[1] as u32) << 8
-                | FONT_BIN[0] as u32
+        const fn read_u32(offset: usize) -> u32 {
+            (FONT_BIN[offset + 3] as u32) << 24
+                | (FONT_BIN[offset + 2] as u32) << 16
+                | (FONT_BIN[offset + 1] as u32) << 8
+                | FONT_BIN[offset] as u32
         }
 
-        const fn get_pixel_height() -> u32 {
-            (FONT_BIN[27] as u32) << 24
-                | (FONT_BIN[26] as u32) << 16
-                | (FONT_BIN[25] as u32) << 8
-                | FONT_BIN[24] as u32
+        if read_u32(0) != PSF2_MAGIC_NUM {
+            panic!("Invalid font binary");
         }
 
-        const fn get_pixel_width() -> u32 {
-            (FONT_BIN[31] as u32) << 24
-                | (FONT_BIN[30] as u32) << 16
-                | (FONT_BIN[29] as u32) << 8
-                | FONT_BIN[28] as u32
-        }
+        let header_size = read_u32(8) as usize;
+        let glyphs_len = read_u32(16) as usize;
+        let glyph_size = read_u32(20) as usize;
+        let unicode_table = if read_u32(12) & PSF2_FLAG_HAS_UNICODE_TABLE != 0 {
+            UnicodeTable::Psf2 {
+                offset: header_size + glyph_size * glyphs_len,
+            }
+        } else {
+            UnicodeTable::None
+        };
 
-        const fn get_glyphs_len() -> u32 {
-            (FONT_BIN[19] as u32) << 24
-                | (FONT_BIN[18] as u32) << 16
-                | (FONT_BIN[17] as u32) << 8
-                | FONT_BIN[16] as u32
+        Self {
+            binary: FONT_BIN,
+            height: read_u32(24) as usize,
+            width: read_u32(28) as usize,
+            glyphs_len,
+            glyph_size,
+            unicode_table,
+            header_size,
         }
+    }
 
-        const fn get_glyph_size() -> u32 {
-            (FONT_BIN[23] as u32) << 24
-                | (FONT_BIN[22] as u32) << 16
-                | (FONT_BIN[21] as u32) << 8
-                | FONT_BIN[20] as u32
-        }
+    pub fn parse(binary: &'static [u8]) -> Result<Self> {
+        let read_u32 = |offset: usize| -> Result<u32> {
+            let bytes = binary
+                .get(offset..offset + 4)
+                .ok_or(FontError::InvalidFontBinaryError)?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
 
-        const fn has_unicode_table() -> bool {
-            let flags = (FONT_BIN[15] as u32) << 24
-                | (FONT_BIN[14] as u32) << 16
-                | (FONT_BIN[13] as u32) << 8
-                | FONT_BIN[12] as u32;
+        let font = if binary.len() >= PSF1_HEADER_SIZE
+            && u16::from_le_bytes([binary[0], binary[1]]) == PSF1_MAGIC_NUM
+        {
+            let mode = binary[2];
+            let height = binary[3] as usize;
+            let glyphs_len = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+            let unicode_table = if mode & (PSF1_MODE_HAS_TAB | PSF1_MODE_SEQ) != 0 {
+                UnicodeTable::Psf1 {
+                    offset: PSF1_HEADER_SIZE + height * glyphs_len,
+                }
+            } else {
+                UnicodeTable::None
+            };
 
-            flags == 1
-        }
+            Self {
+                binary,
+                height,
+                width: 8,
+                glyphs_len,
+                glyph_size: height,
+                unicode_table,
+                header_size: PSF1_HEADER_SIZE,
+            }
+        } else if read_u32(0)? == PSF2_MAGIC_NUM {
+            if read_u32(4)? != 0 {
+                return Err(FontError::UnsupportedFontVersionError.into());
+            }
 
-        const fn get_header_size() -> u32 {
-            (FONT_BIN[11] as u32) << 24
-                | (FONT_BIN[10] as u32) << 16
-                | (FONT_BIN[9] as u32) << 8
-                | FONT_BIN[8] as u32
-        }
+            let header_size = read_u32(8)? as usize;
+            let glyphs_len = read_u32(16)? as usize;
+            let glyph_size = read_u32(20)? as usize;
+            let unicode_table = if read_u32(12)? & PSF2_FLAG_HAS_UNICODE_TABLE != 0 {
+                UnicodeTable::Psf2 {
+                    offset: header_size + glyph_size * glyphs_len,
+                }
+            } else {
+                UnicodeTable::None
+            };
 
-        if get_magic_num() != FONT_MAGIC_NUM {
-            panic!("Invalid font binary");
-        }
+            Self {
+                binary,
+                height: read_u32(24)? as usize,
+                width: read_u32(28)? as usize,
+                glyphs_len,
+                glyph_size,
+                unicode_table,
+                header_size,
+            }
+        } else {
+            return Err(FontError::InvalidFontBinaryError.into());
+        };
 
-        let binary_len = FONT_BIN.len();
-        let height = get_pixel_height() as usize;
-        let width = get_pixel_width() as usize;
-        let glyphs_len = get_glyphs_len() as usize;
-        let glyph_size = get_glyph_size() as usize;
-        let has_unicode_table = has_unicode_table();
-        let header_size = get_header_size() as usize;
-        let unicode_table_offset = header_size + glyph_size * glyphs_len;
-
-        if height > 16 || width > 8 {
-            panic!("Unsupported font size");
+        if font.width == 0
+            || font.height == 0
+            || font.glyph_size < font.bytes_per_row() * font.height
+            || binary.len() < font.header_size + font.glyph_size * font.glyphs_len
+        {
+            return Err(FontError::InvalidFontBinaryError.into());
         }
 
-        Self {
-            binary_len,
-            height,
-            width,
-            glyphs_len,
-            glyph_size,
-            has_unicode_table,
-            header_size,
-            unicode_table_offset,
-        }
+        Ok(font)
     }
 
     pub fn get_height(&self) -> usize {
@@ -114,34 +165,132 @@ impl PsfFont {
         self.width
     }
 
-    // ascii char only
     pub fn unicode_char_to_glyph_index(&self, c: char) -> usize {
-        if !self.has_unicode_table {
-            return c as usize;
+        let index = match self.unicode_table {
+            UnicodeTable::None => Some(c as usize),
+            UnicodeTable::Psf1 { offset } => self.psf1_glyph_index(offset, c),
+            UnicodeTable::Psf2 { offset } => self.psf2_glyph_index(offset, c),
+        };
+
+        // glyph 0 is used for unmapped chars
+        index.filter(|i| *i < self.glyphs_len).unwrap_or(0)
+    }
+
+    pub fn get_glyph(&self, index: usize) -> Result<&'static [u8]> {
+        if index >= self.glyphs_len {
+            return Err(FontError::FontGlyphError.into());
         }
 
-        let code_point = c as u8;
+        let offset = self.header_size + self.glyph_size * index;
+        Ok(&self.binary[offset..offset + self.glyph_size])
+    }
+
+    // each row is padded to a byte boundary
+    pub fn is_glyph_pixel_set(&self, glyph: &[u8], x: usize, y: usize) -> bool {
+        glyph[y * self.bytes_per_row() + x / 8] & (0x80 >> (x % 8)) != 0
+    }
+
+    fn bytes_per_row(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    fn psf1_glyph_index(&self, offset: usize, c: char) -> Option<usize> {
         let mut index = 0;
+        let mut is_seq = false;
 
-        for i in self.unicode_table_offset..self.binary_len {
-            if code_point == FONT_BIN[i] {
-                break;
+        for entry in self.binary.get(offset..)?.chunks_exact(2) {
+            match u16::from_le_bytes([entry[0], entry[1]]) {
+                PSF1_UNICODE_TABLE_SEPARATOR => {
+                    index += 1;
+                    is_seq = false;
+                }
+                PSF1_UNICODE_TABLE_SEQ_START => is_seq = true,
+                code_point if !is_seq && code_point as u32 == c as u32 => return Some(index),
+                _ => (),
             }
+        }
 
-            if FONT_BIN[i] == UNICODE_TABLE_SEPARATOR {
-                index += 1;
+        None
+    }
+
+    fn psf2_glyph_index(&self, offset: usize, c: char) -> Option<usize> {
+        let table = self.binary.get(offset..)?;
+
+        for (index, entry) in table
+            .split(|b| *b == PSF2_UNICODE_TABLE_SEPARATOR)
+            .enumerate()
+        {
+            // sequences of multiple chars are not supported
+            let chars = match entry.split(|b| *b == PSF2_UNICODE_TABLE_SEQ_START).next() {
+                Some(chars) => chars,
+                None => continue,
+            };
+
+            if let Ok(s) = str::from_utf8(chars) {
+                if s.chars().any(|ch| ch == c) {
+                    return Some(index);
+                }
             }
         }
 
-        index
+        None
     }
+}
 
-    pub fn get_glyph(&self, index: usize) -> Result<&'static [u8]> {
-        if index > self.glyphs_len {
-            return Err(FontError::FontGlyphError.into());
+// null means the default font
+pub struct ActiveFont(AtomicPtr<PsfFont>);
+
+impl ActiveFont {
+    const fn new() -> Self {
+        Self(AtomicPtr::new(null_mut()))
+    }
+}
+
+impl Deref for ActiveFont {
+    type Target = PsfFont;
+
+    fn deref(&self) -> &Self::Target {
+        let ptr = self.0.load(Ordering::Acquire);
+        if ptr.is_null() {
+            return &DEFAULT_FONT;
         }
 
-        let offset = self.header_size + self.glyph_size * index;
-        Ok(&FONT_BIN[offset..offset + self.glyph_size])
+        unsafe { &*ptr }
+    }
+}
+
+// replaced fonts are never freed because glyphs may still be borrowed
+pub fn set_active(font: PsfFont) {
+    FONT.0.store(Box::leak(Box::new(font)), Ordering::Release);
+}
+
+pub fn load_psf(bytes: &[u8]) -> Result<()> {
+    let binary: &'static [u8] = Vec::leak(bytes.to_vec());
+    set_active(PsfFont::parse(binary)?);
+    Ok(())
+}
+
+#[test_case]
+fn test_parse_psf1() {
+    // 256 glyphs of 8x2, glyph 1 is mapped to 'A'
+    let mut binary = vec![0x36, 0x04, PSF1_MODE_HAS_TAB, 2];
+    binary.extend_from_slice(&[0; 256 * 2]);
+    binary[PSF1_HEADER_SIZE + 2] = 0x81;
+    binary.extend_from_slice(&[0xff, 0xff]);
+    binary.extend_from_slice(&[b'A', 0x00, 0xff, 0xff]);
+    for _ in 2..256 {
+        binary.extend_from_slice(&[0xff, 0xff]);
     }
+
+    let font = PsfFont::parse(Vec::leak(binary)).unwrap();
+    assert_eq!(font.get_width(), 8);
+    assert_eq!(font.get_height(), 2);
+
+    let index = font.unicode_char_to_glyph_index('A');
+    assert_eq!(index, 1);
+    let glyph = font.get_glyph(index).unwrap();
+    assert!(font.is_glyph_pixel_set(glyph, 0, 0));
+    assert!(!font.is_glyph_pixel_set(glyph, 1, 0));
+    assert!(font.is_glyph_pixel_set(glyph, 7, 0));
+    assert_eq!(font.unicode_char_to_glyph_index('B'), 0);
 }
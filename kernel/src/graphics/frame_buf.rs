@@ -3,11 +3,11 @@ use super::{
     draw::Draw,
     font::{FONT, TAB_DISP_STR},
     multi_layer::{Layer, LayerPositionInfo},
+    rect::{DirtyRects, Rect},
 };
-use crate::{arch::addr::*, error::Result, util::mutex::Mutex};
+use crate::{arch::addr::*, error::Result, fs::file::bitmap, util::mutex::Mutex};
 use alloc::vec::Vec;
 use common::graphic_info::{GraphicInfo, PixelFormat};
-use core::slice;
 
 static mut FRAME_BUF: Mutex<Option<FrameBuffer>> = Mutex::new(None);
 
@@ -30,6 +30,7 @@ pub struct FrameBuffer {
     frame_buf_virt_addr: VirtualAddress,
     stride: usize,
     shadow_buf: Option<Vec<u8>>,
+    dirty_rects: DirtyRects,
 }
 
 impl Draw for FrameBuffer {
@@ -41,6 +42,8 @@ impl Draw for FrameBuffer {
         height: usize,
         color_code: ColorCode,
     ) -> Result<()> {
+        self.dirty_rects.add(Rect::new(x, y, width, height));
+
         for y in y..y + height {
             for x in x..x + width {
                 self.write(x, y, color_code)?;
@@ -80,10 +83,12 @@ impl Draw for FrameBuffer {
 
     fn draw_font(&mut self, x: usize, y: usize, c: char, color_code: ColorCode) -> Result<()> {
         let glyph = FONT.get_glyph(FONT.unicode_char_to_glyph_index(c))?;
+        self.dirty_rects
+            .add(Rect::new(x, y, FONT.get_width(), FONT.get_height()));
 
         for h in 0..FONT.get_height() {
             for w in 0..FONT.get_width() {
-                if !(glyph[h] << w) & 0x80 == 0x80 {
+                if !FONT.is_glyph_pixel_set(glyph, w, h) {
                     continue;
                 }
 
@@ -96,6 +101,8 @@ impl Draw for FrameBuffer {
 
     fn fill(&mut self, color_code: ColorCode) -> Result<()> {
         let (max_x, max_y) = self.get_resolution();
+        self.dirty_rects.add(Rect::new(0, 0, max_x, max_y));
+
         for y in 0..max_y {
             for x in 0..max_x {
                 self.write(x, y, color_code)?;
@@ -108,6 +115,7 @@ impl Draw for FrameBuffer {
     fn copy(&mut self, x: usize, y: usize, to_x: usize, to_y: usize) -> Result<()> {
         let data = self.read_pixel(x, y)?;
         self.write_pixel(to_x, to_y, data)?;
+        self.dirty_rects.add(Rect::new(to_x, to_y, 1, 1));
 
         Ok(())
     }
@@ -118,6 +126,7 @@ impl Draw for FrameBuffer {
     }
 
     fn write(&mut self, x: usize, y: usize, color_code: ColorCode) -> Result<()> {
+        self.dirty_rects.add(Rect::new(x, y, 1, 1));
         self.write_pixel(x, y, color_code.to_color_code(self.format))
     }
 }
@@ -130,6 +139,7 @@ impl FrameBuffer {
             frame_buf_virt_addr: graphic_info.framebuf_addr.into(),
             stride: graphic_info.stride,
             shadow_buf: None,
+            dirty_rects: DirtyRects::new(),
         }
     }
 
@@ -145,6 +155,19 @@ impl FrameBuffer {
         self.format
     }
 
+    pub fn capture(&self) -> Result<Vec<u8>> {
+        let (width, height) = self.get_resolution();
+        let mut data = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                data.push(self.read(x, y)?);
+            }
+        }
+
+        Ok(bitmap::rgb_color_code_to_bitmap(width, height, &data))
+    }
+
     pub fn enable_shadow_buf(&mut self) {
         let (res_x, res_y) = self.resolution;
         let len = res_x * res_y * 4;
@@ -161,11 +184,12 @@ impl FrameBuffer {
         self.shadow_buf = Some(shadow_buf);
     }
 
-    // no check disabled layer
+    // no check disabled layer, rects are in screen coordinates
     pub fn apply_layer_buf(
         &mut self,
-        layer: &mut Layer,
-        transparent_color: ColorCode,
+        layer: &Layer,
+        _transparent_color: ColorCode,
+        rects: &[Rect],
     ) -> Result<()> {
         if layer.format != self.format {
             return Err(FrameBufferError::InvalidPixelFormatError {
@@ -176,68 +200,112 @@ impl FrameBuffer {
         }
 
         let (res_x, res_y) = self.get_resolution();
-        let layer_buf_ptr = layer.buf.as_mut_ptr();
+        let layer_buf_ptr = layer.buf.as_ptr();
         let frame_buf_ptr = if let Some(shadow_buf) = &mut self.shadow_buf {
             shadow_buf.as_mut_ptr()
         } else {
             self.frame_buf_virt_addr.as_ptr_mut()
         };
 
-        let transparent_color = transparent_color.to_color_code(layer.format);
         let LayerPositionInfo {
             x: layer_x,
             y: layer_y,
             width: layer_width,
             height: layer_height,
         } = layer.pos_info;
+        let layer_rect = Rect::new(layer_x, layer_y, layer_width, layer_height);
+        let screen_rect = Rect::new(0, 0, res_x, res_y);
+
+        // TODO: replace transparent color to frame buf color
+        for rect in rects {
+            let rect = match rect
+                .intersection(&layer_rect)
+                .and_then(|r| r.intersection(&screen_rect))
+            {
+                Some(r) => r,
+                None => continue,
+            };
 
-        let layer_x = layer_x.min(res_x);
-        let layer_y = layer_y.min(res_y);
-        let layer_y_end = (layer_y + layer_height).min(res_y);
-
-        for y in layer_y..layer_y_end {
-            let layer_buf_offset = (layer_width * (y - layer_y) * 4) as isize;
-            let frame_buf_offset = ((res_x * y + layer_x) * 4) as isize;
-
-            unsafe {
-                let buf = slice::from_raw_parts_mut(
-                    layer_buf_ptr.offset(layer_buf_offset).cast::<u32>(),
-                    layer_width,
-                );
-
-                // TODO: replace transparent color to frame buf color
-                //but hangged up in this code
-                // for (i, data) in buf.iter_mut().enumerate() {
-                //     if *data == transparent_color {
-                //         *data = frame_buf_ptr
-                //             .offset(frame_buf_offset + (i as isize) * 4)
-                //             .cast::<u32>()
-                //             .read();
-                //     }
-                // }
-
-                frame_buf_ptr
-                    .offset(frame_buf_offset)
-                    .copy_from_nonoverlapping(
-                        buf.as_ptr().cast::<u8>(),
-                        layer_width.min(res_x - layer_x) * 4,
-                    );
+            for y in rect.y..rect.y_end() {
+                let layer_buf_offset = (layer_width * (y - layer_y) + rect.x - layer_x) * 4;
+                let frame_buf_offset = (res_x * y + rect.x) * 4;
+
+                if layer.alpha_enabled {
+                    for i in 0..rect.width {
+                        unsafe {
+                            let src = layer_buf_ptr
+                                .add(layer_buf_offset + i * 4)
+                                .cast::<u32>()
+                                .read_unaligned();
+                            let dst_ptr = frame_buf_ptr.add(frame_buf_offset + i * 4).cast::<u32>();
+                            let dst = dst_ptr.read_unaligned();
+                            dst_ptr.write_unaligned(Self::blend_pixel(src, dst, self.format));
+                        }
+                    }
+                    continue;
+                }
+
+                unsafe {
+                    frame_buf_ptr
+                        .add(frame_buf_offset)
+                        .copy_from_nonoverlapping(
+                            layer_buf_ptr.add(layer_buf_offset),
+                            rect.width * 4,
+                        );
+                }
             }
+
+            self.dirty_rects.add(rect);
         }
 
         Ok(())
     }
 
-    pub fn apply_shadow_buf(&self) {
-        if self.shadow_buf.is_none() {
-            return;
+    // src has alpha in the highest byte
+    fn blend_pixel(src: u32, dst: u32, format: PixelFormat) -> u32 {
+        match (src >> 24) as u8 {
+            0 => dst,
+            0xff => (src & 0xff_ffff) | (dst & 0xff00_0000),
+            a => {
+                let mut src_color_code = ColorCode::from_pixel_data(src, format);
+                src_color_code.a = a;
+                let dst_color_code = ColorCode::from_pixel_data(dst, format);
+                let data = src_color_code
+                    .blend_over(dst_color_code)
+                    .to_color_code(format);
+                (data & 0xff_ffff) | (dst & 0xff00_0000)
+            }
         }
+    }
+
+    // copy only dirty rects of the shadow buffer
+    pub fn apply_shadow_buf(&mut self) {
+        let dirty_rects = self.dirty_rects.take();
+
+        let shadow_buf_ptr = match &self.shadow_buf {
+            Some(shadow_buf) => shadow_buf.as_ptr(),
+            None => return,
+        };
 
         let (res_x, res_y) = self.resolution;
-        let len = res_x * res_y * 4;
-        let shadow_buf_ptr = self.shadow_buf.as_ref().unwrap().as_ptr();
-        self.frame_buf_virt_addr
-            .copy_from_nonoverlapping(shadow_buf_ptr, len);
+        let screen_rect = Rect::new(0, 0, res_x, res_y);
+
+        for rect in dirty_rects {
+            let rect = match rect.intersection(&screen_rect) {
+                Some(r) => r,
+                None => continue,
+            };
+
+            for y in rect.y..rect.y_end() {
+                let offset = (res_x * y + rect.x) * 4;
+                self.frame_buf_virt_addr
+                    .offset(offset)
+                    .copy_from_nonoverlapping(
+                        unsafe { shadow_buf_ptr.add(offset) },
+                        rect.width * 4,
+                    );
+            }
+        }
     }
 
     fn read_pixel(&self, x: usize, y: usize) -> Result<u32> {
@@ -340,6 +408,14 @@ pub fn fill(color_code: ColorCode) -> Result<()> {
         .fill(color_code)
 }
 
+// serialize to a bitmap image
+pub fn capture() -> Result<Vec<u8>> {
+    unsafe { FRAME_BUF.try_lock() }?
+        .as_ref()
+        .ok_or(FrameBufferError::NotInitialized)?
+        .capture()
+}
+
 pub fn enable_shadow_buf() -> Result<()> {
     unsafe { FRAME_BUF.try_lock() }?
         .as_mut()
@@ -348,11 +424,11 @@ pub fn enable_shadow_buf() -> Result<()> {
     Ok(())
 }
 
-pub fn apply_layer_buf(layer: &mut Layer, transparent_color: ColorCode) -> Result<()> {
+pub fn apply_layer_buf(layer: &Layer, transparent_color: ColorCode, rects: &[Rect]) -> Result<()> {
     unsafe { FRAME_BUF.try_lock() }?
         .as_mut()
         .ok_or(FrameBufferError::NotInitialized)?
-        .apply_layer_buf(layer, transparent_color)
+        .apply_layer_buf(layer, transparent_color, rects)
 }
 
 pub fn apply_shadow_buf() -> Result<()> {
@@ -1,11 +1,16 @@
 use super::{
+    ansi::{AnsiOutput, AnsiParser, SgrParam},
     font::{FONT, TAB_DISP_STR},
     frame_buf,
     multi_layer::{self, LayerId, LayerPositionInfo},
 };
 use crate::{arch, error::Result, theme::GLOBAL_THEME, util::mutex::Mutex, ColorCode};
+use alloc::{collections::VecDeque, vec::Vec};
 use core::fmt::{self, Write};
 
+// lines scrolled off the top of the screen
+const SCROLLBACK_LINES_MAX: usize = 512;
+
 static mut FRAME_BUF_CONSOLE: Mutex<Option<FrameBufferConsole>> = Mutex::new(None);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,6 +29,11 @@ pub struct FrameBufferConsole {
     cursor_x: usize,
     cursor_y: usize,
     target_layer_id: Option<LayerId>,
+    // scrollback lines followed by the screen rows
+    lines: VecDeque<Vec<(char, ColorCode)>>,
+    // number of lines scrolled back from the live tail
+    scroll_offset: usize,
+    ansi_parser: AnsiParser,
 }
 
 impl FrameBufferConsole {
@@ -44,6 +54,9 @@ impl FrameBufferConsole {
             cursor_x: 0,
             cursor_y: 0,
             target_layer_id: None,
+            lines: VecDeque::new(),
+            scroll_offset: 0,
+            ansi_parser: AnsiParser::new(),
         });
     }
 
@@ -57,13 +70,19 @@ impl FrameBufferConsole {
             } = multi_layer::get_layer_pos_info(layer_id)?;
             self.max_x_res = width;
             self.max_y_res = height;
-            self.char_max_x_len = self.max_x_res / FONT.get_width() - 1;
-            self.char_max_y_len = self.max_y_res / FONT.get_height() - 1;
         }
 
+        // font may be changed
+        self.char_max_x_len = self.max_x_res / FONT.get_width() - 1;
+        self.char_max_y_len = self.max_y_res / FONT.get_height() - 1;
+
         self.cursor_x = 0;
         self.cursor_y = 2;
 
+        self.lines.clear();
+        self.lines.resize(self.screen_rows_len(), Vec::new());
+        self.scroll_offset = 0;
+
         self.fill(self.back_color)?;
 
         for (i, color_code) in GLOBAL_THEME.sample_rect_colors.iter().enumerate() {
@@ -89,6 +108,15 @@ impl FrameBufferConsole {
     }
 
     pub fn write_char(&mut self, c: char) -> Result<()> {
+        let c = match self.ansi_parser.input(c) {
+            Some(AnsiOutput::Char(c)) => c,
+            Some(output) => {
+                self.apply_ansi_output(output);
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
         match c {
             '\n' => return self.new_line(),
             '\t' => return self.tab(),
@@ -96,12 +124,15 @@ impl FrameBufferConsole {
             _ => (),
         }
 
-        self.draw_font(
-            self.cursor_x * FONT.get_width(),
-            self.cursor_y * FONT.get_height(),
-            c,
-            self.fore_color,
-        )?;
+        self.set_cell(self.cursor_x, self.cursor_y, c, self.fore_color);
+        if self.scroll_offset == 0 {
+            self.draw_font(
+                self.cursor_x * FONT.get_width(),
+                self.cursor_y * FONT.get_height(),
+                c,
+                self.fore_color,
+            )?;
+        }
 
         self.inc_cursor()?;
 
@@ -116,6 +147,94 @@ impl FrameBufferConsole {
         Ok(())
     }
 
+    fn apply_ansi_output(&mut self, output: AnsiOutput) {
+        match output {
+            AnsiOutput::Char(_) => (),
+            AnsiOutput::Sgr(params) => {
+                for param in params {
+                    match param {
+                        SgrParam::Reset | SgrParam::DefaultForeColor => self.reset_fore_color(),
+                        SgrParam::ForeColor(fore_color) => self.set_fore_color(fore_color),
+                        SgrParam::Unsupported(_) => (),
+                    }
+                }
+            }
+            AnsiOutput::CursorUp(n) => self.cursor_y = self.cursor_y.saturating_sub(n),
+            AnsiOutput::CursorDown(n) => {
+                self.cursor_y = (self.cursor_y + n).min(self.char_max_y_len)
+            }
+            AnsiOutput::CursorForward(n) => {
+                self.cursor_x = (self.cursor_x + n).min(self.char_max_x_len)
+            }
+            AnsiOutput::CursorBack(n) => self.cursor_x = self.cursor_x.saturating_sub(n),
+            AnsiOutput::CursorPosition { x, y } => {
+                self.cursor_x = x.min(self.char_max_x_len);
+                self.cursor_y = y.min(self.char_max_y_len);
+            }
+        }
+    }
+
+    pub fn scroll_up(&mut self, lines_len: usize) -> Result<()> {
+        let scrollback_lines_len = self.lines.len() - self.screen_rows_len();
+        let scroll_offset = (self.scroll_offset + lines_len).min(scrollback_lines_len);
+        if scroll_offset == self.scroll_offset {
+            return Ok(());
+        }
+
+        self.scroll_offset = scroll_offset;
+        self.render()
+    }
+
+    pub fn scroll_down(&mut self, lines_len: usize) -> Result<()> {
+        let scroll_offset = self.scroll_offset.saturating_sub(lines_len);
+        if scroll_offset == self.scroll_offset {
+            return Ok(());
+        }
+
+        self.scroll_offset = scroll_offset;
+        self.render()
+    }
+
+    pub fn scroll_to_tail(&mut self) -> Result<()> {
+        self.scroll_down(self.scroll_offset)
+    }
+
+    pub fn screen_rows_len(&self) -> usize {
+        self.char_max_y_len + 1
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, c: char, color_code: ColorCode) {
+        let index = self.lines.len() - self.screen_rows_len() + y;
+        let line = &mut self.lines[index];
+        if line.len() <= x {
+            line.resize(x + 1, (' ', color_code));
+        }
+        line[x] = (c, color_code);
+    }
+
+    // redraw the screen from the lines
+    fn render(&self) -> Result<()> {
+        self.fill(self.back_color)?;
+
+        let start = self.lines.len() - self.screen_rows_len() - self.scroll_offset;
+        for (y, line) in self
+            .lines
+            .range(start..)
+            .take(self.screen_rows_len())
+            .enumerate()
+        {
+            for (x, (c, color_code)) in line.iter().enumerate() {
+                if *c == ' ' {
+                    continue;
+                }
+
+                self.draw_font(x * FONT.get_width(), y * FONT.get_height(), *c, *color_code)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn inc_cursor(&mut self) -> Result<()> {
         self.cursor_x += 1;
 
@@ -166,7 +285,19 @@ impl FrameBufferConsole {
         Ok(())
     }
 
-    fn scroll(&self) -> Result<()> {
+    fn scroll(&mut self) -> Result<()> {
+        self.lines.push_back(Vec::new());
+        if self.lines.len() > SCROLLBACK_LINES_MAX + self.screen_rows_len() {
+            self.lines.pop_front();
+        }
+
+        // keep showing the same lines
+        if self.scroll_offset != 0 {
+            let scrollback_lines_len = self.lines.len() - self.screen_rows_len();
+            self.scroll_offset = (self.scroll_offset + 1).min(scrollback_lines_len);
+            return Ok(());
+        }
+
         let font_glyph_size_y = FONT.get_height();
 
         for y in font_glyph_size_y..self.max_y_res {
@@ -225,6 +356,11 @@ impl FrameBufferConsole {
 
     fn backspace(&mut self) -> Result<()> {
         self.dec_cursor()?;
+        self.set_cell(self.cursor_x, self.cursor_y, ' ', self.fore_color);
+        if self.scroll_offset != 0 {
+            return Ok(());
+        }
+
         self.draw_rect(
             self.cursor_x * FONT.get_width(),
             self.cursor_y * FONT.get_height(),
@@ -265,6 +401,13 @@ pub fn init(back_color: ColorCode, fore_color: ColorCode) -> Result<()> {
     Ok(())
 }
 
+pub fn init_console() -> Result<()> {
+    unsafe { FRAME_BUF_CONSOLE.try_lock() }?
+        .as_mut()
+        .ok_or(FrameBufferConsoleError::NotInitialized)?
+        .init_console()
+}
+
 pub fn set_target_layer_id(layer_id: &LayerId) -> Result<()> {
     unsafe { FRAME_BUF_CONSOLE.try_lock() }?
         .as_mut()
@@ -288,6 +431,40 @@ pub fn reset_fore_color() -> Result<()> {
     Ok(())
 }
 
+// scroll back by a page
+pub fn scroll_page_up() -> Result<()> {
+    let mut fbc = unsafe { FRAME_BUF_CONSOLE.try_lock() }?;
+    let fbc = fbc
+        .as_mut()
+        .ok_or(FrameBufferConsoleError::NotInitialized)?;
+    fbc.scroll_up(fbc.screen_rows_len())
+}
+
+pub fn scroll_page_down() -> Result<()> {
+    let mut fbc = unsafe { FRAME_BUF_CONSOLE.try_lock() }?;
+    let fbc = fbc
+        .as_mut()
+        .ok_or(FrameBufferConsoleError::NotInitialized)?;
+    fbc.scroll_down(fbc.screen_rows_len())
+}
+
+pub fn scroll_to_tail() -> Result<()> {
+    unsafe { FRAME_BUF_CONSOLE.try_lock() }?
+        .as_mut()
+        .ok_or(FrameBufferConsoleError::NotInitialized)?
+        .scroll_to_tail()
+}
+
+// log sink
+pub struct FrameBufferConsoleWriter;
+
+impl Write for FrameBufferConsoleWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let _ = write_fmt(format_args!("{}", s));
+        Ok(())
+    }
+}
+
 pub fn write_fmt(args: fmt::Arguments) -> Result<()> {
     let _ = unsafe { FRAME_BUF_CONSOLE.try_lock() }?
         .as_mut()
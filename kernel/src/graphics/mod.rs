@@ -1,13 +1,16 @@
 use self::color::ColorCode;
+use crate::{error::Result, fs::vfs};
 use common::graphic_info::GraphicInfo;
 use log::{error, info};
 
+pub mod ansi;
 pub mod color;
 pub mod draw;
 pub mod font;
 pub mod frame_buf;
 pub mod frame_buf_console;
 pub mod multi_layer;
+pub mod rect;
 pub mod simple_window_manager;
 
 pub fn init(graphic_info: &GraphicInfo, back_color: ColorCode, fore_color: ColorCode) {
@@ -81,3 +84,20 @@ pub fn init_simple_wm() {
 
     info!("graphics: Initialized simple window manager");
 }
+
+pub fn load_font(path: &str) {
+    let load = || -> Result<()> {
+        let fd_num = vfs::open_file(path)?;
+        let data = vfs::read_file(&fd_num);
+        vfs::close_file(&fd_num)?;
+        font::load_psf(&data?)?;
+        frame_buf_console::init_console()
+    };
+
+    if let Err(err) = load() {
+        error!("graphics: Failed to load font {}: {:?}", path, err);
+        return;
+    }
+
+    info!("graphics: Loaded font {}", path);
+}
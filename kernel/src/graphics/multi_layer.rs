@@ -3,10 +3,11 @@ use super::{
     draw::Draw,
     font::{FONT, TAB_DISP_STR},
     frame_buf,
+    rect::{DirtyRects, Rect},
 };
 use crate::{
     error::{Error, Result},
-    fs::file::bitmap::BitmapImage,
+    fs::file::{bitmap::BitmapImage, png::PngImage},
     util::mutex::Mutex,
 };
 use alloc::vec::Vec;
@@ -59,6 +60,9 @@ pub struct Layer {
     pub disabled: bool,
     pub format: PixelFormat,
     pub always_on_top: bool,
+    // alpha is stored in the highest byte of each pixel and blended when composited
+    pub alpha_enabled: bool,
+    dirty_rects: DirtyRects, // layer coordinates
 }
 
 impl Draw for Layer {
@@ -70,6 +74,8 @@ impl Draw for Layer {
         height: usize,
         color_code: ColorCode,
     ) -> Result<()> {
+        self.mark_dirty(Rect::new(x, y, width, height));
+
         for y in y..y + height {
             for x in x..x + width {
                 self.write(x, y, color_code)?;
@@ -81,6 +87,7 @@ impl Draw for Layer {
 
     fn fill(&mut self, color_code: ColorCode) -> Result<()> {
         let (width, height) = self.get_resolution();
+        self.mark_dirty(Rect::new(0, 0, width, height));
 
         for y in 0..height {
             for x in 0..width {
@@ -130,10 +137,11 @@ impl Draw for Layer {
 
     fn draw_font(&mut self, x: usize, y: usize, c: char, color_code: ColorCode) -> Result<()> {
         let glyph = FONT.get_glyph(FONT.unicode_char_to_glyph_index(c))?;
+        self.mark_dirty(Rect::new(x, y, FONT.get_width(), FONT.get_height()));
 
         for h in 0..FONT.get_height() {
             for w in 0..FONT.get_width() {
-                if !(glyph[h] << w) & 0x80 == 0x80 {
+                if !FONT.is_glyph_pixel_set(glyph, w, h) {
                     continue;
                 }
 
@@ -153,11 +161,23 @@ impl Draw for Layer {
 
     fn read(&self, x: usize, y: usize) -> Result<ColorCode> {
         let data = self.read_pixel(x, y)?;
-        Ok(ColorCode::from_pixel_data(data, self.format))
+        let mut color_code = ColorCode::from_pixel_data(data, self.format);
+        if self.alpha_enabled {
+            color_code.a = (data >> 24) as u8;
+        }
+
+        Ok(color_code)
     }
 
     fn write(&mut self, x: usize, y: usize, color_code: ColorCode) -> Result<()> {
-        self.write_pixel(x, y, color_code.to_color_code(self.format))
+        self.mark_dirty(Rect::new(x, y, 1, 1));
+
+        let mut data = color_code.to_color_code(self.format);
+        if self.alpha_enabled {
+            data = (data & 0xff_ffff) | (color_code.a as u32) << 24;
+        }
+
+        self.write_pixel(x, y, data)
     }
 }
 
@@ -181,9 +201,26 @@ impl Layer {
             disabled: false,
             format,
             always_on_top: false,
+            alpha_enabled: false,
+            dirty_rects: DirtyRects::new(),
         })
     }
 
+    pub fn rect(&self) -> Rect {
+        let LayerPositionInfo {
+            x,
+            y,
+            width,
+            height,
+        } = self.pos_info;
+        Rect::new(x, y, width, height)
+    }
+
+    // rect is in layer coordinates
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        self.dirty_rects.add(rect);
+    }
+
     pub fn move_to(&mut self, x: usize, y: usize) -> Result<()> {
         self.pos_info.x = x;
         self.pos_info.y = y;
@@ -245,6 +282,7 @@ impl Layer {
 struct LayerManager {
     layers: Vec<Layer>,
     pub transparent_color: ColorCode,
+    dirty_rects: DirtyRects, // screen coordinates
 }
 
 impl LayerManager {
@@ -252,23 +290,42 @@ impl LayerManager {
         Self {
             layers: Vec::new(),
             transparent_color,
+            dirty_rects: DirtyRects::new(),
         }
     }
 
     pub fn push_layer(&mut self, layer: Layer) {
+        self.dirty_rects.add(layer.rect());
         self.layers.push(layer);
     }
 
     pub fn remove_layer(&mut self, layer_id: &LayerId) -> Result<()> {
-        if self.get_layer(layer_id).is_err() {
-            return Err(LayerError::InvalidLayerIdError(layer_id.get()).into());
-        }
+        let rect = match self.get_layer(layer_id) {
+            Ok(layer) => layer.rect(),
+            Err(_) => return Err(LayerError::InvalidLayerIdError(layer_id.get()).into()),
+        };
+        self.dirty_rects.add(rect);
 
         self.layers.retain(|l| l.id.get() != layer_id.get());
 
         Ok(())
     }
 
+    // move the layer to the top of the z-order (below always on top layers)
+    pub fn bring_to_front(&mut self, layer_id: &LayerId) -> Result<()> {
+        let index = self
+            .layers
+            .iter()
+            .position(|l| l.id.get() == layer_id.get())
+            .ok_or(LayerError::InvalidLayerIdError(layer_id.get()))?;
+
+        let layer = self.layers.remove(index);
+        self.dirty_rects.add(layer.rect());
+        self.layers.push(layer);
+
+        Ok(())
+    }
+
     pub fn get_layer(&mut self, layer_id: &LayerId) -> Result<&mut Layer> {
         self.layers
             .iter_mut()
@@ -276,16 +333,40 @@ impl LayerManager {
             .ok_or(LayerError::InvalidLayerIdError(layer_id.get()).into())
     }
 
+    pub fn move_layer(&mut self, layer_id: &LayerId, to_x: usize, to_y: usize) -> Result<()> {
+        let layer = self.get_layer(layer_id)?;
+        let rect_before = layer.rect();
+        layer.move_to(to_x, to_y)?;
+        let rect_after = layer.rect();
+
+        self.dirty_rects.add(rect_before);
+        self.dirty_rects.add(rect_after);
+        Ok(())
+    }
+
+    // composite only the dirty regions
     pub fn draw_to_frame_buf(&mut self) -> Result<()> {
+        for layer in &mut self.layers {
+            let (x, y) = (layer.pos_info.x, layer.pos_info.y);
+            for rect in layer.dirty_rects.take() {
+                self.dirty_rects.add(rect.offset(x, y));
+            }
+        }
+
+        if self.dirty_rects.is_empty() {
+            return Ok(());
+        }
+
         self.layers
             .sort_by(|a, b| a.always_on_top.cmp(&b.always_on_top));
 
-        for layer in &mut self.layers {
+        let dirty_rects = self.dirty_rects.take();
+        for layer in &self.layers {
             if layer.disabled {
                 continue;
             }
 
-            frame_buf::apply_layer_buf(layer, self.transparent_color)?;
+            frame_buf::apply_layer_buf(layer, self.transparent_color, &dirty_rects)?;
         }
 
         Ok(())
@@ -331,6 +412,24 @@ pub fn create_layer_from_bitmap_image(
     Ok(layer)
 }
 
+pub fn create_layer_from_png_image(x: usize, y: usize, png_image: &PngImage) -> Result<Layer> {
+    let header = png_image.header()?;
+    let png_image_data = png_image.png_to_rgba_color_code()?;
+    let width = header.width as usize;
+    let height = header.height as usize;
+    let format = frame_buf::get_format()?;
+    let mut layer = Layer::new(x, y, width, height, format)?;
+    layer.alpha_enabled = png_image.has_alpha()?;
+
+    for h in 0..height {
+        for w in 0..width {
+            layer.write(w, h, png_image_data[h * width + w])?;
+        }
+    }
+
+    Ok(layer)
+}
+
 pub fn push_layer(layer: Layer) -> Result<()> {
     unsafe { LAYER_MAN.try_lock() }?
         .as_mut()
@@ -356,11 +455,27 @@ pub fn draw_layer<F: Fn(&mut dyn Draw) -> Result<()>>(layer_id: &LayerId, draw:
 }
 
 pub fn move_layer(layer_id: &LayerId, to_x: usize, to_y: usize) -> Result<()> {
+    unsafe { LAYER_MAN.try_lock() }?
+        .as_mut()
+        .ok_or(LayerError::LayerManagerNotInitialized)?
+        .move_layer(layer_id, to_x, to_y)
+}
+
+// for drawing to the layer buffer without Draw (rect is in layer coordinates)
+pub fn mark_dirty(layer_id: &LayerId, rect: Rect) -> Result<()> {
     unsafe { LAYER_MAN.try_lock() }?
         .as_mut()
         .ok_or(LayerError::LayerManagerNotInitialized)?
         .get_layer(layer_id)?
-        .move_to(to_x, to_y)
+        .mark_dirty(rect);
+    Ok(())
+}
+
+pub fn bring_layer_to_front(layer_id: &LayerId) -> Result<()> {
+    unsafe { LAYER_MAN.try_lock() }?
+        .as_mut()
+        .ok_or(LayerError::LayerManagerNotInitialized)?
+        .bring_to_front(layer_id)
 }
 
 pub fn remove_layer(layer_id: &LayerId) -> Result<()> {
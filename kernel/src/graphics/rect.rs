@@ -0,0 +1,144 @@
+use alloc::vec::Vec;
+
+// rectangles are merged into their bounding box if over this
+const DIRTY_RECTS_MAX_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn x_end(&self) -> usize {
+        self.x + self.width
+    }
+
+    pub fn y_end(&self) -> usize {
+        self.y + self.height
+    }
+
+    pub fn offset(&self, x: usize, y: usize) -> Self {
+        Self::new(self.x + x, self.y + y, self.width, self.height)
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x_end() <= self.x_end()
+            && other.y_end() <= self.y_end()
+    }
+
+    // also true if the rectangles are adjacent
+    pub fn is_touching(&self, other: &Self) -> bool {
+        self.x <= other.x_end()
+            && other.x <= self.x_end()
+            && self.y <= other.y_end()
+            && other.y <= self.y_end()
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self::new(
+            x,
+            y,
+            self.x_end().max(other.x_end()) - x,
+            self.y_end().max(other.y_end()) - y,
+        )
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let x_end = self.x_end().min(other.x_end());
+        let y_end = self.y_end().min(other.y_end());
+
+        if x >= x_end || y >= y_end {
+            return None;
+        }
+
+        Some(Self::new(x, y, x_end - x, y_end - y))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DirtyRects(Vec<Rect>);
+
+impl DirtyRects {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() || self.0.iter().any(|r| r.contains(&rect)) {
+            return;
+        }
+
+        // merge touching rectangles until no more merges
+        let mut rect = rect;
+        while let Some(i) = self.0.iter().position(|r| r.is_touching(&rect)) {
+            rect = rect.union(&self.0.remove(i));
+        }
+        self.0.push(rect);
+
+        if self.0.len() > DIRTY_RECTS_MAX_LEN {
+            let bounding_rect = self.0.iter().fold(self.0[0], |acc, r| acc.union(r));
+            self.0.clear();
+            self.0.push(bounding_rect);
+        }
+    }
+
+    pub fn append(&mut self, other: &mut Self) {
+        for rect in other.take() {
+            self.add(rect);
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<Rect> {
+        core::mem::take(&mut self.0)
+    }
+}
+
+#[test_case]
+fn test_rect() {
+    let a = Rect::new(0, 0, 10, 10);
+    let b = Rect::new(5, 5, 10, 10);
+    assert_eq!(a.intersection(&b), Some(Rect::new(5, 5, 5, 5)));
+    assert_eq!(a.union(&b), Rect::new(0, 0, 15, 15));
+    assert_eq!(a.intersection(&Rect::new(10, 0, 5, 5)), None);
+    assert!(a.contains(&Rect::new(2, 2, 3, 3)));
+}
+
+#[test_case]
+fn test_dirty_rects() {
+    let mut dirty_rects = DirtyRects::new();
+    dirty_rects.add(Rect::new(0, 0, 1, 1));
+    dirty_rects.add(Rect::new(1, 0, 1, 1));
+    dirty_rects.add(Rect::new(0, 0, 1, 1));
+    dirty_rects.add(Rect::new(100, 100, 1, 1));
+    assert_eq!(
+        dirty_rects.take(),
+        [Rect::new(0, 0, 2, 1), Rect::new(100, 100, 1, 1)]
+    );
+    assert!(dirty_rects.is_empty());
+}
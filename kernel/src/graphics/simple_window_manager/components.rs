@@ -1,7 +1,11 @@
 use crate::{
     addr::VirtualAddress,
+    device::ps2_keyboard::{
+        key_event::{KeyEvent, KeyState},
+        scan_code::KeyCode,
+    },
     error::{Error, Result},
-    fs::file::bitmap::BitmapImage,
+    fs::file::{bitmap::BitmapImage, png::PngImage},
     graphics::{
         font::FONT,
         multi_layer::{self, LayerId, LayerPositionInfo},
@@ -22,6 +26,11 @@ pub trait Component {
     fn move_by_root(&self, to_x: usize, to_y: usize) -> Result<()>;
     fn move_by_parent(&self, parent: &dyn Component, to_x: usize, to_y: usize) -> Result<()>;
     fn draw_flush(&mut self) -> Result<()>;
+
+    // returns true if the event was consumed
+    fn key_event(&mut self, _key_event: KeyEvent) -> Result<bool> {
+        Ok(false)
+    }
 }
 
 pub struct Image {
@@ -118,6 +127,27 @@ impl Image {
         })
     }
 
+    pub fn create_and_push_from_png_image(
+        png_image: &PngImage,
+        x: usize,
+        y: usize,
+        always_on_top: bool,
+    ) -> Result<Self> {
+        if !png_image.is_valid() {
+            return Err(Error::Failed("Invalid PNG image"));
+        }
+
+        let mut layer = multi_layer::create_layer_from_png_image(x, y, png_image)?;
+        layer.always_on_top = always_on_top;
+        let layer_id = layer.id.clone();
+        multi_layer::push_layer(layer)?;
+        Ok(Self {
+            layer_id,
+            framebuf_virt_addr: None,
+            pixel_format: None,
+        })
+    }
+
     pub fn create_and_push_from_framebuf(
         x: usize,
         y: usize,
@@ -146,6 +176,7 @@ pub struct Window {
     resize_button: Button,
     minimize_button: Button,
     children: Vec<Box<dyn Component>>,
+    focused_child_index: Option<usize>,
     contents_base_rel_x: usize,
     contents_base_rel_y: usize,
     pub is_closed: bool,
@@ -308,6 +339,7 @@ impl Window {
             close_button,
             resize_button,
             children: Vec::new(),
+            focused_child_index: None,
             minimize_button,
             contents_base_rel_x: 4,
             contents_base_rel_y: 25,
@@ -318,6 +350,18 @@ impl Window {
         &self.title
     }
 
+    pub fn is_title_bar_clickable(&self, x: usize, y: usize) -> Result<bool> {
+        let LayerPositionInfo {
+            x: w_x,
+            y: w_y,
+            width: w_w,
+            height: _,
+        } = self.get_layer_pos_info()?;
+
+        // same area as drawn in draw_flush
+        Ok(x >= w_x + 4 && x < w_x + w_w - 4 && y >= w_y + 4 && y < w_y + 4 + 18)
+    }
+
     pub fn is_close_button_clickable(&self, x: usize, y: usize) -> Result<bool> {
         let LayerPositionInfo {
             x: cb_x,
@@ -329,10 +373,51 @@ impl Window {
         Ok(x >= cb_x && x < cb_x + cb_w && y >= cb_y && y < cb_y + cb_h)
     }
 
+    pub fn bring_to_front(&self) -> Result<()> {
+        multi_layer::bring_layer_to_front(&self.layer_id)?;
+        multi_layer::bring_layer_to_front(&self.close_button.layer_id_clone())?;
+        multi_layer::bring_layer_to_front(&self.resize_button.layer_id_clone())?;
+        multi_layer::bring_layer_to_front(&self.minimize_button.layer_id_clone())?;
+
+        for child in &self.children {
+            multi_layer::bring_layer_to_front(&child.layer_id_clone())?;
+        }
+
+        Ok(())
+    }
+
     pub fn push_child(&mut self, child: Box<dyn Component>) -> Result<()> {
         self.children.push(child);
         Ok(())
     }
+
+    // focus the child under the pointer, or unfocus if there is none
+    pub fn focus_child(&mut self, x: usize, y: usize) -> Result<()> {
+        self.focused_child_index = None;
+
+        for (i, child) in self.children.iter().enumerate() {
+            let LayerPositionInfo {
+                x: c_x,
+                y: c_y,
+                width: c_w,
+                height: c_h,
+            } = child.get_layer_pos_info()?;
+
+            if x >= c_x && x < c_x + c_w && y >= c_y && y < c_y + c_h {
+                self.focused_child_index = Some(i);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn key_event(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match self.focused_child_index {
+            Some(i) => self.children[i].key_event(key_event),
+            None => Ok(false),
+        }
+    }
 }
 
 pub struct Panel {
@@ -627,3 +712,163 @@ impl Label {
         })
     }
 }
+
+pub struct TextBox {
+    layer_id: LayerId,
+    text: String,
+    cursor: usize, // char index
+}
+
+impl Drop for TextBox {
+    fn drop(&mut self) {
+        let _ = multi_layer::remove_layer(&self.layer_id);
+    }
+}
+
+impl Component for TextBox {
+    fn layer_id_clone(&self) -> LayerId {
+        self.layer_id.clone()
+    }
+
+    fn get_layer_pos_info(&self) -> Result<LayerPositionInfo> {
+        multi_layer::get_layer_pos_info(&self.layer_id)
+    }
+
+    fn move_by_root(&self, to_x: usize, to_y: usize) -> Result<()> {
+        multi_layer::move_layer(&self.layer_id, to_x, to_y)
+    }
+
+    fn move_by_parent(&self, parent: &dyn Component, to_x: usize, to_y: usize) -> Result<()> {
+        let LayerPositionInfo {
+            x: p_x,
+            y: p_y,
+            width: _,
+            height: _,
+        } = parent.get_layer_pos_info()?;
+
+        let LayerPositionInfo {
+            x,
+            y,
+            width: _,
+            height: _,
+        } = self.get_layer_pos_info()?;
+
+        self.move_by_root(to_x + x - p_x, to_y + y - p_y)
+    }
+
+    fn draw_flush(&mut self) -> Result<()> {
+        let LayerPositionInfo {
+            x: _,
+            y: _,
+            width,
+            height,
+        } = self.get_layer_pos_info()?;
+
+        multi_layer::draw_layer(&self.layer_id, |l| {
+            // back color
+            l.fill(GLOBAL_THEME.wm_component_back_color)?;
+
+            // borders (sunken)
+            let border_color1 = if GLOBAL_THEME.wm_component_border_flat {
+                GLOBAL_THEME.wm_component_border_color1
+            } else {
+                GLOBAL_THEME.wm_component_border_color2
+            };
+            let border_color2 = GLOBAL_THEME.wm_component_border_color1;
+
+            l.draw_rect(0, 0, 2, height - 2, border_color1)?;
+            l.draw_rect(2, height - 2, width - 2, 2, border_color2)?;
+
+            l.draw_rect(width - 2, 2, 2, height - 2, border_color2)?;
+            l.draw_rect(0, 0, width - 2, 2, border_color1)?;
+
+            // scroll the text to keep the caret visible
+            let (f_w, f_h) = (FONT.get_width(), FONT.get_height());
+            // the layer may be smaller than the font and the padding
+            let max_len = (width.saturating_sub(8) / f_w).max(1);
+            let start = (self.cursor + 1).saturating_sub(max_len);
+            let visible: String = self.text.chars().skip(start).take(max_len).collect();
+            let text_y = (height / 2).saturating_sub(f_h / 2);
+            l.draw_string(4, text_y, &visible, GLOBAL_THEME.wm_component_fore_color)?;
+
+            // caret
+            l.draw_rect(
+                4 + (self.cursor - start) * f_w,
+                text_y,
+                1,
+                f_h,
+                GLOBAL_THEME.wm_component_fore_color,
+            )?;
+
+            Ok(())
+        })
+    }
+
+    fn key_event(&mut self, key_event: KeyEvent) -> Result<bool> {
+        if key_event.state == KeyState::Released {
+            return Ok(true);
+        }
+
+        let len = self.text.chars().count();
+        match key_event.code {
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.remove_char(self.cursor);
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < len {
+                    self.remove_char(self.cursor);
+                }
+            }
+            KeyCode::CursorLeft => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::CursorRight => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            _ => {
+                // printable chars only
+                let c = match key_event.ascii {
+                    Some(code) if (0x20..0x7f).contains(&(code as u8)) => code as u8 as char,
+                    _ => return Ok(false),
+                };
+                let offset = self.byte_offset(self.cursor);
+                self.text.insert(offset, c);
+                self.cursor += 1;
+            }
+        }
+
+        self.draw_flush()?;
+        Ok(true)
+    }
+}
+
+impl TextBox {
+    pub fn create_and_push(x: usize, y: usize, width: usize, height: usize) -> Result<Self> {
+        let layer = multi_layer::create_layer(x, y, width, height)?;
+        let layer_id = layer.id.clone();
+        multi_layer::push_layer(layer)?;
+        Ok(Self {
+            layer_id,
+            text: String::new(),
+            cursor: 0,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn byte_offset(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.text.len())
+    }
+
+    fn remove_char(&mut self, char_index: usize) {
+        let offset = self.byte_offset(char_index);
+        self.text.remove(offset);
+    }
+}
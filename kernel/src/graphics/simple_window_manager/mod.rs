@@ -1,9 +1,13 @@
 use super::{
+    font::FONT,
     frame_buf,
-    multi_layer::{LayerId, LayerPositionInfo},
+    multi_layer::{self, LayerId, LayerPositionInfo},
 };
 use crate::{
-    device::ps2_mouse::MouseEvent, error::Result, fs::file::bitmap::BitmapImage, util::mutex::Mutex,
+    device::{ps2_keyboard::key_event::KeyEvent, ps2_mouse::MouseEvent, rtc},
+    error::Result,
+    fs::file::bitmap::BitmapImage,
+    util::mutex::Mutex,
 };
 use alloc::{boxed::Box, string::String, vec::Vec};
 use components::*;
@@ -89,7 +93,9 @@ impl SimpleWindowManager {
         mouse_pointer.move_by_root(m_x_after, m_y_after)?;
 
         if mouse_event.left {
-            for w in self.windows.iter_mut().rev() {
+            // topmost window under the pointer
+            let mut clicked_index = None;
+            for (i, w) in self.windows.iter().enumerate().rev() {
                 let LayerPositionInfo {
                     x: w_x,
                     y: w_y,
@@ -97,37 +103,72 @@ impl SimpleWindowManager {
                     height: w_h,
                 } = w.get_layer_pos_info()?;
 
-                // drag window event
                 if m_x_before >= w_x
                     && m_x_before < w_x + w_w
                     && m_y_before >= w_y
                     && m_y_before < w_y + w_h
-                // pointer is in window
-                && m_x_before != m_x_after && m_y_before != m_y_after
-                // pointer moved
                 {
-                    let new_w_x =
-                        (w_x as isize + m_x_after as isize - m_x_before as isize).max(0) as usize;
-                    let new_w_y =
-                        (w_y as isize + m_y_after as isize - m_y_before as isize).max(0) as usize;
-
-                    w.move_by_root(new_w_x, new_w_y)?;
+                    clicked_index = Some(i);
                     break;
                 }
+            }
 
-                // click close button event
-                if w.is_close_button_clickable(m_x_before, m_y_before)? {
-                    w.is_closed = true;
-                    self.windows.retain(|w| !w.is_closed);
-                    self.update_taskbar()?;
-                    break;
-                }
+            let i = match clicked_index {
+                Some(i) => i,
+                None => return Ok(()),
+            };
+
+            // raise clicked window
+            if i != self.windows.len() - 1 {
+                let w = self.windows.remove(i);
+                w.bring_to_front()?;
+                self.windows.push(w);
+                multi_layer::draw_to_frame_buf()?;
+            }
+
+            let w = self.windows.last_mut().unwrap();
+
+            // click close button event
+            if w.is_close_button_clickable(m_x_before, m_y_before)? {
+                w.is_closed = true;
+                self.windows.retain(|w| !w.is_closed);
+                self.update_taskbar()?;
+                return Ok(());
+            }
+
+            // keyboard focus
+            w.focus_child(m_x_before, m_y_before)?;
+
+            // drag window event (only from the title bar)
+            let is_pointer_moved = m_x_before != m_x_after && m_y_before != m_y_after;
+            if is_pointer_moved && w.is_title_bar_clickable(m_x_before, m_y_before)? {
+                let LayerPositionInfo {
+                    x: w_x,
+                    y: w_y,
+                    width: _,
+                    height: _,
+                } = w.get_layer_pos_info()?;
+
+                let new_w_x =
+                    (w_x as isize + m_x_after as isize - m_x_before as isize).max(0) as usize;
+                let new_w_y =
+                    (w_y as isize + m_y_after as isize - m_y_before as isize).max(0) as usize;
+
+                w.move_by_root(new_w_x, new_w_y)?;
             }
         }
 
         Ok(())
     }
 
+    // routed to the focused component of the topmost window
+    fn key_event(&mut self, key_event: KeyEvent) -> Result<bool> {
+        match self.windows.last_mut() {
+            Some(w) => w.key_event(key_event),
+            None => Ok(false),
+        }
+    }
+
     fn create_window(
         &mut self,
         title: String,
@@ -221,6 +262,13 @@ impl SimpleWindowManager {
         );
         taskbar.draw_string(7, 7, &s)?;
 
+        // clock on the right end
+        if let Ok(now) = rtc::now() {
+            let s = format!("{}", now);
+            let x = self.res_x.saturating_sub(s.len() * FONT.get_width() + 7);
+            taskbar.draw_string(x, 7, &s)?;
+        }
+
         Ok(())
     }
 }
@@ -245,6 +293,13 @@ pub fn create_taskbar() -> Result<()> {
         .create_taskbar()
 }
 
+pub fn update_taskbar() -> Result<()> {
+    unsafe { SIMPLE_WM.try_lock() }?
+        .as_mut()
+        .ok_or(SimpleWindowManagerError::NotInitialized)?
+        .update_taskbar()
+}
+
 pub fn destroy_window(layer_id: &LayerId) -> Result<()> {
     unsafe { SIMPLE_WM.try_lock() }?
         .as_mut()
@@ -259,6 +314,13 @@ pub fn mouse_pointer_event(mouse_event: MouseEvent) -> Result<()> {
         .mouse_pointer_event(mouse_event)
 }
 
+pub fn key_event(key_event: KeyEvent) -> Result<bool> {
+    unsafe { SIMPLE_WM.try_lock() }?
+        .as_mut()
+        .ok_or(SimpleWindowManagerError::NotInitialized)?
+        .key_event(key_event)
+}
+
 pub fn create_window(
     title: String,
     x: usize,
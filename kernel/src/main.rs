@@ -11,6 +11,7 @@
 #![reexport_test_harness_main = "test_main"]
 
 mod arch;
+mod debug;
 mod device;
 mod env;
 mod error;
@@ -46,6 +47,12 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     device::panic_screen::probe_and_attach(boot_info.graphic_info).unwrap();
     logger::init();
 
+    // override kernel config by the cmdline
+    let kernel_config = boot_info.kernel_config.with_cmdline();
+    logger::set_level(logger::level_filter(kernel_config.log_level));
+    let _ = logger::add_sink(device::uart::UartWriter);
+    let _ = logger::add_sink(graphics::frame_buf_console::FrameBufferConsoleWriter);
+
     // attach uart driver
     // do not use .unwrap() here!!
     let _ = device::uart::probe_and_attach();
@@ -53,6 +60,11 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     // initialize memory management
     mem::init(boot_info.mem_map);
 
+    // set guard page below the kernel stack
+    if let Err(err) = context::set_kernel_stack_guard_page() {
+        error!("context: Failed to set kernel stack guard page: {:?}", err);
+    }
+
     // initialize GDT
     gdt::init();
     // initialize PIC and IDT
@@ -62,6 +74,22 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     // initialize ACPI
     acpi::init(boot_info.rsdp_virt_addr.unwrap().into()).unwrap();
 
+    // route legacy IRQs through I/O APIC (fall back to PIC if not found)
+    if let Err(err) = idt::init_io_apic() {
+        warn!("idt: Failed to initialize I/O APIC: {:?}", err);
+    }
+
+    // start application processors
+    if let Err(err) = smp::init() {
+        warn!("smp: Failed to start application processors: {:?}", err);
+    }
+
+    // initialize HPET (used to calibrate local APIC timer)
+    if let Err(err) = device::hpet::probe_and_attach() {
+        let name = device::hpet::get_device_driver_info().unwrap().name;
+        error!("{}: Failed to probe or attach device: {:?}", name, err);
+    }
+
     // initialize and start local APIC timer
     device::local_apic_timer::probe_and_attach().unwrap();
 
@@ -78,7 +106,11 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     // initialize simple window manager
     graphics::init_simple_wm();
 
-    // initialize PS/2 keyboard and mouse
+    // initialize PS/2 controller, keyboard and mouse
+    if let Err(err) = device::ps2::controller::init() {
+        error!("ps2: Failed to initialize controller: {:?}", err);
+    }
+
     if let Err(err) = device::ps2_keyboard::probe_and_attach() {
         let name = device::ps2_keyboard::get_device_driver_info().unwrap().name;
         error!("{}: Failed to probe or attach device: {:?}", name, err);
@@ -89,9 +121,6 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
         error!("{}: Failed to probe or attach device: {:?}", name, err);
     }
 
-    // clear console input for PS/2 keyboard magic byte
-    let _ = device::console::clear_input_buf();
-
     // initialize pci-bus driver
     if let Err(err) = device::pci_bus::probe_and_attach() {
         let name = device::pci_bus::get_device_driver_info().unwrap().name;
@@ -110,11 +139,16 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     //     error!("{}: Failed to probe or attach device: {:?}", name, err);
     // }
 
+    // configure IPv4 addresses of the network stack
+    if let Err(err) = net::init(&kernel_config) {
+        error!("net: Failed to initialize: {:?}", err);
+    }
+
     // initalize virtio-net driver
-    // if let Err(err) = device::virtio::net::probe_and_attach() {
-    //     let name = device::virtio::net::get_device_driver_info().unwrap().name;
-    //     error!("{}: Failed to probe or attach device: {:?}", name, err);
-    // }
+    if let Err(err) = device::virtio::net::probe_and_attach() {
+        let name = device::virtio::net::get_device_driver_info().unwrap().name;
+        error!("{}: Failed to probe or attach device: {:?}", name, err);
+    }
 
     // initialize speaker driver
     if let Err(err) = device::speaker::probe_and_attach() {
@@ -122,6 +156,13 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
         error!("{}: Failed to probe or attach device: {:?}", name, err);
     }
     // device::speaker::beep();
+    // let _ = device::speaker::play(device::speaker::STARTUP_JINGLE);
+
+    // initialize RTC driver
+    if let Err(err) = device::rtc::probe_and_attach() {
+        let name = device::rtc::get_device_driver_info().unwrap().name;
+        error!("{}: Failed to probe or attach device: {:?}", name, err);
+    }
 
     // initialize RTL8139 driver
     if let Err(err) = device::rtl8139::probe_and_attach() {
@@ -130,10 +171,17 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     }
 
     // initialize initramfs, VFS
-    fs::init(
-        boot_info.initramfs_start_virt_addr.into(),
-        &boot_info.kernel_config,
-    );
+    fs::init(boot_info.initramfs_start_virt_addr.into(), &kernel_config);
+
+    // load console font
+    if let Some(font_path) = kernel_config.font_path {
+        graphics::load_font(font_path);
+    }
+
+    // mount USB mass storage (requires usb-bus driver, disabled with it)
+    // if let Err(err) = fs::mount_usb_mass_storage("/mnt/usb") {
+    //     error!("fs: Failed to mount USB mass storage: {:?}", err);
+    // }
 
     // enable syscall
     syscall::enable();
@@ -148,6 +196,7 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
     let task_poll_virtio_net = async {
         loop {
             let _ = device::virtio::net::poll_normal();
+            let _ = net::poll_loopback();
             task::exec_yield().await;
         }
     };
@@ -173,15 +222,39 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
         }
     };
 
+    // handle port changes of USB hubs
+    let task_poll_usb_bus = async {
+        loop {
+            let _ = device::usb::bus::poll_normal();
+            task::exec_yield().await;
+        }
+    };
+
+    // redraw the taskbar clock
+    let task_update_taskbar = async {
+        let mut last_ms = 0;
+        loop {
+            let current_ms = device::local_apic_timer::get_current_ms().unwrap_or(0);
+            if current_ms.saturating_sub(last_ms) >= 1000 {
+                let _ = simple_window_manager::update_taskbar();
+                last_ms = current_ms;
+            }
+            task::exec_yield().await;
+        }
+    };
+
     task::spawn(task_poll_virtio_net).unwrap();
     task::spawn(task_poll_uart).unwrap();
     task::spawn(task_poll_ps2_keyboard).unwrap();
     task::spawn(task_poll_rtl8139).unwrap();
-    task::spawn(poll_ps2_mouse()).unwrap();
+    task::spawn(task_poll_usb_bus).unwrap();
+    task::spawn(task_update_taskbar).unwrap();
+    task::spawn(poll_mouse()).unwrap();
     task::ready().unwrap();
+    task::set_preemptive(true);
 
     // execute init app
-    let init_app_exec_args = boot_info.kernel_config.init_app_exec_args;
+    let init_app_exec_args = kernel_config.init_app_exec_args;
     if let Some(args) = init_app_exec_args {
         let splited: Vec<&str> = args.split(" ").collect();
 
@@ -189,19 +262,28 @@ pub extern "sysv64" fn kernel_main(boot_info: &BootInfo) -> ! {
             if splited.len() == 0 || splited[0] == "" {
                 error!("Invalid init app exec args: {:?}", args);
                 break;
-            } else if let Err(err) = fs::exec::exec_elf(splited[0], &splited[1..]) {
-                error!("{:?}", err);
-                break;
+            }
+
+            match fs::exec::exec_elf(splited[0], &splited[1..]) {
+                Ok(exit_code) => info!("Init app exited (code: {}), restarting", exit_code),
+                Err(err) => {
+                    error!("{:?}", err);
+                    break;
+                }
             }
         }
     }
 
     loop {
         arch::hlt();
+
+        if !task::is_preemptive() {
+            let _ = task::poll();
+        }
     }
 }
 
-async fn poll_ps2_mouse() {
+async fn poll_mouse() {
     let mut is_created_mouse_pointer_layer = false;
     let mouse_pointer_bmp_fd = loop {
         match vfs::open_file("/mnt/initramfs/sys/mouse_pointer.bmp") {
@@ -237,10 +319,13 @@ async fn poll_ps2_mouse() {
     loop {
         let mouse_event = match device::ps2_mouse::poll_normal() {
             Ok(Some(e)) => e,
-            _ => {
-                task::exec_yield().await;
-                continue;
-            }
+            _ => match device::usb::hid_mouse::poll_normal() {
+                Ok(Some(e)) => e,
+                _ => {
+                    task::exec_yield().await;
+                    continue;
+                }
+            },
         };
 
         if !is_created_mouse_pointer_layer
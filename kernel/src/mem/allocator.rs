@@ -1,32 +1,295 @@
 use super::{bitmap, paging::PAGE_SIZE};
-use crate::error::Result;
-use core::alloc::Layout;
-use linked_list_allocator::LockedHeap;
+use crate::{
+    arch,
+    error::Result,
+    util::mutex::{Mutex, MutexGuard},
+};
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    hint,
+    ptr::{self, NonNull},
+};
+use linked_list_allocator::Heap;
 
 const HEAP_SIZE: usize = 1024 * 1024 * 128; // 128MiB
+const SLAB_SIZE_CLASSES: [usize; 5] = [16, 32, 64, 128, 256];
 
 #[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
+static ALLOCATOR: KernelAllocator = KernelAllocator::new();
 
-pub fn init_heap() -> Result<()> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocatorKind {
+    LinkedList,
+    // segregated free lists for small sizes in front of the linked list heap
+    Slab,
+}
+
+struct FreeBlock {
+    next: Option<NonNull<FreeBlock>>,
+}
+
+struct SlabCache {
+    free_lists: [Option<NonNull<FreeBlock>>; SLAB_SIZE_CLASSES.len()],
+    allocated: usize,   // bytes handed out
+    frame_bytes: usize, // bytes taken from the frame allocator
+    heap_bytes: usize,  // bytes taken from the linked list heap
+}
+
+impl SlabCache {
+    const fn new() -> Self {
+        Self {
+            free_lists: [None; SLAB_SIZE_CLASSES.len()],
+            allocated: 0,
+            frame_bytes: 0,
+            heap_bytes: 0,
+        }
+    }
+
+    fn size_class(layout: Layout) -> Option<usize> {
+        SLAB_SIZE_CLASSES
+            .iter()
+            .position(|size| layout.size().max(layout.align()) <= *size)
+    }
+
+    fn pop(&mut self, class: usize) -> Option<NonNull<u8>> {
+        let block = self.free_lists[class]?;
+        self.free_lists[class] = unsafe { block.as_ref() }.next;
+        self.allocated += SLAB_SIZE_CLASSES[class];
+        Some(block.cast())
+    }
+
+    fn push(&mut self, class: usize, ptr: NonNull<u8>) {
+        let mut block = ptr.cast::<FreeBlock>();
+        unsafe { block.as_mut() }.next = self.free_lists[class];
+        self.free_lists[class] = Some(block);
+        self.allocated -= SLAB_SIZE_CLASSES[class];
+    }
+
+    // carve a new frame into blocks, frames are never returned
+    fn refill(&mut self, class: usize) -> Result<()> {
+        let size = SLAB_SIZE_CLASSES[class];
+        let mem_frame_info = bitmap::alloc_mem_frame(1)?;
+        let frame_start_ptr: *mut u8 = mem_frame_info.frame_start_virt_addr()?.as_ptr_mut();
+
+        for offset in (0..mem_frame_info.frame_size).step_by(size) {
+            let ptr = unsafe { NonNull::new_unchecked(frame_start_ptr.add(offset)) };
+            self.allocated += size;
+            self.push(class, ptr);
+        }
+        self.frame_bytes += mem_frame_info.frame_size;
+
+        Ok(())
+    }
+}
+
+struct KernelHeap {
+    heap: Heap,
+    slab: Option<SlabCache>,
+}
+
+impl KernelHeap {
+    fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let (slab, class) = match (self.slab.as_mut(), SlabCache::size_class(layout)) {
+            (Some(slab), Some(class)) => (slab, class),
+            _ => return self.heap.allocate_first_fit(layout).ok(),
+        };
+
+        if let Some(ptr) = slab.pop(class) {
+            return Some(ptr);
+        }
+
+        if slab.refill(class).is_ok() {
+            return slab.pop(class);
+        }
+
+        // the frame allocator may be locked, borrow a block of the class size from the heap
+        let size = SLAB_SIZE_CLASSES[class];
+        let ptr = self
+            .heap
+            .allocate_first_fit(Layout::from_size_align(size, size).ok()?)
+            .ok()?;
+        slab.heap_bytes += size;
+        slab.allocated += size;
+        Some(ptr)
+    }
+
+    fn dealloc(&mut self, ptr: NonNull<u8>, layout: Layout) {
+        match (self.slab.as_mut(), SlabCache::size_class(layout)) {
+            (Some(slab), Some(class)) => slab.push(class, ptr),
+            _ => unsafe { self.heap.deallocate(ptr, layout) },
+        }
+    }
+}
+
+struct KernelAllocator(Mutex<KernelHeap>);
+
+impl KernelAllocator {
+    const fn new() -> Self {
+        Self(Mutex::new(KernelHeap {
+            heap: Heap::empty(),
+            slab: None,
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<KernelHeap> {
+        loop {
+            if let Ok(heap) = self.0.try_lock() {
+                return heap;
+            }
+            hint::spin_loop();
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for KernelAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.lock()
+            .alloc(layout)
+            .map_or(ptr::null_mut(), |ptr| ptr.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            self.lock().dealloc(ptr, layout);
+        }
+    }
+}
+
+pub fn init_heap(kind: AllocatorKind) -> Result<()> {
     let mem_frame_info = bitmap::alloc_mem_frame((HEAP_SIZE / PAGE_SIZE).max(1))?;
     let frame_start_virt_addr = mem_frame_info.frame_start_virt_addr()?;
     bitmap::mem_clear(&mem_frame_info)?;
 
+    let mut heap = ALLOCATOR.lock();
     unsafe {
-        ALLOCATOR.lock().init(
+        heap.heap.init(
             frame_start_virt_addr.as_ptr_mut(),
             mem_frame_info.frame_size,
         )
     }
+    heap.slab = match kind {
+        AllocatorKind::LinkedList => None,
+        AllocatorKind::Slab => Some(SlabCache::new()),
+    };
+
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllocStats {
+    pub allocated: usize,
+    pub free: usize,
+    pub largest_free_block: usize,
+}
+
+pub fn stats() -> AllocStats {
+    // prevent interrupt handlers from allocating while the heap is locked
+    arch::disabled_int(|| {
+        let mut heap = ALLOCATOR.lock();
+        let (slab_allocated, slab_total, slab_heap_bytes) = match &heap.slab {
+            Some(slab) => (
+                slab.allocated,
+                slab.frame_bytes + slab.heap_bytes,
+                slab.heap_bytes,
+            ),
+            None => (0, 0, 0),
+        };
+
+        // count blocks cached by the slab as free
+        AllocStats {
+            allocated: heap.heap.used() - slab_heap_bytes + slab_allocated,
+            free: heap.heap.free() + slab_total - slab_allocated,
+            largest_free_block: largest_free_block(&mut heap.heap),
+        }
+    })
+}
+
+// the hole list is not exposed, so find the largest allocatable size by binary search
+// (rounded down to the allocator's minimum block granularity)
+fn largest_free_block(heap: &mut Heap) -> usize {
+    let mut min = 0;
+    let mut max = heap.free();
+
+    while min < max {
+        let size = (min + max + 1) / 2;
+        let layout = match Layout::from_size_align(size, 1) {
+            Ok(layout) => layout,
+            Err(_) => break,
+        };
+
+        match heap.allocate_first_fit(layout) {
+            Ok(ptr) => {
+                unsafe { heap.deallocate(ptr, layout) };
+                min = size;
+            }
+            Err(_) => max = size - 1,
+        }
+    }
+
+    min
+}
+
 #[alloc_error_handler]
 fn alloc_error_handler(layout: Layout) -> ! {
     panic!("Allocation error: {:?}", layout);
 }
 
+#[test_case]
+fn test_stats() {
+    use alloc::vec::Vec;
+
+    let before = stats();
+    assert!(before.largest_free_block <= before.free);
+
+    let v: Vec<u8> = Vec::with_capacity(4096);
+    let after = stats();
+    assert!(after.allocated >= before.allocated + v.capacity());
+    assert_eq!(after.allocated + after.free, before.allocated + before.free);
+}
+
+#[test_case]
+fn test_slab_benchmark() {
+    use alloc::vec::Vec;
+    use log::info;
+
+    const LEN: usize = 10000;
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    let mut ptrs: Vec<NonNull<u8>> = Vec::with_capacity(LEN);
+
+    // no allocation while the heap is locked
+    let (linked_list_cycles, slab_cycles) = {
+        let mut heap = ALLOCATOR.lock();
+
+        let start = arch::rdtsc();
+        for _ in 0..LEN {
+            ptrs.push(heap.heap.allocate_first_fit(layout).unwrap());
+        }
+        for ptr in ptrs.drain(..) {
+            unsafe { heap.heap.deallocate(ptr, layout) };
+        }
+        let linked_list_cycles = arch::rdtsc() - start;
+
+        let start = arch::rdtsc();
+        for _ in 0..LEN {
+            ptrs.push(heap.alloc(layout).unwrap());
+        }
+        assert!(ptrs
+            .iter()
+            .all(|ptr| ptr.as_ptr() as usize % layout.align() == 0));
+        for ptr in ptrs.drain(..) {
+            heap.dealloc(ptr, layout);
+        }
+        let slab_cycles = arch::rdtsc() - start;
+
+        (linked_list_cycles, slab_cycles)
+    };
+
+    info!(
+        "allocator: {} small blocks, linked list: {} cycles, current: {} cycles",
+        LEN, linked_list_cycles, slab_cycles
+    );
+}
+
 #[test_case]
 fn test_alloc_string() {
     use alloc::string::String;
@@ -54,6 +54,7 @@ impl MemoryFrameInfo {
                 rw,
                 us,
                 pwt,
+                exec_disable: false,
             })?;
             start = start.offset(PAGE_SIZE);
         }
@@ -401,6 +402,12 @@ impl BitmapMemoryManager {
         let bitmap_pos = self.bitmap_pos(frame_index);
         let bitmap = self.bitmap(self.bitmap_offset(frame_index))?;
 
+        debug_assert!(
+            bitmap.get(bitmap_pos)?,
+            "bitmap: Double free of memory frame (index: {})",
+            frame_index
+        );
+
         // already deallocated
         if !bitmap.get(bitmap_pos)? {
             return Err(
@@ -463,6 +470,19 @@ pub fn alloc_mem_frame(len: usize) -> Result<MemoryFrameInfo> {
         .alloc_multi_mem_frame(len)
 }
 
+// allocate the frame at the specified physical address
+pub fn alloc_mem_frame_at(phys_addr: PhysicalAddress) -> Result<MemoryFrameInfo> {
+    let mut binding = unsafe { BITMAP_MEM_MAN.try_lock() }?;
+    let bitmap_mem_man = binding
+        .as_mut()
+        .ok_or(BitmapMemoryManagerError::NotInitialized)?;
+    let frame_index = phys_addr.get() as usize / PAGE_SIZE;
+    bitmap_mem_man.alloc_frame(frame_index)?;
+    bitmap_mem_man
+        .get_mem_frame(frame_index)
+        .ok_or(Error::IndexOutOfBoundsError(frame_index))
+}
+
 pub fn dealloc_mem_frame(mem_frame_info: MemoryFrameInfo) -> Result<()> {
     unsafe { BITMAP_MEM_MAN.try_lock() }?
         .as_mut()
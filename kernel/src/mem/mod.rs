@@ -1,4 +1,8 @@
 use crate::{
+    arch::{
+        cpu,
+        register::{model_specific::ExtendedFeatureEnableRegister, Register},
+    },
     mem::paging::{EntryMode, PageWriteThroughLevel, ReadWrite, PAGE_SIZE},
     println,
 };
@@ -15,6 +19,13 @@ pub fn init(mem_map: &[MemoryDescriptor]) {
     }
     info!("mem: Bitmap memory manager initialized");
 
+    // enable the execute disable bit of page table entries
+    if cpu::is_nx_supported() {
+        let mut efer = ExtendedFeatureEnableRegister::read();
+        efer.set_no_execute_enable(true);
+        efer.write();
+    }
+
     let start = PAGE_SIZE as u64;
     let end = bitmap::get_total_mem_size().unwrap() as u64;
 
@@ -29,7 +40,7 @@ pub fn init(mem_map: &[MemoryDescriptor]) {
         error!("paging: Failed to create new page table: {:?}", err);
     }
 
-    if let Err(err) = allocator::init_heap() {
+    if let Err(err) = allocator::init_heap(allocator::AllocatorKind::Slab) {
         panic!("mem: {:?}", err);
     }
     info!("mem: Heap allocator initialized");
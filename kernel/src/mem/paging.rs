@@ -1,8 +1,9 @@
 use super::bitmap::MemoryFrameInfo;
 use crate::{
     arch::{
+        self,
         addr::*,
-        register::{control::*, Register},
+        register::{control::*, model_specific::ExtendedFeatureEnableRegister, Register},
     },
     error::Result,
     mem::bitmap,
@@ -11,7 +12,12 @@ use alloc::string::String;
 use log::info;
 
 const PAGE_TABLE_ENTRY_LEN: usize = 512;
+// bits 12-51, the upper bits are flags (XD) or reserved
+const PAGE_TABLE_ENTRY_ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
 pub const PAGE_SIZE: usize = 4096;
+// virtual address range not identity mapped, used by the user memory (mmap, brk)
+pub const USER_AREA_START: u64 = 0x100_0000_0000;
+pub const USER_AREA_END: u64 = 0x200_0000_0000;
 static mut PAGE_MAN: PageManager = PageManager::new();
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
@@ -35,6 +41,21 @@ pub enum PageWriteThroughLevel {
     WriteThrough = 1,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HugePageSize {
+    Size2M,
+    Size1G,
+}
+
+impl HugePageSize {
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Size2M => 0x20_0000,
+            Self::Size1G => 0x4000_0000,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Default)]
 pub struct PageTableEntry(u64);
 
@@ -111,22 +132,41 @@ impl PageTableEntry {
         (self.0 & 0x40) != 0
     }
 
+    pub fn set_page_size(&mut self, value: bool) {
+        self.0 = (self.0 & !0x80) | ((value as u64) << 7);
+    }
+
     pub fn page_size(&self) -> bool {
         (self.0 & 0x80) != 0
     }
 
     pub fn set_addr(&mut self, addr: u64) {
-        self.0 = (self.0 & 0xfff) | (addr & !0xfff);
+        self.0 = (self.0 & !PAGE_TABLE_ENTRY_ADDR_MASK) | (addr & PAGE_TABLE_ENTRY_ADDR_MASK);
     }
 
     pub fn addr(&self) -> u64 {
-        self.0 & !0xfff
+        self.0 & PAGE_TABLE_ENTRY_ADDR_MASK
+    }
+
+    // ignored if the no-execute is not enabled in EFER, the bit is reserved
+    pub fn set_exec_disable(&mut self, value: bool) {
+        let value = value && ExtendedFeatureEnableRegister::read().no_execute_enable();
+        self.0 = (self.0 & !(1 << 63)) | ((value as u64) << 63);
     }
 
     pub fn exec_disable(&self) -> bool {
         (self.0 & (1 << 63)) != 0
     }
 
+    // software available bit, the page table is owned by a user page table
+    pub fn set_private(&mut self, value: bool) {
+        self.0 = (self.0 & !0x200) | ((value as u64) << 9);
+    }
+
+    pub fn private(&self) -> bool {
+        (self.0 & 0x200) != 0
+    }
+
     pub unsafe fn page_table(&self) -> Option<&mut PageTable> {
         match self.page_size() {
             true => None,
@@ -148,6 +188,7 @@ impl PageTableEntry {
         self.set_rw(rw);
         self.set_us(mode);
         self.set_pwt(write_through_level);
+        self.set_exec_disable(false);
         self.set_addr(addr);
     }
 }
@@ -166,6 +207,7 @@ pub struct MappingInfo {
     pub rw: ReadWrite,
     pub us: EntryMode,
     pub pwt: PageWriteThroughLevel,
+    pub exec_disable: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -174,6 +216,8 @@ pub enum PageManagerError {
     VirtualAddressNotAllowedToMapError(VirtualAddress),
     VirtualAddressNotAlignedByPageSizeError(VirtualAddress),
     PhysicalAddressNotMappedError(PhysicalAddress),
+    PhysicalAddressNotAlignedByPageSizeError(PhysicalAddress),
+    VirtualAddressAlreadyMappedError(VirtualAddress),
 }
 
 #[derive(Debug)]
@@ -266,7 +310,6 @@ impl PageManager {
         Err(PageManagerError::PhysicalAddressNotMappedError(phys_addr).into())
     }
 
-    // unsupported 2MB / 1GB pages
     pub unsafe fn calc_phys_addr(&self, virt_addr: VirtualAddress) -> Result<PhysicalAddress> {
         let pml4e_index = virt_addr.get_pml4_entry_index();
         let pml3e_index = virt_addr.get_pml3_entry_index();
@@ -291,7 +334,9 @@ impl PageManager {
         let pml2_table = match entry.page_table() {
             Some(table) => table,
             None => {
-                unimplemented!();
+                // 1GB page
+                let offset = virt_addr.get() & (HugePageSize::Size1G.size() as u64 - 1);
+                return Ok(PhysicalAddress::new(entry.addr() | offset));
             }
         };
         let entry = &pml2_table.entries[pml2e_index];
@@ -303,13 +348,44 @@ impl PageManager {
         let pml1_table = match entry.page_table() {
             Some(table) => table,
             None => {
-                unimplemented!();
+                // 2MB page
+                let offset = virt_addr.get() & (HugePageSize::Size2M.size() as u64 - 1);
+                return Ok(PhysicalAddress::new(entry.addr() | offset));
             }
         };
         let entry = &pml1_table.entries[pml1e_index];
+
+        if !entry.p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
         Ok(PhysicalAddress::new(entry.addr() | page_offset as u64))
     }
 
+    // the page is accessible from user mode only if all levels allow it
+    pub unsafe fn is_user_page(&self, virt_addr: VirtualAddress, rw: ReadWrite) -> bool {
+        let mut table = self.pml4_table();
+        for index in [
+            virt_addr.get_pml4_entry_index(),
+            virt_addr.get_pml3_entry_index(),
+            virt_addr.get_pml2_entry_index(),
+            virt_addr.get_pml1_entry_index(),
+        ] {
+            let entry = &table.entries[index];
+
+            if !entry.p() || entry.us() != EntryMode::User || entry.rw() < rw {
+                return false;
+            }
+
+            table = match entry.page_table() {
+                Some(table) => table,
+                None => return true, // 2MB / 1GB page
+            };
+        }
+
+        true
+    }
+
     pub unsafe fn create_new_page_table(
         &self,
         start: VirtualAddress,
@@ -334,6 +410,7 @@ impl PageManager {
                 rw,
                 mode,
                 write_through_level,
+                false,
             )?;
         }
 
@@ -355,6 +432,7 @@ impl PageManager {
             rw,
             pwt,
             us,
+            exec_disable,
         } = *new_mapping_info;
 
         for i in (start.get() as usize..end.get() as usize).step_by(PAGE_SIZE) {
@@ -367,12 +445,322 @@ impl PageManager {
                 rw,
                 us,
                 pwt,
+                exec_disable,
             )?;
         }
 
         Ok(())
     }
 
+    // map a virtual page to any physical frame in the current page table
+    pub unsafe fn map(
+        &self,
+        virt_addr: VirtualAddress,
+        phys_addr: PhysicalAddress,
+        rw: ReadWrite,
+        mode: EntryMode,
+        write_through_level: PageWriteThroughLevel,
+    ) -> Result<()> {
+        if phys_addr.get() % PAGE_SIZE as u64 != 0 {
+            return Err(
+                PageManagerError::PhysicalAddressNotAlignedByPageSizeError(phys_addr).into(),
+            );
+        }
+
+        let pml4_table = self.pml4_table();
+        self.set_map(
+            virt_addr,
+            phys_addr,
+            pml4_table,
+            rw,
+            mode,
+            write_through_level,
+            false,
+        )
+    }
+
+    // map a 2MB / 1GB page to the physical address in the current page table
+    pub unsafe fn map_huge(
+        &self,
+        virt_addr: VirtualAddress,
+        phys_addr: PhysicalAddress,
+        page_size: HugePageSize,
+        rw: ReadWrite,
+        mode: EntryMode,
+        write_through_level: PageWriteThroughLevel,
+    ) -> Result<()> {
+        let size = page_size.size() as u64;
+
+        if virt_addr.get() == 0 {
+            return Err(PageManagerError::VirtualAddressNotAllowedToMapError(virt_addr).into());
+        }
+
+        if virt_addr.get() % size != 0 {
+            return Err(
+                PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr).into(),
+            );
+        }
+
+        if phys_addr.get() % size != 0 {
+            return Err(
+                PageManagerError::PhysicalAddressNotAlignedByPageSizeError(phys_addr).into(),
+            );
+        }
+
+        let indexes = match page_size {
+            HugePageSize::Size2M => &[
+                virt_addr.get_pml4_entry_index(),
+                virt_addr.get_pml3_entry_index(),
+            ][..],
+            HugePageSize::Size1G => &[virt_addr.get_pml4_entry_index()][..],
+        };
+
+        let mut table = self.pml4_table();
+        for &index in indexes {
+            let entry = &mut table.entries[index];
+
+            if !entry.p() {
+                let mem_info = bitmap::alloc_mem_frame(1)?;
+                self.mem_clear(&mem_info)?;
+                let virt_addr = self.calc_virt_addr(mem_info.frame_start_phys_addr)?;
+                entry.set_entry(virt_addr.get(), rw, mode, write_through_level);
+            }
+
+            if entry.rw() < rw {
+                entry.set_rw(rw);
+            }
+
+            if entry.us() < mode {
+                entry.set_us(mode);
+            }
+
+            table =
+                entry
+                    .page_table()
+                    .ok_or(PageManagerError::VirtualAddressAlreadyMappedError(
+                        virt_addr,
+                    ))?;
+        }
+
+        let index = match page_size {
+            HugePageSize::Size2M => virt_addr.get_pml2_entry_index(),
+            HugePageSize::Size1G => virt_addr.get_pml3_entry_index(),
+        };
+        let entry = &mut table.entries[index];
+
+        // don't overwrite the entry pointing to the page table
+        if entry.p() && !entry.page_size() {
+            return Err(PageManagerError::VirtualAddressAlreadyMappedError(virt_addr).into());
+        }
+
+        entry.set_entry(phys_addr.get(), rw, mode, write_through_level);
+        entry.set_page_size(true);
+        arch::invlpg(virt_addr.get());
+
+        Ok(())
+    }
+
+    // clear the page table entry (or 2MB / 1GB page entry) and return the mapped physical address
+    // huge pages are unmapped only by their aligned start address
+    pub unsafe fn unmap(&self, virt_addr: VirtualAddress) -> Result<PhysicalAddress> {
+        if virt_addr.get() % PAGE_SIZE as u64 != 0 {
+            return Err(
+                PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr).into(),
+            );
+        }
+
+        let pml4_table = self.pml4_table();
+        let pml4_entry: *mut PageTableEntry =
+            &mut pml4_table.entries[virt_addr.get_pml4_entry_index()];
+
+        if !(*pml4_entry).p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
+        let pml3_table: *mut PageTable = (*pml4_entry).page_table().unwrap();
+        let pml3_entry: *mut PageTableEntry =
+            &mut (*pml3_table).entries[virt_addr.get_pml3_entry_index()];
+
+        if !(*pml3_entry).p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
+        let pml2_table: *mut PageTable = match (*pml3_entry).page_table() {
+            Some(table) => table,
+            None => {
+                // 1GB page, must be unmapped by the start address
+                if virt_addr.get() % HugePageSize::Size1G.size() as u64 != 0 {
+                    return Err(PageManagerError::VirtualAddressNotAlignedByPageSizeError(
+                        virt_addr,
+                    )
+                    .into());
+                }
+
+                let phys_addr = (*pml3_entry).addr().into();
+                *pml3_entry = PageTableEntry::default();
+                arch::invlpg(virt_addr.get());
+                return Ok(phys_addr);
+            }
+        };
+        let pml2_entry: *mut PageTableEntry =
+            &mut (*pml2_table).entries[virt_addr.get_pml2_entry_index()];
+
+        if !(*pml2_entry).p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
+        let pml1_table: *mut PageTable = match (*pml2_entry).page_table() {
+            Some(table) => table,
+            None => {
+                // 2MB page, must be unmapped by the start address
+                if virt_addr.get() % HugePageSize::Size2M.size() as u64 != 0 {
+                    return Err(PageManagerError::VirtualAddressNotAlignedByPageSizeError(
+                        virt_addr,
+                    )
+                    .into());
+                }
+
+                let phys_addr = (*pml2_entry).addr().into();
+                *pml2_entry = PageTableEntry::default();
+                arch::invlpg(virt_addr.get());
+                return Ok(phys_addr);
+            }
+        };
+        let entry = &mut (*pml1_table).entries[virt_addr.get_pml1_entry_index()];
+
+        if !entry.p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
+        let phys_addr = entry.addr().into();
+        *entry = PageTableEntry::default();
+        arch::invlpg(virt_addr.get());
+
+        // release the page tables from the bottom while all entries are cleared
+        // the PML4 table is never released
+        for (table, parent_entry) in [
+            (pml1_table, pml2_entry),
+            (pml2_table, pml3_entry),
+            (pml3_table, pml4_entry),
+        ] {
+            if !(*table)
+                .entries
+                .iter()
+                .all(|e| *e == PageTableEntry::default())
+            {
+                break;
+            }
+
+            let table_phys_addr = (*parent_entry).addr().into();
+            *parent_entry = PageTableEntry::default();
+            self.dealloc_page_table(table_phys_addr)?;
+        }
+
+        Ok(phys_addr)
+    }
+
+    // create a new PML4 table sharing all entries with the current page table
+    // except the user area, tables in it are released when emptied by unmap
+    pub unsafe fn create_user_page_table(&self) -> Result<PhysicalAddress> {
+        let table_virt_addr = self.alloc_page_table()?;
+        let table = &mut *table_virt_addr.as_ptr_mut::<PageTable>();
+        table.entries = self.pml4_table().entries;
+
+        let user_area_start = VirtualAddress::new(USER_AREA_START).get_pml4_entry_index();
+        let user_area_end = VirtualAddress::new(USER_AREA_END).get_pml4_entry_index();
+        for entry in table.entries[user_area_start..user_area_end].iter_mut() {
+            *entry = PageTableEntry::default();
+        }
+
+        self.calc_phys_addr(table_virt_addr)
+    }
+
+    // map a virtual page in the user page table
+    // shared page tables on the way are copied before being modified
+    pub unsafe fn map_to_user_page_table(
+        &self,
+        pml4_phys_addr: PhysicalAddress,
+        virt_addr: VirtualAddress,
+        phys_addr: PhysicalAddress,
+        rw: ReadWrite,
+        mode: EntryMode,
+        write_through_level: PageWriteThroughLevel,
+        exec_disable: bool,
+    ) -> Result<()> {
+        if virt_addr.get() == 0 {
+            return Err(PageManagerError::VirtualAddressNotAllowedToMapError(virt_addr).into());
+        }
+
+        if virt_addr.get() % PAGE_SIZE as u64 != 0 {
+            return Err(
+                PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr).into(),
+            );
+        }
+
+        if phys_addr.get() % PAGE_SIZE as u64 != 0 {
+            return Err(
+                PageManagerError::PhysicalAddressNotAlignedByPageSizeError(phys_addr).into(),
+            );
+        }
+
+        let mut table = &mut *self
+            .calc_virt_addr(pml4_phys_addr)?
+            .as_ptr_mut::<PageTable>();
+        for index in [
+            virt_addr.get_pml4_entry_index(),
+            virt_addr.get_pml3_entry_index(),
+            virt_addr.get_pml2_entry_index(),
+        ] {
+            let entry = &mut table.entries[index];
+
+            if !entry.p() {
+                let table_virt_addr = self.alloc_page_table()?;
+                entry.set_entry(table_virt_addr.get(), rw, mode, write_through_level);
+                entry.set_private(true);
+            } else if !entry.private() {
+                let shared_table = entry.page_table().ok_or(
+                    PageManagerError::VirtualAddressAlreadyMappedError(virt_addr),
+                )?;
+                let table_virt_addr = self.alloc_page_table()?;
+                (*table_virt_addr.as_ptr_mut::<PageTable>()).entries = shared_table.entries;
+                entry.set_addr(table_virt_addr.get());
+                entry.set_private(true);
+            }
+
+            if entry.rw() < rw {
+                entry.set_rw(rw);
+            }
+
+            if entry.us() < mode {
+                entry.set_us(mode);
+            }
+
+            table = entry.page_table().unwrap();
+        }
+
+        let entry = &mut table.entries[virt_addr.get_pml1_entry_index()];
+        entry.set_entry(phys_addr.get(), rw, mode, write_through_level);
+        entry.set_exec_disable(exec_disable);
+
+        Ok(())
+    }
+
+    // release the user page table and page tables owned by it
+    pub unsafe fn destroy_user_page_table(&self, pml4_phys_addr: PhysicalAddress) -> Result<()> {
+        let pml4_virt_addr = self.calc_virt_addr(pml4_phys_addr)?;
+        self.dealloc_private_page_tables(&*pml4_virt_addr.as_ptr::<PageTable>(), 3)?;
+        self.dealloc_page_table(pml4_phys_addr)
+    }
+
+    // make the page not present to catch accesses by page fault
+    pub unsafe fn set_guard_page(&self, virt_addr: VirtualAddress) -> Result<()> {
+        let entry = self.page_table_entry_mut(virt_addr)?;
+        entry.set_p(false);
+        arch::invlpg(virt_addr.get());
+
+        Ok(())
+    }
+
     pub unsafe fn page_table_entry(&self, virt_addr: VirtualAddress) -> Result<&PageTableEntry> {
         if virt_addr.get() % PAGE_SIZE as u64 != 0 {
             return Err(
@@ -413,6 +801,77 @@ impl PageManager {
         Ok(entry)
     }
 
+    // unsupported 2MB / 1GB pages
+    unsafe fn page_table_entry_mut(
+        &self,
+        virt_addr: VirtualAddress,
+    ) -> Result<&mut PageTableEntry> {
+        if virt_addr.get() % PAGE_SIZE as u64 != 0 {
+            return Err(
+                PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr).into(),
+            );
+        }
+
+        let mut table = self.pml4_table();
+        for index in [
+            virt_addr.get_pml4_entry_index(),
+            virt_addr.get_pml3_entry_index(),
+            virt_addr.get_pml2_entry_index(),
+        ] {
+            let entry = &table.entries[index];
+
+            if !entry.p() {
+                return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+            }
+
+            table = entry
+                .page_table()
+                .ok_or(PageManagerError::VirtualAddressNotMappedError(virt_addr))?;
+        }
+
+        let entry = &mut table.entries[virt_addr.get_pml1_entry_index()];
+
+        if !entry.p() {
+            return Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into());
+        }
+
+        Ok(entry)
+    }
+
+    // level: 3 (PML3) - 1 (PML1) of the child tables
+    // walk every level and release the private tables, 2MB / 1GB pages are skipped
+    unsafe fn dealloc_private_page_tables(&self, table: &PageTable, level: usize) -> Result<()> {
+        for entry in table.entries.iter().filter(|e| e.p() && e.private()) {
+            let child_table = match entry.page_table() {
+                Some(table) => table,
+                None => continue,
+            };
+
+            if level > 1 {
+                self.dealloc_private_page_tables(child_table, level - 1)?;
+            }
+
+            self.dealloc_page_table(entry.addr().into())?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn alloc_page_table(&self) -> Result<VirtualAddress> {
+        let mem_info = bitmap::alloc_mem_frame(1)?;
+        self.mem_clear(&mem_info)?;
+        self.calc_virt_addr(mem_info.frame_start_phys_addr)
+    }
+
+    unsafe fn dealloc_page_table(&self, table_phys_addr: PhysicalAddress) -> Result<()> {
+        bitmap::dealloc_mem_frame(MemoryFrameInfo {
+            frame_start_phys_addr: table_phys_addr,
+            frame_size: PAGE_SIZE,
+            frame_index: table_phys_addr.get() as usize / PAGE_SIZE,
+            is_allocated: true,
+        })
+    }
+
     fn cr3(&self) -> Cr3 {
         Cr3::read()
     }
@@ -431,6 +890,7 @@ impl PageManager {
         rw: ReadWrite,
         mode: EntryMode,
         write_through_level: PageWriteThroughLevel,
+        exec_disable: bool,
     ) -> Result<()> {
         if virt_addr.get() == 0 {
             return Err(PageManagerError::VirtualAddressNotAllowedToMapError(virt_addr).into());
@@ -503,6 +963,8 @@ impl PageManager {
         let pml1_table = entry.page_table().unwrap();
         let entry = &mut pml1_table.entries[pml1e_index];
         entry.set_entry(phys_addr.get(), rw, mode, write_through_level);
+        entry.set_exec_disable(exec_disable);
+        arch::invlpg(virt_addr.get());
 
         Ok(())
     }
@@ -530,6 +992,19 @@ pub fn calc_phys_addr(virt_addr: VirtualAddress) -> Result<PhysicalAddress> {
     unsafe { PAGE_MAN.calc_phys_addr(virt_addr) }
 }
 
+// whether the range is mapped to user pages in the current page table
+pub fn is_user_accessible(virt_addr: VirtualAddress, len: usize, rw: ReadWrite) -> bool {
+    let start = virt_addr.get() & !(PAGE_SIZE as u64 - 1);
+    let end = match virt_addr.get().checked_add(len as u64) {
+        Some(end) => end,
+        None => return false,
+    };
+
+    (start..end)
+        .step_by(PAGE_SIZE)
+        .all(|addr| unsafe { PAGE_MAN.is_user_page(addr.into(), rw) })
+}
+
 pub fn create_new_page_table(
     start: VirtualAddress,
     end: VirtualAddress,
@@ -560,6 +1035,74 @@ pub fn update_mapping(mapping_info: &MappingInfo) -> Result<()> {
     // );
 }
 
+pub fn map(
+    virt_addr: VirtualAddress,
+    phys_addr: PhysicalAddress,
+    rw: ReadWrite,
+    mode: EntryMode,
+    write_through_level: PageWriteThroughLevel,
+) -> Result<()> {
+    unsafe { PAGE_MAN.map(virt_addr, phys_addr, rw, mode, write_through_level) }
+}
+
+pub fn map_huge(
+    virt_addr: VirtualAddress,
+    phys_addr: PhysicalAddress,
+    page_size: HugePageSize,
+    rw: ReadWrite,
+    mode: EntryMode,
+    write_through_level: PageWriteThroughLevel,
+) -> Result<()> {
+    unsafe {
+        PAGE_MAN.map_huge(
+            virt_addr,
+            phys_addr,
+            page_size,
+            rw,
+            mode,
+            write_through_level,
+        )
+    }
+}
+
+pub fn unmap(virt_addr: VirtualAddress) -> Result<PhysicalAddress> {
+    unsafe { PAGE_MAN.unmap(virt_addr) }
+}
+
+pub fn create_user_page_table() -> Result<PhysicalAddress> {
+    unsafe { PAGE_MAN.create_user_page_table() }
+}
+
+pub fn map_to_user_page_table(
+    pml4_phys_addr: PhysicalAddress,
+    virt_addr: VirtualAddress,
+    phys_addr: PhysicalAddress,
+    rw: ReadWrite,
+    mode: EntryMode,
+    write_through_level: PageWriteThroughLevel,
+    exec_disable: bool,
+) -> Result<()> {
+    unsafe {
+        PAGE_MAN.map_to_user_page_table(
+            pml4_phys_addr,
+            virt_addr,
+            phys_addr,
+            rw,
+            mode,
+            write_through_level,
+            exec_disable,
+        )
+    }
+}
+
+pub fn destroy_user_page_table(pml4_phys_addr: PhysicalAddress) -> Result<()> {
+    unsafe { PAGE_MAN.destroy_user_page_table(pml4_phys_addr) }
+}
+
+pub fn set_guard_page(virt_addr: VirtualAddress) -> Result<()> {
+    unsafe { PAGE_MAN.set_guard_page(virt_addr) }
+}
+
 pub fn read_page_table_entry(virt_addr: VirtualAddress) -> Result<PageTableEntry> {
     Ok(unsafe { PAGE_MAN.page_table_entry(virt_addr)?.clone() })
 }
@@ -585,6 +1128,7 @@ fn test_page_table_entry() {
         rw: ReadWrite::Read,
         us: EntryMode::User,
         pwt: PageWriteThroughLevel::WriteThrough,
+        exec_disable: true,
     })
     .is_ok());
 
@@ -595,6 +1139,7 @@ fn test_page_table_entry() {
     assert_eq!(entry.us(), EntryMode::User);
     assert_eq!(entry.pwt(), PageWriteThroughLevel::WriteThrough);
     assert_eq!(entry.addr(), phys_addr.get());
+    assert_eq!(entry.exec_disable(), arch::cpu::is_nx_supported());
 
     assert!(update_mapping(&MappingInfo {
         start: virt_addr,
@@ -603,6 +1148,7 @@ fn test_page_table_entry() {
         rw: ReadWrite::Write,
         us: EntryMode::Supervisor,
         pwt: PageWriteThroughLevel::WriteThrough,
+        exec_disable: false,
     })
     .is_ok());
 
@@ -613,4 +1159,183 @@ fn test_page_table_entry() {
     assert_eq!(entry.us(), EntryMode::Supervisor);
     assert_eq!(entry.pwt(), PageWriteThroughLevel::WriteThrough);
     assert_eq!(entry.addr(), virt_addr.get());
+    assert!(!entry.exec_disable());
+}
+
+#[test_case]
+fn test_page_table_entry_exec_disable_addr() {
+    // XD is not a part of the address
+    let mut entry = PageTableEntry(1 << 63);
+    entry.set_addr(0x4000123);
+    assert_eq!(entry.addr(), 0x4000000);
+    assert!(entry.exec_disable());
+
+    let virt_addr = VirtualAddress::new(0x3100000);
+    let phys_addr = PhysicalAddress::new(0x4100000);
+
+    assert!(update_mapping(&MappingInfo {
+        start: virt_addr,
+        end: virt_addr.offset(PAGE_SIZE),
+        phys_addr,
+        rw: ReadWrite::Write,
+        us: EntryMode::Supervisor,
+        pwt: PageWriteThroughLevel::WriteBack,
+        exec_disable: true,
+    })
+    .is_ok());
+
+    let entry = read_page_table_entry(virt_addr).unwrap();
+    assert_eq!(entry.exec_disable(), arch::cpu::is_nx_supported());
+    assert_eq!(entry.addr(), phys_addr.get());
+    assert_eq!(
+        calc_phys_addr(virt_addr.offset(0x10)).unwrap().get(),
+        phys_addr.get() + 0x10
+    );
+
+    assert!(update_mapping(&MappingInfo {
+        start: virt_addr,
+        end: virt_addr.offset(PAGE_SIZE),
+        phys_addr: virt_addr.get().into(),
+        rw: ReadWrite::Write,
+        us: EntryMode::Supervisor,
+        pwt: PageWriteThroughLevel::WriteBack,
+        exec_disable: false,
+    })
+    .is_ok());
+}
+
+#[test_case]
+fn test_map_non_identity() {
+    let mem_frame_info = bitmap::alloc_mem_frame(1).unwrap();
+    let phys_addr = mem_frame_info.frame_start_phys_addr;
+    // out of the identity mapped range
+    let virt_addr = VirtualAddress::new(0x80_0000_0000);
+
+    assert!(map(
+        virt_addr,
+        phys_addr,
+        ReadWrite::Write,
+        EntryMode::Supervisor,
+        PageWriteThroughLevel::WriteBack,
+    )
+    .is_ok());
+    assert_eq!(
+        calc_phys_addr(virt_addr.offset(0x10)).unwrap().get(),
+        phys_addr.get() + 0x10
+    );
+
+    unsafe {
+        *virt_addr.as_ptr_mut::<u64>() = 0xdeadbeef;
+        assert_eq!(*(phys_addr.get() as *const u64), 0xdeadbeef);
+    }
+
+    assert!(map(
+        virt_addr,
+        phys_addr.offset(1),
+        ReadWrite::Write,
+        EntryMode::Supervisor,
+        PageWriteThroughLevel::WriteBack,
+    )
+    .is_err());
+}
+
+#[test_case]
+fn test_unmap() {
+    let mem_frame_info = bitmap::alloc_mem_frame(1).unwrap();
+    let phys_addr = mem_frame_info.frame_start_phys_addr;
+    let virt_addr = VirtualAddress::new(0x80_0000_1000);
+
+    map(
+        virt_addr,
+        phys_addr,
+        ReadWrite::Write,
+        EntryMode::Supervisor,
+        PageWriteThroughLevel::WriteBack,
+    )
+    .unwrap();
+
+    assert_eq!(unmap(virt_addr), Ok(phys_addr));
+    assert!(calc_phys_addr(virt_addr).is_err());
+    assert_eq!(
+        unmap(virt_addr),
+        Err(PageManagerError::VirtualAddressNotMappedError(virt_addr).into())
+    );
+
+    bitmap::dealloc_mem_frame(mem_frame_info).unwrap();
+}
+
+#[test_case]
+fn test_map_huge() {
+    let virt_addr = VirtualAddress::new(0x80_4000_0000);
+    let phys_addr = PhysicalAddress::new(0x20_0000);
+
+    assert_eq!(
+        map_huge(
+            virt_addr.offset(PAGE_SIZE),
+            phys_addr,
+            HugePageSize::Size2M,
+            ReadWrite::Write,
+            EntryMode::Supervisor,
+            PageWriteThroughLevel::WriteBack,
+        ),
+        Err(
+            PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr.offset(PAGE_SIZE))
+                .into()
+        )
+    );
+    assert!(map_huge(
+        virt_addr,
+        phys_addr,
+        HugePageSize::Size2M,
+        ReadWrite::Write,
+        EntryMode::Supervisor,
+        PageWriteThroughLevel::WriteBack,
+    )
+    .is_ok());
+    assert_eq!(
+        calc_phys_addr(virt_addr.offset(0x1234)).unwrap().get(),
+        phys_addr.get() + 0x1234
+    );
+    assert_eq!(
+        unmap(virt_addr.offset(PAGE_SIZE)),
+        Err(
+            PageManagerError::VirtualAddressNotAlignedByPageSizeError(virt_addr.offset(PAGE_SIZE))
+                .into()
+        )
+    );
+    assert_eq!(unmap(virt_addr), Ok(phys_addr));
+}
+
+#[test_case]
+fn test_user_page_table() {
+    let mem_frame_info = bitmap::alloc_mem_frame(1).unwrap();
+    let phys_addr = mem_frame_info.frame_start_phys_addr;
+    let virt_addr = VirtualAddress::new(0x80_8000_0000);
+    unsafe { *(phys_addr.get() as *mut u64) = 0xdeadbeef };
+
+    let pml4_phys_addr = create_user_page_table().unwrap();
+    map_to_user_page_table(
+        pml4_phys_addr,
+        virt_addr,
+        phys_addr,
+        ReadWrite::Write,
+        EntryMode::Supervisor,
+        PageWriteThroughLevel::WriteBack,
+        false,
+    )
+    .unwrap();
+
+    // not mapped in the current page table
+    assert!(calc_phys_addr(virt_addr).is_err());
+
+    let kernel_cr3 = Cr3::read();
+    let mut cr3 = Cr3::read();
+    cr3.set_raw(pml4_phys_addr.get());
+    cr3.write();
+    let value = unsafe { *virt_addr.as_ptr::<u64>() };
+    kernel_cr3.write();
+
+    assert_eq!(value, 0xdeadbeef);
+    assert!(destroy_user_page_table(pml4_phys_addr).is_ok());
+    bitmap::dealloc_mem_frame(mem_frame_info).unwrap();
 }
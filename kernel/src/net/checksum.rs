@@ -0,0 +1,86 @@
+use alloc::vec::Vec;
+use core::net::Ipv4Addr;
+
+const UDP_HEADER_LEN: usize = 8;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+// RFC 1071 internet checksum, 0 if the data contains a valid checksum field
+pub fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+pub fn is_valid_icmp_checksum(packet: &[u8]) -> bool {
+    internet_checksum(packet) == 0
+}
+
+// the checksum covers the pseudo header, 0 means the sender did not compute it
+pub fn is_valid_udp_checksum(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, datagram: &[u8]) -> bool {
+    if datagram.len() < UDP_HEADER_LEN {
+        return false;
+    }
+
+    if datagram[6..8] == [0, 0] {
+        return true;
+    }
+
+    internet_checksum(&udp_pseudo_header_and(src_addr, dst_addr, datagram)) == 0
+}
+
+// checksum field of an outgoing datagram, 0 is sent as 0xffff
+pub fn udp_checksum(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, datagram: &[u8]) -> u16 {
+    match internet_checksum(&udp_pseudo_header_and(src_addr, dst_addr, datagram)) {
+        0 => 0xffff,
+        checksum => checksum,
+    }
+}
+
+fn udp_pseudo_header_and(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, datagram: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(12 + datagram.len());
+    data.extend_from_slice(&src_addr.octets());
+    data.extend_from_slice(&dst_addr.octets());
+    data.extend_from_slice(&[0, IP_PROTOCOL_UDP]);
+    data.extend_from_slice(&(datagram.len() as u16).to_be_bytes());
+    data.extend_from_slice(datagram);
+    data
+}
+
+#[test_case]
+fn test_icmp_checksum() {
+    // echo request, id: 0, seq: 1
+    let mut packet = [0x08, 0x00, 0xf7, 0xfe, 0x00, 0x00, 0x00, 0x01];
+    assert!(is_valid_icmp_checksum(&packet));
+
+    packet[7] = 0x02;
+    assert!(!is_valid_icmp_checksum(&packet));
+}
+
+#[test_case]
+fn test_udp_checksum() {
+    let src_addr = Ipv4Addr::new(10, 0, 2, 2);
+    let dst_addr = Ipv4Addr::new(10, 0, 2, 15);
+    // port 1234 -> 5678, "abc" (odd length)
+    let mut datagram = [
+        0x04, 0xd2, 0x16, 0x2e, 0x00, 0x0b, 0x00, 0x00, b'a', b'b', b'c',
+    ];
+
+    // unchecked
+    assert!(is_valid_udp_checksum(src_addr, dst_addr, &datagram));
+
+    let checksum =
+        internet_checksum(&udp_pseudo_header_and(src_addr, dst_addr, &datagram)).to_be_bytes();
+    datagram[6..8].copy_from_slice(&checksum);
+    assert!(is_valid_udp_checksum(src_addr, dst_addr, &datagram));
+    assert!(!is_valid_udp_checksum(src_addr, dst_addr, &datagram[..7]));
+
+    datagram[10] = b'd';
+    assert!(!is_valid_udp_checksum(src_addr, dst_addr, &datagram));
+}
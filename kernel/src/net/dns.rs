@@ -0,0 +1,379 @@
+use super::udp::SocketId;
+use crate::{
+    arch::task,
+    device::local_apic_timer,
+    error::{Error, Result},
+    util::{mutex::Mutex, random},
+};
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::net::Ipv4Addr;
+use log::info;
+
+pub const DNS_PORT: u16 = 53;
+
+const DNS_HEADER_LEN: usize = 12;
+const DNS_FLAGS_QR: u16 = 0x8000;
+const DNS_FLAGS_RD: u16 = 0x0100;
+const DNS_FLAGS_RCODE_MASK: u16 = 0x000f;
+const DNS_RCODE_NXDOMAIN: u16 = 3;
+const DNS_TYPE_A: u16 = 1;
+const DNS_CLASS_IN: u16 = 1;
+const DNS_NAME_MAX_LEN: usize = 255;
+const DNS_LABEL_MAX_LEN: usize = 63;
+const DNS_NAME_POINTER_MASK: u8 = 0xc0;
+// bound of compression pointers followed in a name, avoids pointer loops
+const DNS_NAME_POINTER_MAX: usize = 16;
+
+const DNS_TIMEOUT_MS: usize = 3000;
+const DNS_RESEND_MS: usize = 1000;
+const DNS_CACHE_LEN: usize = 32;
+
+static mut DNS_CACHE: Mutex<DnsCache> = Mutex::new(DnsCache::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DnsARecord {
+    pub addr: Ipv4Addr,
+    pub ttl: u32, // seconds
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DnsCacheEntry {
+    addr: Ipv4Addr,
+    expires_ms: usize,
+}
+
+#[derive(Debug)]
+struct DnsCache {
+    entries: BTreeMap<String, DnsCacheEntry>,
+}
+
+impl DnsCache {
+    const fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, hostname: &str, now_ms: usize) -> Option<Ipv4Addr> {
+        let entry = *self.entries.get(hostname)?;
+        if entry.expires_ms <= now_ms {
+            self.entries.remove(hostname);
+            return None;
+        }
+
+        Some(entry.addr)
+    }
+
+    // the entry closest to expiry is evicted when the cache is full
+    fn insert(&mut self, hostname: String, record: DnsARecord, now_ms: usize) {
+        if record.ttl == 0 {
+            return;
+        }
+
+        self.entries.retain(|_, e| e.expires_ms > now_ms);
+
+        if self.entries.len() >= DNS_CACHE_LEN && !self.entries.contains_key(&hostname) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.expires_ms)
+                .map(|(name, _)| name.clone());
+            if let Some(name) = oldest {
+                self.entries.remove(&name);
+            }
+        }
+
+        let entry = DnsCacheEntry {
+            addr: record.addr,
+            expires_ms: now_ms.saturating_add(record.ttl as usize * 1000),
+        };
+        self.entries.insert(hostname, entry);
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+        None => Err(Error::Failed("DNS message is too short")),
+    }
+}
+
+// returns the name and the offset after the name at the original position
+fn read_name(data: &[u8], offset: usize) -> Result<(String, usize)> {
+    let mut name = String::new();
+    let mut offset = offset;
+    let mut end_offset = None;
+    let mut pointer_count = 0;
+
+    loop {
+        let len = *data
+            .get(offset)
+            .ok_or(Error::Failed("DNS name is out of range"))?;
+
+        if len & DNS_NAME_POINTER_MASK == DNS_NAME_POINTER_MASK {
+            pointer_count += 1;
+            if pointer_count > DNS_NAME_POINTER_MAX {
+                return Err(Error::Failed("Too many DNS name pointers"));
+            }
+
+            let pointer = read_u16(data, offset)? & !((DNS_NAME_POINTER_MASK as u16) << 8);
+            end_offset.get_or_insert(offset + 2);
+            offset = pointer as usize;
+            continue;
+        }
+
+        if len & DNS_NAME_POINTER_MASK != 0 {
+            return Err(Error::Failed("Invalid DNS label type"));
+        }
+
+        offset += 1;
+        if len == 0 {
+            break;
+        }
+
+        let label = data
+            .get(offset..offset + len as usize)
+            .ok_or(Error::Failed("DNS name is out of range"))?;
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&String::from_utf8_lossy(label));
+        offset += len as usize;
+
+        if name.len() > DNS_NAME_MAX_LEN {
+            return Err(Error::Failed("DNS name is too long"));
+        }
+    }
+
+    Ok((name, end_offset.unwrap_or(offset)))
+}
+
+// standard query with recursion desired
+pub fn build_query(id: u16, hostname: &str) -> Result<Vec<u8>> {
+    if hostname.is_empty() || hostname.len() > DNS_NAME_MAX_LEN {
+        return Err(Error::Failed("Invalid host name"));
+    }
+
+    let mut query = Vec::with_capacity(DNS_HEADER_LEN + hostname.len() + 6);
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&DNS_FLAGS_RD.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    query.extend_from_slice(&[0; 6]); // ANCOUNT, NSCOUNT, ARCOUNT
+
+    for label in hostname.split('.') {
+        if label.is_empty() || label.len() > DNS_LABEL_MAX_LEN {
+            return Err(Error::Failed("Invalid host name"));
+        }
+
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+
+    query.extend_from_slice(&DNS_TYPE_A.to_be_bytes());
+    query.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+
+    Ok(query)
+}
+
+// returns the first A record in the answer section, CNAME records are skipped
+pub fn parse_response(id: u16, data: &[u8]) -> Result<DnsARecord> {
+    if data.len() < DNS_HEADER_LEN {
+        return Err(Error::Failed("DNS message is too short"));
+    }
+
+    if read_u16(data, 0)? != id {
+        return Err(Error::Failed("DNS response ID mismatch"));
+    }
+
+    let flags = read_u16(data, 2)?;
+    if flags & DNS_FLAGS_QR == 0 {
+        return Err(Error::Failed("Not a DNS response"));
+    }
+
+    match flags & DNS_FLAGS_RCODE_MASK {
+        0 => (),
+        DNS_RCODE_NXDOMAIN => return Err(Error::Failed("Host name was not found")),
+        _ => return Err(Error::Failed("DNS server returned an error")),
+    }
+
+    let question_count = read_u16(data, 4)?;
+    let answer_count = read_u16(data, 6)?;
+    let mut offset = DNS_HEADER_LEN;
+
+    // QNAME, QTYPE, QCLASS
+    for _ in 0..question_count {
+        let (_, end_offset) = read_name(data, offset)?;
+        offset = end_offset + 4;
+    }
+
+    // NAME, TYPE, CLASS, TTL, RDLENGTH, RDATA
+    for _ in 0..answer_count {
+        let (_, end_offset) = read_name(data, offset)?;
+        let ty = read_u16(data, end_offset)?;
+        let class = read_u16(data, end_offset + 2)?;
+        let ttl = ((read_u16(data, end_offset + 4)? as u32) << 16)
+            | read_u16(data, end_offset + 6)? as u32;
+        let rdata_len = read_u16(data, end_offset + 8)? as usize;
+        let rdata_offset = end_offset + 10;
+        let rdata = data
+            .get(rdata_offset..rdata_offset + rdata_len)
+            .ok_or(Error::Failed("DNS record is out of range"))?;
+        offset = rdata_offset + rdata_len;
+
+        if ty == DNS_TYPE_A && class == DNS_CLASS_IN && rdata.len() == 4 {
+            return Ok(DnsARecord {
+                addr: [rdata[0], rdata[1], rdata[2], rdata[3]].into(),
+                ttl,
+            });
+        }
+    }
+
+    Err(Error::Failed("No A record in the DNS response"))
+}
+
+fn current_ms() -> Result<usize> {
+    local_apic_timer::get_current_ms().ok_or(Error::Failed("Local APIC timer is not ready"))
+}
+
+// the query is resent until the timeout, responses to other queries are ignored
+fn query(sd: SocketId, server_addr: Ipv4Addr, hostname: &str) -> Result<DnsARecord> {
+    let id = random::random_u64() as u16;
+    let query = build_query(id, hostname)?;
+    let start_ms = current_ms()?;
+    let mut sent_ms = None;
+
+    loop {
+        let now_ms = current_ms()?;
+        if now_ms - start_ms >= DNS_TIMEOUT_MS {
+            return Err(Error::Failed("DNS query timed out"));
+        }
+
+        if sent_ms.map_or(true, |ms| now_ms - ms >= DNS_RESEND_MS) {
+            super::send_udp_datagram(sd, server_addr, DNS_PORT, &query)?;
+            sent_ms = Some(now_ms);
+        }
+
+        match super::recv_udp_datagram(sd)? {
+            Some(datagram)
+                if datagram.src_addr == server_addr
+                    && datagram.src_port == DNS_PORT
+                    && read_u16(&datagram.payload, 0).ok() == Some(id) =>
+            {
+                return parse_response(id, &datagram.payload);
+            }
+            Some(_) => (),
+            // the NIC drivers are polled by the timer interrupt while waiting
+            None => task::wait_yield()?,
+        }
+    }
+}
+
+// blocks until the response is received, must not be called from the kernel tasks
+pub fn resolve(hostname: &str) -> Result<Ipv4Addr> {
+    if let Ok(addr) = hostname.parse::<Ipv4Addr>() {
+        return Ok(addr);
+    }
+
+    let hostname = hostname.trim_end_matches('.').to_ascii_lowercase();
+    if let Some(addr) = unsafe { DNS_CACHE.try_lock() }?.get(&hostname, current_ms()?) {
+        return Ok(addr);
+    }
+
+    let server_addr = super::dns_server_addr()?;
+    let sd = super::create_udp_socket()?;
+    let result = query(sd, server_addr, &hostname);
+    super::close_udp_socket(sd)?;
+    let record = result?;
+
+    info!(
+        "net: Resolved {} to {} (TTL: {}s)",
+        hostname, record.addr, record.ttl
+    );
+    unsafe { DNS_CACHE.try_lock() }?.insert(hostname.to_string(), record, current_ms()?);
+
+    Ok(record.addr)
+}
+
+#[test_case]
+fn test_build_dns_query() {
+    let query = build_query(0x1234, "example.com").unwrap();
+    assert_eq!(
+        query,
+        [
+            0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 7, b'e', b'x',
+            b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, 0x00, 0x01, 0x00, 0x01,
+        ]
+    );
+
+    assert!(build_query(0, "").is_err());
+    assert!(build_query(0, "example..com").is_err());
+    assert!(build_query(0, &"a".repeat(DNS_LABEL_MAX_LEN + 1)).is_err());
+}
+
+#[test_case]
+fn test_parse_dns_response() {
+    let mut response = build_query(0x1234, "www.example.com").unwrap();
+    response[2..4].copy_from_slice(&0x8180u16.to_be_bytes()); // QR, RD, RA
+    response[6..8].copy_from_slice(&2u16.to_be_bytes()); // ANCOUNT
+
+    // www.example.com CNAME example.com, the names are compressed
+    response.extend_from_slice(&[0xc0, 12, 0x00, 0x05, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x02]);
+    response.extend_from_slice(&[0xc0, 16]);
+    // example.com A 93.184.216.34
+    response.extend_from_slice(&[
+        0xc0, 16, 0x00, 0x01, 0x00, 0x01, 0, 0, 0x0e, 0x10, 0x00, 0x04,
+    ]);
+    response.extend_from_slice(&[93, 184, 216, 34]);
+
+    let record = parse_response(0x1234, &response).unwrap();
+    assert_eq!(record.addr, Ipv4Addr::new(93, 184, 216, 34));
+    assert_eq!(record.ttl, 3600);
+
+    assert!(parse_response(0x4321, &response).is_err());
+    assert!(parse_response(0x1234, &response[..response.len() - 1]).is_err());
+
+    // NXDOMAIN
+    response[3] = 0x83;
+    assert!(parse_response(0x1234, &response).is_err());
+
+    // pointer to itself
+    let mut looped = build_query(0x1234, "a").unwrap();
+    looped[2] = 0x81;
+    looped[6..8].copy_from_slice(&1u16.to_be_bytes());
+    let pointer_offset = looped.len() as u8;
+    looped.extend_from_slice(&[0xc0, pointer_offset]);
+    assert!(parse_response(0x1234, &looped).is_err());
+}
+
+#[test_case]
+fn test_dns_cache() {
+    let mut cache = DnsCache::new();
+    let record = DnsARecord {
+        addr: Ipv4Addr::new(10, 0, 2, 2),
+        ttl: 1,
+    };
+
+    cache.insert("gateway".to_string(), record, 0);
+    assert_eq!(cache.get("gateway", 999), Some(record.addr));
+    assert_eq!(cache.get("gateway", 1000), None);
+
+    // not cached
+    cache.insert("gateway".to_string(), DnsARecord { ttl: 0, ..record }, 0);
+    assert_eq!(cache.get("gateway", 0), None);
+
+    for i in 0..=DNS_CACHE_LEN {
+        let record = DnsARecord {
+            ttl: i as u32 + 1,
+            ..record
+        };
+        cache.insert(i.to_string(), record, 0);
+    }
+    assert_eq!(cache.entries.len(), DNS_CACHE_LEN);
+    assert_eq!(cache.get("0", 0), None);
+    assert_eq!(cache.get(&DNS_CACHE_LEN.to_string(), 0), Some(record.addr));
+}
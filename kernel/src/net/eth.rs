@@ -1,7 +1,13 @@
 use super::arp;
+use crate::error::{Error, Result};
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
+pub const ETH_HEADER_LEN: usize = 14;
+pub const ETH_FCS_LEN: usize = 4;
+// without the FCS, appended by the NIC on TX
+pub const ETH_FRAME_MIN_LEN: usize = 60;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct EthernetAddress([u8; 6]);
 
@@ -70,6 +76,7 @@ impl Into<[u8; 2]> for EtherType {
 #[derive(Debug)]
 pub enum EthernetPayload {
     Arp(arp::ArpPacket),
+    Ipv4(Vec<u8>),
     None,
 }
 
@@ -77,6 +84,7 @@ impl EthernetPayload {
     pub fn to_vec(&self) -> Vec<u8> {
         match self {
             EthernetPayload::Arp(packet) => packet.raw().to_vec(),
+            EthernetPayload::Ipv4(packet) => packet.clone(),
             EthernetPayload::None => Vec::new(),
         }
     }
@@ -104,24 +112,24 @@ impl<'a> Debug for EthernetFrame<'a> {
 }
 
 impl<'a> EthernetFrame<'a> {
-    pub fn new(data: &'a [u8]) -> Self {
-        let dst_mac = &data[0..6];
-        let src_mac = &data[6..12];
+    // has_fcs: the NIC leaves the 4 bytes CRC at the end of the received frame
+    pub fn parse(data: &'a [u8], has_fcs: bool) -> Result<Self> {
+        let fcs_len = if has_fcs { ETH_FCS_LEN } else { 0 };
+        if data.len() < ETH_HEADER_LEN + fcs_len {
+            return Err(Error::Failed("Ethernet frame is too short"));
+        }
+
+        let dst_mac: [u8; 6] = data[0..6].try_into().unwrap();
+        let src_mac: [u8; 6] = data[6..12].try_into().unwrap();
         let ether_type = [data[12], data[13]].into();
-        let payload = &data[14..];
+        let payload = &data[ETH_HEADER_LEN..data.len() - fcs_len];
 
-        Self {
-            dst_mac_addr: [
-                dst_mac[0], dst_mac[1], dst_mac[2], dst_mac[3], dst_mac[4], dst_mac[5],
-            ]
-            .into(),
-            src_mac_addr: [
-                src_mac[0], src_mac[1], src_mac[2], src_mac[3], src_mac[4], src_mac[5],
-            ]
-            .into(),
+        Ok(Self {
+            dst_mac_addr: dst_mac.into(),
+            src_mac_addr: src_mac.into(),
             ether_type,
             payload,
-        }
+        })
     }
 
     pub fn new_with(
@@ -138,37 +146,84 @@ impl<'a> EthernetFrame<'a> {
         }
     }
 
-    pub fn to_vec(&self) -> Vec<u8> {
-        let mut vec = Vec::new();
-        let dst_mac_addr: [u8; 6] = self.dst_mac_addr.into();
-        let src_mac_addr: [u8; 6] = self.src_mac_addr.into();
-        let ether_type: [u8; 2] = self.ether_type.into();
-        let mut payload = self.payload().to_vec();
-
-        if payload.len() < 46 {
-            payload.resize(46, 0);
-        }
+    // padded to the minimum frame length, without the FCS
+    pub fn build(
+        dst_mac_addr: EthernetAddress,
+        src_mac_addr: EthernetAddress,
+        ether_type: EtherType,
+        payload: &[u8],
+    ) -> Vec<u8> {
+        let dst_mac_addr: [u8; 6] = dst_mac_addr.into();
+        let src_mac_addr: [u8; 6] = src_mac_addr.into();
+        let ether_type: [u8; 2] = ether_type.into();
 
+        let mut vec = Vec::with_capacity((ETH_HEADER_LEN + payload.len()).max(ETH_FRAME_MIN_LEN));
         vec.extend_from_slice(&dst_mac_addr);
         vec.extend_from_slice(&src_mac_addr);
         vec.extend_from_slice(&ether_type);
-        vec.extend_from_slice(&payload);
+        vec.extend_from_slice(payload);
 
-        // padding
-        if vec.len() < 64 {
-            vec.resize(64, 0);
+        if vec.len() < ETH_FRAME_MIN_LEN {
+            vec.resize(ETH_FRAME_MIN_LEN, 0);
         }
 
         vec
     }
 
+    pub fn to_vec(&self) -> Vec<u8> {
+        Self::build(
+            self.dst_mac_addr,
+            self.src_mac_addr,
+            self.ether_type,
+            self.payload,
+        )
+    }
+
+    pub fn raw_payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
     pub fn payload(&self) -> EthernetPayload {
         match self.ether_type {
             EtherType::Arp => {
                 let arp_packet = arp::ArpPacket::new(&self.payload);
                 EthernetPayload::Arp(arp_packet)
             }
+            EtherType::IPv4 => EthernetPayload::Ipv4(self.payload.to_vec()),
             _ => EthernetPayload::None,
         }
     }
 }
+
+#[test_case]
+fn test_build_and_parse_eth_frame() {
+    let dst_mac_addr: EthernetAddress = [0xff; 6].into();
+    let src_mac_addr: EthernetAddress = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56].into();
+
+    let frame = EthernetFrame::build(dst_mac_addr, src_mac_addr, EtherType::Arp, &[1, 2, 3]);
+    assert_eq!(frame.len(), ETH_FRAME_MIN_LEN);
+    assert_eq!(&frame[12..17], &[0x08, 0x06, 1, 2, 3]);
+
+    let parsed = EthernetFrame::parse(&frame, false).unwrap();
+    assert_eq!(parsed.dst_mac_addr, dst_mac_addr);
+    assert_eq!(parsed.src_mac_addr, src_mac_addr);
+    assert_eq!(parsed.ether_type, EtherType::Arp);
+    assert_eq!(
+        parsed.raw_payload().len(),
+        ETH_FRAME_MIN_LEN - ETH_HEADER_LEN
+    );
+
+    // FCS is stripped
+    let mut frame_with_fcs = frame.clone();
+    frame_with_fcs.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    let parsed = EthernetFrame::parse(&frame_with_fcs, true).unwrap();
+    assert_eq!(parsed.raw_payload(), &frame[ETH_HEADER_LEN..]);
+
+    // large payloads are not padded
+    let payload = [0xaa; 100];
+    let frame = EthernetFrame::build(dst_mac_addr, src_mac_addr, EtherType::IPv4, &payload);
+    assert_eq!(frame.len(), ETH_HEADER_LEN + payload.len());
+
+    assert!(EthernetFrame::parse(&frame[..ETH_HEADER_LEN - 1], false).is_err());
+    assert!(EthernetFrame::parse(&frame[..ETH_HEADER_LEN + 3], true).is_err());
+}
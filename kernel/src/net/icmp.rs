@@ -0,0 +1,90 @@
+use super::checksum;
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+
+pub const ICMP_HEADER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IcmpType {
+    EchoReply,
+    EchoRequest,
+    Other(u8),
+}
+
+impl From<u8> for IcmpType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::EchoReply,
+            8 => Self::EchoRequest,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl Into<u8> for IcmpType {
+    fn into(self) -> u8 {
+        match self {
+            Self::EchoReply => 0,
+            Self::EchoRequest => 8,
+            Self::Other(value) => value,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IcmpPacket<'a> {
+    pub ty: IcmpType,
+    pub code: u8,
+    rest: &'a [u8], // rest of the header and the data
+}
+
+impl<'a> IcmpPacket<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < ICMP_HEADER_LEN {
+            return Err(Error::Failed("ICMP packet is too short"));
+        }
+
+        if !checksum::is_valid_icmp_checksum(data) {
+            return Err(Error::Failed("Invalid ICMP checksum"));
+        }
+
+        Ok(Self {
+            ty: data[0].into(),
+            code: data[1],
+            rest: &data[4..],
+        })
+    }
+
+    // the identifier, sequence number and data are echoed back
+    pub fn build_echo_reply(&self) -> Result<Vec<u8>> {
+        if self.ty != IcmpType::EchoRequest {
+            return Err(Error::Failed("ICMP packet is not an echo request"));
+        }
+
+        let mut packet = Vec::with_capacity(4 + self.rest.len());
+        packet.extend_from_slice(&[IcmpType::EchoReply.into(), 0, 0, 0]);
+        packet.extend_from_slice(self.rest);
+
+        let checksum = checksum::internet_checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+        Ok(packet)
+    }
+}
+
+#[test_case]
+fn test_icmp_echo_reply() {
+    // echo request, id: 0, seq: 1
+    let request = [0x08, 0x00, 0xf7, 0xfe, 0x00, 0x00, 0x00, 0x01];
+    let packet = IcmpPacket::parse(&request).unwrap();
+    assert_eq!(packet.ty, IcmpType::EchoRequest);
+
+    let reply = packet.build_echo_reply().unwrap();
+    assert_eq!(reply, [0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x00, 0x01]);
+    assert_eq!(IcmpPacket::parse(&reply).unwrap().ty, IcmpType::EchoReply);
+
+    let mut request = request;
+    request[7] = 0x02;
+    assert!(IcmpPacket::parse(&request).is_err());
+    assert!(IcmpPacket::parse(&request[..4]).is_err());
+}
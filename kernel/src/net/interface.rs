@@ -0,0 +1,32 @@
+use super::eth::EthernetAddress;
+use core::net::Ipv4Addr;
+
+pub const LOOPBACK_MAC_ADDR: [u8; 6] = [0; 6];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkInterface {
+    Loopback, // frames are queued and received by the kernel itself
+    Ethernet, // rtl8139 or virtio-net
+}
+
+impl NetworkInterface {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Loopback => "lo",
+            Self::Ethernet => "eth0",
+        }
+    }
+
+    // 127.0.0.0/8 and the own address never leave the host
+    pub fn route(dst_ipv4_addr: Ipv4Addr, my_ipv4_addr: Ipv4Addr) -> Self {
+        if dst_ipv4_addr.is_loopback() || dst_ipv4_addr == my_ipv4_addr {
+            Self::Loopback
+        } else {
+            Self::Ethernet
+        }
+    }
+
+    pub fn loopback_mac_addr() -> EthernetAddress {
+        LOOPBACK_MAC_ADDR.into()
+    }
+}
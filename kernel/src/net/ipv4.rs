@@ -0,0 +1,137 @@
+use super::checksum;
+use crate::error::{Error, Result};
+use alloc::vec::Vec;
+use core::net::Ipv4Addr;
+
+pub const IPV4_HEADER_MIN_LEN: usize = 20;
+const IPV4_DEFAULT_TTL: u8 = 64;
+const IPV4_FLAGS_DONT_FRAGMENT: u16 = 0x4000;
+const IPV4_FLAGS_MORE_FRAGMENTS: u16 = 0x2000;
+const IPV4_FRAGMENT_OFFSET_MASK: u16 = 0x1fff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpProtocol {
+    Icmp,
+    Tcp,
+    Udp,
+    Other(u8),
+}
+
+impl From<u8> for IpProtocol {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Icmp,
+            6 => Self::Tcp,
+            17 => Self::Udp,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl Into<u8> for IpProtocol {
+    fn into(self) -> u8 {
+        match self {
+            Self::Icmp => 1,
+            Self::Tcp => 6,
+            Self::Udp => 17,
+            Self::Other(value) => value,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Ipv4Packet<'a> {
+    pub src_addr: Ipv4Addr,
+    pub dst_addr: Ipv4Addr,
+    pub protocol: IpProtocol,
+    pub ttl: u8,
+    payload: &'a [u8],
+}
+
+impl<'a> Ipv4Packet<'a> {
+    // validate the header, the padding after the total length is removed
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < IPV4_HEADER_MIN_LEN {
+            return Err(Error::Failed("IPv4 packet is too short"));
+        }
+
+        if data[0] >> 4 != 4 {
+            return Err(Error::Failed("Not an IPv4 packet"));
+        }
+
+        let header_len = (data[0] & 0xf) as usize * 4;
+        let total_len = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if header_len < IPV4_HEADER_MIN_LEN || total_len < header_len || total_len > data.len() {
+            return Err(Error::Failed("Invalid IPv4 packet length"));
+        }
+
+        if checksum::internet_checksum(&data[..header_len]) != 0 {
+            return Err(Error::Failed("Invalid IPv4 header checksum"));
+        }
+
+        // fragments are not reassembled
+        let flags_and_offset = u16::from_be_bytes([data[6], data[7]]);
+        if flags_and_offset & (IPV4_FLAGS_MORE_FRAGMENTS | IPV4_FRAGMENT_OFFSET_MASK) != 0 {
+            return Err(Error::Failed("Fragmented IPv4 packet is not supported"));
+        }
+
+        Ok(Self {
+            src_addr: [data[12], data[13], data[14], data[15]].into(),
+            dst_addr: [data[16], data[17], data[18], data[19]].into(),
+            protocol: data[9].into(),
+            ttl: data[8],
+            payload: &data[header_len..total_len],
+        })
+    }
+
+    pub fn build(
+        src_addr: Ipv4Addr,
+        dst_addr: Ipv4Addr,
+        protocol: IpProtocol,
+        payload: &[u8],
+    ) -> Result<Vec<u8>> {
+        let total_len: u16 = (IPV4_HEADER_MIN_LEN + payload.len())
+            .try_into()
+            .map_err(|_| Error::Failed("IPv4 payload is too large"))?;
+
+        let mut packet = Vec::with_capacity(total_len as usize);
+        packet.extend_from_slice(&[0x45, 0]); // version 4, IHL 5, no TOS
+        packet.extend_from_slice(&total_len.to_be_bytes());
+        packet.extend_from_slice(&[0, 0]); // identification
+        packet.extend_from_slice(&IPV4_FLAGS_DONT_FRAGMENT.to_be_bytes());
+        packet.extend_from_slice(&[IPV4_DEFAULT_TTL, protocol.into()]);
+        packet.extend_from_slice(&[0, 0]); // checksum
+        packet.extend_from_slice(&src_addr.octets());
+        packet.extend_from_slice(&dst_addr.octets());
+
+        let checksum = checksum::internet_checksum(&packet);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        packet.extend_from_slice(payload);
+
+        Ok(packet)
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+}
+
+#[test_case]
+fn test_build_and_parse_ipv4_packet() {
+    let src_addr = Ipv4Addr::new(10, 0, 2, 15);
+    let dst_addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    let mut packet = Ipv4Packet::build(src_addr, dst_addr, IpProtocol::Udp, &[1, 2, 3]).unwrap();
+    // ethernet padding is not a part of the payload
+    packet.extend_from_slice(&[0; 4]);
+
+    let parsed = Ipv4Packet::parse(&packet).unwrap();
+    assert_eq!(parsed.src_addr, src_addr);
+    assert_eq!(parsed.dst_addr, dst_addr);
+    assert_eq!(parsed.protocol, IpProtocol::Udp);
+    assert_eq!(parsed.payload(), &[1, 2, 3]);
+
+    packet[8] = 1; // TTL without updating the checksum
+    assert!(Ipv4Packet::parse(&packet).is_err());
+    assert!(Ipv4Packet::parse(&packet[..IPV4_HEADER_MIN_LEN - 1]).is_err());
+}
@@ -2,29 +2,86 @@ use crate::{
     error::{Error, Result},
     util::mutex::Mutex,
 };
+use alloc::{collections::VecDeque, vec::Vec};
 use arp::{ArpOperation, ArpPacket, ArpTable};
+use common::kernel_config::{
+    KernelConfig, DEFAULT_DNS_SERVER, DEFAULT_IPV4_ADDR, DEFAULT_IPV4_GATEWAY, DEFAULT_IPV4_NETMASK,
+};
 use core::net::Ipv4Addr;
-use eth::{EthernetAddress, EthernetPayload};
-use log::info;
+use eth::{EtherType, EthernetAddress, EthernetFrame, EthernetPayload};
+use icmp::{IcmpPacket, IcmpType};
+use interface::NetworkInterface;
+use ipv4::{IpProtocol, Ipv4Packet};
+use log::{debug, info, warn};
+use udp::{SocketId, UdpDatagram, UdpRecvDatagram, UdpSocketTable};
 
 pub mod arp;
+pub mod checksum;
+pub mod dns;
 pub mod eth;
+pub mod icmp;
+pub mod interface;
+pub mod ipv4;
+pub mod udp;
+
+// bound of frames processed by a poll, replies are queued again
+const LOOPBACK_POLL_MAX: usize = 64;
+// frames waiting to be sent by the NIC driver
+const ETH_TX_QUEUE_LEN: usize = 64;
+// IPv4 packets waiting for the ARP reply
+const ARP_PENDING_LEN: usize = 16;
 
-static mut NETWORK_MAN: Mutex<NetworkManager> =
-    Mutex::new(NetworkManager::new(Ipv4Addr::new(10, 0, 2, 15)));
+// overridden by the kernel config in init()
+static mut NETWORK_MAN: Mutex<NetworkManager> = Mutex::new(NetworkManager::new(DEFAULT_IPV4_ADDR));
 
 struct NetworkManager {
     my_ipv4_addr: Ipv4Addr,
+    my_ipv4_netmask: Ipv4Addr,
+    gateway_ipv4_addr: Ipv4Addr,
+    dns_server_addr: Ipv4Addr,
     my_mac_addr: Option<EthernetAddress>,
     arp_table: Option<ArpTable>,
+    loopback_queue: VecDeque<Vec<u8>>,
+    eth_tx_queue: VecDeque<Vec<u8>>,
+    arp_pending: VecDeque<(Ipv4Addr, Vec<u8>)>,
+    udp_socket_table: UdpSocketTable,
 }
 
 impl NetworkManager {
     pub const fn new(ipv4_addr: Ipv4Addr) -> Self {
         Self {
             my_ipv4_addr: ipv4_addr,
+            my_ipv4_netmask: DEFAULT_IPV4_NETMASK,
+            gateway_ipv4_addr: DEFAULT_IPV4_GATEWAY,
+            dns_server_addr: DEFAULT_DNS_SERVER,
             my_mac_addr: None,
             arp_table: None,
+            loopback_queue: VecDeque::new(),
+            eth_tx_queue: VecDeque::new(),
+            arp_pending: VecDeque::new(),
+            udp_socket_table: UdpSocketTable::new(),
+        }
+    }
+
+    fn set_ipv4_config(&mut self, kernel_config: &KernelConfig) {
+        self.my_ipv4_addr = kernel_config.ipv4_addr;
+        self.my_ipv4_netmask = kernel_config.ipv4_netmask;
+        self.gateway_ipv4_addr = kernel_config.ipv4_gateway;
+        self.dns_server_addr = kernel_config.dns_server;
+
+        info!(
+            "net: IPv4 address: {}, netmask: {}, gateway: {}, DNS server: {}",
+            self.my_ipv4_addr, self.my_ipv4_netmask, self.gateway_ipv4_addr, self.dns_server_addr
+        );
+    }
+
+    // addresses outside of the own subnet are sent to the gateway
+    fn next_hop_ipv4_addr(&self, dst_ipv4_addr: Ipv4Addr) -> Ipv4Addr {
+        let netmask = u32::from(self.my_ipv4_netmask);
+        if u32::from(dst_ipv4_addr) & netmask == u32::from(self.my_ipv4_addr) & netmask {
+            dst_ipv4_addr
+        } else {
+            self.gateway_ipv4_addr
         }
     }
 
@@ -39,6 +96,18 @@ impl NetworkManager {
             .ok_or(Error::Failed("MAC address is not set"))
     }
 
+    fn interface_mac_addr(&self, interface: NetworkInterface) -> Result<EthernetAddress> {
+        match interface {
+            NetworkInterface::Loopback => Ok(NetworkInterface::loopback_mac_addr()),
+            NetworkInterface::Ethernet => self.my_mac_addr(),
+        }
+    }
+
+    fn is_my_ipv4_addr(&self, interface: NetworkInterface, ipv4_addr: Ipv4Addr) -> bool {
+        ipv4_addr == self.my_ipv4_addr
+            || (interface == NetworkInterface::Loopback && ipv4_addr.is_loopback())
+    }
+
     fn arp_table(&mut self) -> &mut ArpTable {
         if self.arp_table.is_none() {
             self.arp_table = Some(ArpTable::new());
@@ -47,8 +116,46 @@ impl NetworkManager {
         self.arp_table.as_mut().unwrap()
     }
 
-    fn receive_arp_packet(&mut self, packet: ArpPacket) -> Result<Option<ArpPacket>> {
-        info!("net: Received ARP packet: {:?}", packet);
+    // send the packets which were waiting for the resolved address
+    fn update_arp_table(
+        &mut self,
+        interface: NetworkInterface,
+        ipv4_addr: Ipv4Addr,
+        mac_addr: EthernetAddress,
+    ) -> Result<()> {
+        let arp_table = self.arp_table();
+        arp_table.insert(ipv4_addr, mac_addr);
+        info!("net: ARP table updated: {:?}", arp_table);
+
+        if interface != NetworkInterface::Ethernet {
+            return Ok(());
+        }
+
+        let (resolved, pending) = self
+            .arp_pending
+            .drain(..)
+            .partition(|(dst_ipv4_addr, _)| *dst_ipv4_addr == ipv4_addr);
+        self.arp_pending = pending;
+
+        for (_, packet) in resolved {
+            let frame =
+                EthernetFrame::build(mac_addr, self.my_mac_addr()?, EtherType::IPv4, &packet);
+            self.push_eth_tx_frame(frame);
+        }
+
+        Ok(())
+    }
+
+    fn receive_arp_packet(
+        &mut self,
+        interface: NetworkInterface,
+        packet: ArpPacket,
+    ) -> Result<Option<ArpPacket>> {
+        info!(
+            "net: Received ARP packet on {}: {:?}",
+            interface.name(),
+            packet
+        );
 
         let arp_op = packet.op()?;
         let sender_ipv4_addr = packet.sender_ipv4_addr;
@@ -57,18 +164,16 @@ impl NetworkManager {
 
         match arp_op {
             ArpOperation::Request => {
-                let arp_table = self.arp_table();
-                arp_table.insert(sender_ipv4_addr, sender_mac_addr);
-                info!("net: ARP table updated: {:?}", arp_table);
+                self.update_arp_table(interface, sender_ipv4_addr, sender_mac_addr)?;
 
-                if target_ipv4_addr != self.my_ipv4_addr {
+                if !self.is_my_ipv4_addr(interface, target_ipv4_addr) {
                     return Ok(None);
                 }
 
                 let reply_packet = ArpPacket::new_with(
                     ArpOperation::Reply,
-                    self.my_mac_addr()?,
-                    self.my_ipv4_addr,
+                    self.interface_mac_addr(interface)?,
+                    target_ipv4_addr,
                     sender_mac_addr,
                     sender_ipv4_addr,
                 );
@@ -77,20 +182,35 @@ impl NetworkManager {
                 Ok(Some(reply_packet))
             }
             ArpOperation::Reply => {
-                unimplemented!()
+                self.update_arp_table(interface, sender_ipv4_addr, sender_mac_addr)?;
+                Ok(None)
             }
         }
     }
 
-    fn receive_eth_payload(&mut self, payload: EthernetPayload) -> Result<Option<EthernetPayload>> {
+    fn receive_eth_payload(
+        &mut self,
+        interface: NetworkInterface,
+        payload: EthernetPayload,
+    ) -> Result<Option<EthernetPayload>> {
         let mut replay_payload = None;
 
         match payload {
             EthernetPayload::Arp(arp_packet) => {
-                if let Some(reply_arp_packet) = self.receive_arp_packet(arp_packet)? {
+                if let Some(reply_arp_packet) = self.receive_arp_packet(interface, arp_packet)? {
                     replay_payload = Some(EthernetPayload::Arp(reply_arp_packet));
                 }
             }
+            EthernetPayload::Ipv4(packet) => {
+                // malformed packets are dropped without stopping the driver
+                if let Err(err) = self.receive_ipv4_packet(interface, &packet) {
+                    warn!(
+                        "net: Dropped IPv4 packet on {}: {:?}",
+                        interface.name(),
+                        err
+                    );
+                }
+            }
             EthernetPayload::None => {
                 info!("net: None payload");
             }
@@ -98,6 +218,188 @@ impl NetworkManager {
 
         Ok(replay_payload)
     }
+
+    fn receive_ipv4_packet(&mut self, interface: NetworkInterface, packet: &[u8]) -> Result<()> {
+        let packet = Ipv4Packet::parse(packet)?;
+        let dst_ipv4_addr = packet.dst_addr;
+
+        if !self.is_my_ipv4_addr(interface, dst_ipv4_addr) && !dst_ipv4_addr.is_broadcast() {
+            return Ok(());
+        }
+
+        match packet.protocol {
+            IpProtocol::Udp => {
+                let datagram =
+                    UdpDatagram::parse(packet.src_addr, dst_ipv4_addr, packet.payload())?;
+                let dst_port = datagram.dst_port;
+                let recv_datagram = UdpRecvDatagram {
+                    src_addr: packet.src_addr,
+                    src_port: datagram.src_port,
+                    payload: datagram.payload().to_vec(),
+                };
+
+                if !self.udp_socket_table.deliver(dst_port, recv_datagram) {
+                    debug!("net: No UDP socket is bound to port {}", dst_port);
+                }
+            }
+            IpProtocol::Icmp => {
+                // packets with an invalid checksum are dropped by the caller
+                let icmp_packet = IcmpPacket::parse(packet.payload())?;
+
+                if icmp_packet.ty == IcmpType::EchoRequest && !dst_ipv4_addr.is_broadcast() {
+                    let reply = icmp_packet.build_echo_reply()?;
+                    let reply_packet = Ipv4Packet::build(
+                        dst_ipv4_addr,
+                        packet.src_addr,
+                        IpProtocol::Icmp,
+                        &reply,
+                    )?;
+                    self.send_ipv4_packet(interface, packet.src_addr, reply_packet)?;
+                }
+            }
+            protocol => {
+                debug!("net: Unsupported IPv4 protocol: {:?}", protocol);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_eth_tx_frame(&mut self, frame: Vec<u8>) {
+        if self.eth_tx_queue.len() >= ETH_TX_QUEUE_LEN {
+            warn!("net: TX queue of eth0 is full, dropped the oldest frame");
+            self.eth_tx_queue.pop_front();
+        }
+
+        self.eth_tx_queue.push_back(frame);
+    }
+
+    fn send_ipv4_packet(
+        &mut self,
+        interface: NetworkInterface,
+        dst_ipv4_addr: Ipv4Addr,
+        packet: Vec<u8>,
+    ) -> Result<()> {
+        if interface == NetworkInterface::Loopback {
+            let lo_mac_addr = NetworkInterface::loopback_mac_addr();
+            let frame = EthernetFrame::build(lo_mac_addr, lo_mac_addr, EtherType::IPv4, &packet);
+            self.send_loopback(frame);
+            return Ok(());
+        }
+
+        let my_mac_addr = self.my_mac_addr()?;
+        let next_hop_ipv4_addr = self.next_hop_ipv4_addr(dst_ipv4_addr);
+        let dst_mac_addr = if dst_ipv4_addr.is_broadcast() {
+            Some([0xff; 6].into())
+        } else {
+            self.arp_table().get(&next_hop_ipv4_addr).copied()
+        };
+
+        if let Some(dst_mac_addr) = dst_mac_addr {
+            let frame = EthernetFrame::build(dst_mac_addr, my_mac_addr, EtherType::IPv4, &packet);
+            self.push_eth_tx_frame(frame);
+            return Ok(());
+        }
+
+        // sent after the ARP reply is received
+        if self.arp_pending.len() >= ARP_PENDING_LEN {
+            self.arp_pending.pop_front();
+        }
+        self.arp_pending.push_back((next_hop_ipv4_addr, packet));
+
+        let request = ArpPacket::new_with(
+            ArpOperation::Request,
+            my_mac_addr,
+            self.my_ipv4_addr,
+            [0; 6].into(),
+            next_hop_ipv4_addr,
+        );
+        let frame = EthernetFrame::build(
+            [0xff; 6].into(),
+            my_mac_addr,
+            EtherType::Arp,
+            &request.raw(),
+        );
+        self.push_eth_tx_frame(frame);
+
+        Ok(())
+    }
+
+    fn send_udp_datagram(
+        &mut self,
+        id: SocketId,
+        dst_ipv4_addr: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<()> {
+        let src_port = self.udp_socket_table.local_port(id)?;
+        let interface = NetworkInterface::route(dst_ipv4_addr, self.my_ipv4_addr);
+        let src_ipv4_addr = if dst_ipv4_addr.is_loopback() {
+            dst_ipv4_addr
+        } else {
+            self.my_ipv4_addr
+        };
+
+        let datagram =
+            UdpDatagram::build(src_ipv4_addr, src_port, dst_ipv4_addr, dst_port, payload)?;
+        let packet = Ipv4Packet::build(src_ipv4_addr, dst_ipv4_addr, IpProtocol::Udp, &datagram)?;
+        self.send_ipv4_packet(interface, dst_ipv4_addr, packet)
+    }
+
+    // returns the reply frame to send from the interface
+    fn receive_eth_frame(
+        &mut self,
+        interface: NetworkInterface,
+        eth_frame: &EthernetFrame,
+    ) -> Result<Option<Vec<u8>>> {
+        let reply_payload = match self.receive_eth_payload(interface, eth_frame.payload())? {
+            Some(payload) => payload,
+            None => return Ok(None),
+        };
+
+        let ether_type = match reply_payload {
+            EthernetPayload::Arp(_) => EtherType::Arp,
+            EthernetPayload::Ipv4(_) => EtherType::IPv4,
+            EthernetPayload::None => return Ok(None),
+        };
+
+        Ok(Some(EthernetFrame::build(
+            eth_frame.src_mac_addr,
+            self.interface_mac_addr(interface)?,
+            ether_type,
+            &reply_payload.to_vec(),
+        )))
+    }
+
+    fn send_loopback(&mut self, frame: Vec<u8>) {
+        self.loopback_queue.push_back(frame);
+    }
+
+    // return the number of received frames
+    fn poll_loopback(&mut self) -> Result<usize> {
+        let mut count = 0;
+        while count < LOOPBACK_POLL_MAX {
+            let frame = match self.loopback_queue.pop_front() {
+                Some(frame) => frame,
+                None => break,
+            };
+            count += 1;
+
+            let eth_frame = EthernetFrame::parse(&frame, false)?;
+            if let Some(reply_frame) =
+                self.receive_eth_frame(NetworkInterface::Loopback, &eth_frame)?
+            {
+                self.send_loopback(reply_frame);
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+pub fn init(kernel_config: &KernelConfig) -> Result<()> {
+    unsafe { NETWORK_MAN.try_lock() }?.set_ipv4_config(kernel_config);
+    Ok(())
 }
 
 pub fn set_my_mac_addr(mac_addr: EthernetAddress) -> Result<()> {
@@ -109,6 +411,192 @@ pub fn my_mac_addr() -> Result<EthernetAddress> {
     unsafe { NETWORK_MAN.try_lock() }?.my_mac_addr()
 }
 
-pub fn receive_eth_payload(payload: EthernetPayload) -> Result<Option<EthernetPayload>> {
-    unsafe { NETWORK_MAN.try_lock() }?.receive_eth_payload(payload)
+pub fn dns_server_addr() -> Result<Ipv4Addr> {
+    Ok(unsafe { NETWORK_MAN.try_lock() }?.dns_server_addr)
+}
+
+// frames received by the NIC drivers, returns the reply frame to send
+pub fn receive_eth_frame(eth_frame: &EthernetFrame) -> Result<Option<Vec<u8>>> {
+    unsafe { NETWORK_MAN.try_lock() }?.receive_eth_frame(NetworkInterface::Ethernet, eth_frame)
+}
+
+pub fn send_loopback(frame: Vec<u8>) -> Result<()> {
+    unsafe { NETWORK_MAN.try_lock() }?.send_loopback(frame);
+    Ok(())
+}
+
+pub fn poll_loopback() -> Result<usize> {
+    unsafe { NETWORK_MAN.try_lock() }?.poll_loopback()
+}
+
+// frames queued by the network stack, sent by the NIC drivers
+pub fn pop_eth_tx_frame() -> Result<Option<Vec<u8>>> {
+    Ok(unsafe { NETWORK_MAN.try_lock() }?.eth_tx_queue.pop_front())
+}
+
+pub fn create_udp_socket() -> Result<SocketId> {
+    Ok(unsafe { NETWORK_MAN.try_lock() }?.udp_socket_table.create())
+}
+
+pub fn close_udp_socket(id: SocketId) -> Result<()> {
+    unsafe { NETWORK_MAN.try_lock() }?
+        .udp_socket_table
+        .close(id)
+}
+
+pub fn bind_udp_socket(id: SocketId, port: u16) -> Result<u16> {
+    unsafe { NETWORK_MAN.try_lock() }?
+        .udp_socket_table
+        .bind(id, port)
+}
+
+pub fn send_udp_datagram(
+    id: SocketId,
+    dst_ipv4_addr: Ipv4Addr,
+    dst_port: u16,
+    payload: &[u8],
+) -> Result<()> {
+    unsafe { NETWORK_MAN.try_lock() }?.send_udp_datagram(id, dst_ipv4_addr, dst_port, payload)
+}
+
+// returns None if no datagram has been received
+pub fn recv_udp_datagram(id: SocketId) -> Result<Option<UdpRecvDatagram>> {
+    let mut network_man = unsafe { NETWORK_MAN.try_lock() }?;
+    let socket = network_man.udp_socket_table.udp_socket_mut(id)?;
+    Ok(socket.pop_recv_datagram())
+}
+
+#[test_case]
+fn test_loopback_arp() {
+    let mut network_man = NetworkManager::new(Ipv4Addr::new(10, 0, 2, 15));
+    let lo_mac_addr = NetworkInterface::loopback_mac_addr();
+    let lo_ipv4_addr = Ipv4Addr::new(127, 0, 0, 1);
+
+    let request = ArpPacket::new_with(
+        ArpOperation::Request,
+        lo_mac_addr,
+        lo_ipv4_addr,
+        [0; 6].into(),
+        lo_ipv4_addr,
+    );
+    network_man.send_loopback(EthernetFrame::build(
+        [0xff; 6].into(),
+        lo_mac_addr,
+        EtherType::Arp,
+        &request.raw(),
+    ));
+
+    // request and its reply, without the NIC
+    assert_eq!(network_man.poll_loopback().unwrap(), 2);
+    assert_eq!(network_man.poll_loopback().unwrap(), 0);
+    assert_eq!(
+        network_man.arp_table().get(&lo_ipv4_addr),
+        Some(&lo_mac_addr)
+    );
+    assert_eq!(
+        NetworkInterface::route(lo_ipv4_addr, network_man.my_ipv4_addr),
+        NetworkInterface::Loopback
+    );
+    assert_eq!(
+        NetworkInterface::route(Ipv4Addr::new(10, 0, 2, 2), network_man.my_ipv4_addr),
+        NetworkInterface::Ethernet
+    );
+}
+
+#[test_case]
+fn test_loopback_udp() {
+    let mut network_man = NetworkManager::new(Ipv4Addr::new(10, 0, 2, 15));
+    let lo_ipv4_addr = Ipv4Addr::new(127, 0, 0, 1);
+
+    let server = network_man.udp_socket_table.create();
+    let client = network_man.udp_socket_table.create();
+    network_man.udp_socket_table.bind(server, 7).unwrap();
+
+    network_man
+        .send_udp_datagram(client, lo_ipv4_addr, 7, b"ping")
+        .unwrap();
+    // the own address is routed to the loopback interface too
+    network_man
+        .send_udp_datagram(client, network_man.my_ipv4_addr, 7, b"pong")
+        .unwrap();
+    assert_eq!(network_man.poll_loopback().unwrap(), 2);
+    assert!(network_man.eth_tx_queue.is_empty());
+
+    let client_port = network_man.udp_socket_table.local_port(client).unwrap();
+    let socket = network_man.udp_socket_table.udp_socket_mut(server).unwrap();
+    let datagram = socket.pop_recv_datagram().unwrap();
+    assert_eq!(datagram.src_addr, lo_ipv4_addr);
+    assert_eq!(datagram.src_port, client_port);
+    assert_eq!(datagram.payload, b"ping");
+    assert_eq!(socket.pop_recv_datagram().unwrap().payload, b"pong");
+    assert!(socket.pop_recv_datagram().is_none());
+}
+
+#[test_case]
+fn test_udp_waits_for_arp_reply() {
+    let mut network_man = NetworkManager::new(Ipv4Addr::new(10, 0, 2, 15));
+    let gateway_ipv4_addr = Ipv4Addr::new(10, 0, 2, 2);
+    let gateway_mac_addr: EthernetAddress = [0x52, 0x55, 0x0a, 0x00, 0x02, 0x02].into();
+    network_man.set_my_mac_addr([0x52, 0x54, 0x00, 0x12, 0x34, 0x56].into());
+
+    let sd = network_man.udp_socket_table.create();
+    network_man
+        .send_udp_datagram(sd, gateway_ipv4_addr, 53, b"query")
+        .unwrap();
+
+    // ARP request only
+    let frame = network_man.eth_tx_queue.pop_front().unwrap();
+    assert_eq!(
+        EthernetFrame::parse(&frame, false).unwrap().ether_type,
+        EtherType::Arp
+    );
+    assert!(network_man.eth_tx_queue.is_empty());
+
+    let reply = ArpPacket::new_with(
+        ArpOperation::Reply,
+        gateway_mac_addr,
+        gateway_ipv4_addr,
+        network_man.my_mac_addr().unwrap(),
+        network_man.my_ipv4_addr,
+    );
+    network_man
+        .receive_arp_packet(NetworkInterface::Ethernet, reply)
+        .unwrap();
+
+    let frame = network_man.eth_tx_queue.pop_front().unwrap();
+    let eth_frame = EthernetFrame::parse(&frame, false).unwrap();
+    assert_eq!(eth_frame.dst_mac_addr, gateway_mac_addr);
+    assert_eq!(eth_frame.ether_type, EtherType::IPv4);
+    assert!(network_man.arp_pending.is_empty());
+}
+
+#[test_case]
+fn test_route_to_gateway() {
+    let mut network_man = NetworkManager::new(Ipv4Addr::new(192, 168, 100, 2));
+    network_man.set_ipv4_config(&KernelConfig {
+        ipv4_addr: Ipv4Addr::new(192, 168, 100, 2),
+        ipv4_netmask: Ipv4Addr::new(255, 255, 255, 0),
+        ipv4_gateway: Ipv4Addr::new(192, 168, 100, 1),
+        ..Default::default()
+    });
+    network_man.set_my_mac_addr([0x52, 0x54, 0x00, 0x12, 0x34, 0x56].into());
+
+    assert_eq!(
+        network_man.next_hop_ipv4_addr(Ipv4Addr::new(192, 168, 100, 3)),
+        Ipv4Addr::new(192, 168, 100, 3)
+    );
+    assert_eq!(
+        network_man.next_hop_ipv4_addr(Ipv4Addr::new(8, 8, 8, 8)),
+        Ipv4Addr::new(192, 168, 100, 1)
+    );
+
+    // the gateway is resolved instead of the destination
+    let sd = network_man.udp_socket_table.create();
+    network_man
+        .send_udp_datagram(sd, Ipv4Addr::new(8, 8, 8, 8), 53, b"query")
+        .unwrap();
+    let frame = network_man.eth_tx_queue.pop_front().unwrap();
+    let eth_frame = EthernetFrame::parse(&frame, false).unwrap();
+    let request = ArpPacket::new(eth_frame.raw_payload());
+    assert_eq!(request.target_ipv4_addr, Ipv4Addr::new(192, 168, 100, 1));
 }
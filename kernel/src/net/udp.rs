@@ -0,0 +1,275 @@
+use super::checksum;
+use crate::error::{Error, Result};
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
+use core::{
+    net::Ipv4Addr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub const UDP_HEADER_LEN: usize = 8;
+// datagrams kept by a socket, the oldest one is dropped
+const UDP_RECV_BUF_LEN: usize = 32;
+const UDP_EPHEMERAL_PORT_START: u16 = 49152;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SocketId(usize);
+impl SocketId {
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub const fn new_val(value: i64) -> Result<Self> {
+        if value < 0 {
+            return Err(Error::Failed("Invalid socket id"));
+        }
+
+        Ok(Self(value as usize))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpDatagram<'a> {
+    pub src_port: u16,
+    pub dst_port: u16,
+    payload: &'a [u8],
+}
+
+impl<'a> UdpDatagram<'a> {
+    // addresses of the IPv4 header are required for the checksum
+    pub fn parse(src_addr: Ipv4Addr, dst_addr: Ipv4Addr, data: &'a [u8]) -> Result<Self> {
+        if data.len() < UDP_HEADER_LEN {
+            return Err(Error::Failed("UDP datagram is too short"));
+        }
+
+        let len = u16::from_be_bytes([data[4], data[5]]) as usize;
+        if len < UDP_HEADER_LEN || len > data.len() {
+            return Err(Error::Failed("Invalid UDP datagram length"));
+        }
+
+        let data = &data[..len];
+        if !checksum::is_valid_udp_checksum(src_addr, dst_addr, data) {
+            return Err(Error::Failed("Invalid UDP checksum"));
+        }
+
+        Ok(Self {
+            src_port: u16::from_be_bytes([data[0], data[1]]),
+            dst_port: u16::from_be_bytes([data[2], data[3]]),
+            payload: &data[UDP_HEADER_LEN..],
+        })
+    }
+
+    pub fn build(
+        src_addr: Ipv4Addr,
+        src_port: u16,
+        dst_addr: Ipv4Addr,
+        dst_port: u16,
+        payload: &[u8],
+    ) -> Result<Vec<u8>> {
+        let len: u16 = (UDP_HEADER_LEN + payload.len())
+            .try_into()
+            .map_err(|_| Error::Failed("UDP payload is too large"))?;
+
+        let mut datagram = Vec::with_capacity(len as usize);
+        datagram.extend_from_slice(&src_port.to_be_bytes());
+        datagram.extend_from_slice(&dst_port.to_be_bytes());
+        datagram.extend_from_slice(&len.to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]); // checksum
+        datagram.extend_from_slice(payload);
+
+        let checksum = checksum::udp_checksum(src_addr, dst_addr, &datagram);
+        datagram[6..8].copy_from_slice(&checksum.to_be_bytes());
+
+        Ok(datagram)
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UdpRecvDatagram {
+    pub src_addr: Ipv4Addr,
+    pub src_port: u16,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct UdpSocket {
+    port: Option<u16>,
+    recv_buf: VecDeque<UdpRecvDatagram>,
+}
+
+impl UdpSocket {
+    fn new() -> Self {
+        Self {
+            port: None,
+            recv_buf: VecDeque::new(),
+        }
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    fn push_recv_datagram(&mut self, datagram: UdpRecvDatagram) {
+        if self.recv_buf.len() >= UDP_RECV_BUF_LEN {
+            self.recv_buf.pop_front();
+        }
+
+        self.recv_buf.push_back(datagram);
+    }
+
+    pub fn pop_recv_datagram(&mut self) -> Option<UdpRecvDatagram> {
+        self.recv_buf.pop_front()
+    }
+}
+
+#[derive(Debug)]
+pub struct UdpSocketTable {
+    sockets: BTreeMap<SocketId, UdpSocket>,
+    next_ephemeral_port: u16,
+}
+
+impl UdpSocketTable {
+    pub const fn new() -> Self {
+        Self {
+            sockets: BTreeMap::new(),
+            next_ephemeral_port: UDP_EPHEMERAL_PORT_START,
+        }
+    }
+
+    pub fn create(&mut self) -> SocketId {
+        let id = SocketId::new();
+        self.sockets.insert(id, UdpSocket::new());
+        id
+    }
+
+    pub fn close(&mut self, id: SocketId) -> Result<()> {
+        self.sockets
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(Error::Failed("Socket was not found"))
+    }
+
+    pub fn udp_socket_mut(&mut self, id: SocketId) -> Result<&mut UdpSocket> {
+        self.sockets
+            .get_mut(&id)
+            .ok_or(Error::Failed("Socket was not found"))
+    }
+
+    fn is_port_used(&self, port: u16) -> bool {
+        self.sockets.values().any(|s| s.port == Some(port))
+    }
+
+    fn alloc_ephemeral_port(&mut self) -> Result<u16> {
+        for _ in UDP_EPHEMERAL_PORT_START..=u16::MAX {
+            let port = self.next_ephemeral_port;
+            self.next_ephemeral_port = port.checked_add(1).unwrap_or(UDP_EPHEMERAL_PORT_START);
+
+            if !self.is_port_used(port) {
+                return Ok(port);
+            }
+        }
+
+        Err(Error::Failed("No ephemeral port is available"))
+    }
+
+    // port 0 binds an ephemeral port, returns the bound port
+    pub fn bind(&mut self, id: SocketId, port: u16) -> Result<u16> {
+        if self.udp_socket_mut(id)?.port.is_some() {
+            return Err(Error::Failed("Socket is already bound"));
+        }
+
+        let port = match port {
+            0 => self.alloc_ephemeral_port()?,
+            port if self.is_port_used(port) => {
+                return Err(Error::Failed("Port is already in use"));
+            }
+            port => port,
+        };
+
+        self.udp_socket_mut(id)?.port = Some(port);
+        Ok(port)
+    }
+
+    // unbound sockets are bound to an ephemeral port on the first send
+    pub fn local_port(&mut self, id: SocketId) -> Result<u16> {
+        match self.udp_socket_mut(id)?.port {
+            Some(port) => Ok(port),
+            None => self.bind(id, 0),
+        }
+    }
+
+    // returns false if no socket is bound to the port
+    pub fn deliver(&mut self, dst_port: u16, datagram: UdpRecvDatagram) -> bool {
+        match self.sockets.values_mut().find(|s| s.port == Some(dst_port)) {
+            Some(socket) => {
+                socket.push_recv_datagram(datagram);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[test_case]
+fn test_build_and_parse_udp_datagram() {
+    let src_addr = Ipv4Addr::new(10, 0, 2, 15);
+    let dst_addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    let datagram = UdpDatagram::build(src_addr, 1234, dst_addr, 5678, b"abc").unwrap();
+    assert_eq!(datagram.len(), UDP_HEADER_LEN + 3);
+
+    let parsed = UdpDatagram::parse(src_addr, dst_addr, &datagram).unwrap();
+    assert_eq!(parsed.src_port, 1234);
+    assert_eq!(parsed.dst_port, 5678);
+    assert_eq!(parsed.payload(), b"abc");
+
+    // the pseudo header is a part of the checksum
+    assert!(UdpDatagram::parse(src_addr, Ipv4Addr::new(10, 0, 2, 3), &datagram).is_err());
+}
+
+#[test_case]
+fn test_udp_socket_table() {
+    let mut table = UdpSocketTable::new();
+    let sd1 = table.create();
+    let sd2 = table.create();
+
+    assert_eq!(table.bind(sd1, 7).unwrap(), 7);
+    assert!(table.bind(sd1, 8).is_err());
+    assert!(table.bind(sd2, 7).is_err());
+    assert_eq!(table.local_port(sd2).unwrap(), UDP_EPHEMERAL_PORT_START);
+
+    for i in 0..=UDP_RECV_BUF_LEN {
+        let datagram = UdpRecvDatagram {
+            src_addr: Ipv4Addr::LOCALHOST,
+            src_port: 1234,
+            payload: vec![i as u8],
+        };
+        assert!(table.deliver(7, datagram));
+    }
+
+    // the oldest datagram was dropped
+    let socket = table.udp_socket_mut(sd1).unwrap();
+    assert_eq!(socket.pop_recv_datagram().unwrap().payload, vec![1]);
+
+    assert!(table.close(sd1).is_ok());
+    assert!(table.close(sd1).is_err());
+    assert!(!table.deliver(
+        7,
+        UdpRecvDatagram {
+            src_addr: Ipv4Addr::LOCALHOST,
+            src_port: 1234,
+            payload: Vec::new(),
+        }
+    ));
+}
@@ -1,5 +1,5 @@
 use crate::{
-    arch,
+    arch, debug,
     device::panic_screen,
     error,
     qemu::{self, EXIT_FAILURE},
@@ -10,6 +10,7 @@ use core::panic::PanicInfo;
 fn panic(info: &PanicInfo) -> ! {
     error!("{:?}", info.message());
     error!("{:?}", info.location());
+    debug::backtrace();
 
     // prevent overwriting by graphics::frame_buf
     arch::disabled_int(|| {
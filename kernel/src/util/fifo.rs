@@ -30,14 +30,23 @@ impl<T: Sized + Copy, const SIZE: usize> Fifo<T, SIZE> {
         }
     }
 
-    pub fn len(&self) -> usize {
-        self.write_ptr.load(Ordering::Relaxed)
+    // one slot is kept empty to distinguish full from empty
+    pub fn capacity(&self) -> usize {
+        self.size - 1
     }
 
-    pub fn is_full(&self) -> bool {
+    pub fn len(&self) -> usize {
         let read_ptr = self.read_ptr.load(Ordering::Relaxed);
         let write_ptr = self.write_ptr.load(Ordering::Relaxed);
-        (write_ptr + 1) % self.size == read_ptr
+        (write_ptr + self.size - read_ptr) % self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
     }
 
     pub fn reset_ptr(&self) {
@@ -104,6 +113,64 @@ impl<T: Sized + Copy, const SIZE: usize> Fifo<T, SIZE> {
         Ok(self.buf.0[read_ptr])
     }
 
+    // enqueue as many values as fit, return enqueued length
+    pub fn enqueue_slice(&mut self, values: &[T]) -> Result<usize> {
+        let write_ptr = self.write_ptr.load(Ordering::Relaxed);
+        let len = values.len().min(self.capacity() - self.len());
+
+        if len == 0 && !values.is_empty() {
+            return Err(FifoError::BufferIsFull.into());
+        }
+
+        for (i, value) in values[..len].iter().enumerate() {
+            self.buf.0[(write_ptr + i) % self.size] = *value;
+        }
+
+        if self
+            .write_ptr
+            .compare_exchange(
+                write_ptr,
+                (write_ptr + len) % self.size,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            )
+            .is_err()
+        {
+            return Err(FifoError::BufferIsLocked.into());
+        }
+
+        Ok(len)
+    }
+
+    // dequeue up to buf.len() values, return dequeued length
+    pub fn dequeue_slice(&mut self, buf: &mut [T]) -> Result<usize> {
+        let read_ptr = self.read_ptr.load(Ordering::Relaxed);
+        let len = buf.len().min(self.len());
+
+        if self.is_empty() {
+            return Err(FifoError::BufferIsEmpty.into());
+        }
+
+        for (i, value) in buf[..len].iter_mut().enumerate() {
+            *value = self.buf.0[(read_ptr + i) % self.size];
+        }
+
+        if self
+            .read_ptr
+            .compare_exchange(
+                read_ptr,
+                (read_ptr + len) % self.size,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            return Err(FifoError::BufferIsLocked.into());
+        }
+
+        Ok(len)
+    }
+
     pub fn get_buf_ref(&self) -> &[T; SIZE] {
         &self.buf.0
     }
@@ -122,7 +189,8 @@ fn test_enqueue_dequeue() {
     assert!(fifo.enqueue(1).is_ok());
     assert!(fifo.enqueue(2).is_ok());
     assert!(fifo.enqueue(3).is_ok());
-    assert!(fifo.enqueue(4).is_err());
+    assert!(fifo.is_full());
+    assert_eq!(fifo.enqueue(4), Err(FifoError::BufferIsFull.into()));
 
     assert_eq!(fifo.dequeue(), Ok(1));
     assert_eq!(fifo.dequeue(), Ok(2));
@@ -141,3 +209,28 @@ fn test_reset() {
     assert_eq!(fifo.get_read_write_ptr(), (0, 0));
     assert!(fifo.dequeue().is_err());
 }
+
+#[test_case]
+fn test_enqueue_dequeue_slice() {
+    let mut fifo: Fifo<u8, 4> = Fifo::new(0);
+    assert_eq!(fifo.enqueue_slice(&[1, 2]), Ok(2));
+    assert_eq!(fifo.dequeue(), Ok(1));
+    assert_eq!(fifo.len(), 1);
+
+    // wrap around
+    assert_eq!(fifo.enqueue_slice(&[3, 4, 5]), Ok(2));
+    assert!(fifo.is_full());
+    assert_eq!(
+        fifo.enqueue_slice(&[5]),
+        Err(FifoError::BufferIsFull.into())
+    );
+
+    let mut buf = [0; 4];
+    assert_eq!(fifo.dequeue_slice(&mut buf), Ok(3));
+    assert_eq!(buf[..3], [2, 3, 4]);
+    assert!(fifo.is_empty());
+    assert_eq!(
+        fifo.dequeue_slice(&mut buf),
+        Err(FifoError::BufferIsEmpty.into())
+    );
+}
@@ -1,7 +1,19 @@
-use crate::{device, graphics::frame_buf_console, print, theme::GLOBAL_THEME};
+use crate::{
+    device,
+    error::{Error, Result},
+    theme::GLOBAL_THEME,
+    util::mutex::Mutex,
+};
+use alloc::boxed::Box;
+use common::kernel_config::LogLevel;
+use core::fmt::{self, Write};
 use log::{Level, LevelFilter, Record};
 
+const LOG_SINKS_MAX: usize = 4;
+
 static LOGGER: SimpleLogger = SimpleLogger;
+static mut LOG_SINKS: Mutex<[Option<Box<dyn Write>>; LOG_SINKS_MAX]> =
+    Mutex::new([const { None }; LOG_SINKS_MAX]);
 
 pub fn init() {
     log::set_logger(&LOGGER)
@@ -9,11 +21,38 @@ pub fn init() {
         .unwrap();
 }
 
+// every log record is written to all sinks
+// zero-sized sinks can be added before the heap is initialized
+pub fn add_sink(sink: impl Write + 'static) -> Result<()> {
+    let mut sinks = unsafe { LOG_SINKS.try_lock() }?;
+    let slot = sinks
+        .iter_mut()
+        .find(|sink| sink.is_none())
+        .ok_or(Error::Failed("Log sinks are full"))?;
+    *slot = Some(Box::new(sink));
+    Ok(())
+}
+
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+pub fn level_filter(log_level: LogLevel) -> LevelFilter {
+    match log_level {
+        LogLevel::Off => LevelFilter::Off,
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
+    }
+}
+
 struct SimpleLogger;
 
 impl log::Log for SimpleLogger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        metadata.level() <= Level::max()
+        metadata.level() <= log::max_level()
     }
 
     fn log(&self, record: &Record) {
@@ -21,43 +60,59 @@ impl log::Log for SimpleLogger {
             return;
         }
 
-        let fore_color = match record.level() {
-            Level::Error => GLOBAL_THEME.log_color_error,
-            Level::Warn => GLOBAL_THEME.log_color_warn,
-            Level::Info => GLOBAL_THEME.log_color_info,
-            Level::Debug => GLOBAL_THEME.log_color_debug,
-            Level::Trace => GLOBAL_THEME.log_color_trace,
+        let mut sinks = match unsafe { LOG_SINKS.try_lock() } {
+            Ok(sinks) => sinks,
+            Err(_) => return,
         };
 
-        let _ = frame_buf_console::set_fore_color(fore_color);
-
-        if let Some(ms) = device::local_apic_timer::get_current_ms() {
-            print!("[{:06}.{:03}]", ms / 1000, ms % 1000,);
-        } else {
-            print!("[??????.???]");
+        for sink in sinks.iter_mut().flatten() {
+            let _ = write_record(sink.as_mut(), record);
         }
+    }
 
-        print!(
-            "[{}{}]: ",
-            match record.level() {
-                Level::Error | Level::Debug | Level::Trace => "",
-                _ => " ",
-            },
-            record.level()
-        );
-
-        if record.level() == Level::Error {
-            print!(
-                "{}@{}: ",
-                record.file().unwrap_or("Unknown"),
-                record.line().unwrap_or(0)
-            );
-        }
+    fn flush(&self) {}
+}
+
+fn write_record(w: &mut dyn Write, record: &Record) -> fmt::Result {
+    let fore_color = match record.level() {
+        Level::Error => GLOBAL_THEME.log_color_error,
+        Level::Warn => GLOBAL_THEME.log_color_warn,
+        Level::Info => GLOBAL_THEME.log_color_info,
+        Level::Debug => GLOBAL_THEME.log_color_debug,
+        Level::Trace => GLOBAL_THEME.log_color_trace,
+    };
 
-        print!("{:?}\n", record.args());
+    // 24-bit fore color
+    write!(
+        w,
+        "\x1b[38;2;{};{};{}m",
+        fore_color.r, fore_color.g, fore_color.b
+    )?;
 
-        let _ = frame_buf_console::reset_fore_color();
+    if let Some(ms) = device::local_apic_timer::get_current_ms() {
+        write!(w, "[{:06}.{:03}]", ms / 1000, ms % 1000,)?;
+    } else {
+        write!(w, "[??????.???]")?;
     }
 
-    fn flush(&self) {}
+    write!(
+        w,
+        "[{}{}]: ",
+        match record.level() {
+            Level::Error | Level::Debug | Level::Trace => "",
+            _ => " ",
+        },
+        record.level()
+    )?;
+
+    if record.level() == Level::Error {
+        write!(
+            w,
+            "{}@{}: ",
+            record.file().unwrap_or("Unknown"),
+            record.line().unwrap_or(0)
+        )?;
+    }
+
+    write!(w, "{:?}\x1b[0m\n", record.args())
 }
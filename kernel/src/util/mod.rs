@@ -7,3 +7,4 @@ pub mod logger;
 pub mod mutex;
 pub mod random;
 pub mod sleep;
+pub mod zlib;
@@ -1,6 +1,7 @@
-use crate::error::Result;
+use crate::{arch::task, error::Result};
 use core::{
     cell::SyncUnsafeCell,
+    hint,
     ops::{Deref, DerefMut},
     sync::atomic::{AtomicBool, Ordering},
 };
@@ -36,6 +37,28 @@ impl<T: Sized> Mutex<T> {
         Err(MutexError::Locked.into())
     }
 
+    // retry up to spins times before giving up
+    pub fn lock_timeout(&self, spins: usize) -> Result<MutexGuard<T>> {
+        for _ in 0..spins {
+            if let Ok(guard) = self.try_lock() {
+                return Ok(guard);
+            }
+            hint::spin_loop();
+        }
+
+        self.try_lock()
+    }
+
+    // yield to other tasks until the lock is acquired
+    pub async fn lock(&self) -> MutexGuard<T> {
+        loop {
+            if let Ok(guard) = self.try_lock() {
+                return guard;
+            }
+            task::exec_yield().await;
+        }
+    }
+
     pub unsafe fn get_force_mut(&mut self) -> &mut T {
         self.value.get_mut()
     }
@@ -103,6 +126,21 @@ fn test_lock_unlock() {
     }
 }
 
+#[test_case]
+fn test_lock_timeout() {
+    let mutex = Mutex::new(0);
+
+    {
+        let _guard = mutex.try_lock().unwrap();
+        assert_eq!(
+            mutex.lock_timeout(100).err(),
+            Some(MutexError::Locked.into())
+        );
+    }
+
+    assert!(mutex.lock_timeout(100).is_ok());
+}
+
 #[test_case]
 fn test_unlock_force() {
     let mut mutex = Mutex::new(0);
@@ -1,4 +1,19 @@
-use crate::device;
+use crate::{
+    arch::{self, cpu},
+    device,
+};
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+// RDRAND may fail while the entropy is being refilled
+const RDRAND_RETRY_MAX: usize = 10;
+
+const RDRAND_UNCHECKED: u8 = 0;
+const RDRAND_SUPPORTED: u8 = 1;
+const RDRAND_UNSUPPORTED: u8 = 2;
+
+static RDRAND_STATE: AtomicU8 = AtomicU8::new(RDRAND_UNCHECKED);
+// fallback PRNG, 0 until seeded
+static PRNG_STATE: AtomicU64 = AtomicU64::new(0);
 
 pub fn xorshift32(seed: u32) -> u32 {
     let mut x = seed;
@@ -20,3 +35,68 @@ pub fn xorshift64_seed_is_apic_timer() -> u64 {
     let seed = device::local_apic_timer::get_current_tick();
     xorshift64(seed as u64)
 }
+
+// RDRAND if available, otherwise the PRNG seeded from the RTC and the TSC
+pub fn random_u64() -> u64 {
+    if is_rdrand_supported() {
+        for _ in 0..RDRAND_RETRY_MAX {
+            if let Some(value) = arch::rdrand() {
+                return value;
+            }
+        }
+    }
+
+    prng_next()
+}
+
+pub fn fill_bytes(buf: &mut [u8]) {
+    for chunk in buf.chunks_mut(8) {
+        let bytes = random_u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+fn is_rdrand_supported() -> bool {
+    match RDRAND_STATE.load(Ordering::Relaxed) {
+        RDRAND_SUPPORTED => true,
+        RDRAND_UNSUPPORTED => false,
+        _ => {
+            let supported = cpu::version_info().feature_rdrnd;
+            RDRAND_STATE.store(
+                if supported {
+                    RDRAND_SUPPORTED
+                } else {
+                    RDRAND_UNSUPPORTED
+                },
+                Ordering::Relaxed,
+            );
+            supported
+        }
+    }
+}
+
+fn prng_seed() -> u64 {
+    let mut seed = arch::rdtsc();
+    if let Ok(now) = device::rtc::now() {
+        let secs =
+            (now.day as u64 * 24 + now.hour as u64) * 3600 + now.min as u64 * 60 + now.sec as u64;
+        seed ^= secs.rotate_left(32);
+    }
+
+    // xorshift never leaves 0
+    match seed {
+        0 => 0x9e3779b97f4a7c15,
+        seed => seed,
+    }
+}
+
+fn prng_next() -> u64 {
+    let mut state = PRNG_STATE.load(Ordering::Relaxed);
+    if state == 0 {
+        state = prng_seed();
+    }
+
+    let next = xorshift64(state);
+    PRNG_STATE.store(next, Ordering::Relaxed);
+    next
+}
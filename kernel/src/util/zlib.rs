@@ -0,0 +1,383 @@
+use crate::error::Result;
+use alloc::vec::Vec;
+
+const MAX_CODE_BITS: usize = 15;
+const NUM_LEN_CODES: usize = 288;
+const NUM_DIST_CODES: usize = 30;
+
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order of code length code lengths
+const CODE_LEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZlibError {
+    InvalidHeaderError,
+    UnexpectedEndOfDataError,
+    InvalidBlockTypeError,
+    InvalidStoredBlockLengthError,
+    InvalidHuffmanCodeError,
+    InvalidDistanceError(usize),
+    InvalidChecksumError { expected: u32, actual: u32 },
+    OutputTooLongError(usize),
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    bit_buf: u32,
+    bit_cnt: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            bit_buf: 0,
+            bit_cnt: 0,
+        }
+    }
+
+    // LSB first
+    fn bits(&mut self, n: usize) -> Result<u32> {
+        while self.bit_cnt < n {
+            let b = *self
+                .data
+                .get(self.offset)
+                .ok_or(ZlibError::UnexpectedEndOfDataError)?;
+            self.bit_buf |= (b as u32) << self.bit_cnt;
+            self.offset += 1;
+            self.bit_cnt += 8;
+        }
+
+        let value = self.bit_buf & ((1u64 << n) - 1) as u32;
+        self.bit_buf = ((self.bit_buf as u64) >> n) as u32;
+        self.bit_cnt -= n;
+        Ok(value)
+    }
+
+    // discard remaining bits in the current byte
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_cnt = 0;
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .ok_or(ZlibError::UnexpectedEndOfDataError)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+// canonical huffman code
+struct Huffman {
+    counts: [u16; MAX_CODE_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lens: &[u8]) -> Result<Self> {
+        let mut counts = [0; MAX_CODE_BITS + 1];
+        for len in lens {
+            counts[*len as usize] += 1;
+        }
+
+        // check over-subscribed
+        let mut left: isize = 1;
+        for len in 1..=MAX_CODE_BITS {
+            left = (left << 1) - counts[len] as isize;
+            if left < 0 {
+                return Err(ZlibError::InvalidHuffmanCodeError.into());
+            }
+        }
+
+        let mut offsets = [0; MAX_CODE_BITS + 1];
+        for len in 1..MAX_CODE_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0; lens.len()];
+        for (symbol, len) in lens.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let mut code: isize = 0;
+        let mut first: isize = 0;
+        let mut index: isize = 0;
+
+        for len in 1..=MAX_CODE_BITS {
+            code |= reader.bits(1)? as isize;
+            let count = self.counts[len] as isize;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(ZlibError::InvalidHuffmanCodeError.into())
+    }
+}
+
+fn check_output_len(output: &Vec<u8>, len: usize, max_len: usize) -> Result<()> {
+    if output.len() + len > max_len {
+        return Err(ZlibError::OutputTooLongError(max_len).into());
+    }
+
+    Ok(())
+}
+
+fn inflate_stored(reader: &mut BitReader, output: &mut Vec<u8>, max_len: usize) -> Result<()> {
+    reader.align();
+    let header = reader.bytes(4)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(ZlibError::InvalidStoredBlockLengthError.into());
+    }
+
+    check_output_len(output, len as usize, max_len)?;
+    output.extend_from_slice(reader.bytes(len as usize)?);
+    Ok(())
+}
+
+fn inflate_codes(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    len_code: &Huffman,
+    dist_code: &Huffman,
+    max_len: usize,
+) -> Result<()> {
+    loop {
+        let symbol = len_code.decode(reader)? as usize;
+
+        if symbol < 256 {
+            check_output_len(output, 1, max_len)?;
+            output.push(symbol as u8);
+            continue;
+        }
+
+        // end of block
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let symbol = symbol - 257;
+        if symbol >= LEN_BASE.len() {
+            return Err(ZlibError::InvalidHuffmanCodeError.into());
+        }
+        let len = LEN_BASE[symbol] as usize + reader.bits(LEN_EXTRA[symbol] as usize)? as usize;
+
+        let symbol = dist_code.decode(reader)? as usize;
+        if symbol >= DIST_BASE.len() {
+            return Err(ZlibError::InvalidHuffmanCodeError.into());
+        }
+        let dist = DIST_BASE[symbol] as usize + reader.bits(DIST_EXTRA[symbol] as usize)? as usize;
+
+        if dist > output.len() {
+            return Err(ZlibError::InvalidDistanceError(dist).into());
+        }
+        check_output_len(output, len, max_len)?;
+
+        // may overlap
+        let start = output.len() - dist;
+        for i in 0..len {
+            output.push(output[start + i]);
+        }
+    }
+}
+
+fn inflate_fixed(reader: &mut BitReader, output: &mut Vec<u8>, max_len: usize) -> Result<()> {
+    let mut lens = [0; NUM_LEN_CODES];
+    lens[..144].fill(8);
+    lens[144..256].fill(9);
+    lens[256..280].fill(7);
+    lens[280..].fill(8);
+
+    let len_code = Huffman::new(&lens)?;
+    let dist_code = Huffman::new(&[5; NUM_DIST_CODES])?;
+    inflate_codes(reader, output, &len_code, &dist_code, max_len)
+}
+
+fn inflate_dynamic(reader: &mut BitReader, output: &mut Vec<u8>, max_len: usize) -> Result<()> {
+    let num_len = reader.bits(5)? as usize + 257;
+    let num_dist = reader.bits(5)? as usize + 1;
+    let num_code = reader.bits(4)? as usize + 4;
+    if num_len > NUM_LEN_CODES || num_dist > NUM_DIST_CODES {
+        return Err(ZlibError::InvalidHuffmanCodeError.into());
+    }
+
+    let mut code_lens = [0; 19];
+    for i in 0..num_code {
+        code_lens[CODE_LEN_ORDER[i]] = reader.bits(3)? as u8;
+    }
+    let code_len_code = Huffman::new(&code_lens)?;
+
+    let mut lens = [0; NUM_LEN_CODES + NUM_DIST_CODES];
+    let mut i = 0;
+    while i < num_len + num_dist {
+        let symbol = code_len_code.decode(reader)?;
+        if symbol < 16 {
+            lens[i] = symbol as u8;
+            i += 1;
+            continue;
+        }
+
+        let (len, repeat) = match symbol {
+            16 if i > 0 => (lens[i - 1], 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            18 => (0, 11 + reader.bits(7)? as usize),
+            _ => return Err(ZlibError::InvalidHuffmanCodeError.into()),
+        };
+
+        if i + repeat > num_len + num_dist {
+            return Err(ZlibError::InvalidHuffmanCodeError.into());
+        }
+        lens[i..i + repeat].fill(len);
+        i += repeat;
+    }
+
+    // end of block code is required
+    if lens[256] == 0 {
+        return Err(ZlibError::InvalidHuffmanCodeError.into());
+    }
+
+    let len_code = Huffman::new(&lens[..num_len])?;
+    let dist_code = Huffman::new(&lens[num_len..num_len + num_dist])?;
+    inflate_codes(reader, output, &len_code, &dist_code, max_len)
+}
+
+// raw deflate stream, returns the decompressed data and the consumed length
+// fails if the decompressed data would be longer than max_len
+pub fn inflate(data: &[u8], max_len: usize) -> Result<(Vec<u8>, usize)> {
+    let mut reader = BitReader::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let is_last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => inflate_stored(&mut reader, &mut output, max_len)?,
+            1 => inflate_fixed(&mut reader, &mut output, max_len)?,
+            2 => inflate_dynamic(&mut reader, &mut output, max_len)?,
+            _ => return Err(ZlibError::InvalidBlockTypeError.into()),
+        }
+
+        if is_last {
+            break;
+        }
+    }
+
+    Ok((output, reader.offset))
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+
+    (b << 16) | a
+}
+
+// zlib stream (RFC 1950)
+pub fn decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
+    if data.len() < 2 {
+        return Err(ZlibError::UnexpectedEndOfDataError.into());
+    }
+
+    let cmf = data[0];
+    let flg = data[1];
+    // deflate, no preset dictionary
+    if cmf & 0xf != 8 || (cmf as u16 * 256 + flg as u16) % 31 != 0 || flg & 0x20 != 0 {
+        return Err(ZlibError::InvalidHeaderError.into());
+    }
+
+    let (output, len) = inflate(&data[2..], max_len)?;
+
+    let checksum = data
+        .get(2 + len..2 + len + 4)
+        .ok_or(ZlibError::UnexpectedEndOfDataError)?;
+    let expected = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+    let actual = adler32(&output);
+    if expected != actual {
+        return Err(ZlibError::InvalidChecksumError { expected, actual }.into());
+    }
+
+    Ok(output)
+}
+
+#[test_case]
+fn test_decompress() {
+    // stored
+    let data = [
+        0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, 0x61, 0x62, 0x63, 0x02, 0x4d, 0x01, 0x27,
+    ];
+    assert_eq!(decompress(&data, usize::MAX).unwrap(), b"abc");
+    assert!(decompress(&data, 2).is_err());
+
+    // fixed huffman codes
+    let data = [
+        0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x27, 0x01, 0x68, 0x03, 0x08,
+        0xb1,
+    ];
+    assert_eq!(
+        decompress(&data, usize::MAX).unwrap(),
+        b"hello hello hello hello"
+    );
+    assert!(decompress(&data, 22).is_err());
+
+    // dynamic huffman codes
+    let data = [
+        0x78, 0xda, 0xb5, 0xca, 0xc7, 0x11, 0x80, 0x20, 0x10, 0x00, 0xc0, 0x56, 0xae, 0x02, 0xc7,
+        0x1c, 0xfa, 0xa0, 0x01, 0x89, 0x82, 0xe1, 0x14, 0x24, 0x48, 0xf5, 0xda, 0x84, 0xef, 0x5d,
+        0xb2, 0x08, 0xb8, 0xbc, 0x66, 0x2b, 0x50, 0x8b, 0xf1, 0x00, 0x89, 0x09, 0x8c, 0xdf, 0x4f,
+        0x07, 0x18, 0x84, 0x85, 0xfb, 0xe3, 0x6d, 0xce, 0x0f, 0x70, 0x54, 0x05, 0x90, 0xdf, 0x72,
+        0x59, 0xd5, 0x4d, 0xdb, 0xf5, 0xc3, 0x38, 0xcd, 0x94, 0x71, 0x21, 0xd5, 0xa2, 0xcd, 0xba,
+        0xed, 0x07, 0x9e, 0x97, 0x75, 0xb7, 0x0f, 0x31, 0x3d, 0xf9, 0x05, 0x9a, 0xff, 0x3d, 0xa2,
+    ];
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        expected.extend_from_slice(b"The quick brown fox jumps over the lazy dog. ");
+    }
+    expected.extend_from_slice(b"0123456789abcdefghijklmnopqrstuvwxyz");
+    assert_eq!(decompress(&data, usize::MAX).unwrap(), expected);
+
+    // broken checksum
+    let mut data = data;
+    data[data.len() - 1] ^= 0xff;
+    assert!(decompress(&data, usize::MAX).is_err());
+}